@@ -1,11 +1,9 @@
 use futures::future::*;
 use gofile_api::*;
-use std::env::*;
-use uuid::Uuid;
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
-    let token = var("GOFILE_TOKEN").unwrap();
+    let token = Token::from_env("GOFILE_TOKEN")?;
     let api = Api::default().authorize(token);
     let account_id = api.get_account_id().await?;
     let account_details = api.get_account_details(account_id).await?;
@@ -20,13 +18,13 @@ async fn main() -> Result<(), Error> {
     let results = {
         let upload_task_1 = tokio::spawn(upload(
             api.clone(),
-            src_dir.id,
+            src_dir.folder_id().unwrap(),
             "test-001.txt",
             "file content 001",
         ));
         let upload_task_2 = tokio::spawn(upload(
             api.clone(),
-            src_dir.id,
+            src_dir.folder_id().unwrap(),
             "test-002.txt",
             "file content 002",
         ));
@@ -37,21 +35,26 @@ async fn main() -> Result<(), Error> {
     let mut content_ids = Vec::new();
     for result in results {
         let upload_result = result.unwrap()?;
-        content_ids.push(upload_result.file_id);
+        content_ids.push(upload_result.file_id.into());
     }
 
-    api.copy_content(content_ids, dst_dir.id).await?;
+    let copied = api
+        .copy_content(content_ids, dst_dir.folder_id().unwrap())
+        .await?;
+    for (src_id, copy_id) in copied.contents {
+        println!("{} -> {}", src_id, copy_id);
+    }
 
     Ok(())
 }
 
 async fn upload(
     api: AuthorizedApi,
-    folder_id: Uuid,
+    folder_id: FolderId,
     filename: &'static str,
     content: &'static str,
 ) -> Result<UploadedFile, Error> {
-    let server = api.get_server().await?;
+    let server = api.get_server(ZonePreference::Any).await?;
     server
         .upload_file_with_filename_to_folder(folder_id, filename, content)
         .await
@@ -1,9 +1,8 @@
 use gofile_api::*;
-use std::env::*;
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
-    let token = var("GOFILE_TOKEN").unwrap();
+    let token = Token::from_env("GOFILE_TOKEN")?;
 
     let api = Api::default().authorize(token);
     let account_id = api.get_account_id().await?;
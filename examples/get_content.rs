@@ -5,7 +5,7 @@ use url::*;
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     let url = Url::parse(&args().collect::<Vec<_>>()[1]).unwrap();
-    let token = var("GOFILE_TOKEN").unwrap();
+    let token = Token::from_env("GOFILE_TOKEN")?;
 
     let api = Api::default().authorize(token);
     let content = api.get_content(&url).await?;
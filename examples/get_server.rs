@@ -2,6 +2,13 @@ use gofile_api::*;
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
-    println!("{:?}", Api::default().get_server().await?);
+    let api = Api::default();
+    for server in api.get_servers().await?.all() {
+        println!("{} ({})", server.name, server.zone);
+    }
+
+    // the closest zone first, any server when none of them has one
+    let preference = ZonePreference::Preferred(vec![Zone::Na, Zone::Eu]);
+    println!("{:?}", api.get_server(preference).await?);
     Ok(())
 }
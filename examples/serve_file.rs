@@ -0,0 +1,49 @@
+use futures::TryStreamExt;
+use gofile_api::*;
+use std::{convert::Infallible, env::*, sync::Arc};
+use warp::{
+    http::{header, Response, StatusCode},
+    hyper::Body,
+    Filter,
+};
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let token = Token::from_env("GOFILE_TOKEN")?;
+    let content_id: ContentId = var("GOFILE_CONTENT_ID").unwrap().parse().unwrap();
+    let api = Api::default().authorize(token);
+    let content = Arc::new(api.get_content_by_id(content_id).await?);
+
+    let route = warp::path::end().and_then(move || serve(api.clone(), content.clone()));
+    warp::serve(route).run(([127, 0, 0, 1], 3030)).await;
+
+    Ok(())
+}
+
+async fn serve(api: AuthorizedApi, content: Arc<Content>) -> Result<Response<Body>, Infallible> {
+    let ContentKind::File { size, mimetype, .. } = &content.kind else {
+        return Ok(error_response(StatusCode::NOT_FOUND));
+    };
+
+    let stream = match api.download_stream(&content).await {
+        Ok(stream) => stream,
+        Err(err) => {
+            eprintln!("{}", err);
+            return Ok(error_response(StatusCode::BAD_GATEWAY));
+        }
+    };
+
+    let response = Response::builder()
+        .header(header::CONTENT_LENGTH, *size)
+        .header(header::CONTENT_TYPE, mimetype.as_ref())
+        .body(Body::wrap_stream(stream.map_err(|err| err.to_string())))
+        .unwrap();
+    Ok(response)
+}
+
+fn error_response(status: StatusCode) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::empty())
+        .unwrap()
+}
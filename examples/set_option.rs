@@ -1,10 +1,9 @@
 use chrono::*;
 use gofile_api::*;
-use std::env::*;
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
-    let token = var("GOFILE_TOKEN").unwrap();
+    let token = Token::from_env("GOFILE_TOKEN")?;
 
     let api = Api::default().authorize(token);
     let account_id = api.get_account_id().await?;
@@ -24,16 +23,16 @@ async fn main() -> Result<(), Error> {
     api.set_tags_option(content_id, vec!["tag1", "tag2"])
         .await?;
 
-    let server = api.get_server().await?;
+    let server = api.get_server(ZonePreference::Any).await?;
     let upload_result = server
-        .upload_file_with_filename_to_folder(dir.id, "test.txt", "file content")
+        .upload_file_with_filename_to_folder(dir.folder_id().unwrap(), "test.txt", "file content")
         .await?;
     let content_id = upload_result.file_id;
-    let link_url = api.get_direct_link(content_id).await?;
+    let link_url = api.get_direct_link(content_id.into()).await?;
 
     println!("{:?}", link_url);
 
-    api.disable_direct_link(content_id).await?;
+    api.disable_direct_link(content_id.into()).await?;
 
     Ok(())
 }
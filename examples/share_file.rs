@@ -0,0 +1,27 @@
+use gofile_api::*;
+use std::env::*;
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let file_path = &args().collect::<Vec<_>>()[1];
+
+    let options = ShareOptions {
+        expire: Some(chrono::Utc::now() + chrono::Duration::days(7)),
+        ..Default::default()
+    };
+    match Api::default().share_file(file_path, options).await {
+        Ok(shared) => println!("{}", shared.download_page),
+        // the file is up, only setting an option failed
+        Err(Error::ShareFailed {
+            step,
+            uploaded: Some(uploaded),
+            source,
+        }) => eprintln!(
+            "{} is up but setting {} failed: {}",
+            uploaded.download_page, step, source
+        ),
+        Err(err) => return Err(err),
+    }
+
+    Ok(())
+}
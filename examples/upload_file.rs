@@ -5,9 +5,20 @@ use std::env::*;
 async fn main() -> Result<(), Error> {
     let file_path = &args().collect::<Vec<_>>()[1];
 
-    let server = Api::default().get_server().await?;
+    let api = Api::default();
+    let server = api.get_server(ZonePreference::Any).await?;
     let uploaded_file_info = server.upload_file(file_path).await?;
     println!("{:?}", uploaded_file_info);
 
+    // The guest token owns the folder created by the anonymous upload
+    let guest_api = api.authorize_guest(&uploaded_file_info)?;
+    let expire = guest_api
+        .set_expire_in(
+            uploaded_file_info.parent_folder.into(),
+            chrono::Duration::days(7),
+        )
+        .await?;
+    println!("{} expires at {}", uploaded_file_info.download_page, expire);
+
     Ok(())
 }
@@ -0,0 +1,35 @@
+use gofile_api::*;
+use std::env::*;
+use tokio::sync::mpsc::unbounded_channel;
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let token = Token::from_env("GOFILE_TOKEN")?;
+    let file_path = &args().collect::<Vec<_>>()[1];
+
+    let api = Api::default().authorize(token);
+    let account_id = api.get_account_id().await?;
+    let root_folder = api.get_account_details(account_id).await?.root_folder;
+    let server_api = api.get_server(ZonePreference::Any).await?;
+
+    let (tx, mut rx) = unbounded_channel::<UploadedMessage>();
+    let progress = tokio::spawn(async move {
+        while let Some(message) = rx.recv().await {
+            eprint!(
+                "\r{}: {:.1}% ({} KiB/s)",
+                message.file_name,
+                message.percent().unwrap_or_default(),
+                message.bytes_per_sec / 1024
+            );
+        }
+        eprintln!();
+    });
+
+    let uploaded_file = server_api
+        .upload_file_to_folder_with_progress(root_folder, file_path, Some(tx))
+        .await?;
+    progress.await.unwrap();
+    println!("{}", uploaded_file.download_page);
+
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use gofile_api::*;
+use std::env::*;
+use tokio::sync::mpsc::unbounded_channel;
+
+// tar -c some_dir | cargo run --example upload_stdin -- some_dir.tar
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let token = Token::from_env("GOFILE_TOKEN")?;
+    let filename = args().nth(1).unwrap_or_else(|| "stdin".into());
+    let api = Api::default().authorize(token);
+    let server_api = api.get_server(ZonePreference::Any).await?;
+
+    let (tx, mut rx) = unbounded_channel::<UploadedMessage>();
+    let progress = tokio::spawn(async move {
+        while let Some(message) = rx.recv().await {
+            eprint!(
+                "\r{}: {} bytes ({} KiB/s)",
+                message.file_name,
+                message.uploaded,
+                message.bytes_per_sec / 1024
+            );
+        }
+        eprintln!();
+    });
+
+    let uploaded_file = server_api
+        .upload_reader(filename, tokio::io::stdin(), None, Some(tx))
+        .await?;
+    progress.await.unwrap();
+    println!("{}", uploaded_file.download_page);
+
+    Ok(())
+}
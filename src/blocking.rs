@@ -0,0 +1,326 @@
+// Synchronous wrappers for programs without a tokio runtime. Every wrapper drives the async
+// api on a small runtime shared by everything derived from the same Api, so uploads still
+// stream from disk. Calling them from inside an async runtime panics.
+use crate::{
+    AccountDetails, Content, ContentId, CopiedContents, DeleteStatus, Error, FolderId,
+    MovedContents, NoInfo, ProgressObserver, Token, UploadOptions, UploadedFile, UploadedMessage,
+    ZonePreference,
+};
+use futures::Future;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{mpsc::Sender, Arc},
+};
+use tokio::runtime::Runtime;
+use url::Url;
+use uuid::Uuid;
+
+fn new_runtime() -> Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("a current thread runtime can be built")
+}
+
+#[derive(Debug)]
+pub struct Api {
+    pub inner: crate::Api,
+    runtime: Arc<Runtime>,
+}
+
+impl Default for Api {
+    fn default() -> Self {
+        Self::from(crate::Api::default())
+    }
+}
+
+// Api::builder() is shared with the async api, the built Api is converted
+impl From<crate::Api> for Api {
+    fn from(inner: crate::Api) -> Self {
+        Self {
+            inner,
+            runtime: Arc::new(new_runtime()),
+        }
+    }
+}
+
+impl Api {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn authorize(&self, token: impl Into<Token>) -> AuthorizedApi {
+        AuthorizedApi {
+            inner: self.inner.authorize(token),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    // Runs any async call the wrappers don't cover, like `api.block_on(|api| api.get_servers())`
+    pub fn block_on<'a, F>(&'a self, call: impl FnOnce(&'a crate::Api) -> F) -> F::Output
+    where
+        F: Future,
+    {
+        self.runtime.block_on(call(&self.inner))
+    }
+
+    pub fn get_server(&self, preference: ZonePreference) -> Result<ServerApi, Error> {
+        let inner = self.block_on(|api| api.get_server(preference))?;
+        Ok(ServerApi {
+            inner,
+            runtime: self.runtime.clone(),
+        })
+    }
+
+    pub fn get_content_by_url(&self, url: &Url) -> Result<Content, Error> {
+        self.block_on(|api| api.get_content_by_url(url))
+    }
+
+    pub fn get_content_by_code(&self, code: impl AsRef<str>) -> Result<Content, Error> {
+        self.block_on(|api| api.get_content_by_code(code))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AuthorizedApi {
+    pub inner: crate::AuthorizedApi,
+    runtime: Arc<Runtime>,
+}
+
+impl AuthorizedApi {
+    pub fn block_on<'a, F>(&'a self, call: impl FnOnce(&'a crate::AuthorizedApi) -> F) -> F::Output
+    where
+        F: Future,
+    {
+        self.runtime.block_on(call(&self.inner))
+    }
+
+    pub fn get_server(&self, preference: ZonePreference) -> Result<AuthorizedServerApi, Error> {
+        let inner = self.block_on(|api| api.get_server(preference))?;
+        Ok(AuthorizedServerApi {
+            inner,
+            runtime: self.runtime.clone(),
+        })
+    }
+
+    pub fn get_account_id(&self) -> Result<Uuid, Error> {
+        self.block_on(|api| api.get_account_id())
+    }
+
+    pub fn get_account_details(&self, account_id: Uuid) -> Result<AccountDetails, Error> {
+        self.block_on(|api| api.get_account_details(account_id))
+    }
+
+    pub fn get_content_by_id(&self, content_id: ContentId) -> Result<Content, Error> {
+        self.block_on(|api| api.get_content_by_id(content_id))
+    }
+
+    pub fn get_content_by_url(&self, url: &Url) -> Result<Content, Error> {
+        self.block_on(|api| api.get_content_by_url(url))
+    }
+
+    pub fn create_folder(
+        &self,
+        parent_folder_id: FolderId,
+        folder_name: impl Into<String>,
+    ) -> Result<Content, Error> {
+        self.block_on(|api| api.create_folder(parent_folder_id, folder_name))
+    }
+
+    pub fn rename_content(
+        &self,
+        content_id: ContentId,
+        new_name: impl Into<String>,
+    ) -> Result<NoInfo, Error> {
+        self.block_on(|api| api.rename_content(content_id, new_name))
+    }
+
+    pub fn copy_content(
+        &self,
+        content_ids: Vec<ContentId>,
+        dest_folder_id: FolderId,
+    ) -> Result<CopiedContents, Error> {
+        self.block_on(|api| api.copy_content(content_ids, dest_folder_id))
+    }
+
+    pub fn move_contents(
+        &self,
+        content_ids: Vec<ContentId>,
+        dest_folder_id: FolderId,
+    ) -> Result<MovedContents, Error> {
+        self.block_on(|api| api.move_contents(content_ids, dest_folder_id))
+    }
+
+    pub fn delete_contents(
+        &self,
+        content_ids: Vec<ContentId>,
+    ) -> Result<HashMap<ContentId, DeleteStatus>, Error> {
+        self.block_on(|api| api.delete_contents(content_ids))
+    }
+
+    pub fn download_file(
+        &self,
+        content: &Content,
+        dest: impl AsRef<Path>,
+    ) -> Result<PathBuf, Error> {
+        self.block_on(|api| async move {
+            let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+            api.download_file(content, dest, tx).await
+        })
+    }
+
+    // Progress goes to on_progress with the number of bytes written so far
+    pub fn download_file_with_observer(
+        &self,
+        content: &Content,
+        dest: impl AsRef<Path>,
+        observer: Arc<dyn ProgressObserver>,
+    ) -> Result<PathBuf, Error> {
+        self.block_on(|api| {
+            api.download_file_with_observer(content, dest, observer, Default::default())
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ServerApi {
+    pub inner: crate::ServerApi,
+    runtime: Arc<Runtime>,
+}
+
+impl ServerApi {
+    pub fn authorize(self, token: impl Into<Token>) -> AuthorizedServerApi {
+        AuthorizedServerApi {
+            inner: self.inner.authorize(token),
+            runtime: self.runtime,
+        }
+    }
+
+    pub fn upload_file(&self, path: impl AsRef<Path>) -> Result<UploadedFile, Error> {
+        self.runtime.block_on(self.inner.upload_file(path))
+    }
+
+    pub fn upload_file_to_folder(
+        &self,
+        folder_id: FolderId,
+        path: impl AsRef<Path>,
+    ) -> Result<UploadedFile, Error> {
+        self.runtime
+            .block_on(self.inner.upload_file_to_folder(folder_id, path))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AuthorizedServerApi {
+    pub inner: crate::AuthorizedServerApi,
+    runtime: Arc<Runtime>,
+}
+
+impl AuthorizedServerApi {
+    pub fn upload_file(&self, path: impl AsRef<Path>) -> Result<UploadedFile, Error> {
+        self.runtime.block_on(self.inner.upload_file(path))
+    }
+
+    pub fn upload_file_to_folder(
+        &self,
+        folder_id: FolderId,
+        path: impl AsRef<Path>,
+        tx: Option<Sender<UploadedMessage>>,
+    ) -> Result<UploadedFile, Error> {
+        match tx {
+            Some(tx) => self.upload_file_to_folder_with_observer(
+                folder_id,
+                path,
+                Arc::new(tx),
+                UploadOptions::default(),
+            ),
+            None => self
+                .runtime
+                .block_on(self.inner.upload_file_to_folder(folder_id, path)),
+        }
+    }
+
+    pub fn upload_file_to_folder_with_observer(
+        &self,
+        folder_id: FolderId,
+        path: impl AsRef<Path>,
+        observer: Arc<dyn ProgressObserver>,
+        options: UploadOptions,
+    ) -> Result<UploadedFile, Error> {
+        self.runtime.block_on(
+            self.inner
+                .upload_file_to_folder_with_observer(folder_id, path, observer, options),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use md5::{Digest, Md5};
+    use mockito::Server;
+    use std::sync::mpsc::channel;
+    use uuid::uuid;
+
+    #[test]
+    fn blocking_upload() -> Result<(), Error> {
+        let mut server = Server::new();
+        let api = Api::from(crate::Api::builder().base_url(server.url()).build()?);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.txt");
+        std::fs::write(&path, "kkk").unwrap();
+
+        let mocks = vec![
+            server
+                .mock("GET", "/servers")
+                .with_body(r#"{"status":"ok","data":{"servers":[{"name":"store1","zone":"eu"}]}}"#)
+                .create(),
+            server
+                .mock("GET", "/accounts/getid?token=gofile_token")
+                .with_body(
+                    r#"{"status":"ok","data":{"id":"00000000-0000-0000-0000-000000000001"}}"#,
+                )
+                .create(),
+            server
+                .mock("POST", "/contents/uploadfile")
+                .with_body(
+                    serde_json::json!({
+                        "status": "ok",
+                        "data": {
+                            "downloadPage": "https://gofile.io/d/foo",
+                            "code": "foo",
+                            "parentFolder": "00000000-0000-0000-0000-000000000001",
+                            "fileId": "00000000-0000-0000-0000-000000000002",
+                            "fileName": "test.txt",
+                            "md5": hex::encode(Md5::digest("kkk"))
+                        }
+                    })
+                    .to_string(),
+                )
+                .create(),
+        ];
+
+        let authorized = api.authorize("gofile_token");
+        let account_id = authorized.get_account_id()?;
+        assert_eq!(account_id, uuid!("00000000-0000-0000-0000-000000000001"));
+        let mut server_api = api
+            .get_server(ZonePreference::Any)?
+            .authorize("gofile_token");
+        // the storage server is the mock too
+        server_api.inner.base_url = server.url().parse().unwrap();
+
+        let (tx, rx) = channel();
+        let uploaded = server_api.upload_file_to_folder(FolderId(account_id), &path, Some(tx))?;
+        assert_eq!(
+            uploaded.file_id,
+            uuid!("00000000-0000-0000-0000-000000000002")
+        );
+        let uploaded: Vec<_> = rx.iter().map(|message| message.uploaded).collect();
+        assert_eq!(uploaded.last(), Some(&3));
+        for mock in mocks {
+            mock.assert();
+        }
+        Ok(())
+    }
+}
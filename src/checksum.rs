@@ -0,0 +1,110 @@
+use md5::{Digest, Md5 as Md5Hasher};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+// An md5 digest, printed and parsed as 32 hex chars like gofile sends it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Md5(#[serde(with = "hex::serde")] pub [u8; 16]);
+
+impl Md5 {
+    pub fn of(data: impl AsRef<[u8]>) -> Self {
+        Md5(Md5Hasher::digest(data).into())
+    }
+
+    pub async fn of_reader(mut reader: impl AsyncRead + Unpin) -> std::io::Result<Self> {
+        let mut hasher = Md5Hasher::new();
+        let mut buf = vec![0; 64 * 1024];
+        loop {
+            let n = reader.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+        }
+        Ok(Md5(hasher.finalize().into()))
+    }
+}
+
+impl From<[u8; 16]> for Md5 {
+    fn from(md5: [u8; 16]) -> Self {
+        Md5(md5)
+    }
+}
+
+impl From<Md5> for [u8; 16] {
+    fn from(md5: Md5) -> Self {
+        md5.0
+    }
+}
+
+impl PartialEq<[u8; 16]> for Md5 {
+    fn eq(&self, other: &[u8; 16]) -> bool {
+        self.0 == *other
+    }
+}
+
+// Either case, like the output of md5sum or of a tool printing upper case
+impl PartialEq<str> for Md5 {
+    fn eq(&self, other: &str) -> bool {
+        other.parse::<Md5>().is_ok_and(|other| *self == other)
+    }
+}
+
+impl PartialEq<&str> for Md5 {
+    fn eq(&self, other: &&str) -> bool {
+        *self == **other
+    }
+}
+
+impl fmt::LowerHex for Md5 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.0))
+    }
+}
+
+impl fmt::Display for Md5 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::LowerHex::fmt(self, f)
+    }
+}
+
+impl FromStr for Md5 {
+    type Err = hex::FromHexError;
+
+    fn from_str(md5: &str) -> Result<Self, Self::Err> {
+        hex::FromHex::from_hex(md5).map(Md5)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{from_value, json, to_value};
+
+    #[tokio::test]
+    async fn md5() {
+        let md5 = Md5::of("ggg");
+        let hex = "ba248c985ace94863880921d8900c53f";
+        assert_eq!(md5.to_string(), hex);
+        assert_eq!(format!("{:x}", md5), hex);
+        assert_eq!(hex.parse::<Md5>().unwrap(), md5);
+        assert_eq!(hex.to_uppercase().parse::<Md5>().unwrap(), md5);
+        assert!("ba248c98".parse::<Md5>().is_err());
+        assert!("not hex".parse::<Md5>().is_err());
+
+        assert_eq!(md5, hex);
+        assert_eq!(md5, *hex.to_uppercase());
+        assert_ne!(md5, "00000000000000000000000000000000");
+        assert_ne!(md5, "not hex");
+        assert_eq!(md5, <[u8; 16]>::from(md5));
+
+        assert_eq!(to_value(md5).unwrap(), json!(hex));
+        assert_eq!(from_value::<Md5>(json!(hex)).unwrap(), md5);
+        assert!(from_value::<Md5>(json!("ba248c98")).is_err());
+
+        let reader = std::io::Cursor::new(vec![b'g'; 3]);
+        assert_eq!(Md5::of_reader(reader).await.unwrap(), md5);
+    }
+}
@@ -0,0 +1,959 @@
+use crate::{default_base_url, Api, Error, ReqwestTransport, Transport};
+use bytes::Bytes;
+use futures::{future, stream, Future, Stream, StreamExt};
+use reqwest::{
+    header::{ACCEPT_ENCODING, LOCATION},
+    redirect, Client, IntoUrl, Method, Proxy, RequestBuilder, Response,
+};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use url::Url;
+
+const TRANSFER_DONE: u64 = u64::MAX;
+const TRANSFER_PENDING: u64 = u64::MAX - 1;
+
+pub const DEFAULT_USER_AGENT: &str = concat!("gofile_api-rs/", env!("CARGO_PKG_VERSION"));
+
+// A redirect that isn't followed ends the request with Error::RedirectBlocked
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RedirectPolicy {
+    None,
+    // at most this many redirects, api calls never follow one to another host
+    Limited(usize),
+    // reqwest's default, up to 10 redirects anywhere
+    #[default]
+    Default,
+}
+
+impl RedirectPolicy {
+    fn reqwest_policy(self, api_host: Option<String>) -> redirect::Policy {
+        match self {
+            RedirectPolicy::None => redirect::Policy::none(),
+            RedirectPolicy::Limited(max) => redirect::Policy::custom(move |attempt| {
+                let from_api = attempt.previous()[0].host_str() == api_host.as_deref();
+                if attempt.previous().len() > max {
+                    attempt.error("too many redirects")
+                } else if from_api && attempt.url().host_str() != api_host.as_deref() {
+                    attempt.stop()
+                } else {
+                    attempt.follow()
+                }
+            }),
+            RedirectPolicy::Default => redirect::Policy::default(),
+        }
+    }
+}
+
+// Where a redirect that wasn't followed points to
+pub(crate) fn redirect_location(res: &Response) -> Option<Url> {
+    if !res.status().is_redirection() {
+        return None;
+    }
+    let location = res.headers().get(LOCATION)?.to_str().ok()?;
+    res.url().join(location).ok()
+}
+
+// The reqwest client and the settings applied to each request, cloned into every
+// AuthorizedApi and ServerApi derived from the same Api
+#[derive(Clone, Debug)]
+pub struct ApiClient {
+    pub(crate) http: Client,
+    // only bounds the json api calls, uploads and downloads may run for hours
+    pub(crate) metadata_timeout: Option<Duration>,
+    // transfers fail once no bytes moved for this long
+    pub(crate) stall_timeout: Option<Duration>,
+    // shared by every clone, None means unlimited
+    pub(crate) limiter: Option<RateLimiter>,
+    // what actually sends the requests built with http
+    pub(crate) transport: Arc<dyn Transport>,
+}
+
+impl Default for ApiClient {
+    fn default() -> Self {
+        let http = Client::builder()
+            .user_agent(DEFAULT_USER_AGENT)
+            .build()
+            .expect("the default client settings are valid");
+        Self::from(http)
+    }
+}
+
+impl From<Client> for ApiClient {
+    fn from(http: Client) -> Self {
+        Self {
+            transport: Arc::new(ReqwestTransport(http.clone())),
+            http,
+            metadata_timeout: None,
+            stall_timeout: None,
+            limiter: None,
+        }
+    }
+}
+
+impl ApiClient {
+    // Requests are still built with the reqwest client, only sending them is replaced
+    pub fn with_transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    pub(crate) fn api_request(&self, method: Method, url: impl IntoUrl) -> RequestBuilder {
+        let req = self.http.request(method, url);
+        match self.metadata_timeout {
+            Some(timeout) => req.timeout(timeout),
+            None => req,
+        }
+    }
+
+    // Files are never asked for compressed, ranges and lengths refer to the stored bytes
+    pub(crate) fn transfer_request(&self, method: Method, url: impl IntoUrl) -> RequestBuilder {
+        self.http
+            .request(method, url)
+            .header(ACCEPT_ENCODING, "identity")
+    }
+
+    // Every request goes through here so the rate limit covers them all, a transfer
+    // counts once no matter how many chunks it moves
+    pub(crate) async fn send(&self, req: RequestBuilder) -> Result<Response, Error> {
+        let req = req.build()?;
+        // only the path, api queries may carry the token
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("request", method = %req.method(), path = req.url().path());
+        let send = async {
+            if let Some(limiter) = &self.limiter {
+                limiter.acquire().await;
+            }
+            let res = self.transport.execute(req).await;
+            #[cfg(feature = "tracing")]
+            match &res {
+                Ok(res) => tracing::debug!(status = res.status().as_u16(), "response"),
+                Err(err) => tracing::debug!(
+                    timeout = matches!(err, Error::HttpRequestError(err) if err.is_timeout()),
+                    "request failed"
+                ),
+            }
+            res
+        };
+        #[cfg(feature = "tracing")]
+        let send = tracing::Instrument::instrument(send, span);
+        send.await
+    }
+
+    pub(crate) fn stall_watch(&self) -> Option<StallWatch> {
+        self.stall_timeout.map(|timeout| StallWatch {
+            timeout,
+            started_at: Instant::now(),
+            last_activity: Arc::new(AtomicU64::new(TRANSFER_PENDING)),
+        })
+    }
+
+    // The downloaded body, failing with Stalled when the next chunk takes too long
+    pub(crate) fn body_stream(
+        &self,
+        res: Response,
+    ) -> impl Stream<Item = Result<Bytes, Error>> + Send + Unpin {
+        let url = res.url().clone();
+        let stall_timeout = self.stall_timeout;
+        let stream = stream::unfold(Some(Box::pin(res.bytes_stream())), move |stream| {
+            let url = url.clone();
+            async move {
+                let mut stream = stream?;
+                let next = match stall_timeout {
+                    Some(timeout) => match tokio::time::timeout(timeout, stream.next()).await {
+                        Ok(next) => next,
+                        Err(_) => return Some((Err(Error::Stalled(url, timeout)), None)),
+                    },
+                    None => stream.next().await,
+                };
+                match next? {
+                    Ok(chunk) => Some((Ok(chunk), Some(stream))),
+                    Err(err) => Some((Err(err.into()), None)),
+                }
+            }
+        });
+        Box::pin(stream)
+    }
+}
+
+// Token bucket allowing `burst` requests at once and one more every `interval` after that
+#[derive(Clone, Debug)]
+pub(crate) struct RateLimiter {
+    interval: Duration,
+    burst: u32,
+    // when the bucket is full again, every request moves it by one interval
+    refilled_at: Arc<Mutex<Instant>>,
+}
+
+impl RateLimiter {
+    pub(crate) fn per_second(requests: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / requests,
+            burst: requests,
+            refilled_at: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    // Takes a slot right away and sleeps until it's due, so waiting tasks are served
+    // in the order they arrived
+    async fn acquire(&self) {
+        if let Some(due) = self.reserve() {
+            event!(debug, wait = ?due.saturating_duration_since(Instant::now()), "rate limit hit");
+            tokio::time::sleep_until(due.into()).await;
+        }
+    }
+
+    // When the slot taken is due, None when it's free right away
+    fn reserve(&self) -> Option<Instant> {
+        let mut refilled_at = self.refilled_at.lock().unwrap();
+        let now = Instant::now();
+        let next = (*refilled_at).max(now) + self.interval;
+        *refilled_at = next;
+        next.checked_sub(self.interval * self.burst)
+            .filter(|due| *due > now)
+    }
+}
+
+// Remembers when an upload body last handed out bytes, the connection is stalled once
+// hyper stops asking for more
+#[derive(Clone, Debug)]
+pub(crate) struct StallWatch {
+    timeout: Duration,
+    started_at: Instant,
+    // millis since started_at, TRANSFER_PENDING until the body is first read, so time
+    // spent waiting on the rate limit isn't a stall, TRANSFER_DONE once it was read whole
+    last_activity: Arc<AtomicU64>,
+}
+
+impl StallWatch {
+    pub(crate) fn touch(&self) {
+        let elapsed = self.started_at.elapsed().as_millis() as u64;
+        self.last_activity.store(elapsed, Ordering::Relaxed);
+    }
+
+    // Waiting for the response isn't a stall, gofile may take a while to answer
+    pub(crate) fn finish(&self) {
+        self.last_activity.store(TRANSFER_DONE, Ordering::Relaxed);
+    }
+
+    async fn stalled(&self) {
+        loop {
+            let last_activity = self.last_activity.load(Ordering::Relaxed);
+            if last_activity == TRANSFER_DONE {
+                return future::pending().await;
+            }
+            if last_activity == TRANSFER_PENDING {
+                tokio::time::sleep(self.timeout).await;
+                continue;
+            }
+            let deadline = self.started_at + Duration::from_millis(last_activity) + self.timeout;
+            if Instant::now() >= deadline {
+                return;
+            }
+            tokio::time::sleep_until(deadline.into()).await;
+        }
+    }
+
+    pub(crate) async fn guard<T>(
+        watch: Option<Self>,
+        url: Url,
+        transfer: impl Future<Output = Result<T, Error>>,
+    ) -> Result<T, Error> {
+        let Some(watch) = watch else {
+            return transfer.await;
+        };
+        tokio::select! {
+            result = transfer => result,
+            () = watch.stalled() => Err(Error::Stalled(url, watch.timeout)),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ApiBuilder {
+    base_url: Option<String>,
+    website_token: Option<String>,
+    connect_timeout: Option<Duration>,
+    metadata_timeout: Option<Duration>,
+    stall_timeout: Option<Duration>,
+    user_agent: Option<String>,
+    proxies: Vec<Proxy>,
+    no_env_proxy: bool,
+    rate_limit: Option<u32>,
+    no_compression: bool,
+    redirect_policy: RedirectPolicy,
+    transport: Option<Arc<dyn Transport>>,
+}
+
+impl ApiBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    pub fn website_token(mut self, website_token: impl Into<String>) -> Self {
+        self.website_token = Some(website_token.into());
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    // Bounds the json api calls, uploads and downloads aren't cut by it
+    pub fn metadata_timeout(mut self, timeout: Duration) -> Self {
+        self.metadata_timeout = Some(timeout);
+        self
+    }
+
+    // Same as metadata_timeout
+    pub fn request_timeout(self, timeout: Duration) -> Self {
+        self.metadata_timeout(timeout)
+    }
+
+    // Uploads and downloads fail with Error::Stalled once no bytes moved for this long
+    pub fn stall_timeout(mut self, timeout: Duration) -> Self {
+        self.stall_timeout = Some(timeout);
+        self
+    }
+
+    // Replaces the default user agent entirely
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    // Puts the application in front of the default user agent, like "myapp/2.1 gofile_api-rs/0.2.0"
+    pub fn app_user_agent(mut self, app: &str) -> Self {
+        self.user_agent = Some(format!("{} {}", app, DEFAULT_USER_AGENT));
+        self
+    }
+
+    // Used for the api and the storage servers alike, socks5 urls need the `socks` feature
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxies.push(proxy);
+        self
+    }
+
+    // HTTP_PROXY, HTTPS_PROXY and ALL_PROXY are honored unless this is set
+    pub fn no_env_proxy(mut self) -> Self {
+        self.no_env_proxy = true;
+        self
+    }
+
+    pub fn redirect_policy(mut self, policy: RedirectPolicy) -> Self {
+        self.redirect_policy = policy;
+        self
+    }
+
+    // Sends every request through transport instead of the network. Timeouts, proxies and
+    // the other client settings only apply if the transport uses them.
+    pub fn transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+
+    // Stops asking for gzip or brotli responses, only matters with those features on
+    pub fn no_compression(mut self) -> Self {
+        self.no_compression = true;
+        self
+    }
+
+    // Caps the requests sent per second across every clone of the built Api, AuthorizedApi
+    // and ServerApi included, unlimited by default
+    pub fn rate_limit(mut self, requests_per_second: u32) -> Self {
+        self.rate_limit = Some(requests_per_second);
+        self
+    }
+
+    pub fn build(self) -> Result<Api, Error> {
+        let base_url = match self.base_url {
+            Some(base_url) => Self::checked_base_url(&base_url)?,
+            None => default_base_url(),
+        };
+        for (name, timeout) in [
+            ("connect_timeout", self.connect_timeout),
+            ("metadata_timeout", self.metadata_timeout),
+            ("stall_timeout", self.stall_timeout),
+        ] {
+            if timeout == Some(Duration::ZERO) {
+                return Err(Error::InvalidConfig(format!(
+                    "The {} must be greater than zero.",
+                    name
+                )));
+            }
+        }
+        if self.rate_limit == Some(0) {
+            return Err(Error::InvalidConfig(
+                "The rate_limit must be greater than zero.".into(),
+            ));
+        }
+
+        let user_agent = self.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT);
+        let api_host = base_url.host_str().map(String::from);
+        let mut builder = Client::builder()
+            .user_agent(user_agent)
+            .redirect(self.redirect_policy.reqwest_policy(api_host));
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if self.no_env_proxy {
+            builder = builder.no_proxy();
+        }
+        if self.no_compression {
+            builder = builder.no_gzip().no_brotli();
+        }
+        for proxy in self.proxies {
+            builder = builder.proxy(proxy);
+        }
+        let http = builder.build()?;
+
+        Ok(Api {
+            base_url,
+            website_token: self.website_token,
+            client: ApiClient {
+                transport: self
+                    .transport
+                    .unwrap_or_else(|| Arc::new(ReqwestTransport(http.clone()))),
+                http,
+                metadata_timeout: self.metadata_timeout,
+                stall_timeout: self.stall_timeout,
+                limiter: self.rate_limit.map(RateLimiter::per_second),
+            },
+        })
+    }
+
+    fn checked_base_url(base_url: &str) -> Result<Url, Error> {
+        let url = Url::parse(base_url)
+            .map_err(|err| Error::InvalidBaseUrl(base_url.into(), err.to_string()))?;
+        Self::checked_url(url)
+    }
+
+    pub(crate) fn checked_url(url: Url) -> Result<Url, Error> {
+        if !matches!(url.scheme(), "http" | "https") || url.host().is_none() {
+            return Err(Error::InvalidBaseUrl(
+                url.into(),
+                "The base url must be an http or https url.".into(),
+            ));
+        }
+        Ok(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ZonePreference;
+    use mockito::Server;
+    use std::thread::sleep;
+
+    #[test]
+    fn build() -> Result<(), Error> {
+        let api = ApiBuilder::new().build()?;
+        assert_eq!(api.base_url.as_str(), "https://api.gofile.io/");
+        assert_eq!(api.client.metadata_timeout, None);
+
+        let api = Api::builder()
+            .base_url(Url::parse("http://127.0.0.1:8080/").unwrap())
+            .website_token("4fd6sg89d7s6")
+            .connect_timeout(Duration::from_secs(5))
+            .request_timeout(Duration::from_secs(30))
+            .user_agent("gofile-test")
+            .proxy(Proxy::all("http://127.0.0.1:3128").unwrap())
+            .build()?;
+        assert_eq!(api.base_url.as_str(), "http://127.0.0.1:8080/");
+        assert_eq!(api.website_token.as_deref(), Some("4fd6sg89d7s6"));
+        assert_eq!(api.client.metadata_timeout, Some(Duration::from_secs(30)));
+
+        for builder in [
+            Api::builder().base_url("not a url"),
+            Api::builder().base_url("ftp://example.com"),
+        ] {
+            assert!(matches!(builder.build(), Err(Error::InvalidBaseUrl(..))));
+        }
+        for builder in [
+            Api::builder().request_timeout(Duration::ZERO),
+            Api::builder().stall_timeout(Duration::ZERO),
+            Api::builder().connect_timeout(Duration::ZERO),
+            Api::builder().rate_limit(0),
+        ] {
+            assert!(matches!(builder.build(), Err(Error::InvalidConfig(_))));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn request_timeout_spares_uploads() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api::builder()
+            .base_url(server.url())
+            .request_timeout(Duration::from_millis(200))
+            .build()?;
+
+        let slow_body = |body: String| {
+            move |w: &mut dyn std::io::Write| {
+                sleep(Duration::from_millis(600));
+                w.write_all(body.as_bytes())
+            }
+        };
+        server
+            .mock("GET", "/servers")
+            .with_chunked_body(slow_body(
+                r#"{"status":"ok","data":{"servers":[{"name":"store1","zone":"eu"}]}}"#.into(),
+            ))
+            .create();
+        server
+            .mock("POST", "/contents/uploadfile")
+            .with_chunked_body(slow_body(
+                serde_json::json!({
+                    "status": "ok",
+                    "data": {
+                        "downloadPage": "https://gofile.io/d/foo",
+                        "code": "foo",
+                        "parentFolder": "00000000-0000-0000-0000-000000000001",
+                        "fileId": "00000000-0000-0000-0000-000000000002",
+                        "fileName": "test.txt",
+                        "md5": "00000000000000000000000000000000"
+                    }
+                })
+                .to_string(),
+            ))
+            .create();
+
+        assert!(matches!(
+            api.get_server(ZonePreference::Any).await,
+            Err(Error::HttpRequestError(err)) if err.is_timeout()
+        ));
+
+        let server_api = crate::ServerApi {
+            base_url: server.url().parse().unwrap(),
+            client: api.client.clone(),
+        };
+        let uploaded = server_api
+            .upload_file_with_filename("test.txt", "ggg")
+            .await?;
+        assert_eq!(uploaded.file_name, "test.txt");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stall_timeout() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api::builder()
+            .base_url(server.url())
+            .stall_timeout(Duration::from_millis(200))
+            .build()?;
+        let server_api = crate::ServerApi {
+            base_url: server.url().parse().unwrap(),
+            client: api.client.clone(),
+        };
+
+        // the reader hands out a few bytes and then nothing, while the writer stays open
+        let (mut writer, reader) = tokio::io::duplex(64);
+        tokio::io::AsyncWriteExt::write_all(&mut writer, b"ggg")
+            .await
+            .unwrap();
+        let result = server_api
+            .upload_reader("test.txt", reader, None, None)
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::Stalled(url, timeout))
+                if url.path() == "/contents/uploadfile" && timeout == Duration::from_millis(200)
+        ));
+        drop(writer);
+
+        server
+            .mock("GET", "/download/test.txt")
+            .with_chunked_body(|w| {
+                w.write_all(b"ggg")?;
+                w.flush()?;
+                sleep(Duration::from_millis(800));
+                w.write_all(b"hhh")
+            })
+            .create();
+        let content: crate::Content = serde_json::from_value(serde_json::json!({
+            "id": "00000000-0000-0000-0000-000000000001",
+            "type": "file",
+            "name": "test.txt",
+            "createTime": 1710264457,
+            "size": 6,
+            "downloadCount": 0,
+            "md5": "00000000000000000000000000000000",
+            "mimetype": "text/plain",
+            "serverSelected": "store1",
+            "link": format!("{}/download/test.txt", server.url()),
+        }))
+        .unwrap();
+        let mut body = api
+            .authorize("gofile_token")
+            .download_stream(&content)
+            .await?;
+        assert_eq!(&body.next().await.unwrap()?[..], b"ggg");
+        assert!(matches!(body.next().await, Some(Err(Error::Stalled(..)))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn user_agent() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let uploaded = serde_json::json!({
+            "status": "ok",
+            "data": {
+                "downloadPage": "https://gofile.io/d/foo",
+                "code": "foo",
+                "parentFolder": "00000000-0000-0000-0000-000000000001",
+                "fileId": "00000000-0000-0000-0000-000000000002",
+                "fileName": "test.txt",
+                "md5": "00000000000000000000000000000000"
+            }
+        })
+        .to_string();
+        let servers = r#"{"status":"ok","data":{"servers":[{"name":"store1","zone":"eu"}]}}"#;
+
+        let default_ua = concat!("gofile_api-rs/", env!("CARGO_PKG_VERSION"));
+        let app_ua = concat!("myapp/2.1 gofile_api-rs/", env!("CARGO_PKG_VERSION"));
+        for (api, user_agent) in [
+            (
+                crate::Api {
+                    base_url: server.url().parse().unwrap(),
+                    ..Default::default()
+                },
+                default_ua,
+            ),
+            (Api::builder().base_url(server.url()).build()?, default_ua),
+            (
+                Api::builder()
+                    .base_url(server.url())
+                    .app_user_agent("myapp/2.1")
+                    .build()?,
+                app_ua,
+            ),
+            (
+                Api::builder()
+                    .base_url(server.url())
+                    .user_agent("myapp/2.1")
+                    .build()?,
+                "myapp/2.1",
+            ),
+        ] {
+            let mocks = vec![
+                server
+                    .mock("GET", "/servers")
+                    .match_header("user-agent", user_agent)
+                    .with_body(servers)
+                    .expect(1)
+                    .create(),
+                server
+                    .mock("POST", "/contents/uploadfile")
+                    .match_header("user-agent", user_agent)
+                    .with_body(&uploaded)
+                    .expect(1)
+                    .create(),
+            ];
+            api.get_server(ZonePreference::Any).await?;
+            crate::ServerApi {
+                base_url: server.url().parse().unwrap(),
+                client: api.client.clone(),
+            }
+            .upload_file_with_filename("test.txt", "ggg")
+            .await?;
+            for mock in mocks {
+                mock.assert();
+                mock.remove();
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn proxy() -> Result<(), Error> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // a proxy gets the absolute url in the request line
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_url = format!("http://{}", listener.local_addr().unwrap());
+        let proxied = tokio::spawn(async move {
+            let mut request_lines = Vec::new();
+            for body in [
+                r#"{"status":"ok","data":{"servers":[{"name":"store1","zone":"eu"}]}}"#.to_string(),
+                serde_json::json!({
+                    "status": "ok",
+                    "data": {
+                        "downloadPage": "https://gofile.io/d/foo",
+                        "code": "foo",
+                        "parentFolder": "00000000-0000-0000-0000-000000000001",
+                        "fileId": "00000000-0000-0000-0000-000000000002",
+                        "fileName": "test.txt",
+                        "md5": "00000000000000000000000000000000"
+                    }
+                })
+                .to_string(),
+            ] {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                // the whole request is read before answering
+                let mut request = Vec::new();
+                let mut buf = vec![0; 4096];
+                let request = loop {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request).into_owned();
+                    let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                        continue;
+                    };
+                    let length = head
+                        .lines()
+                        .find_map(|line| line.strip_prefix("content-length: "))
+                        .map_or(0, |length| length.parse().unwrap());
+                    if n == 0 || body.len() >= length {
+                        break text;
+                    }
+                };
+                request_lines.push(request.lines().next().unwrap_or_default().to_string());
+                let res = format!(
+                    "HTTP/1.1 200 OK\r\nconnection: close\r\ncontent-length: {}\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                socket.write_all(res.as_bytes()).await.unwrap();
+            }
+            request_lines
+        });
+
+        let api = Api::builder()
+            .base_url("http://api.gofile.invalid")
+            .no_env_proxy()
+            .proxy(Proxy::http(&proxy_url).unwrap())
+            .build()?;
+        api.get_server(ZonePreference::Any).await?;
+        crate::ServerApi {
+            base_url: "http://store1.gofile.invalid".parse().unwrap(),
+            client: api.client.clone(),
+        }
+        .upload_file_with_filename("test.txt", "ggg")
+        .await?;
+
+        assert_eq!(
+            proxied.await.unwrap(),
+            vec![
+                "GET http://api.gofile.invalid/servers HTTP/1.1",
+                "POST http://store1.gofile.invalid/contents/uploadfile HTTP/1.1",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rate_limiter_reserve() {
+        let limiter = RateLimiter::per_second(2);
+        assert_eq!(limiter.reserve(), None);
+        assert_eq!(limiter.reserve(), None);
+        assert!(limiter.reserve().is_some_and(|due| due > Instant::now()));
+
+        // spaced out requests never wait
+        let limiter = RateLimiter::per_second(100);
+        for _ in 0..5 {
+            assert_eq!(limiter.reserve(), None);
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    #[tokio::test]
+    async fn rate_limit() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let body = r#"{"status":"ok","data":{"servers":[{"name":"store1","zone":"eu"}]}}"#;
+        let mock = server
+            .mock("GET", "/servers")
+            .with_body(body)
+            .expect(12)
+            .create();
+        let mock_id = server
+            .mock("GET", "/accounts/getid?token=gofile_token")
+            .with_body(r#"{"status":"ok","data":{"id":"00000000-0000-0000-0000-000000000001"}}"#)
+            .expect(4)
+            .create();
+
+        let api = Api::builder()
+            .base_url(server.url())
+            .rate_limit(8)
+            .build()?;
+        let authorized = api.authorize("gofile_token");
+        let started = Instant::now();
+        let servers = (0..12).map(|_| api.get_server(ZonePreference::Any));
+        let ids = (0..4).map(|_| authorized.get_account_id());
+        let (servers, ids) =
+            futures::join!(future::try_join_all(servers), future::try_join_all(ids));
+        servers?;
+        ids?;
+        // 8 requests go out right away, the other 8 are spread over the next second
+        assert!(started.elapsed() >= Duration::from_millis(950));
+        mock.assert();
+        mock_id.assert();
+
+        // no limit unless asked for
+        let started = Instant::now();
+        let api = crate::Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        };
+        let mock = server
+            .mock("GET", "/servers")
+            .with_body(body)
+            .expect(16)
+            .create();
+        future::try_join_all((0..16).map(|_| api.get_server(ZonePreference::Any))).await?;
+        assert!(started.elapsed() < Duration::from_millis(950));
+        mock.assert();
+        Ok(())
+    }
+
+    #[cfg(feature = "gzip")]
+    #[tokio::test]
+    async fn compression() -> Result<(), Error> {
+        use flate2::{write::GzEncoder, Compression};
+        use mockito::Matcher;
+        use std::io::Write;
+
+        let mut server = Server::new_async().await;
+        let servers = r#"{"status":"ok","data":{"servers":[{"name":"store1","zone":"eu"}]}}"#;
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(servers.as_bytes()).unwrap();
+        let gzipped = encoder.finish().unwrap();
+
+        let mock = server
+            .mock("GET", "/servers")
+            .match_header("accept-encoding", Matcher::Regex("gzip".into()))
+            .with_header("content-encoding", "gzip")
+            .with_body(gzipped)
+            .expect(1)
+            .create();
+        let api = Api::builder().base_url(server.url()).build()?;
+        let server_api = api.get_server(ZonePreference::Any).await?;
+        assert_eq!(server_api.base_url.as_str(), "https://store1.gofile.io/");
+        mock.assert();
+
+        // files come as stored whatever the api calls accept
+        let mock = server
+            .mock("GET", "/download/test.txt")
+            .match_header("accept-encoding", "identity")
+            .with_body("ggg")
+            .expect(1)
+            .create();
+        let res = api
+            .client
+            .send(
+                api.client
+                    .transfer_request(Method::GET, format!("{}/download/test.txt", server.url())),
+            )
+            .await?;
+        assert_eq!(res.text().await?, "ggg");
+        mock.assert();
+
+        let mock = server
+            .mock("GET", "/servers")
+            .match_header("accept-encoding", Matcher::Missing)
+            .with_body(servers)
+            .expect(1)
+            .create();
+        let api = Api::builder()
+            .base_url(server.url())
+            .no_compression()
+            .build()?;
+        api.get_server(ZonePreference::Any).await?;
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn redirect_policy() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let servers = r#"{"status":"ok","data":{"servers":[{"name":"store1","zone":"eu"}]}}"#;
+        // same server, another host name
+        let other_host = server.url().replace("127.0.0.1", "localhost");
+        server
+            .mock("GET", "/servers")
+            .with_status(302)
+            .with_header("location", "/v2/servers")
+            .create();
+        server
+            .mock("GET", "/v2/servers")
+            .with_status(302)
+            .with_header("location", &format!("{}/v3/servers", other_host))
+            .create();
+        server
+            .mock("GET", "/v3/servers")
+            .with_body(servers)
+            .create();
+        server
+            .mock("GET", "/download/test.txt")
+            .with_status(302)
+            .with_header("location", &format!("{}/v2/test.txt", server.url()))
+            .create();
+        server.mock("GET", "/v2/test.txt").with_body("ggg").create();
+        let content: crate::Content = serde_json::from_value(serde_json::json!({
+            "id": "00000000-0000-0000-0000-000000000001",
+            "type": "file",
+            "name": "test.txt",
+            "createTime": 1710264457,
+            "size": 3,
+            "downloadCount": 0,
+            "md5": "00000000000000000000000000000000",
+            "mimetype": "text/plain",
+            "serverSelected": "store1",
+            // the storage server is on another host than the api
+            "link": format!("{}/download/test.txt", other_host),
+        }))
+        .unwrap();
+        let build = |policy| {
+            Api::builder()
+                .base_url(server.url())
+                .redirect_policy(policy)
+                .build()
+        };
+
+        let api = build(RedirectPolicy::Default)?;
+        api.get_server(ZonePreference::Any).await?;
+        let mut body = api
+            .authorize("gofile_token")
+            .download_stream(&content)
+            .await?;
+        assert_eq!(&body.next().await.unwrap()?[..], b"ggg");
+
+        let api = build(RedirectPolicy::None)?;
+        assert!(matches!(
+            api.get_server(ZonePreference::Any).await,
+            Err(Error::RedirectBlocked(location)) if location.path() == "/v2/servers"
+        ));
+        let result = api
+            .authorize("gofile_token")
+            .download_stream(&content)
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::RedirectBlocked(location))
+                if location.as_str() == format!("{}/v2/test.txt", server.url())
+        ));
+
+        // api calls stay on the api host, downloads may move to another storage server
+        let api = build(RedirectPolicy::Limited(2))?;
+        assert!(matches!(
+            api.get_server(ZonePreference::Any).await,
+            Err(Error::RedirectBlocked(location)) if location.host_str() == Some("localhost")
+        ));
+        let mut body = api
+            .authorize("gofile_token")
+            .download_stream(&content)
+            .await?;
+        assert_eq!(&body.next().await.unwrap()?[..], b"ggg");
+        Ok(())
+    }
+}
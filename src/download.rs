@@ -0,0 +1,3037 @@
+use crate::{
+    client::redirect_location, Api, ApiClient, AuthorizedApi, Content, ContentId, ContentKind,
+    Error, ErrorContext, FolderId, Md5, ProgressObserver,
+};
+use bytes::Bytes;
+use chrono::Utc;
+use futures::{stream, Stream, StreamExt};
+use md5::{Digest, Md5 as Md5Hasher};
+use reqwest::{
+    header::{
+        ACCEPT_ENCODING, ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, COOKIE, RANGE,
+    },
+    Method, Response, StatusCode,
+};
+use std::{
+    io::SeekFrom,
+    path::{Component, Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use tokio::{
+    fs::{self, File, OpenOptions},
+    io::{AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt},
+    sync::{
+        mpsc::{unbounded_channel, UnboundedSender},
+        Mutex,
+    },
+    task::JoinSet,
+};
+use url::Url;
+use uuid::Uuid;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DownloadedMessage {
+    pub uuid: Uuid,
+    pub downloaded: u64,
+    pub total: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct DownloadOptions {
+    // keep the partially written file when the transfer fails, a file failing the md5 check
+    // is kept as <name>.corrupt
+    pub keep_partial: bool,
+    pub verify_md5: bool,
+    pub collision_policy: CollisionPolicy,
+    // write into <name>.part, locked while the download runs, and rename once verified. A
+    // leftover part file is resumed, concurrent downloads of one name use <name>.<n>.part.
+    pub atomic: bool,
+    // premium only, download through a direct link, falls back to the normal link for other
+    // tiers
+    pub use_direct_link: bool,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            keep_partial: false,
+            verify_md5: true,
+            collision_policy: CollisionPolicy::default(),
+            atomic: true,
+            use_direct_link: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CollisionPolicy {
+    #[default]
+    Overwrite,
+    // an existing file with the same size and md5 counts as downloaded and is reported as
+    // skipped, a different one is downloaded again over it
+    Skip,
+    Error,
+    // download to the first free `name (n).ext`
+    Rename,
+}
+
+type NameFilter = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+#[derive(Clone)]
+pub struct FolderDownloadOptions {
+    pub concurrency: usize,
+    // used for every file in the tree
+    pub download: DownloadOptions,
+    // the folder's password, sent for every subfolder too
+    pub password: Option<String>,
+    filter: Option<NameFilter>,
+}
+
+impl Default for FolderDownloadOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            download: DownloadOptions::default(),
+            password: None,
+            filter: None,
+        }
+    }
+}
+
+impl FolderDownloadOptions {
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    pub fn collision_policy(mut self, collision_policy: CollisionPolicy) -> Self {
+        self.download.collision_policy = collision_policy;
+        self
+    }
+
+    pub fn download_options(mut self, download: DownloadOptions) -> Self {
+        self.download = download;
+        self
+    }
+
+    pub fn password(mut self, password: impl Into<String>) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+
+    // only files whose name matches the filter are downloaded
+    pub fn filter(mut self, filter: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        self.filter = Some(Arc::new(filter));
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FolderDownloadMessage {
+    File(DownloadedMessage),
+    Total {
+        files_done: usize,
+        files_total: usize,
+        bytes_done: u64,
+        bytes_total: u64,
+    },
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct UrlDownloadOptions {
+    pub password: Option<String>,
+    // download every child when the url points to a folder instead of failing with NotAFile
+    pub allow_folder: bool,
+    pub download: DownloadOptions,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileProbe {
+    pub content_length: Option<u64>,
+    pub content_type: Option<String>,
+    pub accepts_ranges: bool,
+    // the reported length differs from the size in the listing
+    pub size_mismatch: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct FolderDownloadReport {
+    pub downloaded: Vec<PathBuf>,
+    pub skipped: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, Error)>,
+}
+
+impl Api {
+    // Anonymous download, only works for public files
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content.id))
+    )]
+    pub async fn download_file(
+        &self,
+        content: &Content,
+        dest: impl AsRef<Path>,
+        tx: UnboundedSender<DownloadedMessage>,
+    ) -> Result<PathBuf, Error> {
+        download_file_impl(
+            &self.client,
+            None,
+            content,
+            dest.as_ref(),
+            tx,
+            DownloadOptions::default(),
+        )
+        .await
+        .map(|(path, _)| path)
+    }
+}
+
+impl AuthorizedApi {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content.id))
+    )]
+    pub async fn download_file(
+        &self,
+        content: &Content,
+        dest: impl AsRef<Path>,
+        tx: UnboundedSender<DownloadedMessage>,
+    ) -> Result<PathBuf, Error> {
+        self.download_file_with_options(content, dest, tx, DownloadOptions::default())
+            .await
+    }
+
+    // Download progress is reported through on_progress, the byte count is what was written
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content.id))
+    )]
+    pub async fn download_file_with_observer(
+        &self,
+        content: &Content,
+        dest: impl AsRef<Path>,
+        observer: Arc<dyn ProgressObserver>,
+        options: DownloadOptions,
+    ) -> Result<PathBuf, Error> {
+        let (tx, mut rx) = unbounded_channel::<DownloadedMessage>();
+        let download = self.download_file_with_options(content, dest, tx, options);
+        let forward = async {
+            while let Some(message) = rx.recv().await {
+                observer.on_progress(message.uuid, message.downloaded, Some(message.total));
+            }
+        };
+        let (result, ()) = futures::join!(download, forward);
+        result
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content.id))
+    )]
+    pub async fn download_file_with_options(
+        &self,
+        content: &Content,
+        dest: impl AsRef<Path>,
+        tx: UnboundedSender<DownloadedMessage>,
+        options: DownloadOptions,
+    ) -> Result<PathBuf, Error> {
+        self.download_one(content, dest.as_ref(), tx, options)
+            .await
+            .map(|(path, _)| path)
+    }
+
+    // Every authorized download of a single file goes through here so use_direct_link is
+    // honored the same way by the file, folder and url downloads
+    async fn download_one(
+        &self,
+        content: &Content,
+        dest: &Path,
+        tx: UnboundedSender<DownloadedMessage>,
+        options: DownloadOptions,
+    ) -> Result<(PathBuf, bool), Error> {
+        if !options.use_direct_link {
+            return download_file_impl(
+                &self.client,
+                Some(self.token.as_str()),
+                content,
+                dest,
+                tx,
+                options,
+            )
+            .await;
+        }
+
+        let (content, created) = self.with_direct_link(content).await?;
+        let result = download_file_impl(
+            &self.client,
+            Some(self.token.as_str()),
+            &content,
+            dest,
+            tx,
+            options,
+        )
+        .await;
+        if let Some(direct_link_id) = created {
+            self.delete_temporary_direct_link(content.id, direct_link_id)
+                .await;
+        }
+        result
+    }
+
+    // The download itself went through, a direct link left behind only expires later so a
+    // failure is only logged
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    async fn delete_temporary_direct_link(&self, content_id: ContentId, direct_link_id: Uuid) {
+        let _ = self
+            .delete_direct_link(content_id, direct_link_id)
+            .await
+            .inspect_err(|err| {
+                event!(
+                    warn,
+                    %direct_link_id,
+                    error = %err,
+                    "couldn't delete the temporary direct link"
+                )
+            });
+    }
+
+    // Returns the content with its link replaced by an active direct link, and the id of the
+    // direct link if it was created here and has to be cleaned up
+    async fn with_direct_link(&self, content: &Content) -> Result<(Content, Option<Uuid>), Error> {
+        let ContentKind::File { direct_links, .. } = &content.kind else {
+            return Err(Error::NotAFile(content.id));
+        };
+
+        let now = Utc::now();
+        let active = direct_links
+            .iter()
+            .flat_map(|direct_links| direct_links.values())
+            .find(|direct_link| direct_link.expire_time.is_none_or(|expire| now < expire));
+        let (direct_link, created) = match active {
+            Some(direct_link) => (direct_link.link.clone(), None),
+            None => match self.create_direct_link(content.id).await {
+                Ok(direct_link) => (direct_link.link, Some(direct_link.id)),
+                // other tiers and a server refusing it fall back to the normal link
+                Err(Error::NotPremium(_)) => return Ok((content.clone(), None)),
+                Err(err) => return Err(err),
+            },
+        };
+
+        let mut content = content.clone();
+        if let ContentKind::File { link, .. } = &mut content.kind {
+            *link = Some(direct_link);
+        }
+        Ok((content, created))
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content.id))
+    )]
+    pub async fn download_to_writer(
+        &self,
+        content: &Content,
+        writer: &mut (impl AsyncWrite + Unpin),
+        tx: Option<UnboundedSender<DownloadedMessage>>,
+    ) -> Result<u64, Error> {
+        let res = request_file(&self.client, Some(self.token.as_str()), content).await?;
+        let body = self.client.body_stream(res);
+        write_body(content, body, writer, 0, tx.as_ref(), None).await
+    }
+
+    // Downloads into <name>.part and keeps it when the transfer fails, calling this again
+    // resumes where it stopped
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content.id))
+    )]
+    pub async fn download_file_resumable(
+        &self,
+        content: &Content,
+        dest: impl AsRef<Path>,
+        tx: UnboundedSender<DownloadedMessage>,
+    ) -> Result<PathBuf, Error> {
+        let options = DownloadOptions {
+            keep_partial: true,
+            ..Default::default()
+        };
+        self.download_one(content, dest.as_ref(), tx, options)
+            .await
+            .map(|(path, _)| path)
+    }
+
+    // Fetches the file in `segments` ranges at once. Written like download_file_with_options
+    // writes, into a part file unless options.atomic is off, but a leftover part file is
+    // started over instead of resumed.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content.id))
+    )]
+    pub async fn download_file_segmented(
+        &self,
+        content: &Content,
+        dest: impl AsRef<Path>,
+        segments: usize,
+        tx: UnboundedSender<DownloadedMessage>,
+        options: DownloadOptions,
+    ) -> Result<PathBuf, Error> {
+        if !options.use_direct_link {
+            return self
+                .download_segmented(content, dest.as_ref(), segments, tx, options)
+                .await;
+        }
+
+        // every segment goes through the same direct link
+        let (content, created) = self.with_direct_link(content).await?;
+        let options = DownloadOptions {
+            use_direct_link: false,
+            ..options
+        };
+        let result = self
+            .download_segmented(&content, dest.as_ref(), segments, tx, options)
+            .await;
+        if let Some(direct_link_id) = created {
+            self.delete_temporary_direct_link(content.id, direct_link_id)
+                .await;
+        }
+        result
+    }
+
+    async fn download_segmented(
+        &self,
+        content: &Content,
+        dest: &Path,
+        segments: usize,
+        tx: UnboundedSender<DownloadedMessage>,
+        options: DownloadOptions,
+    ) -> Result<PathBuf, Error> {
+        let ContentKind::File { size, md5, .. } = &content.kind else {
+            return Err(Error::NotAFile(content.id));
+        };
+
+        let path = dest_path(content, dest).await?;
+        let segment_size = size.div_ceil(segments.max(1) as u64).max(1);
+        let ranges: Vec<(u64, u64)> = (0..*size)
+            .step_by(segment_size as usize)
+            .map(|start| (start, (start + segment_size).min(*size) - 1))
+            .collect();
+        if ranges.len() < 2 {
+            return self
+                .download_one(content, &path, tx, options)
+                .await
+                .map(|(path, _)| path);
+        }
+        if existing_counts_as_downloaded(content, &path, options.collision_policy).await? {
+            return Ok(path);
+        }
+
+        // the first segment doubles as a check that the server honors ranges
+        let (start, end) = ranges[0];
+        let res = request_file_range(
+            &self.client,
+            Some(self.token.as_str()),
+            content,
+            start,
+            Some(end),
+        )
+        .await?;
+        let mut target = open_target(&path, &options).await?;
+        let result = if res.status() != StatusCode::PARTIAL_CONTENT {
+            let mut hasher = options.verify_md5.then(Md5Hasher::new);
+            let write_path = target.path().to_path_buf();
+            let body = self.client.body_stream(res);
+            write_body(content, body, target.file(), 0, Some(&tx), hasher.as_mut())
+                .await
+                .map_err(|err| match err {
+                    Error::CouldntWrite(err) => Error::CouldntWriteFile(write_path.clone(), err),
+                    err => err,
+                })
+                .and_then(|_| match hasher {
+                    Some(hasher) => check_md5(md5, Md5(hasher.finalize().into()), &write_path),
+                    None => Ok(()),
+                })
+        } else {
+            match self
+                .download_segments(content, &mut target, ranges, res, &tx)
+                .await
+            {
+                Ok(()) if options.verify_md5 => verify_md5(&mut target, md5).await,
+                result => result,
+            }
+        };
+        finish_target(target, result, path, &options).await
+    }
+
+    async fn download_segments(
+        &self,
+        content: &Content,
+        target: &mut Target,
+        ranges: Vec<(u64, u64)>,
+        first: Response,
+        tx: &UnboundedSender<DownloadedMessage>,
+    ) -> Result<(), Error> {
+        let path = target.path().to_path_buf();
+        let total = ranges.last().map_or(0, |(_, end)| end + 1);
+        let write_error = |err: std::io::Error| Error::CouldntWriteFile(path.clone(), err);
+        target.file().set_len(total).await.map_err(write_error)?;
+        // the segments share one handle, a part file's lock only covers its own handle
+        let file = target.file().try_clone().await.map_err(write_error)?;
+        let file = Arc::new(Mutex::new(file));
+
+        let (progress_tx, mut progress_rx) = unbounded_channel();
+        let mut first = Some(first);
+        let mut tasks = JoinSet::new();
+        for (start, end) in ranges {
+            let api = self.clone();
+            let content = content.clone();
+            let path = path.clone();
+            let file = file.clone();
+            let res = first.take();
+            let progress_tx = progress_tx.clone();
+            tasks.spawn(async move {
+                let res = match res {
+                    Some(res) => res,
+                    None => {
+                        request_file_range(
+                            &api.client,
+                            Some(api.token.as_str()),
+                            &content,
+                            start,
+                            Some(end),
+                        )
+                        .await?
+                    }
+                };
+                check_segment(&res, start, end)?;
+                write_segment(
+                    api.client.body_stream(res),
+                    &file,
+                    &path,
+                    start,
+                    progress_tx,
+                )
+                .await
+            });
+        }
+        drop(progress_tx);
+
+        let mut downloaded = 0;
+        let mut send_progress = |n| {
+            downloaded += n;
+            let _ = tx.send(DownloadedMessage {
+                uuid: *content.id,
+                downloaded,
+                total,
+            });
+        };
+        loop {
+            tokio::select! {
+                Some(n) = progress_rx.recv() => send_progress(n),
+                joined = tasks.join_next() => {
+                    let result = match joined {
+                        None => break,
+                        Some(Ok(result)) => result,
+                        Some(Err(err)) => Err(Error::TaskFailed(err)),
+                    };
+                    if let Err(err) = result {
+                        // the other segments would keep writing into the file
+                        tasks.shutdown().await;
+                        return Err(err);
+                    }
+                }
+            }
+        }
+        while let Ok(n) = progress_rx.try_recv() {
+            send_progress(n);
+        }
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(folder_id = %folder_id)))]
+    pub async fn download_folder(
+        &self,
+        folder_id: FolderId,
+        dest_dir: impl AsRef<Path>,
+        options: FolderDownloadOptions,
+        tx: Option<UnboundedSender<FolderDownloadMessage>>,
+    ) -> Result<FolderDownloadReport, Error> {
+        let mut report = FolderDownloadReport::default();
+        let files = self
+            .enumerate_folder(folder_id, dest_dir.as_ref(), &options, &mut report)
+            .await?;
+
+        let files_total = files.len();
+        let bytes_total = files.iter().map(|(content, _)| file_size(content)).sum();
+        let files_done = &AtomicUsize::new(0);
+        let bytes_done = &AtomicU64::new(0);
+        let tx = &tx;
+        let send_total = move || {
+            if let Some(tx) = tx {
+                let _ = tx.send(FolderDownloadMessage::Total {
+                    files_done: files_done.load(Ordering::Relaxed),
+                    files_total,
+                    bytes_done: bytes_done.load(Ordering::Relaxed),
+                    bytes_total,
+                });
+            }
+        };
+        send_total();
+
+        let download_options = &options.download;
+        let results = stream::iter(files)
+            .map(|(content, path)| async move {
+                let (file_tx, mut file_rx) = unbounded_channel();
+                let download =
+                    self.download_one(&content, &path, file_tx, download_options.clone());
+                let forward = async {
+                    let mut last = 0;
+                    while let Some(message) = file_rx.recv().await {
+                        bytes_done.fetch_add(message.downloaded - last, Ordering::Relaxed);
+                        last = message.downloaded;
+                        if let Some(tx) = tx {
+                            let _ = tx.send(FolderDownloadMessage::File(message));
+                        }
+                        send_total();
+                    }
+                };
+                let (result, ()) = futures::join!(download, forward);
+                if let Ok((_, false)) = result {
+                    bytes_done.fetch_add(file_size(&content), Ordering::Relaxed);
+                }
+                files_done.fetch_add(1, Ordering::Relaxed);
+                send_total();
+                match result {
+                    Ok((path, downloaded)) => (path, Ok(downloaded)),
+                    Err(err) => (path, Err(err)),
+                }
+            })
+            .buffer_unordered(options.concurrency.max(1))
+            .collect::<Vec<_>>()
+            .await;
+
+        for (path, result) in results {
+            match result {
+                Ok(true) => report.downloaded.push(path),
+                Ok(false) => report.skipped.push(path),
+                Err(err) => report.failed.push((path, err)),
+            }
+        }
+        report.downloaded.sort();
+        report.skipped.sort();
+        Ok(report)
+    }
+
+    // Lists the folder tree and creates the local directories, subfolders that can't be
+    // listed end up in the report instead of failing the whole download
+    async fn enumerate_folder(
+        &self,
+        folder_id: FolderId,
+        dest_dir: &Path,
+        options: &FolderDownloadOptions,
+        report: &mut FolderDownloadReport,
+    ) -> Result<Vec<(Content, PathBuf)>, Error> {
+        let password = options.password.as_deref();
+        let root = self.get_folder(folder_id.into(), password).await?;
+        if !matches!(root.kind, ContentKind::Folder { .. }) {
+            return Err(Error::NotAFolder(folder_id.into()));
+        }
+
+        let mut files = Vec::new();
+        let mut pending = vec![(root, dest_dir.to_path_buf())];
+        while let Some((folder, dir)) = pending.pop() {
+            let ContentKind::Folder { children, .. } = folder.kind else {
+                continue;
+            };
+            if let Err(err) = fs::create_dir_all(&dir).await {
+                let err = Error::CouldntWriteFile(dir.clone(), err);
+                report.failed.push((dir, err));
+                continue;
+            }
+
+            for child in children.unwrap_or_default().into_values() {
+                let path = match checked_name(&child.name) {
+                    Ok(name) => dir.join(name),
+                    // reported against the folder, the name can't be joined
+                    Err(err) => {
+                        report.failed.push((dir.clone(), err));
+                        continue;
+                    }
+                };
+                match child.kind {
+                    ContentKind::Folder { .. } => match self.get_folder(child.id, password).await {
+                        Ok(folder) => pending.push((folder, path)),
+                        Err(err) => report.failed.push((path, err)),
+                    },
+                    ContentKind::File { .. } => {
+                        if options
+                            .filter
+                            .as_ref()
+                            .is_none_or(|filter| filter(&child.name))
+                        {
+                            files.push((child, path));
+                        }
+                    }
+                    ContentKind::Unknown { .. } => {}
+                }
+            }
+        }
+        files.sort_by(|a, b| a.1.cmp(&b.1));
+        Ok(files)
+    }
+
+    async fn get_folder(
+        &self,
+        content_id: ContentId,
+        password: Option<&str>,
+    ) -> Result<Content, Error> {
+        match password {
+            Some(password) => {
+                self.get_content_with_password(content_id.to_string(), password)
+                    .await
+            }
+            None => self.get_content_by_id(content_id).await,
+        }
+    }
+
+    // A folder is downloaded like download_folder does, its report has every file that failed
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(url = %url)))]
+    pub async fn download_from_url(
+        &self,
+        url: &Url,
+        dest_dir: &Path,
+        options: UrlDownloadOptions,
+    ) -> Result<FolderDownloadReport, Error> {
+        let code = Api::code_from_content_url(url)?;
+        let content = match &options.password {
+            Some(password) => self.get_content_with_password(&code, password).await?,
+            None => self.get_content_by_code(&code).await?,
+        };
+
+        match content.kind {
+            ContentKind::File { .. } => {
+                let (tx, _rx) = unbounded_channel();
+                let (path, downloaded) = self
+                    .download_one(&content, dest_dir, tx, options.download)
+                    .await?;
+                let mut report = FolderDownloadReport::default();
+                if downloaded {
+                    report.downloaded.push(path);
+                } else {
+                    report.skipped.push(path);
+                }
+                Ok(report)
+            }
+            ContentKind::Folder { .. } if options.allow_folder => {
+                let folder_options = FolderDownloadOptions {
+                    download: options.download,
+                    password: options.password,
+                    ..Default::default()
+                };
+                self.download_folder(FolderId(content.id.0), dest_dir, folder_options, None)
+                    .await
+            }
+            ContentKind::Folder { .. } | ContentKind::Unknown { .. } => {
+                Err(Error::NotAFile(content.id))
+            }
+        }
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content.id))
+    )]
+    pub async fn probe_file(&self, content: &Content) -> Result<FileProbe, Error> {
+        let ContentKind::File { size, .. } = &content.kind else {
+            return Err(Error::NotAFile(content.id));
+        };
+        let link = content
+            .kind
+            .best_link()
+            .ok_or(Error::MissingLink(content.id))?;
+
+        let req = self
+            .client
+            .api_request(Method::HEAD, link.clone())
+            .header(COOKIE, format!("accountToken={}", self.token.as_str()))
+            // the length of the stored file, not of a compressed response
+            .header(ACCEPT_ENCODING, "identity");
+        let res = self.client.send(req).await?;
+        let (res, content_length, accepts_ranges) = if res.status().is_success() {
+            let accepts_ranges = header_str(&res, ACCEPT_RANGES).is_some_and(|v| v == "bytes");
+            let content_length = header_str(&res, CONTENT_LENGTH).and_then(|v| v.parse().ok());
+            (res, content_length, accepts_ranges)
+        } else {
+            // some storage servers reject HEAD, a one byte range tells the same
+            let res =
+                request_file_range(&self.client, Some(self.token.as_str()), content, 0, Some(0))
+                    .await?;
+            if res.status() == StatusCode::PARTIAL_CONTENT {
+                let content_length = header_str(&res, CONTENT_RANGE)
+                    .and_then(|v| v.rsplit_once('/'))
+                    .and_then(|(_, total)| total.parse().ok());
+                (res, content_length, true)
+            } else {
+                let content_length = res.content_length();
+                (res, content_length, false)
+            }
+        };
+
+        let content_type = header_str(&res, CONTENT_TYPE).map(String::from);
+        Ok(FileProbe {
+            content_length,
+            content_type,
+            accepts_ranges,
+            size_mismatch: content_length.is_some_and(|length| length != *size),
+        })
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content.id))
+    )]
+    pub async fn download_stream(
+        &self,
+        content: &Content,
+    ) -> Result<impl Stream<Item = Result<Bytes, Error>> + Send, Error> {
+        let res = request_file(&self.client, Some(self.token.as_str()), content).await?;
+        Ok(self.client.body_stream(res))
+    }
+}
+
+fn header_str(res: &Response, name: reqwest::header::HeaderName) -> Option<&str> {
+    res.headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+}
+
+pub(crate) fn file_size(content: &Content) -> u64 {
+    match &content.kind {
+        ContentKind::File { size, .. } => *size,
+        ContentKind::Folder { .. } | ContentKind::Unknown { .. } => 0,
+    }
+}
+
+async fn dest_path(content: &Content, dest: &Path) -> Result<PathBuf, Error> {
+    if fs::metadata(dest).await.is_ok_and(|m| m.is_dir()) {
+        Ok(dest.join(checked_name(&content.name)?))
+    } else {
+        Ok(dest.to_path_buf())
+    }
+}
+
+pub(crate) async fn same_file(content: &Content, path: &Path) -> Result<bool, Error> {
+    let ContentKind::File { size, md5, .. } = &content.kind else {
+        return Err(Error::NotAFile(content.id));
+    };
+    match fs::metadata(path).await {
+        Ok(metadata) if metadata.len() == *size => {}
+        _ => return Ok(false),
+    }
+    // without an md5 the file can't be told apart from another one of the same size
+    let Some(md5) = md5 else {
+        return Ok(false);
+    };
+    let mut hasher = Md5Hasher::new();
+    hash_file(path, &mut hasher).await?;
+    Ok(*md5 == <[u8; 16]>::from(hasher.finalize()))
+}
+
+fn numbered_path(path: &Path, n: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{} ({}).{}", stem, n, ext.to_string_lossy()),
+        None => format!("{} ({})", stem, n),
+    };
+    path.with_file_name(name)
+}
+
+// `name (n).ext` candidates tried before giving up with FileExists
+const MAX_RENAMES: usize = 1000;
+
+// create_new reserves the name so concurrent downloads into the same directory can't clobber
+// each other. Opened for reading too, a segmented download reads the file back to verify it.
+async fn open_dest(path: PathBuf, policy: CollisionPolicy) -> Result<(File, PathBuf), Error> {
+    let open_error = |path: PathBuf, err: std::io::Error| match err.kind() {
+        std::io::ErrorKind::AlreadyExists => Error::FileExists(path),
+        _ => Error::CouldntOpenFile(path, err),
+    };
+
+    match policy {
+        CollisionPolicy::Overwrite | CollisionPolicy::Skip => {
+            match OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(&path)
+                .await
+            {
+                Ok(file) => Ok((file, path)),
+                Err(err) => Err(open_error(path, err)),
+            }
+        }
+        CollisionPolicy::Error => {
+            match OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create_new(true)
+                .open(&path)
+                .await
+            {
+                Ok(file) => Ok((file, path)),
+                Err(err) => Err(open_error(path, err)),
+            }
+        }
+        CollisionPolicy::Rename => {
+            for n in 0..=MAX_RENAMES {
+                let candidate = match n {
+                    0 => path.clone(),
+                    n => numbered_path(&path, n),
+                };
+                match OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .create_new(true)
+                    .open(&candidate)
+                    .await
+                {
+                    Ok(file) => return Ok((file, candidate)),
+                    Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {}
+                    Err(err) => return Err(open_error(candidate, err)),
+                }
+            }
+            Err(Error::FileExists(path))
+        }
+    }
+}
+
+pub(crate) async fn hash_file(path: &Path, hasher: &mut Md5Hasher) -> Result<(), Error> {
+    let read_error = |err: std::io::Error| Error::CouldntOpenFile(path.into(), err);
+
+    let mut file = File::open(path).await.map_err(read_error)?;
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf).await.map_err(read_error)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(())
+}
+
+// Hashes the first len bytes through the open file and leaves it right after them
+async fn hash_prefix(file: &mut File, len: u64, hasher: &mut Md5Hasher) -> std::io::Result<()> {
+    file.rewind().await?;
+    let mut reader = (&mut *file).take(len);
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(())
+}
+
+// Passes when the listing had no md5 to compare against
+fn check_md5(expected: &Option<Md5>, actual: Md5, path: &Path) -> Result<(), Error> {
+    let Some(expected) = expected else {
+        return Ok(());
+    };
+    if actual != *expected {
+        return Err(Error::ChecksumMismatch {
+            expected: expected.to_string(),
+            actual: actual.to_string(),
+            path: path.into(),
+        });
+    }
+    Ok(())
+}
+
+// Segments arrive out of order so the file has to be read back once complete
+async fn verify_md5(target: &mut Target, expected: &Option<Md5>) -> Result<(), Error> {
+    if expected.is_none() {
+        return Ok(());
+    }
+    let mut hasher = Md5Hasher::new();
+    hash_prefix(target.file(), u64::MAX, &mut hasher)
+        .await
+        .map_err(|err| Error::CouldntOpenFile(target.path().into(), err))?;
+    check_md5(expected, Md5(hasher.finalize().into()), target.path())
+}
+
+// The first byte of a 206 answer, from a Content-Range like "bytes 5-11/12"
+fn range_start(res: &Response) -> Option<u64> {
+    let range = res.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    let (start, _) = range.strip_prefix("bytes ")?.split_once('-')?;
+    start.parse().ok()
+}
+
+async fn request_file(
+    client: &ApiClient,
+    token: Option<&str>,
+    content: &Content,
+) -> Result<Response, Error> {
+    request_file_range(client, token, content, 0, None).await
+}
+
+// Asks for the file from offset on. A range starting anywhere else can't be appended, the
+// whole file is asked for then.
+async fn request_file_from(
+    client: &ApiClient,
+    token: Option<&str>,
+    content: &Content,
+    offset: u64,
+) -> Result<Response, Error> {
+    let res = request_file_range(client, token, content, offset, None).await?;
+    if res.status() == StatusCode::PARTIAL_CONTENT && range_start(&res) != Some(offset) {
+        return request_file(client, token, content).await;
+    }
+    Ok(res)
+}
+
+// The response is either 200 with the whole file or 206 with the requested range
+async fn request_file_range(
+    client: &ApiClient,
+    token: Option<&str>,
+    content: &Content,
+    start: u64,
+    end: Option<u64>,
+) -> Result<Response, Error> {
+    let ContentKind::File { mimetype, .. } = &content.kind else {
+        return Err(Error::NotAFile(content.id));
+    };
+    let link = content
+        .kind
+        .best_link()
+        .ok_or(Error::MissingLink(content.id))?;
+
+    let mut req = client.transfer_request(Method::GET, link.clone());
+    if let Some(token) = token {
+        // storage servers only serve non-public files with the account cookie
+        req = req.header(COOKIE, format!("accountToken={}", token));
+    }
+    let ranged = start > 0 || end.is_some();
+    if ranged {
+        let end = end.map(|end| end.to_string()).unwrap_or_default();
+        req = req.header(RANGE, format!("bytes={}-{}", start, end));
+    }
+    let res = client.send(req).await?;
+
+    if let Some(location) = redirect_location(&res) {
+        return Err(Error::RedirectBlocked(location));
+    }
+    let status = res.status();
+    if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+        return Err(Error::DownloadForbidden(res.url().clone()));
+    }
+    if status != StatusCode::OK && !(ranged && status == StatusCode::PARTIAL_CONTENT) {
+        let url = res.url().clone();
+        return Err(Error::HttpStatusCodeError(
+            url,
+            status,
+            ErrorContext::read(res).await,
+        ));
+    }
+
+    // gofile answers with an html error page instead of a status code
+    let is_html = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/html"));
+    if is_html && mimetype.essence_str() != "text/html" {
+        return Err(Error::DownloadForbidden(res.url().clone()));
+    }
+
+    Ok(res)
+}
+
+pub(crate) async fn download_file_impl(
+    client: &ApiClient,
+    token: Option<&str>,
+    content: &Content,
+    dest: &Path,
+    tx: UnboundedSender<DownloadedMessage>,
+    options: DownloadOptions,
+) -> Result<(PathBuf, bool), Error> {
+    let ContentKind::File { size, md5, .. } = &content.kind else {
+        return Err(Error::NotAFile(content.id));
+    };
+    let path = dest_path(content, dest).await?;
+
+    // checked before requesting so existing files don't cost a download
+    if existing_counts_as_downloaded(content, &path, options.collision_policy).await? {
+        return Ok((path, false));
+    }
+
+    let mut part = match options.atomic {
+        true => Some(claim_part(&path).await?),
+        false => None,
+    };
+    let offset = part.as_ref().map_or(0, |part| part.resumable_len(*size));
+    let res = match request_file_from(client, token, content, offset).await {
+        Ok(res) => res,
+        Err(err) => {
+            // nothing was written, only a leftover worth resuming is kept
+            if let Some(part) = part.filter(|_| offset == 0) {
+                part.remove().await;
+            }
+            return Err(err);
+        }
+    };
+    let resumed = offset > 0 && res.status() == StatusCode::PARTIAL_CONTENT;
+
+    let mut hasher = options.verify_md5.then(Md5Hasher::new);
+    let mut target = match part.take() {
+        Some(mut part) => match part
+            .prepare(resumed.then_some(offset), hasher.as_mut())
+            .await
+        {
+            Ok(()) => Target::Part(part),
+            Err(err) => {
+                return finish_target(Target::Part(part), Err(err), path, &options)
+                    .await
+                    .map(|path| (path, true))
+            }
+        },
+        None => {
+            let (file, path) = open_dest(path.clone(), options.collision_policy).await?;
+            Target::Dest(file, path)
+        }
+    };
+    let offset = if resumed { offset } else { 0 };
+    let write_path = target.path().to_path_buf();
+    let body = client.body_stream(res);
+    let result = write_body(
+        content,
+        body,
+        target.file(),
+        offset,
+        Some(&tx),
+        hasher.as_mut(),
+    )
+    .await
+    .map_err(|err| match err {
+        Error::CouldntWrite(err) => Error::CouldntWriteFile(write_path, err),
+        err => err,
+    });
+    let result = match (result, hasher) {
+        (Ok(_), Some(hasher)) => check_md5(md5, Md5(hasher.finalize().into()), target.path()),
+        (result, _) => result.map(|_| ()),
+    };
+    finish_target(target, result, path, &options)
+        .await
+        .map(|path| (path, true))
+}
+
+// Whether a file already at path stands in for the download, Error fails on any file there
+async fn existing_counts_as_downloaded(
+    content: &Content,
+    path: &Path,
+    policy: CollisionPolicy,
+) -> Result<bool, Error> {
+    if !fs::try_exists(path).await.unwrap_or(false) {
+        return Ok(false);
+    }
+    match policy {
+        CollisionPolicy::Skip => same_file(content, path).await,
+        CollisionPolicy::Error => Err(Error::FileExists(path.into())),
+        CollisionPolicy::Overwrite | CollisionPolicy::Rename => Ok(false),
+    }
+}
+
+// Opens what a download writes into from the start, a part file unless options.atomic is off
+async fn open_target(path: &Path, options: &DownloadOptions) -> Result<Target, Error> {
+    if !options.atomic {
+        let (file, path) = open_dest(path.into(), options.collision_policy).await?;
+        return Ok(Target::Dest(file, path));
+    }
+    let mut part = claim_part(path).await?;
+    match part.prepare(None, None).await {
+        Ok(()) => Ok(Target::Part(part)),
+        Err(err) => {
+            part.remove().await;
+            Err(err)
+        }
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    path.into()
+}
+
+// Part files looked at for one name, concurrent downloads of it each hold one of them
+const MAX_PART_FILES: usize = 32;
+
+fn part_path(path: &Path, n: usize) -> PathBuf {
+    match n {
+        0 => with_suffix(path, ".part"),
+        n => with_suffix(path, &format!(".{}.part", n)),
+    }
+}
+
+// A part file locked by this download. The lock goes away with the handle, so the part file of
+// a download that crashed or was killed is free for the next one to claim.
+struct PartFile {
+    file: File,
+    path: PathBuf,
+    len: u64,
+}
+
+impl PartFile {
+    // A leftover shorter than the listing can be resumed, anything else is started over
+    fn resumable_len(&self, size: u64) -> u64 {
+        match self.len < size {
+            true => self.len,
+            false => 0,
+        }
+    }
+
+    // Hashes the resumed bytes and moves to their end, or empties the file to start over
+    async fn prepare(
+        &mut self,
+        resume_at: Option<u64>,
+        hasher: Option<&mut Md5Hasher>,
+    ) -> Result<(), Error> {
+        let write_error = |err: std::io::Error| Error::CouldntWriteFile(self.path.clone(), err);
+        match (resume_at, hasher) {
+            (Some(offset), Some(hasher)) => hash_prefix(&mut self.file, offset, hasher)
+                .await
+                .map_err(|err| Error::CouldntOpenFile(self.path.clone(), err)),
+            (Some(offset), None) => self
+                .file
+                .seek(SeekFrom::Start(offset))
+                .await
+                .map(|_| ())
+                .map_err(write_error),
+            (None, _) => {
+                self.file.set_len(0).await.map_err(write_error)?;
+                self.file.rewind().await.map(|_| ()).map_err(write_error)
+            }
+        }
+    }
+
+    // Still holding the lock, so nobody claims the file in between
+    async fn remove(self) {
+        let _ = fs::remove_file(&self.path).await;
+    }
+}
+
+// Takes the first part file no other download holds, leftovers before new ones, so a part file
+// left behind is either resumed or started over by the next download of the same name
+async fn claim_part(path: &Path) -> Result<PartFile, Error> {
+    let path = path.to_path_buf();
+    let (file, path, len) = tokio::task::spawn_blocking(move || {
+        for create in [false, true] {
+            for n in 0..MAX_PART_FILES {
+                if let Some(claimed) = try_claim_part(part_path(&path, n), create)? {
+                    return Ok(claimed);
+                }
+            }
+        }
+        Err(Error::FileExists(part_path(&path, 0)))
+    })
+    .await
+    .map_err(Error::TaskFailed)??;
+    Ok(PartFile {
+        file: File::from_std(file),
+        path,
+        len,
+    })
+}
+
+fn try_claim_part(
+    part: PathBuf,
+    create: bool,
+) -> Result<Option<(std::fs::File, PathBuf, u64)>, Error> {
+    loop {
+        let file = match std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(create)
+            .truncate(false)
+            .open(&part)
+        {
+            Ok(file) => file,
+            Err(err) if !create && err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(Error::CouldntOpenFile(part, err)),
+        };
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(std::fs::TryLockError::WouldBlock) => return Ok(None),
+            Err(std::fs::TryLockError::Error(err)) => {
+                return Err(Error::CouldntOpenFile(part, err))
+            }
+        }
+        // the download that held it may have renamed or removed it before letting go, the name
+        // is tried again then
+        match still_named(&file, &part) {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(err) => return Err(Error::CouldntOpenFile(part, err)),
+        }
+        let len = file
+            .metadata()
+            .map_err(|err| Error::CouldntOpenFile(part.clone(), err))?
+            .len();
+        return Ok(Some((file, part, len)));
+    }
+}
+
+#[cfg(unix)]
+fn still_named(file: &std::fs::File, path: &Path) -> std::io::Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+
+    let opened = file.metadata()?;
+    match std::fs::metadata(path) {
+        Ok(named) => Ok(opened.dev() == named.dev() && opened.ino() == named.ino()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+// Without inode numbers the name agreeing on length and modification time has to do
+#[cfg(not(unix))]
+fn still_named(file: &std::fs::File, path: &Path) -> std::io::Result<bool> {
+    let opened = file.metadata()?;
+    match std::fs::metadata(path) {
+        Ok(named) => Ok(opened.len() == named.len() && opened.modified()? == named.modified()?),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+// Where a download writes, the destination itself or a part file moved there once verified
+enum Target {
+    Dest(File, PathBuf),
+    Part(PartFile),
+}
+
+impl Target {
+    fn file(&mut self) -> &mut File {
+        match self {
+            Target::Dest(file, _) => file,
+            Target::Part(part) => &mut part.file,
+        }
+    }
+
+    fn path(&self) -> &Path {
+        match self {
+            Target::Dest(_, path) => path,
+            Target::Part(part) => &part.path,
+        }
+    }
+}
+
+// Moves a verified part file into place. After a failure the written file is removed, or with
+// keep_partial kept: a corrupt one as <name>.corrupt, a part file where the next download
+// resumes it. The part file stays locked until it's renamed or removed.
+async fn finish_target(
+    target: Target,
+    result: Result<(), Error>,
+    path: PathBuf,
+    options: &DownloadOptions,
+) -> Result<PathBuf, Error> {
+    let result = match (&target, result) {
+        (Target::Part(part), Ok(())) => {
+            finish_part(&part.path, path.clone(), options.collision_policy).await
+        }
+        (Target::Dest(_, path), Ok(())) => Ok(path.clone()),
+        (_, Err(err)) => Err(err),
+    };
+    match result {
+        Ok(path) => Ok(path),
+        Err(Error::ChecksumMismatch {
+            expected, actual, ..
+        }) if options.keep_partial => {
+            let corrupt = match &target {
+                Target::Part(_) => with_suffix(&path, ".corrupt"),
+                Target::Dest(_, path) => with_suffix(path, ".corrupt"),
+            };
+            let _ = fs::rename(target.path(), &corrupt).await;
+            Err(Error::ChecksumMismatch {
+                expected,
+                actual,
+                path: corrupt,
+            })
+        }
+        Err(err) => {
+            if !options.keep_partial {
+                let _ = fs::remove_file(target.path()).await;
+            }
+            Err(err)
+        }
+    }
+}
+
+async fn finish_part(
+    part: &Path,
+    path: PathBuf,
+    policy: CollisionPolicy,
+) -> Result<PathBuf, Error> {
+    let path = match policy {
+        CollisionPolicy::Overwrite | CollisionPolicy::Skip => path,
+        // reserve the final name, the rename then replaces the empty file
+        CollisionPolicy::Error | CollisionPolicy::Rename => open_dest(path, policy).await?.1,
+    };
+    match fs::rename(part, &path).await {
+        Ok(()) => Ok(path),
+        Err(err) => Err(Error::CouldntWriteFile(path, err)),
+    }
+}
+
+// A server or proxy ignoring the range answers 200 with the whole file, which written at the
+// segment's offset corrupts the file
+fn check_segment(res: &Response, start: u64, end: u64) -> Result<(), Error> {
+    let range = res
+        .headers()
+        .get(CONTENT_RANGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("bytes "))
+        .and_then(|value| value.split_once('/'))
+        .map(|(range, _)| range);
+    if res.status() != StatusCode::PARTIAL_CONTENT
+        || range != Some(format!("{}-{}", start, end).as_str())
+    {
+        return Err(Error::RangeIgnored(res.url().clone(), start, end));
+    }
+    Ok(())
+}
+
+async fn write_segment(
+    mut body: impl Stream<Item = Result<Bytes, Error>> + Unpin,
+    file: &Mutex<File>,
+    path: &Path,
+    start: u64,
+    progress: UnboundedSender<u64>,
+) -> Result<(), Error> {
+    let write_error = |err: std::io::Error| Error::CouldntWriteFile(path.into(), err);
+
+    let mut offset = start;
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk?;
+        // seek and write under one lock, the other segments move the same handle
+        let mut file = file.lock().await;
+        file.seek(SeekFrom::Start(offset))
+            .await
+            .map_err(write_error)?;
+        file.write_all(&chunk).await.map_err(write_error)?;
+        file.flush().await.map_err(write_error)?;
+        drop(file);
+        offset += chunk.len() as u64;
+        let _ = progress.send(chunk.len() as u64);
+    }
+    Ok(())
+}
+
+async fn write_body(
+    content: &Content,
+    mut body: impl Stream<Item = Result<Bytes, Error>> + Unpin,
+    writer: &mut (impl AsyncWrite + Unpin),
+    offset: u64,
+    tx: Option<&UnboundedSender<DownloadedMessage>>,
+    mut hasher: Option<&mut Md5Hasher>,
+) -> Result<u64, Error> {
+    let write_error = |err: std::io::Error| Error::CouldntWrite(err);
+    let total = file_size(content);
+
+    // progress starts at the resumed offset, the return value only counts new bytes
+    let mut downloaded = offset;
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk?;
+        writer.write_all(&chunk).await.map_err(write_error)?;
+        if let Some(hasher) = hasher.as_mut() {
+            hasher.update(&chunk);
+        }
+        downloaded += chunk.len() as u64;
+        if let Some(tx) = tx {
+            let _ = tx.send(DownloadedMessage {
+                uuid: *content.id,
+                downloaded,
+                total,
+            });
+        }
+    }
+    writer.flush().await.map_err(write_error)?;
+    Ok(downloaded - offset)
+}
+
+// A name gofile sent, used as a local file or folder name. Names like "..", "a/b" or "/etc"
+// would put the file outside the destination.
+pub(crate) fn checked_name(name: &str) -> Result<&str, Error> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(name),
+        _ => Err(Error::InvalidName(
+            name.into(),
+            "The name must be a plain file name, not a path.".into(),
+        )),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use futures::TryStreamExt;
+    use mockito::{Matcher, Server};
+    use serde_json::json;
+    use tokio::sync::mpsc::unbounded_channel;
+    use uuid::uuid;
+
+    fn file_content(base_url: &str, name: &str, size: u64) -> Content {
+        serde_json::from_value(json!({
+            "id": "00000000-0000-0000-0000-000000000001",
+            "type": "file",
+            "name": name,
+            "createTime": 1710264451,
+            "size": size,
+            "downloadCount": 0,
+            "md5": "c7dfde837b22280147a8cc2d9cb4d8a4",
+            "mimetype": "text/plain",
+            "serverSelected": "store1",
+            "link": format!("{}/download/web/00000000-0000-0000-0000-000000000001/{}", base_url, name),
+        }))
+        .unwrap()
+    }
+
+    fn file_content_with_body(base_url: &str, name: &str, body: &str) -> Content {
+        let mut content = file_content(base_url, name, body.len() as u64);
+        if let ContentKind::File { md5, .. } = &mut content.kind {
+            *md5 = Some(Md5::of(body));
+        }
+        content
+    }
+
+    #[tokio::test]
+    async fn download_file() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api::new().authorize("gofile_token");
+        let dir = tempfile::tempdir().unwrap();
+
+        let mock = server
+            .mock(
+                "GET",
+                "/download/web/00000000-0000-0000-0000-000000000001/foo.txt",
+            )
+            .match_header("cookie", "accountToken=gofile_token")
+            .with_status(200)
+            .with_body("file content")
+            .expect(1)
+            .create();
+        let content = file_content_with_body(&server.url(), "foo.txt", "file content");
+        let (tx, mut rx) = unbounded_channel();
+        let path = api.download_file(&content, dir.path(), tx).await?;
+        assert_eq!(path, dir.path().join("foo.txt"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "file content");
+        let mut last = None;
+        while let Some(message) = rx.recv().await {
+            last = Some(message);
+        }
+        assert_eq!(
+            last,
+            Some(DownloadedMessage {
+                uuid: uuid!("00000000-0000-0000-0000-000000000001"),
+                downloaded: 12,
+                total: 12,
+            })
+        );
+        mock.assert();
+
+        let mock = server
+            .mock(
+                "GET",
+                "/download/web/00000000-0000-0000-0000-000000000001/bar.txt",
+            )
+            .with_status(200)
+            .with_body("bar")
+            .expect(1)
+            .create();
+        let content = file_content_with_body(&server.url(), "bar.txt", "bar");
+        let (tx, _rx) = unbounded_channel();
+        let dest = dir.path().join("renamed.txt");
+        let path = api.download_file(&content, &dest, tx).await?;
+        assert_eq!(path, dest);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "bar");
+        mock.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn download_file_with_observer() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api::new().authorize("gofile_token");
+        let dir = tempfile::tempdir().unwrap();
+
+        let mock = server
+            .mock(
+                "GET",
+                "/download/web/00000000-0000-0000-0000-000000000001/foo.txt",
+            )
+            .with_status(200)
+            .with_body("file content")
+            .create();
+        let content = file_content_with_body(&server.url(), "foo.txt", "file content");
+        let last = Arc::new(std::sync::Mutex::new(None));
+        let observer = {
+            let last = last.clone();
+            move |_uuid, downloaded, total| *last.lock().unwrap() = Some((downloaded, total))
+        };
+        api.download_file_with_observer(
+            &content,
+            dir.path(),
+            Arc::new(observer),
+            DownloadOptions::default(),
+        )
+        .await?;
+        assert_eq!(*last.lock().unwrap(), Some((12, Some(12))));
+        mock.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn download_file_rejects_folders() {
+        let api = Api::new().authorize("gofile_token");
+        let content: Content = serde_json::from_value(json!({
+            "id": "00000000-0000-0000-0000-000000000001",
+            "type": "folder",
+            "name": "foo",
+            "createTime": 1710264457,
+            "code": "foo",
+        }))
+        .unwrap();
+        let (tx, _rx) = unbounded_channel();
+        let result = api.download_file(&content, "foo", tx).await;
+        assert!(matches!(result, Err(Error::NotAFile(_))));
+    }
+
+    #[tokio::test]
+    async fn download_file_rejects_path_names() {
+        let mut server = Server::new_async().await;
+        let api = Api::new().authorize("gofile_token");
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("dest");
+        std::fs::create_dir(&dir).unwrap();
+
+        let mock = server.mock("GET", Matcher::Any).expect(0).create();
+        for name in ["../evil.txt", "/tmp/evil.txt", "a/b.txt", "..", "."] {
+            let content = file_content(&server.url(), name, 12);
+            let (tx, _rx) = unbounded_channel();
+            let result = api.download_file(&content, &dir, tx).await;
+            assert!(
+                matches!(&result, Err(Error::InvalidName(invalid, _)) if invalid == name),
+                "{}: {:?}",
+                name,
+                result
+            );
+        }
+        assert!(!root.path().join("evil.txt").exists());
+        mock.assert();
+
+        assert_eq!(checked_name("foo.txt").unwrap(), "foo.txt");
+        assert_eq!(checked_name("..foo").unwrap(), "..foo");
+        assert!(checked_name("").is_err());
+    }
+
+    #[tokio::test]
+    async fn download_file_http_error() {
+        let mut server = Server::new_async().await;
+        let api = Api::new().authorize("gofile_token");
+        let dir = tempfile::tempdir().unwrap();
+
+        let mock = server
+            .mock("GET", Matcher::Any)
+            .with_status(404)
+            .expect(1)
+            .create();
+        let content = file_content(&server.url(), "foo.txt", 12);
+        let (tx, _rx) = unbounded_channel();
+        let result = api.download_file(&content, dir.path(), tx).await;
+        assert!(matches!(
+            result,
+            Err(Error::HttpStatusCodeError(_, StatusCode::NOT_FOUND, _))
+        ));
+        assert!(!dir.path().join("foo.txt").exists());
+        mock.assert();
+    }
+
+    #[tokio::test]
+    async fn download_file_forbidden() {
+        let mut server = Server::new_async().await;
+        let api = Api::new().authorize("gofile_token");
+        let dir = tempfile::tempdir().unwrap();
+        let content = file_content(&server.url(), "foo.txt", 12);
+
+        let mock = server
+            .mock("GET", Matcher::Any)
+            .with_status(403)
+            .expect(1)
+            .create();
+        let (tx, _rx) = unbounded_channel();
+        let result = api.download_file(&content, dir.path(), tx).await;
+        assert!(matches!(result, Err(Error::DownloadForbidden(_))));
+        mock.assert();
+
+        let mock = server
+            .mock("GET", Matcher::Any)
+            .with_status(200)
+            .with_header("content-type", "text/html; charset=utf-8")
+            .with_body("<html>error</html>")
+            .expect(1)
+            .create();
+        let (tx, _rx) = unbounded_channel();
+        let result = api.download_file(&content, dir.path(), tx).await;
+        assert!(matches!(result, Err(Error::DownloadForbidden(_))));
+        assert!(!dir.path().join("foo.txt").exists());
+        mock.assert();
+    }
+
+    #[tokio::test]
+    async fn download_public_file_anonymously() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let dir = tempfile::tempdir().unwrap();
+
+        let mock = server
+            .mock(
+                "GET",
+                "/download/web/00000000-0000-0000-0000-000000000001/foo.txt",
+            )
+            .match_header("cookie", Matcher::Missing)
+            .with_status(200)
+            .with_body("file content")
+            .expect(1)
+            .create();
+        let content = file_content_with_body(&server.url(), "foo.txt", "file content");
+        let (tx, _rx) = unbounded_channel();
+        let path = Api::new().download_file(&content, dir.path(), tx).await?;
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "file content");
+        mock.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn download_to_writer() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api::new().authorize("gofile_token");
+
+        let mock = server
+            .mock(
+                "GET",
+                "/download/web/00000000-0000-0000-0000-000000000001/foo.txt",
+            )
+            .match_header("cookie", "accountToken=gofile_token")
+            .with_status(200)
+            .with_body("file content")
+            .expect(1)
+            .create();
+        let content = file_content(&server.url(), "foo.txt", 12);
+        let mut writer = Vec::new();
+        let written = api.download_to_writer(&content, &mut writer, None).await?;
+        assert_eq!(written, 12);
+        assert_eq!(writer, b"file content");
+        mock.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn download_stream() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api::new().authorize("gofile_token");
+
+        let mock = server
+            .mock(
+                "GET",
+                "/download/web/00000000-0000-0000-0000-000000000001/foo.txt",
+            )
+            .match_header("cookie", "accountToken=gofile_token")
+            .with_status(200)
+            .with_body("file content")
+            .expect(1)
+            .create();
+        let content = file_content(&server.url(), "foo.txt", 12);
+        let chunks: Vec<Bytes> = api.download_stream(&content).await?.try_collect().await?;
+        assert_eq!(chunks.concat(), b"file content");
+        mock.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn download_file_resumable() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api::new().authorize("gofile_token");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("foo.txt");
+        let part = dir.path().join("foo.txt.part");
+        let content = file_content_with_body(&server.url(), "foo.txt", "file content");
+
+        // server honors the range, the rest is appended
+        std::fs::write(&part, "file ").unwrap();
+        let mock = server
+            .mock("GET", Matcher::Any)
+            .match_header("cookie", "accountToken=gofile_token")
+            .match_header("range", "bytes=5-")
+            .with_status(206)
+            .with_header("content-range", "bytes 5-11/12")
+            .with_body("content")
+            .expect(1)
+            .create();
+        let (tx, mut rx) = unbounded_channel();
+        api.download_file_resumable(&content, dir.path(), tx)
+            .await?;
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "file content");
+        let mut progress = Vec::new();
+        while let Some(message) = rx.recv().await {
+            progress.push(message.downloaded);
+        }
+        assert!(progress.iter().all(|downloaded| *downloaded > 5));
+        assert_eq!(progress.last(), Some(&12));
+        mock.assert();
+
+        // server ignores the range, the file is restarted
+        std::fs::write(&part, "file ").unwrap();
+        let mock = server
+            .mock("GET", Matcher::Any)
+            .with_status(200)
+            .with_body("file content")
+            .expect(1)
+            .create();
+        let (tx, _rx) = unbounded_channel();
+        api.download_file_resumable(&content, &path, tx).await?;
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "file content");
+        mock.assert();
+
+        // server answers a different range, the file is restarted instead of appended to
+        std::fs::write(&part, "file ").unwrap();
+        let mock_range = server
+            .mock("GET", Matcher::Any)
+            .match_header("range", "bytes=5-")
+            .with_status(206)
+            .with_header("content-range", "bytes 0-11/12")
+            .with_body("file content")
+            .expect(1)
+            .create();
+        let mock_full = server
+            .mock("GET", Matcher::Any)
+            .match_header("range", Matcher::Missing)
+            .with_status(200)
+            .with_body("file content")
+            .expect(1)
+            .create();
+        let (tx, _rx) = unbounded_channel();
+        api.download_file_resumable(&content, &path, tx).await?;
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "file content");
+        mock_range.assert();
+        mock_full.assert();
+
+        // resumed data doesn't match the listing
+        std::fs::write(&part, "fill ").unwrap();
+        let mock = server
+            .mock("GET", Matcher::Any)
+            .with_status(206)
+            .with_header("content-range", "bytes 5-11/12")
+            .with_body("content")
+            .expect(1)
+            .create();
+        let (tx, _rx) = unbounded_channel();
+        let result = api.download_file_resumable(&content, &path, tx).await;
+        assert!(matches!(result, Err(Error::ChecksumMismatch { .. })));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "file content");
+        assert!(!part.exists());
+        assert!(dir.path().join("foo.txt.corrupt").exists());
+        mock.assert();
+        mock.remove();
+
+        // a failed transfer keeps the part file for the next call
+        std::fs::write(&part, "file ").unwrap();
+        let mock = server
+            .mock("GET", Matcher::Any)
+            .with_status(500)
+            .expect(1)
+            .create();
+        let (tx, _rx) = unbounded_channel();
+        assert!(api
+            .download_file_resumable(&content, &path, tx)
+            .await
+            .is_err());
+        assert_eq!(std::fs::read_to_string(&part).unwrap(), "file ");
+        mock.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn download_file_segmented() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api::new().authorize("gofile_token");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("foo.txt");
+        let content = file_content_with_body(&server.url(), "foo.txt", "file content");
+
+        let first = server
+            .mock("GET", Matcher::Any)
+            .match_header("range", "bytes=0-5")
+            .with_status(206)
+            .with_header("content-range", "bytes 0-5/12")
+            .with_body("file c")
+            .expect(2)
+            .create();
+        let second = server
+            .mock("GET", Matcher::Any)
+            .match_header("range", "bytes=6-11")
+            .with_status(206)
+            .with_header("content-range", "bytes 6-11/12")
+            .with_body("ontent")
+            .expect(2)
+            .create();
+        let (tx, mut rx) = unbounded_channel();
+        api.download_file_segmented(&content, dir.path(), 2, tx, Default::default())
+            .await?;
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "file content");
+        let mut last = None;
+        while let Some(message) = rx.recv().await {
+            last = Some(message.downloaded);
+        }
+        assert_eq!(last, Some(12));
+
+        // written straight to the destination, next to the existing file
+        let (tx, _rx) = unbounded_channel();
+        let options = DownloadOptions {
+            atomic: false,
+            collision_policy: CollisionPolicy::Rename,
+            ..Default::default()
+        };
+        let renamed = api
+            .download_file_segmented(&content, dir.path(), 2, tx, options)
+            .await?;
+        assert_eq!(renamed, dir.path().join("foo (1).txt"));
+        assert_eq!(std::fs::read_to_string(&renamed).unwrap(), "file content");
+        first.assert();
+        second.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn download_file_segmented_failed_segment() {
+        let mut server = Server::new_async().await;
+        let api = Api::new().authorize("gofile_token");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("foo.txt");
+        let content = file_content_with_body(&server.url(), "foo.txt", "file content");
+
+        let mocks = [("0-3", "file"), ("4-7", " con")].map(|(range, body)| {
+            server
+                .mock("GET", Matcher::Any)
+                .match_header("range", format!("bytes={}", range).as_str())
+                .with_status(206)
+                .with_header("content-range", &format!("bytes {}/12", range))
+                .with_body(body)
+                .create()
+        });
+        let failed = server
+            .mock("GET", Matcher::Any)
+            .match_header("range", "bytes=8-11")
+            .with_status(500)
+            .expect(1)
+            .create();
+        let (tx, _rx) = unbounded_channel();
+        let result = api
+            .download_file_segmented(&content, dir.path(), 3, tx, Default::default())
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::HttpStatusCodeError(
+                _,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                _
+            ))
+        ));
+        assert!(!path.exists());
+        assert!(!dir.path().join("foo.txt.part").exists());
+        mocks[0].assert();
+        failed.assert();
+    }
+
+    #[tokio::test]
+    async fn download_file_segmented_ignored_range() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api::new().authorize("gofile_token");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("foo.txt");
+        let content = file_content_with_body(&server.url(), "foo.txt", "file content");
+
+        let _first = server
+            .mock("GET", Matcher::Any)
+            .match_header("range", "bytes=0-5")
+            .with_status(206)
+            .with_header("content-range", "bytes 0-5/12")
+            .with_body("file c")
+            .create();
+        // the whole file, or a range other than the one asked for
+        for (status, content_range, body) in [
+            (200, None, "file content"),
+            (206, Some("bytes 0-5/12"), "file c"),
+        ] {
+            let mut second = server
+                .mock("GET", Matcher::Any)
+                .match_header("range", "bytes=6-11")
+                .with_status(status)
+                .with_body(body);
+            if let Some(content_range) = content_range {
+                second = second.with_header("content-range", content_range);
+            }
+            let second = second.expect(1).create();
+            let (tx, _rx) = unbounded_channel();
+            let result = api
+                .download_file_segmented(&content, dir.path(), 2, tx, Default::default())
+                .await;
+            assert!(matches!(result, Err(Error::RangeIgnored(_, 6, 11))));
+            assert!(!path.exists());
+            second.assert();
+            second.remove();
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn download_file_segmented_without_range_support() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api::new().authorize("gofile_token");
+        let dir = tempfile::tempdir().unwrap();
+        let content = file_content_with_body(&server.url(), "foo.txt", "file content");
+
+        let mock = server
+            .mock("GET", Matcher::Any)
+            .with_status(200)
+            .with_body("file content")
+            .expect(1)
+            .create();
+        let (tx, _rx) = unbounded_channel();
+        let path = api
+            .download_file_segmented(&content, dir.path(), 4, tx, Default::default())
+            .await?;
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "file content");
+        mock.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn download_file_segmented_with_direct_link() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        }
+        .authorize("gofile_token");
+        let dir = tempfile::tempdir().unwrap();
+        let content = file_content_with_body(&server.url(), "foo.txt", "file content");
+
+        let mut mocks = vec![
+            server
+                .mock("GET", "/accounts/getid?token=gofile_token")
+                .with_body(r#"{"status":"ok","data":{"id":"00000000-0000-0000-0000-000000000003"}}"#)
+                .expect(1)
+                .create(),
+            server
+                .mock(
+                    "GET",
+                    "/accounts/00000000-0000-0000-0000-000000000003?token=gofile_token",
+                )
+                .with_body(
+                    json!({
+                        "status": "ok",
+                        "data": {
+                            "id": "00000000-0000-0000-0000-000000000003",
+                            "token": "gofile_token",
+                            "email": "foo@example.com",
+                            "tier": "premium",
+                            "rootFolder": "00000000-0000-0000-0000-000000000004",
+                            "filesCount": 1,
+                            "totalSize": 12,
+                        }
+                    })
+                    .to_string(),
+                )
+                .expect(1)
+                .create(),
+            server
+                .mock("POST", "/contents/00000000-0000-0000-0000-000000000001/directlinks")
+                .with_body(
+                    json!({
+                        "status": "ok",
+                        "data": {
+                            "id": "00000000-0000-0000-0000-000000000002",
+                            "directLink": format!("{}/download/direct/00000000-0000-0000-0000-000000000002/foo.txt", server.url()),
+                        }
+                    })
+                    .to_string(),
+                )
+                .expect(1)
+                .create(),
+            server
+                .mock(
+                    "DELETE",
+                    "/contents/00000000-0000-0000-0000-000000000001/directlinks/00000000-0000-0000-0000-000000000002",
+                )
+                .with_body(r#"{ "status": "ok", "data": {} }"#)
+                .expect(1)
+                .create(),
+        ];
+        mocks.extend(
+            [("0-5", "file c"), ("6-11", "ontent")].map(|(range, body)| {
+                server
+                    .mock(
+                        "GET",
+                        "/download/direct/00000000-0000-0000-0000-000000000002/foo.txt",
+                    )
+                    .match_header("range", format!("bytes={}", range).as_str())
+                    .with_status(206)
+                    .with_header("content-range", &format!("bytes {}/12", range))
+                    .with_body(body)
+                    .expect(1)
+                    .create()
+            }),
+        );
+        let options = DownloadOptions {
+            use_direct_link: true,
+            ..Default::default()
+        };
+        let (tx, _rx) = unbounded_channel();
+        let path = api
+            .download_file_segmented(&content, dir.path(), 2, tx, options)
+            .await?;
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "file content");
+        for mock in mocks {
+            mock.assert();
+        }
+
+        Ok(())
+    }
+
+    // A file as it appears in a folder listing, shared with the sync and upload tests
+    pub(crate) fn file_entry(
+        base_url: &str,
+        id: &str,
+        name: &str,
+        body: &str,
+    ) -> serde_json::Value {
+        json!({
+            "id": id,
+            "type": "file",
+            "name": name,
+            "createTime": 1710264451,
+            "size": body.len(),
+            "downloadCount": 0,
+            "md5": hex::encode(Md5Hasher::digest(body)),
+            "mimetype": "text/plain",
+            "serverSelected": "store1",
+            "link": format!("{}/download/web/{}/{}", base_url, id, name),
+        })
+    }
+
+    #[tokio::test]
+    async fn download_folder() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        }
+        .authorize("gofile_token");
+        let dir = tempfile::tempdir().unwrap();
+        let url = server.url();
+
+        let root = json!({
+            "status": "ok",
+            "data": {
+                "id": "00000000-0000-0000-0000-000000000001",
+                "type": "folder",
+                "name": "root",
+                "createTime": 1710264457,
+                "code": "root",
+                "children": {
+                    "00000000-0000-0000-0000-000000000010": {
+                        "id": "00000000-0000-0000-0000-000000000010",
+                        "type": "folder",
+                        "name": "sub",
+                        "createTime": 1710264457,
+                        "code": "sub"
+                    },
+                    "00000000-0000-0000-0000-000000000011": {
+                        "id": "00000000-0000-0000-0000-000000000011",
+                        "type": "folder",
+                        "name": "locked",
+                        "createTime": 1710264457,
+                        "code": "locked"
+                    },
+                    "00000000-0000-0000-0000-000000000012": file_entry(&url, "00000000-0000-0000-0000-000000000012", "a.txt", "aaa"),
+                    "00000000-0000-0000-0000-000000000013": file_entry(&url, "00000000-0000-0000-0000-000000000013", "b.log", "bbb"),
+                    "00000000-0000-0000-0000-000000000014": file_entry(&url, "00000000-0000-0000-0000-000000000014", "c.txt", "ccc"),
+                    "00000000-0000-0000-0000-000000000015": file_entry(&url, "00000000-0000-0000-0000-000000000015", "../escape.txt", "eee"),
+                    "00000000-0000-0000-0000-000000000016": {
+                        "id": "00000000-0000-0000-0000-000000000016",
+                        "type": "folder",
+                        "name": "..",
+                        "createTime": 1710264457,
+                        "code": "parent"
+                    },
+                }
+            }
+        });
+        let sub = json!({
+            "status": "ok",
+            "data": {
+                "id": "00000000-0000-0000-0000-000000000010",
+                "type": "folder",
+                "name": "sub",
+                "createTime": 1710264457,
+                "code": "sub",
+                "children": {
+                    "00000000-0000-0000-0000-000000000020": file_entry(&url, "00000000-0000-0000-0000-000000000020", "d.txt", "ddd"),
+                }
+            }
+        });
+        let mocks = vec![
+            server
+                .mock(
+                    "GET",
+                    "/contents/00000000-0000-0000-0000-000000000001?token=gofile_token",
+                )
+                .with_status(200)
+                .with_body(root.to_string())
+                .create(),
+            server
+                .mock(
+                    "GET",
+                    "/contents/00000000-0000-0000-0000-000000000010?token=gofile_token",
+                )
+                .with_status(200)
+                .with_body(sub.to_string())
+                .create(),
+            server
+                .mock(
+                    "GET",
+                    "/contents/00000000-0000-0000-0000-000000000011?token=gofile_token",
+                )
+                .with_status(401)
+                .with_body(r#"{ "status": "error-passwordRequired", "data": {} }"#)
+                .create(),
+            server
+                .mock(
+                    "GET",
+                    "/download/web/00000000-0000-0000-0000-000000000012/a.txt",
+                )
+                .with_status(200)
+                .with_body("aaa")
+                .create(),
+            server
+                .mock(
+                    "GET",
+                    "/download/web/00000000-0000-0000-0000-000000000020/d.txt",
+                )
+                .with_status(200)
+                .with_body("ddd")
+                .create(),
+        ];
+        std::fs::write(dir.path().join("c.txt"), "ccc").unwrap();
+        // changed since the last run, downloaded again instead of failing
+        std::fs::write(dir.path().join("a.txt"), "old").unwrap();
+
+        let options = FolderDownloadOptions::default()
+            .collision_policy(CollisionPolicy::Skip)
+            .filter(|name| name.ends_with(".txt"));
+        let (tx, mut rx) = unbounded_channel();
+        let report = api
+            .download_folder(
+                uuid!("00000000-0000-0000-0000-000000000001").into(),
+                dir.path(),
+                options,
+                Some(tx),
+            )
+            .await?;
+        assert_eq!(
+            report.downloaded,
+            vec![dir.path().join("a.txt"), dir.path().join("sub/d.txt")]
+        );
+        assert_eq!(report.skipped, vec![dir.path().join("c.txt")]);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("a.txt")).unwrap(),
+            "aaa"
+        );
+        assert_eq!(report.failed.len(), 3);
+        for (path, err) in &report.failed {
+            match err {
+                Error::PasswordRequired(_) => assert_eq!(*path, dir.path().join("locked")),
+                Error::InvalidName(name, _) => {
+                    assert!(name == ".." || name == "../escape.txt");
+                    assert_eq!(path, dir.path());
+                }
+                err => panic!("unexpected error {:?}", err),
+            }
+        }
+        assert!(!dir.path().join("../escape.txt").exists());
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("sub/d.txt")).unwrap(),
+            "ddd"
+        );
+        assert!(!dir.path().join("b.log").exists());
+
+        let mut last = None;
+        while let Some(message) = rx.recv().await {
+            if let FolderDownloadMessage::Total { .. } = message {
+                last = Some(message);
+            }
+        }
+        assert_eq!(
+            last,
+            Some(FolderDownloadMessage::Total {
+                files_done: 3,
+                files_total: 3,
+                bytes_done: 9,
+                bytes_total: 9,
+            })
+        );
+        for mock in mocks {
+            mock.assert();
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn download_file_checksum_mismatch() {
+        let mut server = Server::new_async().await;
+        let api = Api::new().authorize("gofile_token");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("foo.txt");
+        let content = file_content_with_body(&server.url(), "foo.txt", "file content");
+
+        let mock = server
+            .mock("GET", Matcher::Any)
+            .with_status(200)
+            .with_body("file c0ntent")
+            .expect(3)
+            .create();
+
+        let (tx, _rx) = unbounded_channel();
+        let result = api.download_file(&content, dir.path(), tx).await;
+        assert!(matches!(
+            result,
+            Err(Error::ChecksumMismatch { expected, actual, .. })
+                if expected == "d10b4c3ff123b26dc068d43a8bef2d23"
+                    && actual == hex::encode(Md5Hasher::digest("file c0ntent"))
+        ));
+        assert!(!path.exists());
+
+        let (tx, _rx) = unbounded_channel();
+        let options = DownloadOptions {
+            keep_partial: true,
+            ..Default::default()
+        };
+        let result = api
+            .download_file_with_options(&content, dir.path(), tx, options)
+            .await;
+        let corrupt = dir.path().join("foo.txt.corrupt");
+        assert!(matches!(result, Err(Error::ChecksumMismatch { path, .. }) if path == corrupt));
+        assert!(corrupt.exists());
+        assert!(!path.exists());
+
+        let (tx, _rx) = unbounded_channel();
+        let options = DownloadOptions {
+            verify_md5: false,
+            ..Default::default()
+        };
+        api.download_file_with_options(&content, dir.path(), tx, options)
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "file c0ntent");
+        mock.assert();
+    }
+
+    #[tokio::test]
+    async fn download_file_collision_policy() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api::new().authorize("gofile_token");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("foo.txt");
+        let content = file_content_with_body(&server.url(), "foo.txt", "file content");
+        let options = |collision_policy| DownloadOptions {
+            collision_policy,
+            ..Default::default()
+        };
+
+        let mock = server
+            .mock("GET", Matcher::Any)
+            .with_status(200)
+            .with_body("file content")
+            .expect(4)
+            .create();
+
+        // identical file is not downloaded again
+        std::fs::write(&path, "file content").unwrap();
+        let (tx, _rx) = unbounded_channel();
+        let result = api
+            .download_file_with_options(&content, dir.path(), tx, options(CollisionPolicy::Skip))
+            .await?;
+        assert_eq!(result, path);
+
+        // a different file is downloaded again
+        std::fs::write(&path, "other").unwrap();
+        let (tx, _rx) = unbounded_channel();
+        let result = api
+            .download_file_with_options(&content, dir.path(), tx, options(CollisionPolicy::Skip))
+            .await?;
+        assert_eq!(result, path);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "file content");
+
+        std::fs::write(&path, "other").unwrap();
+
+        let (tx, _rx) = unbounded_channel();
+        let result = api
+            .download_file_with_options(&content, dir.path(), tx, options(CollisionPolicy::Error))
+            .await;
+        assert!(matches!(result, Err(Error::FileExists(_))));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "other");
+
+        for expected in ["foo (1).txt", "foo (2).txt"] {
+            let (tx, _rx) = unbounded_channel();
+            let result = api
+                .download_file_with_options(
+                    &content,
+                    dir.path(),
+                    tx,
+                    options(CollisionPolicy::Rename),
+                )
+                .await?;
+            assert_eq!(result, dir.path().join(expected));
+            assert_eq!(std::fs::read_to_string(&result).unwrap(), "file content");
+        }
+
+        let (tx, _rx) = unbounded_channel();
+        api.download_file_with_options(
+            &content,
+            dir.path(),
+            tx,
+            options(CollisionPolicy::Overwrite),
+        )
+        .await?;
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "file content");
+        mock.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn open_dest_renames() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("foo.txt");
+        std::fs::write(&path, "").unwrap();
+        for n in 1..MAX_RENAMES {
+            std::fs::write(numbered_path(&path, n), "").unwrap();
+        }
+        let (_, renamed) = open_dest(path.clone(), CollisionPolicy::Rename)
+            .await
+            .unwrap();
+        assert_eq!(renamed, numbered_path(&path, MAX_RENAMES));
+
+        let result = open_dest(path.clone(), CollisionPolicy::Rename).await;
+        assert!(matches!(result, Err(Error::FileExists(exists)) if exists == path));
+    }
+
+    #[tokio::test]
+    async fn download_file_atomic() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api::new().authorize("gofile_token");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("foo.txt");
+        let part = dir.path().join("foo.txt.part");
+        let content = file_content_with_body(&server.url(), "foo.txt", "file content");
+
+        // a failed download leaves the existing file alone
+        std::fs::write(&path, "old").unwrap();
+        let mock = server
+            .mock("GET", Matcher::Any)
+            .with_status(500)
+            .expect(1)
+            .create();
+        let (tx, _rx) = unbounded_channel();
+        let result = api.download_file(&content, dir.path(), tx).await;
+        assert!(result.is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "old");
+        assert!(!part.exists());
+        mock.assert();
+
+        // a leftover part file is resumed
+        std::fs::write(&part, "file ").unwrap();
+        let mock = server
+            .mock("GET", Matcher::Any)
+            .match_header("range", "bytes=5-")
+            .with_status(206)
+            .with_header("content-range", "bytes 5-11/12")
+            .with_body("content")
+            .expect(1)
+            .create();
+        let (tx, _rx) = unbounded_channel();
+        api.download_file(&content, dir.path(), tx).await?;
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "file content");
+        assert!(!part.exists());
+        mock.assert();
+
+        // a part file another download holds is left alone, a leftover next to it is resumed
+        std::fs::write(&part, "file c").unwrap();
+        let held = std::fs::File::open(&part).unwrap();
+        held.try_lock().unwrap();
+        let leftover = dir.path().join("foo.txt.1.part");
+        std::fs::write(&leftover, "file ").unwrap();
+        mock.remove();
+        let mock = server
+            .mock("GET", Matcher::Any)
+            .match_header("range", "bytes=5-")
+            .with_status(206)
+            .with_header("content-range", "bytes 5-11/12")
+            .with_body("content")
+            .expect(1)
+            .create();
+        let (tx, _rx) = unbounded_channel();
+        api.download_file(&content, dir.path(), tx).await?;
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "file content");
+        assert_eq!(std::fs::read_to_string(&part).unwrap(), "file c");
+        assert!(!leftover.exists());
+        mock.assert();
+        mock.remove();
+        drop(held);
+
+        // once free, the part file left by a crashed download is claimed again
+        let mock = server
+            .mock("GET", Matcher::Any)
+            .match_header("range", "bytes=6-")
+            .with_status(206)
+            .with_header("content-range", "bytes 6-11/12")
+            .with_body("ontent")
+            .expect(1)
+            .create();
+        let (tx, _rx) = unbounded_channel();
+        api.download_file(&content, dir.path(), tx).await?;
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "file content");
+        assert!(!part.exists());
+        mock.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn download_file_concurrent_renames() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api::new().authorize("gofile_token");
+        let dir = tempfile::tempdir().unwrap();
+        let content = file_content_with_body(&server.url(), "foo.txt", "file content");
+        let options = DownloadOptions {
+            collision_policy: CollisionPolicy::Rename,
+            ..Default::default()
+        };
+
+        // the pause keeps both downloads writing at the same time
+        let mock = server
+            .mock("GET", Matcher::Any)
+            .with_status(200)
+            .with_chunked_body(|w| {
+                w.write_all(b"file ")?;
+                std::thread::sleep(std::time::Duration::from_millis(100));
+                w.write_all(b"content")
+            })
+            .expect(2)
+            .create();
+        let (tx, _rx) = unbounded_channel();
+        let first =
+            api.download_file_with_options(&content, dir.path(), tx.clone(), options.clone());
+        let second = api.download_file_with_options(&content, dir.path(), tx, options);
+        let (first, second) = futures::join!(first, second);
+        let mut paths = vec![first?, second?];
+        paths.sort();
+        assert_eq!(
+            paths,
+            [dir.path().join("foo (1).txt"), dir.path().join("foo.txt")]
+        );
+        for path in paths {
+            assert_eq!(std::fs::read_to_string(&path).unwrap(), "file content");
+        }
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+        mock.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn probe_file() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api::new().authorize("gofile_token");
+        let content = file_content(&server.url(), "foo.txt", 12);
+
+        let mock = server
+            .mock("HEAD", Matcher::Any)
+            .match_header("cookie", "accountToken=gofile_token")
+            .with_status(200)
+            .with_header("accept-ranges", "bytes")
+            .with_header("content-type", "text/plain")
+            .with_header("content-length", "12")
+            .expect(1)
+            .create();
+        let probe = api.probe_file(&content).await?;
+        assert_eq!(
+            probe,
+            FileProbe {
+                content_length: Some(12),
+                content_type: Some("text/plain".into()),
+                accepts_ranges: true,
+                size_mismatch: false,
+            }
+        );
+        mock.assert();
+
+        let head = server
+            .mock("HEAD", Matcher::Any)
+            .with_status(405)
+            .expect(1)
+            .create();
+        let get = server
+            .mock("GET", Matcher::Any)
+            .match_header("cookie", "accountToken=gofile_token")
+            .match_header("range", "bytes=0-0")
+            .with_status(206)
+            .with_header("content-range", "bytes 0-0/20")
+            .with_header("content-type", "text/plain")
+            .with_body("f")
+            .expect(1)
+            .create();
+        let probe = api.probe_file(&content).await?;
+        assert_eq!(
+            probe,
+            FileProbe {
+                content_length: Some(20),
+                content_type: Some("text/plain".into()),
+                accepts_ranges: true,
+                size_mismatch: true,
+            }
+        );
+        head.assert();
+        get.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn download_from_url() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        }
+        .authorize("gofile_token");
+        let dir = tempfile::tempdir().unwrap();
+        let url = server.url();
+
+        let file = json!({
+            "status": "ok",
+            "data": file_entry(&url, "00000000-0000-0000-0000-000000000012", "a.txt", "aaa"),
+        });
+        let folder = json!({
+            "status": "ok",
+            "data": {
+                "id": "00000000-0000-0000-0000-000000000001",
+                "type": "folder",
+                "name": "root",
+                "createTime": 1710264457,
+                "code": "folder",
+                "children": {
+                    "00000000-0000-0000-0000-000000000010": {
+                        "id": "00000000-0000-0000-0000-000000000010",
+                        "type": "folder",
+                        "name": "sub",
+                        "createTime": 1710264457,
+                        "code": "sub"
+                    },
+                    "00000000-0000-0000-0000-000000000012": file_entry(&url, "00000000-0000-0000-0000-000000000012", "a.txt", "aaa"),
+                    "00000000-0000-0000-0000-000000000013": file_entry(&url, "00000000-0000-0000-0000-000000000013", "b.txt", "bbb"),
+                }
+            }
+        });
+        let sub = json!({
+            "status": "ok",
+            "data": {
+                "id": "00000000-0000-0000-0000-000000000010",
+                "type": "folder",
+                "name": "sub",
+                "createTime": 1710264457,
+                "code": "sub",
+                "children": {
+                    "00000000-0000-0000-0000-000000000020": file_entry(&url, "00000000-0000-0000-0000-000000000020", "c.txt", "ccc"),
+                    "00000000-0000-0000-0000-000000000021": file_entry(&url, "00000000-0000-0000-0000-000000000021", "d.txt", "ddd"),
+                }
+            }
+        });
+        let with_password = |path: &str| {
+            Matcher::Regex(format!(
+                r"^/contents/{}\?token=gofile_token&password=\w+$",
+                path
+            ))
+        };
+        let mut mocks = [
+            ("00000000-0000-0000-0000-000000000012/a.txt", 200, "aaa"),
+            ("00000000-0000-0000-0000-000000000013/b.txt", 404, ""),
+            ("00000000-0000-0000-0000-000000000020/c.txt", 200, "ccc"),
+            ("00000000-0000-0000-0000-000000000021/d.txt", 404, ""),
+        ]
+        .into_iter()
+        .map(|(path, status, body)| {
+            server
+                .mock("GET", format!("/download/web/{}", path).as_str())
+                .with_status(status)
+                .with_body(body)
+                .create()
+        })
+        .collect::<Vec<_>>();
+        mocks.extend([
+            server
+                .mock("GET", "/contents/file?token=gofile_token")
+                .with_status(200)
+                .with_body(file.to_string())
+                .create(),
+            server
+                .mock("GET", with_password("folder"))
+                .with_status(200)
+                .with_body(folder.to_string())
+                .expect(2)
+                .create(),
+            // the password is needed for the whole tree
+            server
+                .mock("GET", with_password("00000000-0000-0000-0000-000000000001"))
+                .with_status(200)
+                .with_body(folder.to_string())
+                .create(),
+            server
+                .mock("GET", with_password("00000000-0000-0000-0000-000000000010"))
+                .with_status(200)
+                .with_body(sub.to_string())
+                .create(),
+        ]);
+
+        let report = api
+            .download_from_url(
+                &Url::parse("https://gofile.io/d/file").unwrap(),
+                dir.path(),
+                UrlDownloadOptions::default(),
+            )
+            .await?;
+        assert_eq!(report.downloaded, vec![dir.path().join("a.txt")]);
+
+        let options = UrlDownloadOptions {
+            password: Some("secret".into()),
+            ..Default::default()
+        };
+        let result = api
+            .download_from_url(
+                &Url::parse("https://gofile.io/d/folder").unwrap(),
+                dir.path(),
+                options.clone(),
+            )
+            .await;
+        assert!(matches!(result, Err(Error::NotAFile(_))));
+
+        // download options apply to every file in the tree
+        let options = UrlDownloadOptions {
+            allow_folder: true,
+            download: DownloadOptions {
+                collision_policy: CollisionPolicy::Skip,
+                ..Default::default()
+            },
+            ..options
+        };
+        let mut report = api
+            .download_from_url(
+                &Url::parse("https://gofile.io/d/folder").unwrap(),
+                dir.path(),
+                options,
+            )
+            .await?;
+        assert_eq!(report.downloaded, vec![dir.path().join("sub/c.txt")]);
+        assert_eq!(report.skipped, vec![dir.path().join("a.txt")]);
+        report.failed.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            report
+                .failed
+                .iter()
+                .map(|(path, _)| path.clone())
+                .collect::<Vec<_>>(),
+            vec![dir.path().join("b.txt"), dir.path().join("sub/d.txt")]
+        );
+        for mock in mocks {
+            mock.assert();
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn download_file_with_direct_link() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let unauthorized = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        };
+        let api = unauthorized.authorize("gofile_token");
+        let dir = tempfile::tempdir().unwrap();
+        let content = file_content_with_body(&server.url(), "foo.txt", "file content");
+        let options = DownloadOptions {
+            use_direct_link: true,
+            ..Default::default()
+        };
+        let account = |tier: &str| {
+            json!({
+                "status": "ok",
+                "data": {
+                    "id": "00000000-0000-0000-0000-000000000003",
+                    "token": "gofile_token",
+                    "email": "foo@example.com",
+                    "tier": tier,
+                    "rootFolder": "00000000-0000-0000-0000-000000000004",
+                    "filesCount": 1,
+                    "totalSize": 12,
+                }
+            })
+            .to_string()
+        };
+        let getid = server
+            .mock("GET", "/accounts/getid?token=gofile_token")
+            .with_body(r#"{"status":"ok","data":{"id":"00000000-0000-0000-0000-000000000003"}}"#)
+            .expect(2)
+            .create();
+        let account_path = "/accounts/00000000-0000-0000-0000-000000000003?token=gofile_token";
+        let premium = server
+            .mock("GET", account_path)
+            .with_body(account("Premium"))
+            // the tier is asked once per api
+            .expect(1)
+            .create();
+
+        let mocks = vec![
+            server
+                .mock("POST", "/contents/00000000-0000-0000-0000-000000000001/directlinks")
+                .match_header("authorization", "Bearer gofile_token")
+                .with_status(200)
+                .with_body(
+                    json!({
+                        "status": "ok",
+                        "data": {
+                            "id": "00000000-0000-0000-0000-000000000002",
+                            "directLink": format!("{}/download/direct/00000000-0000-0000-0000-000000000002/foo.txt", server.url()),
+                        }
+                    })
+                    .to_string(),
+                )
+                .expect(1)
+                .create(),
+            server
+                .mock(
+                    "GET",
+                    "/download/direct/00000000-0000-0000-0000-000000000002/foo.txt",
+                )
+                .with_status(200)
+                .with_body("file content")
+                .expect(1)
+                .create(),
+            server
+                .mock(
+                    "DELETE",
+                    "/contents/00000000-0000-0000-0000-000000000001/directlinks/00000000-0000-0000-0000-000000000002",
+                )
+                .match_header("authorization", "Bearer gofile_token")
+                .with_status(200)
+                .with_body(r#"{ "status": "ok", "data": {} }"#)
+                .expect(1)
+                .create(),
+        ];
+        let (tx, _rx) = unbounded_channel();
+        let path = api
+            .download_file_with_options(&content, dir.path(), tx, options.clone())
+            .await?;
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "file content");
+        for mock in mocks {
+            mock.assert();
+        }
+
+        // the server refusing the direct link also falls back to the normal link
+        let mocks = vec![
+            server
+                .mock(
+                    "POST",
+                    "/contents/00000000-0000-0000-0000-000000000001/directlinks",
+                )
+                .with_status(200)
+                .with_body(r#"{ "status": "error-notPremium", "data": {} }"#)
+                .expect(1)
+                .create(),
+            server
+                .mock(
+                    "GET",
+                    "/download/web/00000000-0000-0000-0000-000000000001/foo.txt",
+                )
+                .with_status(200)
+                .with_body("file content")
+                .expect(2)
+                .create(),
+        ];
+        let (tx, _rx) = unbounded_channel();
+        let path = api
+            .download_file_with_options(&content, dir.path(), tx, options.clone())
+            .await?;
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "file content");
+        premium.assert();
+        premium.remove();
+
+        // accounts without premium don't try to create one
+        let api = unauthorized.authorize("gofile_token");
+        let standard = server
+            .mock("GET", account_path)
+            .with_body(account("standard"))
+            .expect(1)
+            .create();
+        let (tx, _rx) = unbounded_channel();
+        let path = api
+            .download_file_with_options(&content, dir.path(), tx, options)
+            .await?;
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "file content");
+        for mock in mocks {
+            mock.assert();
+        }
+        standard.assert();
+        getid.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn download_folder_with_direct_link() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        }
+        .authorize("gofile_token");
+        let dir = tempfile::tempdir().unwrap();
+        let url = server.url();
+        let folder = json!({
+            "status": "ok",
+            "data": {
+                "id": "00000000-0000-0000-0000-000000000010",
+                "type": "folder",
+                "name": "root",
+                "createTime": 1710264457,
+                "code": "root",
+                "children": {
+                    "00000000-0000-0000-0000-000000000001": file_entry(&url, "00000000-0000-0000-0000-000000000001", "foo.txt", "file content"),
+                }
+            }
+        });
+
+        let mocks = vec![
+            server
+                .mock(
+                    "GET",
+                    "/contents/00000000-0000-0000-0000-000000000010?token=gofile_token",
+                )
+                .with_body(folder.to_string())
+                .expect(1)
+                .create(),
+            server
+                .mock("GET", "/accounts/getid?token=gofile_token")
+                .with_body(r#"{"status":"ok","data":{"id":"00000000-0000-0000-0000-000000000003"}}"#)
+                .expect(1)
+                .create(),
+            server
+                .mock(
+                    "GET",
+                    "/accounts/00000000-0000-0000-0000-000000000003?token=gofile_token",
+                )
+                .with_body(
+                    json!({
+                        "status": "ok",
+                        "data": {
+                            "id": "00000000-0000-0000-0000-000000000003",
+                            "token": "gofile_token",
+                            "email": "foo@example.com",
+                            "tier": "premium",
+                            "rootFolder": "00000000-0000-0000-0000-000000000004",
+                            "filesCount": 1,
+                            "totalSize": 12,
+                        }
+                    })
+                    .to_string(),
+                )
+                .expect(1)
+                .create(),
+            server
+                .mock("POST", "/contents/00000000-0000-0000-0000-000000000001/directlinks")
+                .with_body(
+                    json!({
+                        "status": "ok",
+                        "data": {
+                            "id": "00000000-0000-0000-0000-000000000002",
+                            "directLink": format!("{}/download/direct/00000000-0000-0000-0000-000000000002/foo.txt", url),
+                        }
+                    })
+                    .to_string(),
+                )
+                .expect(1)
+                .create(),
+            server
+                .mock(
+                    "GET",
+                    "/download/direct/00000000-0000-0000-0000-000000000002/foo.txt",
+                )
+                .with_body("file content")
+                .expect(1)
+                .create(),
+            // a failed cleanup doesn't fail the download
+            server
+                .mock(
+                    "DELETE",
+                    "/contents/00000000-0000-0000-0000-000000000001/directlinks/00000000-0000-0000-0000-000000000002",
+                )
+                .with_status(500)
+                .expect_at_least(1)
+                .create(),
+        ];
+        let options = FolderDownloadOptions::default().download_options(DownloadOptions {
+            use_direct_link: true,
+            ..Default::default()
+        });
+        let report = api
+            .download_folder(
+                FolderId(uuid!("00000000-0000-0000-0000-000000000010")),
+                dir.path(),
+                options,
+                None,
+            )
+            .await?;
+        assert_eq!(report.downloaded, [dir.path().join("foo.txt")]);
+        assert!(report.failed.is_empty());
+        for mock in mocks {
+            mock.assert();
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+use crate::Error;
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::{fmt, ops::Deref, str::FromStr};
+use uuid::Uuid;
+
+// Ids of gofile contents. Each kind gets its own type so a file id can't be passed where a
+// folder is expected, ContentId takes either.
+macro_rules! id_type {
+    ($name:ident) => {
+        #[derive(
+            Clone,
+            Copy,
+            Debug,
+            Default,
+            PartialEq,
+            Eq,
+            Hash,
+            PartialOrd,
+            Ord,
+            Serialize,
+            Deserialize,
+        )]
+        #[serde(transparent)]
+        pub struct $name(pub Uuid);
+
+        impl From<Uuid> for $name {
+            fn from(id: Uuid) -> Self {
+                $name(id)
+            }
+        }
+
+        impl From<$name> for Uuid {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl Deref for $name {
+            type Target = Uuid;
+
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+
+        impl PartialEq<Uuid> for $name {
+            fn eq(&self, other: &Uuid) -> bool {
+                self.0 == *other
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = uuid::Error;
+
+            fn from_str(id: &str) -> Result<Self, Self::Err> {
+                Uuid::parse_str(id).map($name)
+            }
+        }
+    };
+}
+
+id_type!(ContentId);
+id_type!(FolderId);
+id_type!(FileId);
+
+impl From<FolderId> for ContentId {
+    fn from(id: FolderId) -> Self {
+        ContentId(id.0)
+    }
+}
+
+impl From<FileId> for ContentId {
+    fn from(id: FileId) -> Self {
+        ContentId(id.0)
+    }
+}
+
+// The short code of a content, the XXXX of https://gofile.io/d/XXXX
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(transparent)]
+pub struct Code(String);
+
+impl Code {
+    pub fn new(code: impl Into<String>) -> Result<Self, Error> {
+        let code = code.into();
+        if code.is_empty() {
+            return Err(Error::InvalidCode(code, "The code is empty.".into()));
+        }
+        if let Some(c) = code
+            .chars()
+            .find(|c| !c.is_ascii_alphanumeric() && *c != '-' && *c != '_')
+        {
+            return Err(Error::InvalidCode(
+                code,
+                format!("The code can't contain {:?}.", c),
+            ));
+        }
+        Ok(Code(code))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for Code {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Code {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for Code {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Code {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for Code {
+    type Err = Error;
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        Code::new(code)
+    }
+}
+
+impl TryFrom<String> for Code {
+    type Error = Error;
+
+    fn try_from(code: String) -> Result<Self, Self::Error> {
+        Code::new(code)
+    }
+}
+
+impl TryFrom<&str> for Code {
+    type Error = Error;
+
+    fn try_from(code: &str) -> Result<Self, Self::Error> {
+        Code::new(code)
+    }
+}
+
+impl<'de> Deserialize<'de> for Code {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Code::new(String::deserialize(d)?).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{from_value, json, to_value};
+    use uuid::uuid;
+
+    #[test]
+    fn ids() {
+        let uuid = uuid!("00000000-0000-0000-0000-000000000001");
+        let folder_id = FolderId::from(uuid);
+        assert_eq!(to_value(folder_id).unwrap(), json!(uuid));
+        assert_eq!(
+            from_value::<FolderId>(json!("00000000-0000-0000-0000-000000000001")).unwrap(),
+            folder_id
+        );
+        assert_eq!(folder_id.to_string(), uuid.to_string());
+        assert_eq!(*folder_id, uuid);
+        assert_eq!(ContentId::from(folder_id), ContentId(uuid));
+        assert_eq!(ContentId::from(FileId(uuid)), folder_id.0);
+        assert_eq!(
+            "00000000-0000-0000-0000-000000000001".parse(),
+            Ok(FileId(uuid))
+        );
+        assert!(from_value::<FileId>(json!("not an id")).is_err());
+    }
+
+    #[test]
+    fn code() {
+        assert_eq!(Code::new("aBc123").unwrap(), "aBc123");
+        assert_eq!(
+            from_value::<Code>(json!("x-Y_z")).unwrap().as_str(),
+            "x-Y_z"
+        );
+        assert_eq!(to_value(Code::new("abc").unwrap()).unwrap(), json!("abc"));
+        assert!(matches!(Code::new(""), Err(Error::InvalidCode(..))));
+        assert!(matches!(
+            Code::try_from("d/abc"),
+            Err(Error::InvalidCode(code, _)) if code == "d/abc"
+        ));
+        assert!("ab cd".parse::<Code>().is_err());
+        assert!(from_value::<Code>(json!("abc?wt=1")).is_err());
+    }
+}
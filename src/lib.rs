@@ -1,76 +1,729 @@
+// Forwards to tracing with the `tracing` feature, compiles to nothing without it
+macro_rules! event {
+    ($level:ident, $($arg:tt)+) => {{
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)+);
+    }};
+}
+
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod checksum;
+mod client;
+mod download;
+mod ids;
 mod payload;
+mod progress;
+mod sync;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+mod token;
+mod transport;
+mod upload;
+#[cfg(feature = "vcr")]
+pub mod vcr;
+use bytes::Bytes;
 use chrono::{DateTime, Utc};
+use futures::{stream, stream::FuturesUnordered, FutureExt, Stream, StreamExt};
+use md5::Md5 as Md5Hasher;
 use reqwest::{
+    header::RETRY_AFTER,
     multipart::{Form, Part},
-    Body, Method, Response, StatusCode,
+    Body, Client, Method, Response, StatusCode,
 };
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::Value;
-use std::path::{Path, PathBuf};
-use tokio::fs::File;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{
+    fs::File,
+    io::AsyncRead,
+    sync::mpsc::{Sender, UnboundedSender},
+};
+use tokio_util::io::ReaderStream;
 use url::Url;
 use uuid::Uuid;
 
+pub use checksum::*;
+pub use client::*;
+pub use download::*;
+pub use ids::*;
 pub use payload::*;
+pub use progress::*;
+pub use sync::*;
+pub use token::*;
+pub use transport::*;
+pub use upload::*;
 
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum Error {
+    // the url inside has its token redacted, see From<reqwest::Error>
     #[error("HttpRequestError: {0}")]
-    HttpRequestError(#[from] reqwest::Error),
+    HttpRequestError(#[source] reqwest::Error),
 
     #[error("HttpStatusCodeError: url {0}, error code {1}")]
-    HttpStatusCodeError(Url, StatusCode),
+    HttpStatusCodeError(Url, StatusCode, Box<ErrorContext>),
 
+    // the code keeps the status as gofile sent it, see GofileErrorCode::as_str
     #[error("ApiStatusError: url {0}, error {1}")]
-    ApiStatusError(Url, String),
+    ApiStatusError(Url, GofileErrorCode, Box<ErrorContext>),
+
+    // body_snippet is the start of the body with tokens redacted, kept out of the message. The
+    // url is redacted like the other urls kept in errors.
+    #[error("Decode: url {url}, error {serde_error}")]
+    Decode {
+        url: Url,
+        #[source]
+        serde_error: serde_json::Error,
+        body_snippet: Box<str>,
+    },
 
     #[error("Gofile returned empty server list")]
     EmptyServerList,
 
+    #[error("Gofile has no server in zone {0}")]
+    NoServerInZone(Zone),
+
+    #[error("Couldn't find the website token at url {0}")]
+    WebsiteTokenNotFound(Url),
+
+    #[error("Couldn't read the token from env var {0}. Error: {1}")]
+    TokenEnvError(String, #[source] std::env::VarError),
+
     #[error("InvalidFilePath at path {0}. Error: {1}")]
     InvalidFilePath(PathBuf, String),
 
     #[error("Could not open file at path {0}. Error: {1}")]
-    CouldntOpenFile(PathBuf, String),
+    CouldntOpenFile(PathBuf, #[source] std::io::Error),
+
+    #[error("Could not write file at path {0}. Error: {1}")]
+    CouldntWriteFile(PathBuf, #[source] std::io::Error),
+
+    #[error("Could not write downloaded data. Error: {0}")]
+    CouldntWrite(#[source] std::io::Error),
+
+    #[error("A download task failed. Error: {0}")]
+    TaskFailed(#[source] tokio::task::JoinError),
+
+    #[error("Checksum mismatch for file at path {path:?}: expected {expected}, got {actual}")]
+    ChecksumMismatch {
+        expected: String,
+        actual: String,
+        path: PathBuf,
+    },
+
+    #[error("Gofile content {0} is not a file")]
+    NotAFile(ContentId),
+
+    #[error("Gofile content {0} is not a folder")]
+    NotAFolder(ContentId),
+
+    #[error("Gofile content {0} has no download link")]
+    MissingLink(ContentId),
+
+    #[error("File already exists at path {0}")]
+    FileExists(PathBuf),
 
     #[error("Gofile InvalidContentUrl at url {0}. Error: {1}")]
     InvalidContentUrl(Url, String),
+
+    #[error("InvalidName {0:?}. Error: {1}")]
+    InvalidName(String, String),
+
+    #[error("InvalidCode {0:?}. Error: {1}")]
+    InvalidCode(String, String),
+
+    #[error("Upload of the file at path {0} was cancelled")]
+    UploadCancelled(PathBuf),
+
+    #[error("InvalidMime {0:?}. Error: {1}")]
+    InvalidMime(String, String),
+
+    #[error("InvalidChunkSize {0}: the chunk size must be greater than zero")]
+    InvalidChunkSize(usize),
+
+    #[error("InvalidPattern {0:?}. Error: {1}")]
+    InvalidPattern(String, String),
+
+    #[error("InvalidBaseUrl {0:?}. Error: {1}")]
+    InvalidBaseUrl(String, String),
+
+    #[error("InvalidExpire: {0}")]
+    InvalidExpire(String),
+
+    #[error("Gofile content at url {0} requires a password")]
+    PasswordRequired(Url),
+
+    #[error("Wrong password for gofile content at url {0}")]
+    WrongPassword(Url),
+
+    #[error("InvalidConfig: {0}")]
+    InvalidConfig(String),
+
+    #[error("Gofile premium account is required for url {0}")]
+    NotPremium(Url),
+
+    #[error("No data moved for {1:?} on the transfer at url {0}")]
+    Stalled(Url, Duration),
+
+    #[error("Gofile rate limited the request to url {url}")]
+    #[non_exhaustive]
+    RateLimited {
+        url: Url,
+        // how long the server asked to wait, from the Retry-After header
+        retry_after: Option<Duration>,
+    },
+
+    #[error("Redirect to {0} wasn't followed")]
+    RedirectBlocked(Url),
+
+    #[error("Gofile refused to serve the file at url {0}")]
+    DownloadForbidden(Url),
+
+    #[error("The server didn't answer with bytes {1}-{2} of the file at url {0}")]
+    RangeIgnored(Url, u64, u64),
+
+    #[error("Uploaded file {0} has no guest token, it was uploaded with an account token")]
+    MissingGuestToken(FileId),
+
+    // uploaded is set when the step failed after the upload, the download page exists then
+    #[error("Sharing the file failed at the {step} step. Error: {source}")]
+    ShareFailed {
+        step: ShareStep,
+        uploaded: Option<Box<UploadedFile>>,
+        #[source]
+        source: Box<Error>,
+    },
+
+    #[error(
+        "Not enough storage left on the account: {needed} bytes needed, {available} available"
+    )]
+    QuotaExceeded { needed: u64, available: u64 },
+
+    // expected is the closest unused recorded request, None once every one was replayed. Both
+    // are described the way the cassette stores them, tokens redacted.
+    #[cfg(feature = "vcr")]
+    #[error(
+        "No unused interaction in the cassette at {path:?} matches the request\nexpected:\n{}actual:\n{actual}",
+        .expected.as_deref().unwrap_or("  nothing, every interaction was replayed\n")
+    )]
+    CassetteMismatch {
+        path: PathBuf,
+        expected: Option<String>,
+        actual: String,
+    },
+}
+
+impl Error {
+    // What the response looked like, worth including when reporting a problem to gofile
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Error::HttpStatusCodeError(_, _, context) | Error::ApiStatusError(_, _, context) => {
+                Some(context)
+            }
+            _ => None,
+        }
+    }
+
+    // The gofile status behind the error, including the ones with a variant of their own
+    pub fn code(&self) -> Option<GofileErrorCode> {
+        match self {
+            Error::ApiStatusError(_, code, _) => Some(code.clone()),
+            Error::PasswordRequired(_) => Some(GofileErrorCode::PasswordRequired),
+            Error::WrongPassword(_) => Some(GofileErrorCode::WrongPassword),
+            Error::NotPremium(_) => Some(GofileErrorCode::NotPremium),
+            Error::RateLimited { .. } => Some(GofileErrorCode::RateLimit),
+            _ => None,
+        }
+    }
+
+    // Whether the same request may work later: connection problems, 5xx and 429 responses,
+    // stalled transfers and gofile rate limiting. A corrupted upload is retried by the upload
+    // itself, it's not a property of the request.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::HttpRequestError(err) => {
+                err.is_connect()
+                    || err.is_timeout()
+                    || err.is_request()
+                    || err.status().is_some_and(is_retryable_status)
+            }
+            Error::HttpStatusCodeError(_, status, _) => is_retryable_status(*status),
+            Error::Stalled(..) => true,
+            _ => self.code().is_some_and(|code| code.is_retryable()),
+        }
+    }
+
+    pub fn status_code(&self) -> Option<StatusCode> {
+        match self {
+            Error::HttpRequestError(err) => err.status(),
+            Error::HttpStatusCodeError(_, status, _) => Some(*status),
+            _ => None,
+        }
+    }
+
+    // The request the error came from, when there was one
+    pub fn url(&self) -> Option<&Url> {
+        match self {
+            Error::HttpRequestError(err) => err.url(),
+            Error::HttpStatusCodeError(url, ..)
+            | Error::ApiStatusError(url, ..)
+            | Error::Decode { url, .. }
+            | Error::WebsiteTokenNotFound(url)
+            | Error::InvalidContentUrl(url, _)
+            | Error::PasswordRequired(url)
+            | Error::WrongPassword(url)
+            | Error::NotPremium(url)
+            | Error::Stalled(url, _)
+            | Error::RateLimited { url, .. }
+            | Error::RedirectBlocked(url)
+            | Error::DownloadForbidden(url)
+            | Error::RangeIgnored(url, ..) => Some(url),
+            _ => None,
+        }
+    }
+
+    // The io::ErrorKind the error becomes when converted to an io::Error
+    pub fn kind_hint(&self) -> io::ErrorKind {
+        match self {
+            Error::CouldntOpenFile(_, err) | Error::CouldntWriteFile(_, err) => err.kind(),
+            Error::CouldntWrite(err) => err.kind(),
+            Error::HttpRequestError(err) if err.is_timeout() => io::ErrorKind::TimedOut,
+            Error::HttpRequestError(err) => err.status().map_or(io::ErrorKind::Other, status_kind),
+            Error::HttpStatusCodeError(_, status, _) => status_kind(*status),
+            Error::Stalled(..) => io::ErrorKind::TimedOut,
+            Error::FileExists(_) => io::ErrorKind::AlreadyExists,
+            Error::ChecksumMismatch { .. } | Error::Decode { .. } | Error::RangeIgnored(..) => {
+                io::ErrorKind::InvalidData
+            }
+            Error::DownloadForbidden(_) => io::ErrorKind::PermissionDenied,
+            Error::ShareFailed { source, .. } => source.kind_hint(),
+            Error::InvalidFilePath(..)
+            | Error::InvalidName(..)
+            | Error::InvalidCode(..)
+            | Error::InvalidMime(..)
+            | Error::InvalidChunkSize(_)
+            | Error::InvalidPattern(..)
+            | Error::InvalidBaseUrl(..)
+            | Error::InvalidConfig(_)
+            | Error::InvalidExpire(_)
+            | Error::InvalidContentUrl(..) => io::ErrorKind::InvalidInput,
+            _ => match self.code() {
+                Some(GofileErrorCode::NotFound) => io::ErrorKind::NotFound,
+                Some(
+                    GofileErrorCode::Auth
+                    | GofileErrorCode::PasswordRequired
+                    | GofileErrorCode::WrongPassword
+                    | GofileErrorCode::NotPremium,
+                ) => io::ErrorKind::PermissionDenied,
+                Some(GofileErrorCode::RateLimit) => io::ErrorKind::WouldBlock,
+                _ => io::ErrorKind::Other,
+            },
+        }
+    }
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+fn status_kind(status: StatusCode) -> io::ErrorKind {
+    match status {
+        StatusCode::NOT_FOUND => io::ErrorKind::NotFound,
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => io::ErrorKind::PermissionDenied,
+        StatusCode::TOO_MANY_REQUESTS => io::ErrorKind::WouldBlock,
+        StatusCode::REQUEST_TIMEOUT | StatusCode::GATEWAY_TIMEOUT => io::ErrorKind::TimedOut,
+        _ => io::ErrorKind::Other,
+    }
+}
+
+// For callers that only deal in io errors, like a filesystem layer. The original error stays
+// reachable through get_ref and source.
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        io::Error::new(err.kind_hint(), err)
+    }
+}
+
+// The `error-*` statuses gofile answers with, an unknown one is kept as it came
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum GofileErrorCode {
+    NotFound,
+    NotPremium,
+    RateLimit,
+    Auth,
+    PasswordRequired,
+    WrongPassword,
+    NoServer,
+    Other(String),
+}
+
+impl GofileErrorCode {
+    // Worth trying again later, the others fail the same way every time
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, GofileErrorCode::RateLimit)
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            GofileErrorCode::NotFound => "error-notFound",
+            GofileErrorCode::NotPremium => "error-notPremium",
+            GofileErrorCode::RateLimit => "error-rateLimit",
+            GofileErrorCode::Auth => "error-auth",
+            GofileErrorCode::PasswordRequired => "error-passwordRequired",
+            GofileErrorCode::WrongPassword => "error-passwordWrong",
+            GofileErrorCode::NoServer => "error-noServer",
+            GofileErrorCode::Other(status) => status,
+        }
+    }
+}
+
+impl From<&str> for GofileErrorCode {
+    fn from(status: &str) -> Self {
+        match status {
+            "error-notFound" => GofileErrorCode::NotFound,
+            "error-notPremium" => GofileErrorCode::NotPremium,
+            "error-rateLimit" => GofileErrorCode::RateLimit,
+            "error-auth" => GofileErrorCode::Auth,
+            "error-passwordRequired" => GofileErrorCode::PasswordRequired,
+            "error-passwordWrong" => GofileErrorCode::WrongPassword,
+            "error-noServer" => GofileErrorCode::NoServer,
+            _ => GofileErrorCode::Other(status.into()),
+        }
+    }
+}
+
+impl std::fmt::Display for GofileErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Authorized requests send the token in the query, reqwest keeps the url in its errors
+impl From<reqwest::Error> for Error {
+    fn from(mut err: reqwest::Error) -> Self {
+        if let Some(url) = err.url_mut() {
+            *url = token::redacted_url(url);
+        }
+        Error::HttpRequestError(err)
+    }
+}
+
+// The start of a body that didn't decode, enough to see what gofile changed
+fn body_snippet(body: &[u8]) -> Box<str> {
+    token::scrub_tokens(&lossy_prefix(body, DECODE_SNIPPET_LIMIT)).into()
+}
+
+fn lossy_prefix(body: &[u8], limit: usize) -> String {
+    let limit = body.len().min(limit);
+    let mut text = String::from_utf8_lossy(&body[..limit]).into_owned();
+    // a character cut in half at the limit isn't worth a replacement char
+    if limit < body.len() && text.ends_with(char::REPLACEMENT_CHARACTER) {
+        text.pop();
+    }
+    text
+}
+
+// Headers kept in ErrorContext, the ids gofile and cloudflare need to find a request
+const CONTEXT_HEADERS: [&str; 3] = ["x-request-id", "cf-ray", "content-type"];
+const CONTEXT_BODY_LIMIT: usize = 4096;
+const DECODE_SNIPPET_LIMIT: usize = 2048;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ErrorContext {
+    // the start of the response body, lossily decoded, with tokens redacted
+    pub body: String,
+    pub body_truncated: bool,
+    pub headers: Vec<(String, String)>,
+}
+
+impl ErrorContext {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn from_headers(res: &Response) -> Self {
+        let headers = CONTEXT_HEADERS
+            .iter()
+            .filter_map(|name| {
+                let value = res.headers().get(*name)?.to_str().ok()?;
+                Some((name.to_string(), value.into()))
+            })
+            .collect();
+        Self {
+            headers,
+            ..Default::default()
+        }
+    }
+
+    fn with_body(mut self, body: &[u8]) -> Self {
+        self.body = token::scrub_tokens(&lossy_prefix(body, CONTEXT_BODY_LIMIT));
+        self.body_truncated = CONTEXT_BODY_LIMIT < body.len();
+        self
+    }
+
+    // Reads no more of the body than is kept, error pages may be large
+    pub(crate) async fn read(mut res: Response) -> Box<Self> {
+        let context = Self::from_headers(&res);
+        let mut body = Vec::new();
+        while body.len() <= CONTEXT_BODY_LIMIT {
+            match res.chunk().await {
+                Ok(Some(chunk)) => body.extend_from_slice(&chunk),
+                _ => break,
+            }
+        }
+        Box::new(context.with_body(&body))
+    }
 }
 
+const API_BASE_URL: &str = "https://api.gofile.io";
+
+pub(crate) fn default_base_url() -> Url {
+    Url::parse(API_BASE_URL).expect("the default base url is valid")
+}
+const WEBSITE_GLOBAL_JS_URL: &str = "https://gofile.io/dist/js/global.js";
+
 #[derive(Debug)]
 pub struct Api {
-    pub base_url: String,
+    pub base_url: Url,
+
+    /// Website token (`wt`) sent when fetching content anonymously. Authorized
+    /// requests don't need it.
+    pub website_token: Option<String>,
+
+    // shared by every request so connections are reused, clones share the same pool
+    pub client: ApiClient,
 }
 
 impl Default for Api {
     fn default() -> Self {
         Self {
-            base_url: "https://api.gofile.io".into(),
+            base_url: default_base_url(),
+            website_token: None,
+            client: ApiClient::default(),
         }
     }
 }
 
 impl Api {
-    pub fn authorize(&self, token: impl Into<String>) -> AuthorizedApi {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn builder() -> ApiBuilder {
+        ApiBuilder::new()
+    }
+
+    pub fn with_website_token(mut self, website_token: impl Into<String>) -> Self {
+        self.website_token = Some(website_token.into());
+        self
+    }
+
+    // Accepts a Url or a &str, paths like "https://example.com/gofile" are kept
+    pub fn with_base_url<U>(mut self, base_url: U) -> Result<Self, Error>
+    where
+        U: TryInto<Url> + std::fmt::Display,
+        U::Error: std::fmt::Display,
+    {
+        let raw = base_url.to_string();
+        let base_url = base_url
+            .try_into()
+            .map_err(|err| Error::InvalidBaseUrl(raw, err.to_string()))?;
+        self.base_url = ApiBuilder::checked_url(base_url)?;
+        Ok(self)
+    }
+
+    // Used for every request, including the uploads to the storage servers, so it must
+    // not set a total request timeout that cuts long uploads and must allow streaming bodies
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client.into();
+        self
+    }
+
+    pub fn authorize(&self, token: impl Into<Token>) -> AuthorizedApi {
         AuthorizedApi {
             base_url: self.base_url.clone(),
             token: token.into(),
+            client: self.client.clone(),
+            tier: Default::default(),
+        }
+    }
+
+    // Anonymous uploads return a guest account token that owns the new folder
+    pub fn authorize_guest(&self, uploaded_file: &UploadedFile) -> Result<AuthorizedApi, Error> {
+        match &uploaded_file.guest_token {
+            Some(token) => Ok(self.authorize(token.clone())),
+            None => Err(Error::MissingGuestToken(uploaded_file.file_id)),
         }
     }
 
-    pub async fn get_server(&self) -> Result<ServerApi, Error> {
-        let Servers { servers } = Api::get(&self.base_url, "servers").await?;
-        let server = servers
+    // The first server matching preference, pick from get_servers and pass it to server for
+    // any other choice
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(preference = ?preference)))]
+    pub async fn get_server(&self, preference: ZonePreference) -> Result<ServerApi, Error> {
+        let servers = self.get_servers().await?;
+        match (servers.select(&preference), preference) {
+            (Some(server), _) => self.server(server),
+            (None, ZonePreference::Zone(zone)) => Err(Error::NoServerInZone(zone)),
+            (None, _) => Err(Error::EmptyServerList),
+        }
+    }
+
+    // Every server gofile lists, suggested ones first
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn get_servers(&self) -> Result<Servers, Error> {
+        let servers: Servers = Api::get(&self.client, &self.base_url, "servers").await?;
+        if servers.all().is_empty() {
+            return Err(Error::EmptyServerList);
+        }
+        Ok(servers)
+    }
+
+    // Asks gofile for the servers of zone only, what it answers is still checked
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(zone = %zone)))]
+    pub async fn get_server_in_zone(&self, zone: Zone) -> Result<ServerApi, Error> {
+        let params = vec![("zone", zone.to_string())];
+        let servers: Servers =
+            Api::get_with_params(&self.client, &self.base_url, "servers", params).await?;
+        let server = servers.in_zone(&zone).into_iter().next();
+        self.server(server.ok_or(Error::NoServerInZone(zone))?)
+    }
+
+    // Probes every server at once and takes the first one to answer, answers that came in
+    // together go to the one get_server would pick. Probing stops after probe_timeout and
+    // get_server's choice is used when no server answered, so this adds at most probe_timeout.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(probe_timeout = ?probe_timeout)))]
+    pub async fn get_fastest_server(&self, probe_timeout: Duration) -> Result<ServerApi, Error> {
+        let servers = self.get_servers().await?;
+        let mut candidates = servers
+            .all()
             .into_iter()
-            .next()
-            .ok_or(Error::EmptyServerList)?
-            .name;
+            .map(|server| self.server(server))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut probes: FuturesUnordered<_> = candidates
+            .iter()
+            .enumerate()
+            .map(|(i, server)| async move { server.probe().await.then_some(i) })
+            .collect();
+        let race = async {
+            while let Some(answered) = probes.next().await {
+                let Some(mut fastest) = answered else {
+                    continue;
+                };
+                loop {
+                    match probes.next().now_or_never() {
+                        Some(Some(Some(i))) => fastest = fastest.min(i),
+                        Some(Some(None)) => continue,
+                        _ => return Some(fastest),
+                    }
+                }
+            }
+            None
+        };
+        let fastest = tokio::time::timeout(probe_timeout, race)
+            .await
+            .ok()
+            .flatten();
+        // cancels the probes still running, they borrow the candidates
+        drop(probes);
+        match fastest {
+            Some(i) => {
+                let server = candidates.swap_remove(i);
+                event!(debug, url = %server.base_url, "fastest server");
+                Ok(server)
+            }
+            None => {
+                // nobody answered, pick the way get_server does
+                event!(warn, "no server answered the probe in time");
+                match servers.select(&ZonePreference::default()) {
+                    Some(server) => self.server(server),
+                    None => Err(Error::EmptyServerList),
+                }
+            }
+        }
+    }
+
+    pub fn server(&self, server: &Server) -> Result<ServerApi, Error> {
+        let base_url = format!("https://{}.gofile.io", server.name);
         Ok(ServerApi {
-            base_url: format!("https://{}.gofile.io", server),
+            base_url: Url::parse(&base_url)
+                .map_err(|err| Error::InvalidBaseUrl(base_url, err.to_string()))?,
+            client: self.client.clone(),
+        })
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(url = %url)))]
+    pub async fn get_content_by_url(&self, url: &Url) -> Result<Content, Error> {
+        let code = Api::code_from_content_url(url)?;
+        self.get_content_by_code(code).await
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(code = code.as_ref())))]
+    pub async fn get_content_by_code(&self, code: impl AsRef<str>) -> Result<Content, Error> {
+        let params = match &self.website_token {
+            Some(website_token) => vec![("wt", website_token.clone())],
+            None => vec![],
+        };
+        Api::get_with_params(
+            &self.client,
+            &self.base_url,
+            format!("contents/{}", code.as_ref()),
+            params,
+        )
+        .await
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn fetch_website_token(&self) -> Result<String, Error> {
+        let url = Url::parse(WEBSITE_GLOBAL_JS_URL).unwrap();
+        Self::fetch_website_token_from(&self.client, url).await
+    }
+
+    async fn fetch_website_token_from(client: &ApiClient, url: Url) -> Result<String, Error> {
+        let res = client
+            .send(client.api_request(Method::GET, url.clone()))
+            .await?;
+        let status = res.status();
+        if status != StatusCode::OK {
+            return Err(Error::HttpStatusCodeError(
+                url,
+                status,
+                ErrorContext::read(res).await,
+            ));
+        }
+        let js = res.text().await?;
+        Self::website_token_from_js(&js).ok_or(Error::WebsiteTokenNotFound(url))
+    }
+
+    fn website_token_from_js(js: &str) -> Option<String> {
+        js.match_indices("wt").find_map(|(idx, _)| {
+            // skip longer identifiers ending in wt, like swt or newt
+            let before = js[..idx].chars().next_back();
+            if before.is_some_and(|c| c.is_alphanumeric() || matches!(c, '_' | '$')) {
+                return None;
+            }
+            let rest = js[idx + 2..].trim_start();
+            let rest = rest
+                .strip_prefix('=')
+                .or_else(|| rest.strip_prefix(':'))?
+                .trim_start();
+            let quote = rest.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+            let (token, _) = rest[1..].split_once(quote)?;
+            Some(token.to_string()).filter(|token| !token.is_empty())
         })
     }
 
-    fn code_from_content_url(url: &Url) -> Result<String, Error> {
+    fn code_from_content_url(url: &Url) -> Result<Code, Error> {
         let Some(mut segs) = url.path_segments() else {
             return Err(Error::InvalidContentUrl(
                 url.clone(),
@@ -92,40 +745,93 @@ impl Api {
                 "The content url must have two path segments like '/d/XXXX'.".into(),
             ));
         };
-        Ok(code.into())
+        Code::new(code).map_err(|err| Error::InvalidContentUrl(url.clone(), err.to_string()))
     }
 
-    fn url(base_url: impl AsRef<str>, path: impl AsRef<str>) -> Url {
-        let path = path.as_ref();
-        Url::parse(&(format!("{}/{}", base_url.as_ref(), path))).unwrap()
+    fn hash_password(password: &str) -> String {
+        hex::encode(Sha256::digest(password.as_bytes()))
+    }
+
+    // The path is appended to whatever path the base url already has, with or without a
+    // trailing slash
+    fn url(base_url: &Url, path: impl AsRef<str>) -> Result<Url, Error> {
+        let mut base_url = base_url.clone();
+        if !base_url.path().ends_with('/') {
+            base_url.set_path(&format!("{}/", base_url.path()));
+        }
+        let url = base_url
+            .join(path.as_ref().trim_start_matches('/'))
+            .map_err(|err| Error::InvalidBaseUrl(base_url.to_string(), err.to_string()))?;
+        // an absolute url as the path would send the token elsewhere
+        if url.origin() != base_url.origin() {
+            return Err(Error::InvalidBaseUrl(
+                base_url.to_string(),
+                format!("The path {:?} leaves the base url.", path.as_ref()),
+            ));
+        }
+        Ok(url)
     }
 
-    async fn get<T>(base_url: impl AsRef<str>, path: impl AsRef<str>) -> Result<T, Error>
+    async fn get<T>(client: &ApiClient, base_url: &Url, path: impl AsRef<str>) -> Result<T, Error>
     where
         T: DeserializeOwned,
     {
-        Self::get_with_params(base_url, path, vec![]).await
+        Self::get_with_params(client, base_url, path, vec![]).await
     }
 
     async fn get_with_params<T>(
-        base_url: impl AsRef<str>,
+        client: &ApiClient,
+        base_url: &Url,
+        path: impl AsRef<str>,
+        params: Vec<(&str, String)>,
+    ) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        Self::get_with_params_and_token(client, base_url, path, params, None).await
+    }
+
+    async fn get_with_params_and_token<T>(
+        client: &ApiClient,
+        base_url: &Url,
         path: impl AsRef<str>,
-        params: Vec<(&'static str, String)>,
+        params: Vec<(&str, String)>,
+        token: Option<&str>,
     ) -> Result<T, Error>
     where
         T: DeserializeOwned,
     {
-        let mut url = Self::url(base_url, path);
+        let mut url = Self::url(base_url, path)?;
         for (key, value) in params {
             url.query_pairs_mut().append_pair(key, &value);
         }
 
-        let res = reqwest::get(url).await?;
+        let req = client.api_request(Method::GET, url);
+        let req = if let Some(token) = token {
+            req.bearer_auth(token)
+        } else {
+            req
+        };
+        let res = client.send(req).await?;
         Self::parse_res(res).await
     }
 
+    async fn post_with_payload<T, P>(
+        client: &ApiClient,
+        base_url: &Url,
+        path: impl AsRef<str>,
+        payload: P,
+    ) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+        P: Serialize,
+    {
+        Self::request_with_payload(client, Method::POST, base_url, path, payload).await
+    }
+
     async fn put_with_payload<T, P>(
-        base_url: impl AsRef<str>,
+        client: &ApiClient,
+        base_url: &Url,
         path: impl AsRef<str>,
         payload: P,
     ) -> Result<T, Error>
@@ -133,120 +839,485 @@ impl Api {
         T: DeserializeOwned,
         P: Serialize,
     {
-        Self::request_with_payload(Method::PUT, base_url, path, payload).await
+        Self::request_with_payload(client, Method::PUT, base_url, path, payload).await
     }
 
     async fn delete_with_payload<T, P>(
-        base_url: impl AsRef<str>,
+        client: &ApiClient,
+        base_url: &Url,
+        path: impl AsRef<str>,
+        payload: P,
+    ) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+        P: Serialize,
+    {
+        Self::request_with_payload(client, Method::DELETE, base_url, path, payload).await
+    }
+
+    async fn put_with_payload_and_token<T, P>(
+        client: &ApiClient,
+        base_url: &Url,
         path: impl AsRef<str>,
+        token: &str,
         payload: P,
     ) -> Result<T, Error>
     where
         T: DeserializeOwned,
         P: Serialize,
     {
-        Self::request_with_payload(Method::DELETE, base_url, path, payload).await
+        Self::request_with_payload_and_token(
+            client,
+            Method::PUT,
+            base_url,
+            path,
+            Some(token),
+            payload,
+        )
+        .await
     }
 
     async fn request_with_payload<T, P>(
+        client: &ApiClient,
+        method: Method,
+        base_url: &Url,
+        path: impl AsRef<str>,
+        payload: P,
+    ) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+        P: Serialize,
+    {
+        Self::request_with_payload_and_token(client, method, base_url, path, None, payload).await
+    }
+
+    async fn request_with_payload_and_token<T, P>(
+        client: &ApiClient,
         method: Method,
-        base_url: impl AsRef<str>,
+        base_url: &Url,
         path: impl AsRef<str>,
+        token: Option<&str>,
         payload: P,
     ) -> Result<T, Error>
     where
         T: DeserializeOwned,
         P: Serialize,
     {
-        let url = Self::url(base_url, path);
-        let client = reqwest::Client::new();
-        let res = client.request(method, url).json(&payload).send().await?;
+        let url = Self::url(base_url, path)?;
+        let req = client.api_request(method, url).json(&payload);
+        let req = if let Some(token) = token {
+            req.bearer_auth(token)
+        } else {
+            req
+        };
+        let res = client.send(req).await?;
         Self::parse_res(res).await
     }
 
+    fn status_error(
+        url: Url,
+        status: String,
+        retry_after: Option<Duration>,
+        context: Box<ErrorContext>,
+    ) -> Error {
+        event!(debug, status, "gofile answered with an error status");
+        match GofileErrorCode::from(status.as_str()) {
+            GofileErrorCode::PasswordRequired => Error::PasswordRequired(url),
+            GofileErrorCode::WrongPassword => Error::WrongPassword(url),
+            GofileErrorCode::NotPremium => Error::NotPremium(url),
+            GofileErrorCode::RateLimit => {
+                event!(warn, ?retry_after, "rate limited by gofile");
+                Error::RateLimited { url, retry_after }
+            }
+            code => Error::ApiStatusError(url, code, context),
+        }
+    }
+
+    // Either a number of seconds or an http date
+    fn retry_after(res: &Response) -> Option<Duration> {
+        let value = res.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+        if let Ok(secs) = value.parse() {
+            return Some(Duration::from_secs(secs));
+        }
+        let date = DateTime::parse_from_rfc2822(value).ok()?;
+        Some(
+            (date.with_timezone(&Utc) - Utc::now())
+                .to_std()
+                .unwrap_or_default(),
+        )
+    }
+
     async fn parse_res<T>(res: Response) -> Result<T, Error>
     where
         T: DeserializeOwned,
     {
+        if let Some(location) = redirect_location(&res) {
+            return Err(Error::RedirectBlocked(location));
+        }
         let status = res.status();
-        let url = res.url().clone();
+        // kept in the error, which must not show the token
+        let url = token::redacted_url(res.url());
+        let retry_after = Self::retry_after(&res);
+        let context = ErrorContext::from_headers(&res);
+        let body = res.bytes().await?;
+        let res_obj = serde_json::from_slice::<ApiResult<Value>>(&body);
+        let context = || Box::new(context.with_body(&body));
         if status != StatusCode::OK {
-            return match res.json::<ApiResult<Value>>().await {
-                Ok(res_obj) => Err(Error::ApiStatusError(url, res_obj.status)),
-                Err(_) => Err(Error::HttpStatusCodeError(url, status)),
+            return match res_obj {
+                Ok(res_obj) => Err(Self::status_error(
+                    url,
+                    res_obj.status,
+                    retry_after,
+                    context(),
+                )),
+                Err(_) if status == StatusCode::TOO_MANY_REQUESTS => Err(Self::status_error(
+                    url,
+                    "error-rateLimit".into(),
+                    retry_after,
+                    context(),
+                )),
+                Err(_) => Err(Error::HttpStatusCodeError(url, status, context())),
             };
         };
 
-        let res_obj = res.json::<ApiResult<T>>().await?;
+        let decode_error = |serde_error| Error::Decode {
+            url: url.clone(),
+            serde_error,
+            body_snippet: body_snippet(&body),
+        };
+        let res_obj = res_obj.map_err(decode_error)?;
         if res_obj.status != "ok" {
-            return Err(Error::ApiStatusError(url, res_obj.status));
+            return Err(Self::status_error(
+                url,
+                res_obj.status,
+                retry_after,
+                context(),
+            ));
         };
 
-        Ok(res_obj.data)
+        serde_json::from_value(res_obj.data).map_err(decode_error)
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct AuthorizedApi {
-    pub base_url: String,
-    pub token: String,
+    pub base_url: Url,
+    pub token: Token,
+    pub client: ApiClient,
+    // looked up once for the premium only calls, shared by the clones
+    tier: Arc<tokio::sync::OnceCell<Tier>>,
 }
 
 impl AuthorizedApi {
-    pub async fn get_server(&self) -> Result<AuthorizedServerApi, Error> {
-        let ServerApi { base_url } = Api::default().get_server().await?;
-        Ok(AuthorizedServerApi {
-            base_url,
-            token: self.token.clone(),
-        })
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn get_server(
+        &self,
+        preference: ZonePreference,
+    ) -> Result<AuthorizedServerApi, Error> {
+        let server = self.server_list_api().get_server(preference).await?;
+        Ok(server.authorize(self.token.clone()))
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(zone = %zone)))]
+    pub async fn get_server_in_zone(&self, zone: Zone) -> Result<AuthorizedServerApi, Error> {
+        let server = self.server_list_api().get_server_in_zone(zone).await?;
+        Ok(server.authorize(self.token.clone()))
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(probe_timeout = ?probe_timeout)))]
+    pub async fn get_fastest_server(
+        &self,
+        probe_timeout: Duration,
+    ) -> Result<AuthorizedServerApi, Error> {
+        let server = self
+            .server_list_api()
+            .get_fastest_server(probe_timeout)
+            .await?;
+        Ok(server.authorize(self.token.clone()))
+    }
+
+    // The server list always comes from the public api, whatever the base url is
+    fn server_list_api(&self) -> Api {
+        Api {
+            base_url: default_base_url(),
+            website_token: None,
+            client: self.client.clone(),
+        }
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(url = %url)))]
     pub async fn get_content(&self, url: &Url) -> Result<Content, Error> {
+        self.get_content_by_url(url).await
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(url = %url)))]
+    pub async fn get_content_by_url(&self, url: &Url) -> Result<Content, Error> {
         let code = Api::code_from_content_url(url)?;
         self.get_content_by_code(code).await
     }
 
-    pub async fn get_content_by_id(&self, content_id: Uuid) -> Result<Content, Error> {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content_id))
+    )]
+    pub async fn get_content_by_id(&self, content_id: ContentId) -> Result<Content, Error> {
         self.get_content_impl(content_id.to_string()).await
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(code = code.as_ref())))]
     pub async fn get_content_by_code(&self, code: impl AsRef<str>) -> Result<Content, Error> {
         self.get_content_impl(code).await
     }
 
-    async fn get_content_impl(&self, id_or_code: impl AsRef<str>) -> Result<Content, Error> {
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(code = code.as_ref())))]
+    pub async fn get_content_with_password(
+        &self,
+        code: impl AsRef<str>,
+        password: &str,
+    ) -> Result<Content, Error> {
         Api::get_with_params(
+            &self.client,
             &self.base_url,
-            format!("contents/{}", id_or_code.as_ref()),
-            vec![("token", self.token.clone())],
+            format!("contents/{}", code.as_ref()),
+            vec![
+                ("token", self.token.as_str().to_string()),
+                ("password", Api::hash_password(password)),
+            ],
         )
         .await
     }
 
-    pub async fn get_account_id(&self) -> Result<Uuid, Error> {
+    async fn get_content_impl(&self, id_or_code: impl AsRef<str>) -> Result<Content, Error> {
         Api::get_with_params(
+            &self.client,
             &self.base_url,
-            "accounts/getid",
-            vec![("token", self.token.clone())],
+            format!("contents/{}", id_or_code.as_ref()),
+            vec![("token", self.token.as_str().to_string())],
         )
         .await
     }
 
-    pub async fn get_account_details(&self, account_id: Uuid) -> Result<AccountDetails, Error> {
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(folder_id = %folder_id)))]
+    pub async fn get_content_with_options(
+        &self,
+        folder_id: FolderId,
+        options: GetContentOptions,
+    ) -> Result<Content, Error> {
+        let mut params = vec![("token", self.token.as_str().to_string())];
+        params.extend(options.params());
         Api::get_with_params(
+            &self.client,
             &self.base_url,
-            format!("accounts/{}", account_id),
-            vec![("token", self.token.clone())],
+            format!("contents/{}", folder_id),
+            params,
         )
         .await
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(folder_id = %folder_id)))]
+    pub async fn get_folder_page(
+        &self,
+        folder_id: FolderId,
+        page: u32,
+        page_size: u32,
+    ) -> Result<ContentPage, Error> {
+        Api::get_with_params(
+            &self.client,
+            &self.base_url,
+            format!("contents/{}", folder_id),
+            vec![
+                ("token", self.token.as_str().to_string()),
+                ("page", page.to_string()),
+                ("pageSize", page_size.to_string()),
+            ],
+        )
+        .await
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(folder_id = %folder_id)))]
+    pub async fn get_folder_all_pages(
+        &self,
+        folder_id: FolderId,
+        page_size: u32,
+    ) -> Result<Content, Error> {
+        if page_size == 0 {
+            return Err(Error::InvalidConfig(
+                "The page size must be at least 1.".into(),
+            ));
+        }
+        let ContentPage {
+            content: mut folder,
+            metadata,
+        } = self.get_folder_page(folder_id, 1, page_size).await?;
+        let mut fetched = Self::children_count(&folder);
+        let mut last_fetched = fetched;
+
+        let mut page = 1;
+        while last_fetched == page_size as usize && (fetched as u64) < metadata.total_children {
+            page += 1;
+            let ContentPage { content, .. } =
+                self.get_folder_page(folder_id, page, page_size).await?;
+            last_fetched = Self::merge_folder_page(&mut folder, content);
+            // an empty page ends it even when total_children promised more
+            if last_fetched == 0 {
+                break;
+            }
+            fetched += last_fetched;
+        }
+
+        Ok(folder)
+    }
+
+    fn children_count(folder: &Content) -> usize {
+        match &folder.kind {
+            ContentKind::Folder {
+                children: Some(children),
+                ..
+            } => children.len(),
+            _ => 0,
+        }
+    }
+
+    fn merge_folder_page(folder: &mut Content, page: Content) -> usize {
+        let (
+            ContentKind::Folder {
+                children_ids,
+                children,
+                ..
+            },
+            ContentKind::Folder {
+                children_ids: page_children_ids,
+                children: Some(page_children),
+                ..
+            },
+        ) = (&mut folder.kind, page.kind)
+        else {
+            return 0;
+        };
+
+        let count = page_children.len();
+        for id in page_children_ids {
+            if !children_ids.contains(&id) {
+                children_ids.push(id);
+            }
+        }
+        children
+            .get_or_insert_with(HashMap::new)
+            .extend(page_children);
+        count
+    }
+
+    pub fn walk_folder(
+        &self,
+        folder_id: FolderId,
+        options: WalkOptions,
+    ) -> impl Stream<Item = Result<(PathBuf, Content), Error>> + Send + 'static {
+        let state = WalkState {
+            api: self.clone(),
+            // depth 0 stops before the walked folder is even listed
+            pending_folders: match options.max_depth {
+                Some(0) => vec![],
+                _ => vec![(folder_id, PathBuf::new(), 0)],
+            },
+            options,
+            ready: VecDeque::new(),
+        };
+        stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(item) = state.ready.pop_front() {
+                    return Some((Ok(item), state));
+                }
+                let (folder_id, path, depth) = state.pending_folders.pop()?;
+                let folder = match state.api.get_content_by_id(folder_id.into()).await {
+                    Ok(folder) => folder,
+                    Err(err) => return Some((Err(err), state)),
+                };
+                let ContentKind::Folder {
+                    children: Some(children),
+                    ..
+                } = folder.kind
+                else {
+                    continue;
+                };
+
+                let mut children = children.into_values().collect::<Vec<_>>();
+                children.sort_by(|a, b| a.name.cmp(&b.name));
+                let child_depth = depth + 1;
+                let descend = state.options.max_depth.is_none_or(|max| child_depth < max);
+                // push in reverse so subfolders are visited in name order
+                for child in children.into_iter().rev() {
+                    let child_path = path.join(&child.name);
+                    match child.kind {
+                        ContentKind::Folder { .. } => {
+                            if descend {
+                                state.pending_folders.push((
+                                    FolderId(child.id.0),
+                                    child_path.clone(),
+                                    child_depth,
+                                ));
+                            }
+                            if !state.options.include_folders {
+                                continue;
+                            }
+                        }
+                        ContentKind::Unknown { .. } if !state.options.include_unknown => continue,
+                        _ => {}
+                    }
+                    state.ready.push_front((child_path, child));
+                }
+            }
+        })
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn get_account_id(&self) -> Result<Uuid, Error> {
+        let AccountId { id } = Api::get_with_params(
+            &self.client,
+            &self.base_url,
+            "accounts/getid",
+            vec![("token", self.token.as_str().to_string())],
+        )
+        .await?;
+        Ok(id)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(account_id = %account_id))
+    )]
+    pub async fn get_account_details(&self, account_id: Uuid) -> Result<AccountDetails, Error> {
+        Api::get_with_params(
+            &self.client,
+            &self.base_url,
+            format!("accounts/{}", account_id),
+            vec![("token", self.token.as_str().to_string())],
+        )
+        .await
+    }
+
+    // Tier of the account owning the token
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn get_tier(&self) -> Result<Tier, Error> {
+        let account_id = self.get_account_id().await?;
+        Ok(self.get_account_details(account_id).await?.tier)
+    }
+
+    // get_tier for the checks in front of premium only calls, asked once per token
+    async fn cached_tier(&self) -> Result<&Tier, Error> {
+        self.tier.get_or_try_init(|| self.get_tier()).await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(parent_folder_id = %parent_folder_id))
+    )]
     pub async fn create_folder(
         &self,
-        parent_folder_id: Uuid,
+        parent_folder_id: FolderId,
         folder_name: impl Into<String>,
     ) -> Result<Content, Error> {
-        Api::put_with_payload(
+        Api::post_with_payload(
+            &self.client,
             &self.base_url,
             "contents/createFolder",
             CreateFolderApiPayload {
@@ -258,42 +1329,341 @@ impl AuthorizedApi {
         .await
     }
 
-    pub async fn set_public_option(&self, content_id: Uuid, public: bool) -> Result<NoInfo, Error> {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(root = %root, path = ?path.as_ref()))
+    )]
+    pub async fn create_folder_path(
+        &self,
+        root: FolderId,
+        path: impl AsRef<Path>,
+    ) -> Result<FolderId, Error> {
+        let path = path.as_ref();
+        let mut folder_id = root;
+        let mut created = false;
+        for component in path.components() {
+            let name = match component {
+                Component::Normal(name) => name,
+                Component::CurDir | Component::RootDir => continue,
+                _ => {
+                    return Err(Error::InvalidFilePath(
+                        path.into(),
+                        "The folder path must not contain prefixes or '..'.".into(),
+                    ))
+                }
+            };
+            let Some(name) = name.to_str() else {
+                return Err(Error::InvalidFilePath(
+                    path.into(),
+                    "The folder name couldn't convert to a utf-8 string.".into(),
+                ));
+            };
+
+            // a freshly created folder has no children to look up
+            let existing = if created {
+                None
+            } else {
+                self.find_child_folder(folder_id, name).await?
+            };
+            folder_id = match existing {
+                Some(child_id) => child_id,
+                None => match self.create_folder(folder_id, name).await {
+                    Ok(folder) => {
+                        created = true;
+                        FolderId(folder.id.0)
+                    }
+                    // someone else may have created it concurrently
+                    Err(err) => match self.find_child_folder(folder_id, name).await? {
+                        Some(child_id) => child_id,
+                        None => return Err(err),
+                    },
+                },
+            };
+        }
+        Ok(folder_id)
+    }
+
+    async fn find_child_folder(
+        &self,
+        folder_id: FolderId,
+        name: &str,
+    ) -> Result<Option<FolderId>, Error> {
+        Ok(self
+            .find_child(folder_id, |child| {
+                child.name == name && matches!(child.kind, ContentKind::Folder { .. })
+            })
+            .await?
+            .map(|child| FolderId(child.id.0)))
+    }
+
+    async fn find_child<F>(&self, folder_id: FolderId, pred: F) -> Result<Option<Content>, Error>
+    where
+        F: Fn(&Content) -> bool,
+    {
+        let folder = self.get_content_by_id(folder_id.into()).await?;
+        let ContentKind::Folder {
+            children: Some(children),
+            ..
+        } = folder.kind
+        else {
+            return Ok(None);
+        };
+        Ok(children.into_values().find(|child| pred(child)))
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(root = %root, path)))]
+    pub async fn resolve_path(&self, root: FolderId, path: &str) -> Result<Option<Content>, Error> {
+        self.resolve_path_impl(root, path, false).await
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(root = %root, path)))]
+    pub async fn resolve_path_case_insensitive(
+        &self,
+        root: FolderId,
+        path: &str,
+    ) -> Result<Option<Content>, Error> {
+        self.resolve_path_impl(root, path, true).await
+    }
+
+    async fn resolve_path_impl(
+        &self,
+        root: FolderId,
+        path: &str,
+        case_insensitive: bool,
+    ) -> Result<Option<Content>, Error> {
+        let mut segs = path.split('/').filter(|seg| !seg.is_empty()).peekable();
+        if segs.peek().is_none() {
+            return self.get_content_by_id(root.into()).await.map(Some);
+        }
+
+        let mut folder_id = root;
+        while let Some(seg) = segs.next() {
+            let is_last = segs.peek().is_none();
+            let child = self
+                .find_child(folder_id, |child| {
+                    let name_matches = if case_insensitive {
+                        child.name.to_lowercase() == seg.to_lowercase()
+                    } else {
+                        child.name == seg
+                    };
+                    name_matches && (is_last || matches!(child.kind, ContentKind::Folder { .. }))
+                })
+                .await?;
+            match child {
+                Some(child) if is_last => return Ok(Some(child)),
+                Some(child) => folder_id = FolderId(child.id.0),
+                None => return Ok(None),
+            }
+        }
+        Ok(None)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content_id))
+    )]
+    pub async fn set_public_option(
+        &self,
+        content_id: ContentId,
+        public: bool,
+    ) -> Result<NoInfo, Error> {
         self.set_option(content_id, ContentOpt::Public(public))
             .await
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content_id))
+    )]
+    pub async fn rename_content(
+        &self,
+        content_id: ContentId,
+        new_name: impl Into<String>,
+    ) -> Result<NoInfo, Error> {
+        let new_name = new_name.into();
+        if new_name.is_empty() {
+            return Err(Error::InvalidName(
+                new_name,
+                "The name must not be empty.".into(),
+            ));
+        }
+        if new_name.contains(['/', '\\']) {
+            return Err(Error::InvalidName(
+                new_name,
+                "The name must not contain path separators.".into(),
+            ));
+        }
+        self.set_option(content_id, ContentOpt::Name(new_name))
+            .await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content_id))
+    )]
     pub async fn set_password_option(
         &self,
-        content_id: Uuid,
+        content_id: ContentId,
         password: impl Into<String>,
     ) -> Result<NoInfo, Error> {
         self.set_option(content_id, ContentOpt::Password(password.into()))
             .await
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content_id))
+    )]
     pub async fn set_description_option(
         &self,
-        content_id: Uuid,
+        content_id: ContentId,
         description: impl Into<String>,
     ) -> Result<NoInfo, Error> {
         self.set_option(content_id, ContentOpt::Description(description.into()))
             .await
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content_id))
+    )]
     pub async fn set_expire_option(
         &self,
-        content_id: Uuid,
+        content_id: ContentId,
         expire: DateTime<Utc>,
     ) -> Result<NoInfo, Error> {
         self.set_option(content_id, ContentOpt::Expire(expire))
             .await
     }
 
-    pub async fn set_tags_option<S>(&self, content_id: Uuid, tags: Vec<S>) -> Result<NoInfo, Error>
-    where
-        S: Into<String>,
-    {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content_id))
+    )]
+    pub async fn set_expire_in(
+        &self,
+        content_id: ContentId,
+        duration: chrono::Duration,
+    ) -> Result<DateTime<Utc>, Error> {
+        if duration <= chrono::Duration::zero() {
+            return Err(Error::InvalidExpire(format!(
+                "The duration must be positive, got {}.",
+                duration
+            )));
+        }
+        let Some(expire) = Utc::now().checked_add_signed(duration) else {
+            return Err(Error::InvalidExpire(format!(
+                "The duration {} is out of range.",
+                duration
+            )));
+        };
+        self.set_expire_option(content_id, expire).await?;
+        Ok(expire)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content_id))
+    )]
+    pub async fn set_expire_in_std(
+        &self,
+        content_id: ContentId,
+        duration: std::time::Duration,
+    ) -> Result<DateTime<Utc>, Error> {
+        let duration = chrono::Duration::from_std(duration).map_err(|err| {
+            Error::InvalidExpire(format!("The duration is out of range: {}.", err))
+        })?;
+        self.set_expire_in(content_id, duration).await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content_id))
+    )]
+    pub async fn add_tags(
+        &self,
+        content_id: ContentId,
+        tags: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Result<Vec<String>, Error> {
+        let current = self.get_tags(content_id).await?;
+        let tags = Self::merge_tags(current, tags.into_iter().map(|s| s.into()));
+        self.set_tags_option(content_id, tags.clone()).await?;
+        Ok(tags)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content_id))
+    )]
+    pub async fn remove_tags(
+        &self,
+        content_id: ContentId,
+        tags: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Result<Vec<String>, Error> {
+        let removed = tags.into_iter().map(|s| s.into()).collect::<Vec<String>>();
+        let current = self.get_tags(content_id).await?;
+        let tags = Self::merge_tags(current, [])
+            .into_iter()
+            .filter(|tag| !removed.contains(tag))
+            .collect::<Vec<_>>();
+        self.set_tags_option(content_id, tags.clone()).await?;
+        Ok(tags)
+    }
+
+    async fn get_tags(&self, content_id: ContentId) -> Result<Vec<String>, Error> {
+        let content = self.get_content_by_id(content_id).await?;
+        match content.kind {
+            ContentKind::Folder { tags, .. } | ContentKind::File { tags, .. } => {
+                Ok(tags.unwrap_or_default())
+            }
+            ContentKind::Unknown { .. } => Ok(vec![]),
+        }
+    }
+
+    fn merge_tags(current: Vec<String>, added: impl IntoIterator<Item = String>) -> Vec<String> {
+        let mut tags: Vec<String> = vec![];
+        for tag in current.into_iter().chain(added) {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        tags
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content_id))
+    )]
+    pub async fn clear_password_option(&self, content_id: ContentId) -> Result<NoInfo, Error> {
+        self.set_option(content_id, ContentOpt::ClearPassword).await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content_id))
+    )]
+    pub async fn clear_description_option(&self, content_id: ContentId) -> Result<NoInfo, Error> {
+        self.set_option(content_id, ContentOpt::ClearDescription)
+            .await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content_id))
+    )]
+    pub async fn clear_expire_option(&self, content_id: ContentId) -> Result<NoInfo, Error> {
+        self.set_option(content_id, ContentOpt::ClearExpire).await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content_id))
+    )]
+    pub async fn set_tags_option(
+        &self,
+        content_id: ContentId,
+        tags: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Result<NoInfo, Error> {
         self.set_option(
             content_id,
             ContentOpt::Tags(tags.into_iter().map(|s| s.into()).collect()),
@@ -301,34 +1671,139 @@ impl AuthorizedApi {
         .await
     }
 
-    pub async fn get_direct_link(&self, content_id: Uuid) -> Result<Url, Error> {
-        self.set_direct_link_option(content_id, true).await
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content_id))
+    )]
+    pub async fn get_direct_link(&self, content_id: ContentId) -> Result<Url, Error> {
+        let content = self.get_content_by_id(content_id).await?;
+        if let ContentKind::File {
+            direct_links: Some(direct_links),
+            ..
+        } = content.kind
+        {
+            let now = Utc::now();
+            let active = direct_links
+                .into_values()
+                .find(|direct_link| direct_link.expire_time.is_none_or(|expire| now < expire));
+            if let Some(direct_link) = active {
+                return Ok(direct_link.link);
+            }
+        }
+        Ok(self.create_direct_link(content_id).await?.link)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content_id))
+    )]
+    pub async fn disable_direct_link(&self, content_id: ContentId) -> Result<NoInfo, Error> {
+        let content = self.get_content_by_id(content_id).await?;
+        if let ContentKind::File {
+            direct_links: Some(direct_links),
+            ..
+        } = content.kind
+        {
+            for direct_link_id in direct_links.into_keys() {
+                self.delete_direct_link(content_id, direct_link_id).await?;
+            }
+        }
+        Ok(NoInfo {})
     }
 
-    pub async fn disable_direct_link(&self, content_id: Uuid) -> Result<NoInfo, Error> {
-        self.set_direct_link_option(content_id, false).await
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content_id))
+    )]
+    pub async fn create_direct_link(&self, content_id: ContentId) -> Result<DirectLink, Error> {
+        self.create_direct_link_with_options(content_id, DirectLinkOptions::default())
+            .await
     }
 
-    pub async fn set_direct_link_option<T>(
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content_id))
+    )]
+    pub async fn create_direct_link_with_options(
         &self,
-        content_id: Uuid,
-        direct_link: bool,
-    ) -> Result<T, Error>
+        content_id: ContentId,
+        options: DirectLinkOptions,
+    ) -> Result<DirectLink, Error> {
+        let path = format!("contents/{}/directlinks", content_id);
+        // direct links are premium only, the free tiers fail without sending the request and
+        // an unknown tier is left to the server
+        if self.cached_tier().await?.is_free() {
+            return Err(Error::NotPremium(Api::url(&self.base_url, path)?));
+        }
+        Api::request_with_payload_and_token(
+            &self.client,
+            Method::POST,
+            &self.base_url,
+            path,
+            Some(self.token.as_str()),
+            options,
+        )
+        .await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            fields(content_id = %content_id, direct_link_id = %direct_link_id)
+        )
+    )]
+    pub async fn delete_direct_link(
+        &self,
+        content_id: ContentId,
+        direct_link_id: Uuid,
+    ) -> Result<NoInfo, Error> {
+        Api::request_with_payload_and_token(
+            &self.client,
+            Method::DELETE,
+            &self.base_url,
+            format!("contents/{}/directlinks/{}", content_id, direct_link_id),
+            Some(self.token.as_str()),
+            NoInfo {},
+        )
+        .await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content_id))
+    )]
+    pub async fn set_option<T>(&self, content_id: ContentId, opt: ContentOpt) -> Result<T, Error>
     where
         T: DeserializeOwned,
     {
-        self.set_option(content_id, ContentOpt::DirectLink(direct_link))
-            .await
+        Api::put_with_payload_and_token(
+            &self.client,
+            &self.base_url,
+            format!("contents/{}/update", content_id),
+            self.token.as_str(),
+            UpdateContentApiPayload { opt },
+        )
+        .await
     }
 
-    pub async fn set_option<T>(&self, content_id: Uuid, opt: ContentOpt) -> Result<T, Error>
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(content_id = %content_id))
+    )]
+    pub async fn set_option_legacy<T>(
+        &self,
+        content_id: ContentId,
+        opt: ContentOpt,
+    ) -> Result<T, Error>
     where
         T: DeserializeOwned,
     {
         Api::put_with_payload(
+            &self.client,
             &self.base_url,
             format!("contents/{}/update", content_id),
-            UpdateContentApiPayload {
+            LegacyUpdateContentApiPayload {
                 token: self.token.clone(),
                 opt,
             },
@@ -336,12 +1811,52 @@ impl AuthorizedApi {
         .await
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(contents = content_ids.len()))
+    )]
+    pub async fn set_option_bulk(
+        &self,
+        content_ids: &[ContentId],
+        opt: ContentOpt,
+        concurrency: usize,
+    ) -> BulkReport {
+        let results = stream::iter(content_ids.iter().copied())
+            .map(|content_id| {
+                let opt = opt.clone();
+                async move {
+                    let result = self.set_option::<NoInfo>(content_id, opt).await;
+                    (content_id, result)
+                }
+            })
+            .buffer_unordered(concurrency.max(1))
+            .collect::<Vec<_>>()
+            .await;
+
+        let mut report = BulkReport::default();
+        for (content_id, result) in results {
+            match result {
+                Ok(_) => report.succeeded.push(content_id),
+                Err(err) => report.failed.push((content_id, err)),
+            }
+        }
+        report
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            fields(contents = content_ids.len(), dest_folder_id = %dest_folder_id)
+        )
+    )]
     pub async fn copy_content(
         &self,
-        content_ids: Vec<Uuid>,
-        dest_folder_id: Uuid,
-    ) -> Result<NoInfo, Error> {
-        Api::put_with_payload(
+        content_ids: Vec<ContentId>,
+        dest_folder_id: FolderId,
+    ) -> Result<CopiedContents, Error> {
+        Api::post_with_payload(
+            &self.client,
             &self.base_url,
             "contents/copy",
             CopyContentApiPayload {
@@ -353,8 +1868,41 @@ impl AuthorizedApi {
         .await
     }
 
-    pub async fn delete_content(&self, content_ids: Vec<Uuid>) -> Result<NoInfo, Error> {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            fields(contents = content_ids.len(), dest_folder_id = %dest_folder_id)
+        )
+    )]
+    pub async fn move_contents(
+        &self,
+        content_ids: Vec<ContentId>,
+        dest_folder_id: FolderId,
+    ) -> Result<MovedContents, Error> {
+        Api::put_with_payload(
+            &self.client,
+            &self.base_url,
+            "contents/move",
+            MoveContentApiPayload {
+                token: self.token.clone(),
+                contents_id: content_ids,
+                folder_id_dest: dest_folder_id,
+            },
+        )
+        .await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(contents = content_ids.len()))
+    )]
+    pub async fn delete_contents(
+        &self,
+        content_ids: Vec<ContentId>,
+    ) -> Result<HashMap<ContentId, DeleteStatus>, Error> {
         Api::delete_with_payload(
+            &self.client,
             &self.base_url,
             "contents",
             DeleteContentApiPayload {
@@ -364,80 +1912,552 @@ impl AuthorizedApi {
         )
         .await
     }
+
+    // Untyped access to endpoints this crate doesn't model yet, returning the `data` field.
+    // The token goes in the authorization header, it must not be added to the path or params.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(path)))]
+    pub async fn raw_get(&self, path: &str, params: &[(&str, &str)]) -> Result<Value, Error> {
+        let params = params
+            .iter()
+            .map(|(key, value)| (*key, value.to_string()))
+            .collect();
+        Api::get_with_params_and_token(
+            &self.client,
+            &self.base_url,
+            path,
+            params,
+            Some(self.token.as_str()),
+        )
+        .await
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(path)))]
+    pub async fn raw_post_json(&self, path: &str, body: Value) -> Result<Value, Error> {
+        self.raw_request(Method::POST, path, body).await
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(path)))]
+    pub async fn raw_put_json(&self, path: &str, body: Value) -> Result<Value, Error> {
+        self.raw_request(Method::PUT, path, body).await
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(path)))]
+    pub async fn raw_delete_json(&self, path: &str, body: Value) -> Result<Value, Error> {
+        self.raw_request(Method::DELETE, path, body).await
+    }
+
+    async fn raw_request(&self, method: Method, path: &str, body: Value) -> Result<Value, Error> {
+        Api::request_with_payload_and_token(
+            &self.client,
+            method,
+            &self.base_url,
+            path,
+            Some(self.token.as_str()),
+            body,
+        )
+        .await
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct WalkOptions {
+    // depth 1 means only the direct children of the walked folder, depth 0 yields nothing
+    pub max_depth: Option<usize>,
+    pub include_folders: bool,
+    // contents of a type this crate doesn't know, see ContentKind::Unknown
+    pub include_unknown: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct BulkReport {
+    pub succeeded: Vec<ContentId>,
+    pub failed: Vec<(ContentId, Error)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UploadedMessage {
+    // generated per upload, the gofile id is only known once the upload finished
+    pub uuid: Uuid,
+    pub uploaded: u64,
+    // unknown when uploading from a reader without a size
+    pub total: Option<u64>,
+    // set once an upload is restarted, the first message of each retry reports 0 bytes
+    pub retry: bool,
+    pub file_name: Arc<str>,
+    // since the start of this attempt
+    pub elapsed: Duration,
+    // average over the elapsed time
+    pub bytes_per_sec: u64,
+}
+
+impl UploadedMessage {
+    pub fn percent(&self) -> Option<f64> {
+        match self.total {
+            Some(0) => Some(100.0),
+            Some(total) => Some(self.uploaded as f64 * 100.0 / total as f64),
+            None => None,
+        }
+    }
+}
+
+struct WalkState {
+    api: AuthorizedApi,
+    options: WalkOptions,
+    pending_folders: Vec<(FolderId, PathBuf, usize)>,
+    ready: VecDeque<(PathBuf, Content)>,
+}
+
+// Holds back progress messages until both enough bytes and enough time went by since the
+// previous one, the final count is always sent once. Reporting stops for good once the
+// receiver is gone.
+struct ProgressReporter {
+    sink: ProgressSink,
+    closed: bool,
+    abort_on_close: bool,
+    uuid: Uuid,
+    file_name: Arc<str>,
+    started_at: Instant,
+    total: Option<u64>,
+    uploaded: u64,
+    sent: Option<u64>,
+    sent_at: Instant,
+    every_bytes: u64,
+    every: Duration,
+}
+
+impl ProgressReporter {
+    async fn chunk(&mut self, len: usize) {
+        self.uploaded += len as u64;
+        #[cfg(feature = "tracing")]
+        self.milestone(len as u64);
+        let done = self.total == Some(self.uploaded);
+        let due = self.uploaded - self.sent.unwrap_or(0) >= self.every_bytes
+            && self.sent_at.elapsed() >= self.every;
+        if done || due {
+            self.send().await;
+        }
+    }
+
+    async fn finish(&mut self) {
+        if self.sent != Some(self.uploaded) {
+            self.send().await;
+        }
+    }
+
+    fn aborted(&self) -> bool {
+        self.closed && self.abort_on_close
+    }
+
+    // An event every 10%, whatever the progress interval is
+    #[cfg(feature = "tracing")]
+    fn milestone(&self, len: u64) {
+        let Some(total) = self.total.filter(|total| *total > 0) else {
+            return;
+        };
+        let tenths = |bytes: u64| bytes.saturating_mul(10) / total;
+        let reached = tenths(self.uploaded);
+        if reached > tenths(self.uploaded - len) {
+            tracing::debug!(
+                uploaded = self.uploaded,
+                total,
+                "upload at {}%",
+                reached.min(10) * 10
+            );
+        }
+    }
+
+    async fn send(&mut self) {
+        if self.closed {
+            return;
+        }
+        self.sent = Some(self.uploaded);
+        self.sent_at = Instant::now();
+        let elapsed = self.started_at.elapsed();
+        let message = UploadedMessage {
+            uuid: self.uuid,
+            uploaded: self.uploaded,
+            total: self.total,
+            retry: false,
+            file_name: self.file_name.clone(),
+            elapsed,
+            bytes_per_sec: bytes_per_sec(self.uploaded, elapsed),
+        };
+        self.closed = !self.sink.send(message).await;
+    }
+}
+
+pub(crate) fn bytes_per_sec(bytes: u64, elapsed: Duration) -> u64 {
+    match elapsed.as_secs_f64() {
+        secs if secs > 0.0 => (bytes as f64 / secs) as u64,
+        _ => 0,
+    }
 }
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct ServerApi {
-    pub base_url: String,
+    pub base_url: Url,
+
+    #[serde(skip)]
+    pub client: ApiClient,
 }
 
 impl ServerApi {
+    pub fn authorize(self, token: impl Into<Token>) -> AuthorizedServerApi {
+        AuthorizedServerApi {
+            base_url: self.base_url,
+            token: token.into(),
+            client: self.client,
+        }
+    }
+
+    // Done once the headers of a HEAD request came back, false when the server failed
+    async fn probe(&self) -> bool {
+        let req = self.client.api_request(Method::HEAD, self.base_url.clone());
+        matches!(self.client.send(req).await, Ok(res) if !res.status().is_server_error())
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(path = ?path.as_ref())))]
     pub async fn upload_file(&self, path: impl AsRef<Path>) -> Result<UploadedFile, Error> {
-        let (filename, file) = Self::open_file(path).await?;
-        self.upload_file_with_filename(filename, file).await
+        let options = UploadOptions::default();
+        self.upload_path_impl(path.as_ref(), None, None, None, &options)
+            .await
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(folder_id = %folder_id, path = ?path.as_ref()))
+    )]
     pub async fn upload_file_to_folder(
         &self,
-        folder_id: Uuid,
+        folder_id: FolderId,
         path: impl AsRef<Path>,
     ) -> Result<UploadedFile, Error> {
-        let (filename, file) = Self::open_file(path).await?;
-        self.upload_file_with_filename_to_folder(folder_id, filename, file)
+        let options = UploadOptions::default();
+        self.upload_path_impl(path.as_ref(), Some(folder_id), None, None, &options)
             .await
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     pub async fn upload_file_with_filename(
         &self,
         filename: impl Into<String>,
         body: impl Into<Body>,
     ) -> Result<UploadedFile, Error> {
-        Self::upload_file_impl(&self.base_url, filename, body, None, None).await
+        self.upload_file_impl(filename, body, None, None, None, &UploadOptions::default())
+            .await
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(folder_id = %folder_id)))]
     pub async fn upload_file_with_filename_to_folder(
         &self,
-        folder_id: Uuid,
+        folder_id: FolderId,
         filename: impl Into<String>,
         body: impl Into<Body>,
     ) -> Result<UploadedFile, Error> {
-        Self::upload_file_impl(&self.base_url, filename, body, Some(folder_id), None).await
+        self.upload_file_impl(
+            filename,
+            body,
+            None,
+            Some(folder_id),
+            None,
+            &UploadOptions::default(),
+        )
+        .await
     }
 
-    pub async fn open_file(path: impl AsRef<Path>) -> Result<(String, File), Error> {
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn upload_bytes(
+        &self,
+        filename: impl Into<String>,
+        data: impl Into<Bytes>,
+        tx: Option<UnboundedSender<UploadedMessage>>,
+    ) -> Result<UploadedFile, Error> {
+        self.upload_bytes_impl(filename, data.into(), None, None, tx)
+            .await
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(folder_id = %folder_id)))]
+    pub async fn upload_bytes_to_folder(
+        &self,
+        folder_id: FolderId,
+        filename: impl Into<String>,
+        data: impl Into<Bytes>,
+        tx: Option<UnboundedSender<UploadedMessage>>,
+    ) -> Result<UploadedFile, Error> {
+        self.upload_bytes_impl(filename, data.into(), Some(folder_id), None, tx)
+            .await
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(path = ?path.as_ref())))]
+    pub async fn open_file(path: impl AsRef<Path>) -> Result<(String, File), Error> {
+        Self::open_file_with_policy(path, FilenamePolicy::Strict).await
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(path = ?path.as_ref())))]
+    pub async fn open_file_with_policy(
+        path: impl AsRef<Path>,
+        policy: FilenamePolicy,
+    ) -> Result<(String, File), Error> {
         let path = path.as_ref();
-        let Some(filename) = path.file_name() else {
-            return Err(Error::InvalidFilePath(
-                path.into(),
-                "Couldn't get the filename.".into(),
-            ));
-        };
-        let Some(filename) = filename.to_str() else {
-            return Err(Error::InvalidFilePath(
-                path.into(),
-                "The filename couldn't convert to a utf-8 stirng.".into(),
-            ));
-        };
+        let filename = upload_filename(path, policy)?;
 
         let file = match File::open(path).await {
             Ok(file) => file,
-            Err(err) => return Err(Error::CouldntOpenFile(path.into(), format!("{}", err))),
+            Err(err) => return Err(Error::CouldntOpenFile(path.into(), err)),
         };
 
-        Ok((filename.into(), file))
+        Ok((filename, file))
+    }
+
+    async fn open_file_with_length(
+        path: impl AsRef<Path>,
+        policy: FilenamePolicy,
+    ) -> Result<(String, File, u64), Error> {
+        let path = path.as_ref();
+        let (filename, file) = Self::open_file_with_policy(path, policy).await?;
+        match file.metadata().await {
+            Ok(metadata) => Ok((filename, file, metadata.len())),
+            Err(err) => Err(Error::CouldntOpenFile(path.into(), err)),
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn upload_reader(
+        &self,
+        filename: impl Into<String>,
+        reader: impl AsyncRead + Send + Sync + 'static,
+        size: Option<u64>,
+        tx: Option<UnboundedSender<UploadedMessage>>,
+    ) -> Result<UploadedFile, Error> {
+        self.upload_reader_impl(filename, reader, size, None, None, tx)
+            .await
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(folder_id = %folder_id)))]
+    pub async fn upload_reader_to_folder(
+        &self,
+        folder_id: FolderId,
+        filename: impl Into<String>,
+        reader: impl AsyncRead + Send + Sync + 'static,
+        size: Option<u64>,
+        tx: Option<UnboundedSender<UploadedMessage>>,
+    ) -> Result<UploadedFile, Error> {
+        self.upload_reader_impl(filename, reader, size, Some(folder_id), None, tx)
+            .await
+    }
+
+    // The hasher is fed the same chunks that go out on the wire
+    fn reader_body(
+        reader: impl AsyncRead + Send + Sync + 'static,
+        file_name: Arc<str>,
+        total: Option<u64>,
+        sink: Option<ProgressSink>,
+        hasher: Option<Arc<Mutex<Md5Hasher>>>,
+        watch: Option<StallWatch>,
+        options: &UploadOptions,
+    ) -> Result<Body, Error> {
+        let stream = ReaderStream::with_capacity(reader, options.checked_chunk_size()?);
+        if sink.is_none() && hasher.is_none() && watch.is_none() {
+            return Ok(Body::wrap_stream(stream));
+        }
+
+        let progress = sink.map(|sink| ProgressReporter {
+            sink,
+            closed: false,
+            abort_on_close: options.abort_on_closed_progress,
+            uuid: Uuid::new_v4(),
+            file_name,
+            started_at: Instant::now(),
+            total,
+            uploaded: 0,
+            sent: None,
+            sent_at: Instant::now(),
+            every_bytes: options.progress_bytes,
+            every: options.progress_interval,
+        });
+        let stream = stream::unfold(
+            (Box::pin(stream), hasher, progress, watch),
+            |(mut stream, hasher, mut progress, watch)| async move {
+                if progress.as_ref().is_some_and(ProgressReporter::aborted) {
+                    return None;
+                }
+                let Some(mut chunk) = stream.next().await else {
+                    if let Some(watch) = &watch {
+                        watch.finish();
+                    }
+                    let mut progress = progress?;
+                    progress.finish().await;
+                    if !progress.aborted() {
+                        return None;
+                    }
+                    let err = std::io::Error::other("the progress receiver was dropped");
+                    return Some((Err(err), (stream, hasher, Some(progress), watch)));
+                };
+                if let Ok(bytes) = &chunk {
+                    if let Some(watch) = &watch {
+                        watch.touch();
+                    }
+                    if let Some(hasher) = &hasher {
+                        hasher.lock().unwrap().update(bytes);
+                    }
+                    if let Some(progress) = &mut progress {
+                        progress.chunk(bytes.len()).await;
+                        if progress.aborted() {
+                            chunk = Err(std::io::Error::other("the progress receiver was dropped"));
+                        }
+                    }
+                }
+                Some((chunk, (stream, hasher, progress, watch)))
+            },
+        );
+        Ok(Body::wrap_stream(stream))
+    }
+
+    async fn upload_reader_impl(
+        &self,
+        filename: impl Into<String>,
+        reader: impl AsyncRead + Send + Sync + 'static,
+        size: Option<u64>,
+        folder_id: Option<FolderId>,
+        token: Option<Token>,
+        tx: Option<UnboundedSender<UploadedMessage>>,
+    ) -> Result<UploadedFile, Error> {
+        let options = UploadOptions::default();
+        let filename = filename.into();
+        let file_name = Arc::from(filename.as_str());
+        let watch = self.client.stall_watch();
+        let body = Self::reader_body(
+            reader,
+            file_name,
+            size,
+            channel_sink(tx),
+            None,
+            watch.clone(),
+            &options,
+        )?;
+        let upload = self.upload_file_impl(filename, body, size, folder_id, token, &options);
+        StallWatch::guard(watch, self.upload_url()?, upload).await
+    }
+
+    async fn upload_path_impl(
+        &self,
+        path: &Path,
+        folder_id: Option<FolderId>,
+        token: Option<Token>,
+        sink: Option<ProgressSink>,
+        options: &UploadOptions,
+    ) -> Result<UploadedFile, Error> {
+        options.checked_chunk_size()?;
+        let (filename, file, total) =
+            Self::open_file_with_length(path, options.filename_policy).await?;
+        let hasher = options
+            .verify_md5
+            .then(|| Arc::new(Mutex::new(Md5Hasher::new())));
+        let file_name = Arc::from(filename.as_str());
+        // kept to check on the receiver once the upload failed
+        let closed = sink.clone();
+        let watch = self.client.stall_watch();
+        let body = Self::reader_body(
+            file,
+            file_name,
+            Some(total),
+            sink,
+            hasher.clone(),
+            watch.clone(),
+            options,
+        )?;
+        let upload = self.upload_file_impl(filename, body, Some(total), folder_id, token, options);
+        let mut uploaded = StallWatch::guard(watch, self.upload_url()?, upload)
+            .await
+            .map_err(|err| match closed {
+                Some(sink) if options.abort_on_closed_progress && sink.is_closed() => {
+                    Error::UploadCancelled(path.into())
+                }
+                _ => err,
+            })?;
+
+        if let Some(hasher) = hasher {
+            let local_md5 = Md5(hasher.lock().unwrap().clone().finalize().into());
+            if local_md5 != uploaded.md5 {
+                return Err(Error::ChecksumMismatch {
+                    expected: local_md5.to_string(),
+                    actual: uploaded.md5.to_string(),
+                    path: path.into(),
+                });
+            }
+            uploaded.local_md5 = Some(local_md5);
+        }
+        Ok(uploaded)
+    }
+
+    // In-memory payloads are small enough that a single message once done is enough progress
+    async fn upload_bytes_impl(
+        &self,
+        filename: impl Into<String>,
+        data: Bytes,
+        folder_id: Option<FolderId>,
+        token: Option<Token>,
+        tx: Option<UnboundedSender<UploadedMessage>>,
+    ) -> Result<UploadedFile, Error> {
+        let total = data.len() as u64;
+        let filename = filename.into();
+        let file_name = Arc::from(filename.as_str());
+        let started_at = Instant::now();
+        let uploaded_file = self
+            .upload_file_impl(
+                filename,
+                data,
+                Some(total),
+                folder_id,
+                token,
+                &UploadOptions::default(),
+            )
+            .await?;
+        if let Some(tx) = tx {
+            let _ = tx.send(UploadedMessage {
+                uuid: Uuid::new_v4(),
+                uploaded: total,
+                total: Some(total),
+                retry: false,
+                file_name,
+                elapsed: started_at.elapsed(),
+                bytes_per_sec: bytes_per_sec(total, started_at.elapsed()),
+            });
+        }
+        Ok(uploaded_file)
     }
 
+    // With a known length the request is sent with a Content-Length instead of chunked
     async fn upload_file_impl(
-        base_url: &str,
+        &self,
         filename: impl Into<String>,
         body: impl Into<Body>,
-        folder_id: Option<Uuid>,
-        token: Option<String>,
+        length: Option<u64>,
+        folder_id: Option<FolderId>,
+        token: Option<Token>,
+        options: &UploadOptions,
     ) -> Result<UploadedFile, Error> {
-        let client = reqwest::Client::new();
-
-        let part = Part::stream(body).file_name(filename.into());
+        // buffered bodies know their length even when the caller didn't pass one
+        let body = body.into();
+        let length = length.or_else(|| body.as_bytes().map(|bytes| bytes.len() as u64));
+        let part = match length {
+            Some(length) => Part::stream_with_length(body, length),
+            None => Part::stream(body),
+        };
+        let part = part.file_name(filename.into());
+        let part = match &options.mime {
+            Some(mime) => part
+                .mime_str(mime.as_ref())
+                .map_err(|err| Error::InvalidMime(mime.to_string(), format!("{}", err)))?,
+            None => part,
+        };
         let form = Form::new().part("file", part);
+        let form = options
+            .extra_fields
+            .iter()
+            .fold(form, |form, (name, value)| {
+                form.text(name.clone(), value.clone())
+            });
 
         let form = if let Some(folder_id) = folder_id {
             form.text("folderId", folder_id.to_string())
@@ -446,77 +2466,260 @@ impl ServerApi {
         };
 
         let form = if let Some(token) = token {
-            form.text("token", token)
+            form.text("token", token.as_str().to_string())
         } else {
             form
         };
 
-        let url = Url::parse(&(format!("{}/contents/uploadfile", base_url))).unwrap();
+        let req = self
+            .client
+            .transfer_request(Method::POST, self.upload_url()?)
+            .multipart(form);
+        let res = self.client.send(req).await?;
 
-        let res = client.post(url).multipart(form).send().await?;
+        let mut uploaded: UploadedFile = Api::parse_res(res).await?;
+        uploaded.server = Some(self.base_url.as_str().trim_end_matches('/').into());
+        Ok(uploaded)
+    }
 
-        Api::parse_res(res).await
+    fn upload_url(&self) -> Result<Url, Error> {
+        Api::url(&self.base_url, "contents/uploadfile")
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct AuthorizedServerApi {
-    pub base_url: String,
-    pub token: String,
+    pub base_url: Url,
+    pub token: Token,
+    pub client: ApiClient,
 }
 
 impl AuthorizedServerApi {
-    pub fn authorize(self, token: impl Into<String>) -> AuthorizedServerApi {
+    pub fn authorize(self, token: impl Into<Token>) -> AuthorizedServerApi {
         AuthorizedServerApi {
             base_url: self.base_url,
             token: token.into(),
+            client: self.client,
+        }
+    }
+
+    // The uploads themselves don't depend on the token, it's only sent as a form field
+    fn server_api(&self) -> ServerApi {
+        ServerApi {
+            base_url: self.base_url.clone(),
+            client: self.client.clone(),
         }
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(path = ?path.as_ref())))]
     pub async fn upload_file(&self, path: impl AsRef<Path>) -> Result<UploadedFile, Error> {
-        let (filename, file) = ServerApi::open_file(path).await?;
-        self.upload_file_with_filename(filename, file).await
+        self.upload_path_impl(path, None, None).await
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(folder_id = %folder_id, path = ?path.as_ref()))
+    )]
     pub async fn upload_file_to_folder(
         &self,
-        folder_id: Uuid,
+        folder_id: FolderId,
         path: impl AsRef<Path>,
     ) -> Result<UploadedFile, Error> {
-        let (filename, file) = ServerApi::open_file(path).await?;
-        self.upload_file_with_filename_to_folder(folder_id, filename, file)
-            .await
+        self.upload_path_impl(path, Some(folder_id), None).await
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     pub async fn upload_file_with_filename(
         &self,
         filename: impl Into<String>,
         body: impl Into<Body>,
     ) -> Result<UploadedFile, Error> {
-        ServerApi::upload_file_impl(
-            &self.base_url,
-            filename,
-            body,
-            None,
-            Some(self.token.clone()),
-        )
-        .await
+        self.server_api()
+            .upload_file_impl(
+                filename,
+                body,
+                None,
+                None,
+                Some(self.token.clone()),
+                &UploadOptions::default(),
+            )
+            .await
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(folder_id = %folder_id)))]
     pub async fn upload_file_with_filename_to_folder(
         &self,
-        folder_id: Uuid,
+        folder_id: FolderId,
         filename: impl Into<String>,
         body: impl Into<Body>,
     ) -> Result<UploadedFile, Error> {
-        ServerApi::upload_file_impl(
-            &self.base_url,
-            filename,
-            body,
-            Some(folder_id),
-            Some(self.token.clone()),
-        )
-        .await
+        self.server_api()
+            .upload_file_impl(
+                filename,
+                body,
+                None,
+                Some(folder_id),
+                Some(self.token.clone()),
+                &UploadOptions::default(),
+            )
+            .await
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn upload_bytes(
+        &self,
+        filename: impl Into<String>,
+        data: impl Into<Bytes>,
+        tx: Option<UnboundedSender<UploadedMessage>>,
+    ) -> Result<UploadedFile, Error> {
+        self.server_api()
+            .upload_bytes_impl(filename, data.into(), None, Some(self.token.clone()), tx)
+            .await
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(folder_id = %folder_id)))]
+    pub async fn upload_bytes_to_folder(
+        &self,
+        folder_id: FolderId,
+        filename: impl Into<String>,
+        data: impl Into<Bytes>,
+        tx: Option<UnboundedSender<UploadedMessage>>,
+    ) -> Result<UploadedFile, Error> {
+        self.server_api()
+            .upload_bytes_impl(
+                filename,
+                data.into(),
+                Some(folder_id),
+                Some(self.token.clone()),
+                tx,
+            )
+            .await
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn upload_reader(
+        &self,
+        filename: impl Into<String>,
+        reader: impl AsyncRead + Send + Sync + 'static,
+        size: Option<u64>,
+        tx: Option<UnboundedSender<UploadedMessage>>,
+    ) -> Result<UploadedFile, Error> {
+        self.server_api()
+            .upload_reader_impl(filename, reader, size, None, Some(self.token.clone()), tx)
+            .await
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(folder_id = %folder_id)))]
+    pub async fn upload_reader_to_folder(
+        &self,
+        folder_id: FolderId,
+        filename: impl Into<String>,
+        reader: impl AsyncRead + Send + Sync + 'static,
+        size: Option<u64>,
+        tx: Option<UnboundedSender<UploadedMessage>>,
+    ) -> Result<UploadedFile, Error> {
+        let token = Some(self.token.clone());
+        self.server_api()
+            .upload_reader_impl(filename, reader, size, Some(folder_id), token, tx)
+            .await
+    }
+
+    // Without a channel the file is sent as is, no progress stream is set up
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(folder_id = %folder_id, path = ?path.as_ref()))
+    )]
+    pub async fn upload_file_to_folder_with_progress(
+        &self,
+        folder_id: FolderId,
+        path: impl AsRef<Path>,
+        tx: Option<UnboundedSender<UploadedMessage>>,
+    ) -> Result<UploadedFile, Error> {
+        self.upload_path_impl(path, Some(folder_id), tx).await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(folder_id = %folder_id, path = ?path.as_ref()))
+    )]
+    pub async fn upload_file_to_folder_with_options(
+        &self,
+        folder_id: FolderId,
+        path: impl AsRef<Path>,
+        tx: Option<UnboundedSender<UploadedMessage>>,
+        options: UploadOptions,
+    ) -> Result<UploadedFile, Error> {
+        self.server_api()
+            .upload_path_impl(
+                path.as_ref(),
+                Some(folder_id),
+                Some(self.token.clone()),
+                channel_sink(tx),
+                &options,
+            )
+            .await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(folder_id = %folder_id, path = ?path.as_ref()))
+    )]
+    pub async fn upload_file_to_folder_with_observer(
+        &self,
+        folder_id: FolderId,
+        path: impl AsRef<Path>,
+        observer: Arc<dyn ProgressObserver>,
+        options: UploadOptions,
+    ) -> Result<UploadedFile, Error> {
+        self.server_api()
+            .upload_path_impl(
+                path.as_ref(),
+                Some(folder_id),
+                Some(self.token.clone()),
+                Some(ProgressSink::Observer(observer)),
+                &options,
+            )
+            .await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(folder_id = %folder_id, path = ?path.as_ref()))
+    )]
+    pub async fn upload_file_to_folder_with_bounded_progress(
+        &self,
+        folder_id: FolderId,
+        path: impl AsRef<Path>,
+        tx: Sender<UploadedMessage>,
+        policy: BackpressurePolicy,
+        options: UploadOptions,
+    ) -> Result<UploadedFile, Error> {
+        self.server_api()
+            .upload_path_impl(
+                path.as_ref(),
+                Some(folder_id),
+                Some(self.token.clone()),
+                Some(ProgressSink::Bounded(tx, policy)),
+                &options,
+            )
+            .await
+    }
+
+    async fn upload_path_impl(
+        &self,
+        path: impl AsRef<Path>,
+        folder_id: Option<FolderId>,
+        tx: Option<UnboundedSender<UploadedMessage>>,
+    ) -> Result<UploadedFile, Error> {
+        self.server_api()
+            .upload_path_impl(
+                path.as_ref(),
+                folder_id,
+                Some(self.token.clone()),
+                channel_sink(tx),
+                &UploadOptions::default(),
+            )
+            .await
     }
 }
 
@@ -528,23 +2731,68 @@ mod tests {
 
     #[tokio::test]
     async fn it_works() -> Result<(), Error> {
-        let mut server = Server::new();
+        let mut server = Server::new_async().await;
         let base_url = server.url();
 
-        assert_eq!(Api::default().base_url, "https://api.gofile.io");
+        assert_eq!(Api::default().base_url.as_str(), "https://api.gofile.io/");
+        assert_eq!(Api::new().base_url.as_str(), "https://api.gofile.io/");
 
         let api = Api {
-            base_url: base_url.clone(),
+            base_url: base_url.parse().unwrap(),
+            ..Default::default()
         };
         let authorized_api = api.authorize("gofile_token");
 
         let mock = server.mock("GET", "/servers")
             .with_status(200)
             .with_body(r#"{ "status": "ok", "data": { "servers": [ {"name":"store1","zone":"eu"}, {"name":"store3","zone":"na"} ] } }"#)
+            .expect(5)
+            .create();
+        let server_api = api.get_server(ZonePreference::Any).await?;
+        assert_eq!(server_api.base_url.as_str(), "https://store1.gofile.io/");
+        let server_api = api.get_server(ZonePreference::Zone("na".into())).await?;
+        assert_eq!(server_api.base_url.as_str(), "https://store3.gofile.io/");
+        let server_api = api
+            .get_server(ZonePreference::Preferred(vec!["ap".into(), "na".into()]))
+            .await?;
+        assert_eq!(server_api.base_url.as_str(), "https://store3.gofile.io/");
+        let result = api.get_server(ZonePreference::Zone("ap".into())).await;
+        assert!(matches!(result, Err(Error::NoServerInZone(Zone::Ap))));
+        let servers = api.get_servers().await?;
+        assert_eq!(servers.all().len(), 2);
+        // picking a server by hand
+        let server_api = api.server(servers.in_zone(&Zone::Na)[0])?;
+        assert_eq!(server_api.base_url.as_str(), "https://store3.gofile.io/");
+        mock.assert();
+
+        let mock = server
+            .mock("GET", "/servers")
+            .match_query(Matcher::UrlEncoded("zone".into(), "na".into()))
+            .with_status(200)
+            .with_body(
+                r#"{ "status": "ok", "data": { "servers": [ {"name":"store3","zone":"na"} ] } }"#,
+            )
             .expect(1)
             .create();
-        let server_api = api.get_server().await?;
-        assert_eq!(server_api.base_url, "https://store1.gofile.io");
+        let server_api = api.get_server_in_zone(Zone::Na).await?;
+        assert_eq!(server_api.base_url.as_str(), "https://store3.gofile.io/");
+        mock.assert();
+
+        // a zone gofile added later is sent as it is, a list it didn't filter isn't trusted
+        let mock = server
+            .mock("GET", "/servers")
+            .match_query(Matcher::UrlEncoded("zone".into(), "sa".into()))
+            .with_status(200)
+            .with_body(
+                r#"{ "status": "ok", "data": { "servers": [ {"name":"store1","zone":"eu"} ] } }"#,
+            )
+            .expect(1)
+            .create();
+        let result = api.get_server_in_zone(Zone::from("sa")).await;
+        assert!(matches!(
+            result,
+            Err(Error::NoServerInZone(Zone::Other(zone))) if zone == "sa"
+        ));
         mock.assert();
 
         let mock = server
@@ -568,7 +2816,8 @@ mod tests {
             .expect(1)
             .create();
         let server_api = ServerApi {
-            base_url: base_url.clone(),
+            base_url: base_url.parse().unwrap(),
+            client: ApiClient::default(),
         };
         let uploaded_file = server_api
             .upload_file_with_filename("test.txt", "file content")
@@ -646,6 +2895,3015 @@ mod tests {
         assert_eq!(content.id, uuid!("5e042945-0e5c-4c1d-9293-4574d376e496"));
         mock.assert();
 
+        let mock = server
+            .mock(
+                "GET",
+                "/contents/cb35b4aa-274d-4d0f-9e81-97a58dd7fb37?token=gofile_token",
+            )
+            .with_status(200)
+            .with_body(
+                r#"{
+                "status": "ok",
+                "data": {
+                    "id": "cb35b4aa-274d-4d0f-9e81-97a58dd7fb37",
+                    "type": "file",
+                    "name": "public.zip",
+                    "parentFolder": "5e042945-0e5c-4c1d-9293-4574d376e496",
+                    "createTime": 1710264451,
+                    "size": 26178,
+                    "downloadCount": 0,
+                    "md5": "c7dfde837b22280147a8cc2d9cb4d8a4",
+                    "mimetype": "application/zip",
+                    "serverSelected": "store2",
+                    "link": "https://store2.gofile.io/download/web/cb35b4aa-274d-4d0f-9e81-97a58dd7fb37/public.zip"
+                }
+            }"#,
+            )
+            .expect(1)
+            .create();
+        let content = authorized_api
+            .get_content_by_id(uuid!("cb35b4aa-274d-4d0f-9e81-97a58dd7fb37").into())
+            .await?;
+        assert!(matches!(
+            content.kind,
+            ContentKind::File { size: 26178, .. }
+        ));
+        mock.assert();
+
+        let result = authorized_api
+            .get_content_by_url(&Url::parse("https://gofile.io/x/foo").unwrap())
+            .await;
+        assert!(matches!(result, Err(Error::InvalidContentUrl(..))));
+
+        let mock = server
+            .mock("GET", "/contents/locked")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("token".into(), "gofile_token".into()),
+                Matcher::UrlEncoded(
+                    "password".into(),
+                    "5e884898da28047151d0e56f8dc6292773603d0d6aabbdd62a11ef721d1542d8".into(),
+                ),
+            ]))
+            .with_status(200)
+            .with_body(r#"{ "status": "error-passwordWrong", "data": {} }"#)
+            .expect(1)
+            .create();
+        let result = authorized_api
+            .get_content_with_password("locked", "password")
+            .await;
+        assert!(matches!(result, Err(Error::WrongPassword(_))));
+        mock.assert();
+
+        let mock = server
+            .mock("GET", "/contents/locked?token=gofile_token")
+            .with_status(401)
+            .with_body(r#"{ "status": "error-passwordRequired", "data": {} }"#)
+            .expect(1)
+            .create();
+        let result = authorized_api.get_content_by_code("locked").await;
+        assert!(matches!(result, Err(Error::PasswordRequired(_))));
+        mock.assert();
+
+        let mock = server
+            .mock("GET", "/accounts/getid?token=gofile_token")
+            .with_status(200)
+            .with_body(
+                r#"{ "status": "ok", "data": { "id": "00000000-0000-0000-0000-000000000001" } }"#,
+            )
+            .expect(1)
+            .create();
+        let account_id = authorized_api.get_account_id().await?;
+        assert_eq!(account_id, uuid!("00000000-0000-0000-0000-000000000001"));
+        mock.assert();
+
+        let mock = server
+            .mock("GET", "/accounts/getid?token=bad_token")
+            .with_status(200)
+            .with_body(r#"{ "status": "error-auth", "data": {} }"#)
+            .expect(1)
+            .create();
+        let result = api.authorize("bad_token").get_account_id().await;
+        assert!(matches!(
+            result,
+            Err(Error::ApiStatusError(_, GofileErrorCode::Auth, _))
+        ));
+        mock.assert();
+
+        let mock = server
+            .mock("POST", "/contents/createFolder")
+            .match_body(Matcher::Json(serde_json::json!({
+                "token": "gofile_token",
+                "parentFolderId": "00000000-0000-0000-0000-000000000001",
+                "folderName": "foo",
+            })))
+            .with_status(200)
+            .with_body(
+                r#"{
+                "status": "ok",
+                "data": {
+                    "id": "00000000-0000-0000-0000-000000000002",
+                    "type": "folder",
+                    "name": "foo",
+                    "parentFolder": "00000000-0000-0000-0000-000000000001",
+                    "createTime": 1710264457,
+                    "code": "bar"
+                }
+            }"#,
+            )
+            .expect(1)
+            .create();
+        let folder = authorized_api
+            .create_folder(uuid!("00000000-0000-0000-0000-000000000001").into(), "foo")
+            .await?;
+        assert_eq!(folder.id, uuid!("00000000-0000-0000-0000-000000000002"));
+        assert!(matches!(
+            folder.kind,
+            ContentKind::Folder { children_ids, children: None, .. } if children_ids.is_empty()
+        ));
+        mock.assert();
+
+        let mock = server
+            .mock("POST", "/contents/copy")
+            .match_body(Matcher::Json(serde_json::json!({
+                "token": "gofile_token",
+                "contentsId": "00000000-0000-0000-0000-000000000001",
+                "folderIdDest": "00000000-0000-0000-0000-000000000002",
+            })))
+            .with_status(200)
+            .with_body(
+                r#"{
+                "status": "ok",
+                "data": {
+                    "00000000-0000-0000-0000-000000000001": "00000000-0000-0000-0000-000000000003"
+                }
+            }"#,
+            )
+            .expect(1)
+            .create();
+        let copied = authorized_api
+            .copy_content(
+                vec![uuid!("00000000-0000-0000-0000-000000000001").into()],
+                uuid!("00000000-0000-0000-0000-000000000002").into(),
+            )
+            .await?;
+        assert_eq!(
+            copied.contents[&ContentId(uuid!("00000000-0000-0000-0000-000000000001"))],
+            uuid!("00000000-0000-0000-0000-000000000003")
+        );
+        mock.assert();
+
+        let mock = server
+            .mock("DELETE", "/contents")
+            .match_body(Matcher::Json(serde_json::json!({
+                "token": "gofile_token",
+                "contentsId": "00000000-0000-0000-0000-000000000001,00000000-0000-0000-0000-000000000002",
+            })))
+            .with_status(200)
+            .with_body(
+                r#"{
+                "status": "ok",
+                "data": {
+                    "00000000-0000-0000-0000-000000000001": { "status": "ok", "data": {} },
+                    "00000000-0000-0000-0000-000000000002": { "status": "error-notFound", "data": {} }
+                }
+            }"#,
+            )
+            .expect(1)
+            .create();
+        let deleted = authorized_api
+            .delete_contents(vec![
+                uuid!("00000000-0000-0000-0000-000000000001").into(),
+                uuid!("00000000-0000-0000-0000-000000000002").into(),
+            ])
+            .await?;
+        assert!(deleted[&ContentId(uuid!("00000000-0000-0000-0000-000000000001"))].is_ok());
+        assert!(!deleted[&ContentId(uuid!("00000000-0000-0000-0000-000000000002"))].is_ok());
+        mock.assert();
+
+        let mock = server
+            .mock("PUT", "/contents/move")
+            .match_body(Matcher::Json(serde_json::json!({
+                "token": "gofile_token",
+                "contentsId": "00000000-0000-0000-0000-000000000001",
+                "folderIdDest": "00000000-0000-0000-0000-000000000002",
+            })))
+            .with_status(200)
+            .with_body(
+                r#"{
+                "status": "ok",
+                "data": {
+                    "00000000-0000-0000-0000-000000000001": "00000000-0000-0000-0000-000000000001"
+                }
+            }"#,
+            )
+            .expect(1)
+            .create();
+        let moved = authorized_api
+            .move_contents(
+                vec![uuid!("00000000-0000-0000-0000-000000000001").into()],
+                uuid!("00000000-0000-0000-0000-000000000002").into(),
+            )
+            .await?;
+        assert!(moved
+            .contents
+            .contains_key(&uuid!("00000000-0000-0000-0000-000000000001").into()));
+        mock.assert();
+
+        let mock = server
+            .mock("PUT", "/contents/move")
+            .with_status(400)
+            .with_body(r#"{ "status": "error-moveIntoChild", "data": {} }"#)
+            .expect(1)
+            .create();
+        let result = authorized_api
+            .move_contents(
+                vec![uuid!("00000000-0000-0000-0000-000000000001").into()],
+                uuid!("00000000-0000-0000-0000-000000000003").into(),
+            )
+            .await;
+        assert!(
+            matches!(result, Err(Error::ApiStatusError(_, status, _)) if status.as_str() == "error-moveIntoChild")
+        );
+        mock.assert();
+
+        Ok(())
+    }
+
+    #[test]
+    fn hash_password() {
+        assert_eq!(
+            Api::hash_password("password"),
+            "5e884898da28047151d0e56f8dc6292773603d0d6aabbdd62a11ef721d1542d8"
+        );
+        assert_eq!(
+            Api::hash_password(""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[tokio::test]
+    async fn website_token() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let base_url = server.url();
+
+        let mock = server
+            .mock("GET", "/dist/js/global.js")
+            .with_status(200)
+            .with_body(
+                r#"var appdata = {}; appdata.wt = "4fd6sg89d7s6"; appdata.apiServer = "api";"#,
+            )
+            .expect(1)
+            .create();
+        let website_token = Api::fetch_website_token_from(
+            &ApiClient::default(),
+            Url::parse(&format!("{}/dist/js/global.js", base_url)).unwrap(),
+        )
+        .await?;
+        assert_eq!(website_token, "4fd6sg89d7s6");
+        mock.assert();
+
+        let mock = server
+            .mock("GET", "/contents/foo?wt=4fd6sg89d7s6")
+            .with_status(200)
+            .with_body(
+                r#"{
+                "status": "ok",
+                "data": {
+                    "id": "00000000-0000-0000-0000-000000000001",
+                    "type": "folder",
+                    "name": "foo",
+                    "createTime": 1710264457,
+                    "code": "foo"
+                }
+            }"#,
+            )
+            .expect(1)
+            .create();
+        let api = Api {
+            base_url: base_url.parse().unwrap(),
+            ..Default::default()
+        }
+        .with_website_token(website_token);
+        let content = api.get_content_by_code("foo").await?;
+        assert_eq!(content.id, uuid!("00000000-0000-0000-0000-000000000001"));
+        mock.assert();
+
+        let mock = server
+            .mock("GET", "/contents/foo")
+            .match_query(Matcher::Missing)
+            .with_status(401)
+            .with_body(r#"{ "status": "error-notPublic", "data": {} }"#)
+            .expect(1)
+            .create();
+        let api = Api {
+            base_url: base_url.parse().unwrap(),
+            ..Default::default()
+        };
+        let result = api.get_content_by_code("foo").await;
+        assert!(
+            matches!(result, Err(Error::ApiStatusError(_, status, _)) if status.as_str() == "error-notPublic")
+        );
+        mock.assert();
+
+        Ok(())
+    }
+
+    #[test]
+    fn website_token_from_js() {
+        assert_eq!(
+            Api::website_token_from_js(r#"fetchData = { wt: "abc" };"#),
+            Some(String::from("abc"))
+        );
+        assert_eq!(
+            Api::website_token_from_js("const wt='abc', wtExpire = 1;"),
+            Some(String::from("abc"))
+        );
+        assert_eq!(Api::website_token_from_js("appdata.wtExpire = 1;"), None);
+        assert_eq!(Api::website_token_from_js(r#"wt = "";"#), None);
+        assert_eq!(
+            Api::website_token_from_js(r#"var swt = "decoy"; c = {newt:"decoy", wt: "abc"};"#),
+            Some(String::from("abc"))
+        );
+    }
+
+    #[tokio::test]
+    async fn folder_pages() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        }
+        .authorize("gofile_token");
+
+        let mock_page_1 = server
+            .mock("GET", "/contents/00000000-0000-0000-0000-000000000001")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("page".into(), "1".into()),
+                Matcher::UrlEncoded("pageSize".into(), "2".into()),
+            ]))
+            .with_status(200)
+            .with_body(folder_page_body(&["000000000002", "000000000003"], 3))
+            .expect(1)
+            .create();
+        let mock_page_2 = server
+            .mock("GET", "/contents/00000000-0000-0000-0000-000000000001")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("page".into(), "2".into()),
+                Matcher::UrlEncoded("pageSize".into(), "2".into()),
+            ]))
+            .with_status(200)
+            .with_body(folder_page_body(&["000000000004"], 3))
+            .expect(1)
+            .create();
+
+        let folder = api
+            .get_folder_all_pages(uuid!("00000000-0000-0000-0000-000000000001").into(), 2)
+            .await?;
+        let ContentKind::Folder {
+            children_ids,
+            children: Some(children),
+            ..
+        } = folder.kind
+        else {
+            panic!("expected a folder with children");
+        };
+        assert_eq!(children_ids.len(), 3);
+        assert_eq!(children.len(), 3);
+        assert!(children.contains_key(&uuid!("00000000-0000-0000-0000-000000000004").into()));
+        mock_page_1.assert();
+        mock_page_2.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn content_options() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        }
+        .authorize("gofile_token");
+
+        let mock = server
+            .mock("GET", "/contents/00000000-0000-0000-0000-000000000001")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("token".into(), "gofile_token".into()),
+                Matcher::UrlEncoded("sortField".into(), "createTime".into()),
+                Matcher::UrlEncoded("sortDirection".into(), "-1".into()),
+                Matcher::UrlEncoded("contentFilter".into(), "*.mkv".into()),
+            ]))
+            .with_status(200)
+            .with_body(folder_page_body(&["000000000002"], 1))
+            .expect(1)
+            .create();
+        api.get_content_with_options(
+            uuid!("00000000-0000-0000-0000-000000000001").into(),
+            GetContentOptions::default()
+                .sort_by_newest()
+                .filter("*.mkv"),
+        )
+        .await?;
+        mock.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn folder_pages_zero_page_size() {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        }
+        .authorize("gofile_token");
+        let mock = server
+            .mock("GET", "/contents/00000000-0000-0000-0000-000000000001")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(folder_page_body(&[], 3))
+            .expect(0)
+            .create();
+
+        let result = api
+            .get_folder_all_pages(uuid!("00000000-0000-0000-0000-000000000001").into(), 0)
+            .await;
+        assert!(matches!(result, Err(Error::InvalidConfig(_))));
+        mock.assert();
+    }
+
+    #[tokio::test]
+    async fn folder_pages_overstated_total() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        }
+        .authorize("gofile_token");
+
+        let mut mock_page = |page: &str, child_ids: &[&str]| {
+            server
+                .mock("GET", "/contents/00000000-0000-0000-0000-000000000001")
+                .match_query(Matcher::AllOf(vec![
+                    Matcher::UrlEncoded("page".into(), page.into()),
+                    Matcher::UrlEncoded("pageSize".into(), "2".into()),
+                ]))
+                .with_status(200)
+                .with_body(folder_page_body(child_ids, 10))
+                .expect(1)
+                .create()
+        };
+        let mock_page_1 = mock_page("1", &["000000000002", "000000000003"]);
+        let mock_page_2 = mock_page("2", &[]);
+
+        let folder = api
+            .get_folder_all_pages(uuid!("00000000-0000-0000-0000-000000000001").into(), 2)
+            .await?;
+        let ContentKind::Folder {
+            children: Some(children),
+            ..
+        } = folder.kind
+        else {
+            panic!("expected a folder with children");
+        };
+        assert_eq!(children.len(), 2);
+        mock_page_1.assert();
+        mock_page_2.assert();
+
+        Ok(())
+    }
+
+    fn folder_page_body(child_ids: &[&str], total_count: u64) -> String {
+        let children = child_ids
+            .iter()
+            .map(|id| {
+                (
+                    format!("00000000-0000-0000-0000-{}", id),
+                    serde_json::json!({
+                        "id": format!("00000000-0000-0000-0000-{}", id),
+                        "type": "folder",
+                        "name": id,
+                        "createTime": 1710264457,
+                        "code": id,
+                    }),
+                )
+            })
+            .collect::<serde_json::Map<_, _>>();
+        serde_json::json!({
+            "status": "ok",
+            "data": {
+                "id": "00000000-0000-0000-0000-000000000001",
+                "type": "folder",
+                "name": "foo",
+                "createTime": 1710264457,
+                "code": "foo",
+                "childrenIds": children.keys().collect::<Vec<_>>(),
+                "children": children,
+                "metadata": { "totalCount": total_count, "page": 1, "pageSize": 2 },
+            }
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn create_folder_path() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        }
+        .authorize("gofile_token");
+
+        let root_mock = server
+            .mock(
+                "GET",
+                "/contents/00000000-0000-0000-0000-000000000001?token=gofile_token",
+            )
+            .with_status(200)
+            .with_body(
+                r#"{
+                "status": "ok",
+                "data": {
+                    "id": "00000000-0000-0000-0000-000000000001",
+                    "type": "folder",
+                    "name": "root",
+                    "createTime": 1710264457,
+                    "code": "root",
+                    "childrenIds": ["00000000-0000-0000-0000-000000000010"],
+                    "children": {
+                        "00000000-0000-0000-0000-000000000010": {
+                            "id": "00000000-0000-0000-0000-000000000010",
+                            "type": "folder",
+                            "name": "backups",
+                            "createTime": 1710264457,
+                            "code": "backups"
+                        }
+                    }
+                }
+            }"#,
+            )
+            .expect(1)
+            .create();
+        let backups_mock = server
+            .mock(
+                "GET",
+                "/contents/00000000-0000-0000-0000-000000000010?token=gofile_token",
+            )
+            .with_status(200)
+            .with_body(
+                r#"{
+                "status": "ok",
+                "data": {
+                    "id": "00000000-0000-0000-0000-000000000010",
+                    "type": "folder",
+                    "name": "backups",
+                    "createTime": 1710264457,
+                    "code": "backups",
+                    "children": {}
+                }
+            }"#,
+            )
+            .expect(1)
+            .create();
+        let create_2024_mock = server
+            .mock("POST", "/contents/createFolder")
+            .match_body(Matcher::PartialJson(serde_json::json!({
+                "parentFolderId": "00000000-0000-0000-0000-000000000010",
+                "folderName": "2024",
+            })))
+            .with_status(200)
+            .with_body(
+                r#"{ "status": "ok", "data": { "id": "00000000-0000-0000-0000-000000000020", "type": "folder", "name": "2024", "createTime": 1710264457, "code": "a" } }"#,
+            )
+            .expect(1)
+            .create();
+        let create_06_mock = server
+            .mock("POST", "/contents/createFolder")
+            .match_body(Matcher::PartialJson(serde_json::json!({
+                "parentFolderId": "00000000-0000-0000-0000-000000000020",
+                "folderName": "06",
+            })))
+            .with_status(200)
+            .with_body(
+                r#"{ "status": "ok", "data": { "id": "00000000-0000-0000-0000-000000000030", "type": "folder", "name": "06", "createTime": 1710264457, "code": "b" } }"#,
+            )
+            .expect(1)
+            .create();
+
+        let folder_id = api
+            .create_folder_path(
+                uuid!("00000000-0000-0000-0000-000000000001").into(),
+                "backups/2024/06/",
+            )
+            .await?;
+        assert_eq!(folder_id, uuid!("00000000-0000-0000-0000-000000000030"));
+        root_mock.assert();
+        backups_mock.assert();
+        create_2024_mock.assert();
+        create_06_mock.assert();
+
+        let result = api
+            .create_folder_path(
+                uuid!("00000000-0000-0000-0000-000000000001").into(),
+                "../foo",
+            )
+            .await;
+        assert!(matches!(result, Err(Error::InvalidFilePath(..))));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn resolve_path() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        }
+        .authorize("gofile_token");
+
+        let root_mock = server
+            .mock(
+                "GET",
+                "/contents/00000000-0000-0000-0000-000000000001?token=gofile_token",
+            )
+            .with_status(200)
+            .with_body(
+                r#"{
+                "status": "ok",
+                "data": {
+                    "id": "00000000-0000-0000-0000-000000000001",
+                    "type": "folder",
+                    "name": "root",
+                    "createTime": 1710264457,
+                    "code": "root",
+                    "children": {
+                        "00000000-0000-0000-0000-000000000010": {
+                            "id": "00000000-0000-0000-0000-000000000010",
+                            "type": "folder",
+                            "name": "Projects",
+                            "createTime": 1710264457,
+                            "code": "projects"
+                        }
+                    }
+                }
+            }"#,
+            )
+            .expect(3)
+            .create();
+        let projects_mock = server
+            .mock("GET", "/contents/00000000-0000-0000-0000-000000000010?token=gofile_token")
+            .with_status(200)
+            .with_body(
+                r#"{
+                "status": "ok",
+                "data": {
+                    "id": "00000000-0000-0000-0000-000000000010",
+                    "type": "folder",
+                    "name": "Projects",
+                    "createTime": 1710264457,
+                    "code": "projects",
+                    "children": {
+                        "00000000-0000-0000-0000-000000000020": {
+                            "id": "00000000-0000-0000-0000-000000000020",
+                            "type": "file",
+                            "name": "logo.png",
+                            "createTime": 1710264451,
+                            "size": 26178,
+                            "downloadCount": 0,
+                            "md5": "c7dfde837b22280147a8cc2d9cb4d8a4",
+                            "mimetype": "image/png",
+                            "serverSelected": "store2",
+                            "link": "https://store2.gofile.io/download/web/00000000-0000-0000-0000-000000000020/logo.png"
+                        }
+                    }
+                }
+            }"#,
+            )
+            .expect(2)
+            .create();
+
+        let root = uuid!("00000000-0000-0000-0000-000000000001");
+        assert_eq!(
+            api.resolve_path(FolderId(root), "projects/logo.png")
+                .await?,
+            None
+        );
+        let logo = api
+            .resolve_path_case_insensitive(FolderId(root), "projects/logo.png")
+            .await?
+            .unwrap();
+        assert_eq!(logo.id, uuid!("00000000-0000-0000-0000-000000000020"));
+        assert_eq!(
+            api.resolve_path(FolderId(root), "Projects/missing.png")
+                .await?,
+            None
+        );
+        root_mock.assert();
+        projects_mock.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn walk_folder() -> Result<(), Error> {
+        use futures::TryStreamExt;
+
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        }
+        .authorize("gofile_token");
+
+        let root_mock = server
+            .mock("GET", "/contents/00000000-0000-0000-0000-000000000001?token=gofile_token")
+            .with_status(200)
+            .with_body(
+                r#"{
+                "status": "ok",
+                "data": {
+                    "id": "00000000-0000-0000-0000-000000000001",
+                    "type": "folder",
+                    "name": "root",
+                    "createTime": 1710264457,
+                    "code": "root",
+                    "children": {
+                        "00000000-0000-0000-0000-000000000010": {
+                            "id": "00000000-0000-0000-0000-000000000010",
+                            "type": "folder",
+                            "name": "sub",
+                            "createTime": 1710264457,
+                            "code": "sub"
+                        },
+                        "00000000-0000-0000-0000-000000000011": {
+                            "id": "00000000-0000-0000-0000-000000000011",
+                            "type": "file",
+                            "name": "a.txt",
+                            "createTime": 1710264451,
+                            "size": 1,
+                            "downloadCount": 0,
+                            "md5": "c7dfde837b22280147a8cc2d9cb4d8a4",
+                            "mimetype": "text/plain",
+                            "serverSelected": "store2",
+                            "link": "https://store2.gofile.io/download/web/00000000-0000-0000-0000-000000000011/a.txt"
+                        },
+                        "00000000-0000-0000-0000-000000000012": {
+                            "id": "00000000-0000-0000-0000-000000000012",
+                            "type": "shortcut",
+                            "name": "link",
+                            "createTime": 1710264451,
+                            "target": "00000000-0000-0000-0000-000000000010"
+                        }
+                    }
+                }
+            }"#,
+            )
+            .expect(2)
+            .create();
+        let sub_mock = server
+            .mock("GET", "/contents/00000000-0000-0000-0000-000000000010?token=gofile_token")
+            .with_status(200)
+            .with_body(
+                r#"{
+                "status": "ok",
+                "data": {
+                    "id": "00000000-0000-0000-0000-000000000010",
+                    "type": "folder",
+                    "name": "sub",
+                    "createTime": 1710264457,
+                    "code": "sub",
+                    "children": {
+                        "00000000-0000-0000-0000-000000000020": {
+                            "id": "00000000-0000-0000-0000-000000000020",
+                            "type": "file",
+                            "name": "b.txt",
+                            "createTime": 1710264451,
+                            "size": 1,
+                            "downloadCount": 0,
+                            "md5": "c7dfde837b22280147a8cc2d9cb4d8a4",
+                            "mimetype": "text/plain",
+                            "serverSelected": "store2",
+                            "link": "https://store2.gofile.io/download/web/00000000-0000-0000-0000-000000000020/b.txt"
+                        }
+                    }
+                }
+            }"#,
+            )
+            .expect(1)
+            .create();
+
+        let root = uuid!("00000000-0000-0000-0000-000000000001");
+        let paths = api
+            .walk_folder(
+                FolderId(root),
+                WalkOptions {
+                    include_folders: true,
+                    ..Default::default()
+                },
+            )
+            .map_ok(|(path, _)| path)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("a.txt"),
+                PathBuf::from("sub"),
+                PathBuf::from("sub/b.txt")
+            ]
+        );
+
+        let paths = api
+            .walk_folder(
+                FolderId(root),
+                WalkOptions {
+                    max_depth: Some(1),
+                    include_unknown: true,
+                    ..Default::default()
+                },
+            )
+            .map_ok(|(path, _)| path)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(paths, vec![PathBuf::from("a.txt"), PathBuf::from("link")]);
+
+        let paths = api
+            .walk_folder(
+                FolderId(root),
+                WalkOptions {
+                    max_depth: Some(0),
+                    include_folders: true,
+                    ..Default::default()
+                },
+            )
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert!(paths.is_empty());
+        root_mock.assert();
+        sub_mock.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn set_option_bulk() -> Result<(), Error> {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+        use tokio::{
+            io::{AsyncReadExt, AsyncWriteExt},
+            net::TcpListener,
+            time::{sleep, Duration, Instant},
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        {
+            let in_flight = in_flight.clone();
+            let max_in_flight = max_in_flight.clone();
+            tokio::spawn(async move {
+                loop {
+                    let (mut socket, _) = listener.accept().await.unwrap();
+                    let in_flight = in_flight.clone();
+                    let max_in_flight = max_in_flight.clone();
+                    tokio::spawn(async move {
+                        let mut buf = vec![0; 4096];
+                        let n = socket.read(&mut buf).await.unwrap();
+                        let req = String::from_utf8_lossy(&buf[..n]).to_string();
+                        let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                        max_in_flight.fetch_max(current, Ordering::SeqCst);
+                        // hold the request until a second one comes in, so the bound is reached
+                        let held_at = Instant::now();
+                        while in_flight.load(Ordering::SeqCst) < 2
+                            && held_at.elapsed() < Duration::from_secs(1)
+                        {
+                            sleep(Duration::from_millis(5)).await;
+                        }
+                        in_flight.fetch_sub(1, Ordering::SeqCst);
+                        let body = if req.contains("00000000-0000-0000-0000-000000000003") {
+                            r#"{ "status": "error-notFound", "data": {} }"#
+                        } else {
+                            r#"{ "status": "ok", "data": {} }"#
+                        };
+                        let res = format!(
+                            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                            body.len(),
+                            body
+                        );
+                        socket.write_all(res.as_bytes()).await.unwrap();
+                    });
+                }
+            });
+        }
+
+        let api = Api {
+            base_url: base_url.parse().unwrap(),
+            ..Default::default()
+        }
+        .authorize("gofile_token");
+        let content_ids = (1..=8)
+            .map(|id| ContentId(Uuid::from_u128(id)))
+            .collect::<Vec<_>>();
+        let report = api
+            .set_option_bulk(&content_ids, ContentOpt::Public(true), 2)
+            .await;
+        assert_eq!(report.succeeded.len(), 7);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, Uuid::from_u128(3));
+        let max_in_flight = max_in_flight.load(Ordering::SeqCst);
+        assert!(max_in_flight <= 2);
+        assert_eq!(max_in_flight, 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn set_option() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        }
+        .authorize("gofile_token");
+        let content_id = uuid!("00000000-0000-0000-0000-000000000001");
+
+        let expected_bodies = [
+            serde_json::json!({ "attribute": "public", "attributeValue": "true" }),
+            serde_json::json!({ "attribute": "name", "attributeValue": "baz" }),
+            serde_json::json!({ "attribute": "password", "attributeValue": "foo" }),
+            serde_json::json!({ "attribute": "description", "attributeValue": "bar" }),
+            serde_json::json!({ "attribute": "expiry", "attributeValue": 1000000000 }),
+            serde_json::json!({ "attribute": "tags", "attributeValue": "tag1,tag2" }),
+            serde_json::json!({ "attribute": "password", "attributeValue": "" }),
+            serde_json::json!({ "attribute": "description", "attributeValue": "" }),
+            serde_json::json!({ "attribute": "expiry", "attributeValue": "" }),
+        ];
+        let mocks = expected_bodies
+            .into_iter()
+            .map(|body| {
+                server
+                    .mock(
+                        "PUT",
+                        "/contents/00000000-0000-0000-0000-000000000001/update",
+                    )
+                    .match_header("authorization", "Bearer gofile_token")
+                    .match_body(Matcher::Json(body))
+                    .with_status(200)
+                    .with_body(r#"{ "status": "ok", "data": {} }"#)
+                    .expect(1)
+                    .create()
+            })
+            .collect::<Vec<_>>();
+
+        api.set_public_option(ContentId(content_id), true).await?;
+        api.rename_content(ContentId(content_id), "baz").await?;
+        api.set_password_option(ContentId(content_id), "foo")
+            .await?;
+        api.set_description_option(ContentId(content_id), "bar")
+            .await?;
+        api.set_expire_option(
+            ContentId(content_id),
+            DateTime::from_timestamp(1000000000, 0).unwrap(),
+        )
+        .await?;
+        api.set_tags_option(ContentId(content_id), ["tag1", "tag2"])
+            .await?;
+        api.clear_password_option(ContentId(content_id)).await?;
+        api.clear_description_option(ContentId(content_id)).await?;
+        api.clear_expire_option(ContentId(content_id)).await?;
+        for mock in mocks {
+            mock.assert();
+        }
+
+        let mock = server
+            .mock(
+                "PUT",
+                "/contents/00000000-0000-0000-0000-000000000001/update",
+            )
+            .match_header("authorization", "Bearer gofile_token")
+            .match_body(Matcher::PartialJson(
+                serde_json::json!({ "attribute": "expiry" }),
+            ))
+            .with_status(200)
+            .with_body(r#"{ "status": "ok", "data": {} }"#)
+            .expect(2)
+            .create();
+        let before = Utc::now();
+        let expire = api
+            .set_expire_in(ContentId(content_id), chrono::Duration::days(7))
+            .await?;
+        assert!(expire >= before + chrono::Duration::days(7));
+        let expire = api
+            .set_expire_in_std(ContentId(content_id), std::time::Duration::from_secs(60))
+            .await?;
+        assert!(expire >= before + chrono::Duration::seconds(60));
+        mock.assert();
+
+        assert!(matches!(
+            api.set_expire_in(ContentId(content_id), chrono::Duration::days(-1))
+                .await,
+            Err(Error::InvalidExpire(_))
+        ));
+        assert!(matches!(
+            api.set_expire_in_std(ContentId(content_id), std::time::Duration::ZERO)
+                .await,
+            Err(Error::InvalidExpire(_))
+        ));
+
+        assert!(matches!(
+            api.rename_content(ContentId(content_id), "").await,
+            Err(Error::InvalidName(..))
+        ));
+        assert!(matches!(
+            api.rename_content(ContentId(content_id), "foo/bar").await,
+            Err(Error::InvalidName(..))
+        ));
+        assert!(matches!(
+            api.rename_content(ContentId(content_id), "foo\\bar").await,
+            Err(Error::InvalidName(..))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge_tags() {
+        assert_eq!(
+            AuthorizedApi::merge_tags(
+                vec![String::from("tag1"), String::from("tag2")],
+                vec![
+                    String::from("tag2"),
+                    String::from("Tag2"),
+                    String::from("tag3")
+                ]
+            ),
+            vec!["tag1", "tag2", "Tag2", "tag3"]
+        );
+        assert_eq!(
+            AuthorizedApi::merge_tags(vec![String::from("a"), String::from("a")], vec![]),
+            vec!["a"]
+        );
+    }
+
+    #[tokio::test]
+    async fn add_and_remove_tags() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        }
+        .authorize("gofile_token");
+        let content_id = uuid!("00000000-0000-0000-0000-000000000001");
+
+        let get_mock = server
+            .mock(
+                "GET",
+                "/contents/00000000-0000-0000-0000-000000000001?token=gofile_token",
+            )
+            .with_status(200)
+            .with_body(
+                r#"{
+                "status": "ok",
+                "data": {
+                    "id": "00000000-0000-0000-0000-000000000001",
+                    "type": "folder",
+                    "name": "foo",
+                    "createTime": 1710264457,
+                    "code": "foo",
+                    "tags": "tag1,tag2"
+                }
+            }"#,
+            )
+            .expect(2)
+            .create();
+        let add_mock = server
+            .mock(
+                "PUT",
+                "/contents/00000000-0000-0000-0000-000000000001/update",
+            )
+            .match_header("authorization", "Bearer gofile_token")
+            .match_body(Matcher::Json(
+                serde_json::json!({ "attribute": "tags", "attributeValue": "tag1,tag2,tag3" }),
+            ))
+            .with_status(200)
+            .with_body(r#"{ "status": "ok", "data": {} }"#)
+            .expect(1)
+            .create();
+        let remove_mock = server
+            .mock(
+                "PUT",
+                "/contents/00000000-0000-0000-0000-000000000001/update",
+            )
+            .match_header("authorization", "Bearer gofile_token")
+            .match_body(Matcher::Json(
+                serde_json::json!({ "attribute": "tags", "attributeValue": "tag2" }),
+            ))
+            .with_status(200)
+            .with_body(r#"{ "status": "ok", "data": {} }"#)
+            .expect(1)
+            .create();
+
+        assert_eq!(
+            api.add_tags(ContentId(content_id), ["tag2", "tag3"])
+                .await?,
+            vec!["tag1", "tag2", "tag3"]
+        );
+        assert_eq!(
+            api.remove_tags(ContentId(content_id), ["tag1"]).await?,
+            vec!["tag2"]
+        );
+        get_mock.assert();
+        add_mock.assert();
+        remove_mock.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn direct_links() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let unauthorized = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        };
+        let api = unauthorized.authorize("gofile_token");
+        let content_id = uuid!("00000000-0000-0000-0000-000000000001");
+        let account = |tier: &str| {
+            serde_json::json!({
+                "status": "ok",
+                "data": {
+                    "id": "00000000-0000-0000-0000-000000000009",
+                    "token": "gofile_token",
+                    "email": "foo@example.com",
+                    "tier": tier,
+                    "rootFolder": "00000000-0000-0000-0000-000000000004",
+                    "filesCount": 1,
+                    "totalSize": 1,
+                }
+            })
+            .to_string()
+        };
+        let account_path = "/accounts/00000000-0000-0000-0000-000000000009?token=gofile_token";
+        let getid_mock = server
+            .mock("GET", "/accounts/getid?token=gofile_token")
+            .with_body(r#"{"status":"ok","data":{"id":"00000000-0000-0000-0000-000000000009"}}"#)
+            .expect(3)
+            .create();
+        let premium_mock = server
+            .mock("GET", account_path)
+            .with_body(account("premium"))
+            // asked once, every direct link of the same api reuses it
+            .expect(1)
+            .create();
+
+        let get_mock = server
+            .mock("GET", "/contents/00000000-0000-0000-0000-000000000001?token=gofile_token")
+            .with_status(200)
+            .with_body(
+                r#"{
+                "status": "ok",
+                "data": {
+                    "id": "00000000-0000-0000-0000-000000000001",
+                    "type": "file",
+                    "name": "foo.txt",
+                    "createTime": 1710264451,
+                    "size": 1,
+                    "downloadCount": 0,
+                    "md5": "c7dfde837b22280147a8cc2d9cb4d8a4",
+                    "mimetype": "text/plain",
+                    "serverSelected": "store1",
+                    "link": "https://store1.gofile.io/download/web/00000000-0000-0000-0000-000000000001/foo.txt",
+                    "directLinks": {
+                        "00000000-0000-0000-0000-000000000002": {
+                            "id": "00000000-0000-0000-0000-000000000002",
+                            "directLink": "https://store1.gofile.io/download/direct/00000000-0000-0000-0000-000000000002/foo.txt",
+                            "expireTime": 1000000000
+                        }
+                    }
+                }
+            }"#,
+            )
+            .expect(2)
+            .create();
+
+        let mock = server
+            .mock("POST", "/contents/00000000-0000-0000-0000-000000000001/directlinks")
+            .match_header("authorization", "Bearer gofile_token")
+            .match_body(Matcher::Json(serde_json::json!({})))
+            .with_status(200)
+            .with_body(
+                r#"{
+                "status": "ok",
+                "data": {
+                    "id": "00000000-0000-0000-0000-000000000002",
+                    "directLink": "https://store1.gofile.io/download/direct/00000000-0000-0000-0000-000000000002/foo.txt",
+                    "expireTime": 1000000000
+                }
+            }"#,
+            )
+            .expect(1)
+            .create();
+        let link = api.get_direct_link(ContentId(content_id)).await?;
+        assert_eq!(
+            link.as_str(),
+            "https://store1.gofile.io/download/direct/00000000-0000-0000-0000-000000000002/foo.txt"
+        );
+        mock.assert();
+
+        let delete_mock = server
+            .mock(
+                "DELETE",
+                "/contents/00000000-0000-0000-0000-000000000001/directlinks/00000000-0000-0000-0000-000000000002",
+            )
+            .match_header("authorization", "Bearer gofile_token")
+            .with_status(200)
+            .with_body(r#"{ "status": "ok", "data": {} }"#)
+            .expect(1)
+            .create();
+        api.disable_direct_link(ContentId(content_id)).await?;
+        get_mock.assert();
+
+        let get_mock = server
+            .mock("GET", "/contents/00000000-0000-0000-0000-000000000005?token=gofile_token")
+            .with_status(200)
+            .with_body(
+                r#"{
+                "status": "ok",
+                "data": {
+                    "id": "00000000-0000-0000-0000-000000000005",
+                    "type": "file",
+                    "name": "bar.txt",
+                    "createTime": 1710264451,
+                    "size": 1,
+                    "downloadCount": 0,
+                    "md5": "c7dfde837b22280147a8cc2d9cb4d8a4",
+                    "mimetype": "text/plain",
+                    "serverSelected": "store1",
+                    "link": "https://store1.gofile.io/download/web/00000000-0000-0000-0000-000000000005/bar.txt",
+                    "directLinks": {
+                        "00000000-0000-0000-0000-000000000006": {
+                            "id": "00000000-0000-0000-0000-000000000006",
+                            "directLink": "https://store1.gofile.io/download/direct/00000000-0000-0000-0000-000000000006/bar.txt"
+                        }
+                    }
+                }
+            }"#,
+            )
+            .expect(1)
+            .create();
+        let create_mock = server
+            .mock(
+                "POST",
+                "/contents/00000000-0000-0000-0000-000000000005/directlinks",
+            )
+            .expect(0)
+            .create();
+        let link = api
+            .get_direct_link(uuid!("00000000-0000-0000-0000-000000000005").into())
+            .await?;
+        assert_eq!(
+            link.as_str(),
+            "https://store1.gofile.io/download/direct/00000000-0000-0000-0000-000000000006/bar.txt"
+        );
+        get_mock.assert();
+        create_mock.assert();
+        delete_mock.assert();
+
+        let mock = server
+            .mock("POST", "/contents/00000000-0000-0000-0000-000000000001/directlinks")
+            .match_body(Matcher::Json(serde_json::json!({
+                "sourceIpsAllowed": ["192.0.2.1"],
+                "domainsAllowed": ["example.com"],
+            })))
+            .with_status(200)
+            .with_body(
+                r#"{
+                "status": "ok",
+                "data": {
+                    "id": "00000000-0000-0000-0000-000000000004",
+                    "directLink": "https://store1.gofile.io/download/direct/00000000-0000-0000-0000-000000000004/foo.txt"
+                }
+            }"#,
+            )
+            .expect(1)
+            .create();
+        let direct_link = api
+            .create_direct_link_with_options(
+                ContentId(content_id),
+                DirectLinkOptions::default()
+                    .source_ip_allowed([192, 0, 2, 1].into())
+                    .domain_allowed("example.com"),
+            )
+            .await?;
+        assert_eq!(
+            direct_link.id,
+            uuid!("00000000-0000-0000-0000-000000000004")
+        );
+        assert_eq!(direct_link.expire_time, None);
+        mock.assert();
+
+        let mock = server
+            .mock(
+                "POST",
+                "/contents/00000000-0000-0000-0000-000000000003/directlinks",
+            )
+            .with_status(200)
+            .with_body(r#"{ "status": "error-notPremium", "data": {} }"#)
+            .expect(1)
+            .create();
+        let result = api
+            .create_direct_link(uuid!("00000000-0000-0000-0000-000000000003").into())
+            .await;
+        assert!(matches!(result, Err(Error::NotPremium(_))));
+        mock.assert();
+        premium_mock.assert();
+        premium_mock.remove();
+
+        // the free tiers don't send the request at all
+        let api = unauthorized.authorize("gofile_token");
+        let standard_mock = server
+            .mock("GET", account_path)
+            .with_body(account("standard"))
+            .expect(1)
+            .create();
+        let mock = server
+            .mock(
+                "POST",
+                "/contents/00000000-0000-0000-0000-000000000003/directlinks",
+            )
+            .expect(0)
+            .create();
+        let result = api
+            .create_direct_link(uuid!("00000000-0000-0000-0000-000000000003").into())
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::NotPremium(url))
+                if url.path() == "/contents/00000000-0000-0000-0000-000000000003/directlinks"
+        ));
+        mock.assert();
+        standard_mock.assert();
+        standard_mock.remove();
+
+        // a tier this crate doesn't know is left to the server
+        let api = unauthorized.authorize("gofile_token");
+        let other_mock = server
+            .mock("GET", account_path)
+            .with_body(account("enterprise"))
+            .expect(1)
+            .create();
+        let mock = server
+            .mock(
+                "POST",
+                "/contents/00000000-0000-0000-0000-000000000003/directlinks",
+            )
+            .with_body(r#"{ "status": "error-notPremium", "data": {} }"#)
+            .expect(1)
+            .create();
+        let result = api
+            .create_direct_link(uuid!("00000000-0000-0000-0000-000000000003").into())
+            .await;
+        assert!(matches!(result, Err(Error::NotPremium(_))));
+        mock.assert();
+        other_mock.assert();
+        getid_mock.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upload_file_with_length() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let server_api = AuthorizedServerApi {
+            base_url: server.url().parse().unwrap(),
+            token: "gofile_token".into(),
+            client: ApiClient::default(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.txt");
+        std::fs::write(&path, "file content").unwrap();
+
+        let mock = server
+            .mock("POST", "/contents/uploadfile")
+            .match_header("content-length", Matcher::Any)
+            .match_header("transfer-encoding", Matcher::Missing)
+            .match_body(Matcher::Regex(String::from(r#"file content"#)))
+            .with_status(200)
+            .with_body(
+                r#"{
+                "status": "ok",
+                "data": {
+                    "downloadPage": "http://example.com/path/file.txt",
+                    "code": "bar",
+                    "parentFolder": "00000000-0000-0000-0000-000000000001",
+                    "fileId": "00000000-0000-0000-0000-000000000002",
+                    "fileName": "test.txt",
+                    "md5": "d10b4c3ff123b26dc068d43a8bef2d23"
+                }
+            }"#,
+            )
+            .expect(3)
+            .create();
+        let folder_id = uuid!("00000000-0000-0000-0000-000000000001");
+        server_api
+            .upload_file_to_folder(FolderId(folder_id), &path)
+            .await?;
+        server_api
+            .upload_file_to_folder_with_progress(FolderId(folder_id), &path, None)
+            .await?;
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        server_api
+            .upload_file_to_folder_with_progress(FolderId(folder_id), &path, Some(tx))
+            .await?;
+        let mut last = None;
+        while let Some(message) = rx.recv().await {
+            assert_eq!(&*message.file_name, "test.txt");
+            assert_eq!(message.percent(), Some(100.0));
+            last = Some((message.uploaded, message.total));
+        }
+        assert_eq!(last, Some((12, Some(12))));
+        mock.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upload_content_length() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let server_api = AuthorizedServerApi {
+            base_url: server.url().parse().unwrap(),
+            token: "gofile_token".into(),
+            client: ApiClient::default(),
+        };
+        let response = r#"{
+            "status": "ok",
+            "data": {
+                "downloadPage": "http://example.com/path/file.txt",
+                "code": "bar",
+                "parentFolder": "00000000-0000-0000-0000-000000000001",
+                "fileId": "00000000-0000-0000-0000-000000000002",
+                "fileName": "test.txt",
+                "md5": "d10b4c3ff123b26dc068d43a8bef2d23"
+            }
+        }"#;
+
+        let mock = server
+            .mock("POST", "/contents/uploadfile")
+            .match_header("content-length", Matcher::Any)
+            .match_header("transfer-encoding", Matcher::Missing)
+            .match_body(Matcher::Regex(String::from("file content")))
+            .with_status(200)
+            .with_body(response)
+            .expect(2)
+            .create();
+        server_api
+            .upload_file_with_filename("test.txt", "file content")
+            .await?;
+        server_api
+            .upload_reader("test.txt", &b"file content"[..], Some(12), None)
+            .await?;
+        mock.assert();
+
+        // without a size the reader can only be sent chunked
+        let mock = server
+            .mock("POST", "/contents/uploadfile")
+            .match_header("content-length", Matcher::Missing)
+            .match_header("transfer-encoding", "chunked")
+            .with_status(200)
+            .with_body(response)
+            .create();
+        server_api
+            .upload_reader("test.txt", &b"file content"[..], None, None)
+            .await?;
+        mock.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upload_file_mime_and_extra_fields() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let server_api = AuthorizedServerApi {
+            base_url: server.url().parse().unwrap(),
+            token: "gofile_token".into(),
+            client: ApiClient::default(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("README");
+        std::fs::write(&path, "file content").unwrap();
+
+        let mock = server
+            .mock("POST", "/contents/uploadfile")
+            .match_body(Matcher::AllOf(vec![
+                Matcher::Regex(String::from(
+                    "filename=\"README\"\r\nContent-Type: text/markdown\r\n\r\nfile content",
+                )),
+                Matcher::Regex(String::from("name=\"description\"\r\n\r\nproject readme")),
+            ]))
+            .with_status(200)
+            .with_body(
+                r#"{
+                "status": "ok",
+                "data": {
+                    "downloadPage": "http://example.com/path/file.txt",
+                    "code": "bar",
+                    "parentFolder": "00000000-0000-0000-0000-000000000001",
+                    "fileId": "00000000-0000-0000-0000-000000000002",
+                    "fileName": "README",
+                    "md5": "d10b4c3ff123b26dc068d43a8bef2d23"
+                }
+            }"#,
+            )
+            .create();
+        let options = UploadOptions::default()
+            .mime_str("text/markdown")?
+            .extra_field("description", "project readme");
+        server_api
+            .upload_file_to_folder_with_options(
+                uuid!("00000000-0000-0000-0000-000000000001").into(),
+                &path,
+                None,
+                options,
+            )
+            .await?;
+        mock.assert();
+
+        assert!(matches!(
+            UploadOptions::default().mime_str("not a mime"),
+            Err(Error::InvalidMime(..))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upload_file_dropped_progress() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let server_api = AuthorizedServerApi {
+            base_url: server.url().parse().unwrap(),
+            token: "gofile_token".into(),
+            client: ApiClient::default(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.txt");
+        std::fs::write(&path, "file content").unwrap();
+        let folder_id = uuid!("00000000-0000-0000-0000-000000000001");
+
+        let mock = server
+            .mock("POST", "/contents/uploadfile")
+            .with_status(200)
+            .with_body(
+                r#"{
+                "status": "ok",
+                "data": {
+                    "downloadPage": "http://example.com/path/file.txt",
+                    "code": "bar",
+                    "parentFolder": "00000000-0000-0000-0000-000000000001",
+                    "fileId": "00000000-0000-0000-0000-000000000002",
+                    "fileName": "test.txt",
+                    "md5": "d10b4c3ff123b26dc068d43a8bef2d23"
+                }
+            }"#,
+            )
+            .create();
+        let options = UploadOptions::default()
+            .chunk_size(4)
+            .progress_interval(0, Duration::ZERO);
+
+        // the receiver goes away after the first message, the upload carries on
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<UploadedMessage>();
+        let receiver = tokio::spawn(async move { rx.recv().await.map(|message| message.uploaded) });
+        server_api
+            .upload_file_to_folder_with_options(
+                FolderId(folder_id),
+                &path,
+                Some(tx),
+                options.clone(),
+            )
+            .await?;
+        assert_eq!(receiver.await.unwrap(), Some(4));
+        mock.assert();
+
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        drop(rx);
+        let options = UploadOptions {
+            abort_on_closed_progress: true,
+            ..options
+        };
+        let result = server_api
+            .upload_file_to_folder_with_options(FolderId(folder_id), &path, Some(tx), options)
+            .await;
+        assert!(matches!(result, Err(Error::UploadCancelled(_))));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upload_file_with_observer() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let server_api = AuthorizedServerApi {
+            base_url: server.url().parse().unwrap(),
+            token: "gofile_token".into(),
+            client: ApiClient::default(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.txt");
+        std::fs::write(&path, "file content").unwrap();
+
+        let mock = server
+            .mock("POST", "/contents/uploadfile")
+            .with_status(200)
+            .with_body(
+                r#"{
+                "status": "ok",
+                "data": {
+                    "downloadPage": "http://example.com/path/file.txt",
+                    "code": "bar",
+                    "parentFolder": "00000000-0000-0000-0000-000000000001",
+                    "fileId": "00000000-0000-0000-0000-000000000002",
+                    "fileName": "test.txt",
+                    "md5": "d10b4c3ff123b26dc068d43a8bef2d23"
+                }
+            }"#,
+            )
+            .expect(2)
+            .create();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let observer = {
+            let seen = seen.clone();
+            move |_uuid, uploaded, total| seen.lock().unwrap().push((uploaded, total))
+        };
+        let options = UploadOptions::default()
+            .chunk_size(6)
+            .progress_interval(0, Duration::ZERO);
+        server_api
+            .upload_file_to_folder_with_observer(
+                uuid!("00000000-0000-0000-0000-000000000001").into(),
+                &path,
+                Arc::new(observer),
+                options,
+            )
+            .await?;
+        assert_eq!(*seen.lock().unwrap(), vec![(6, Some(12)), (12, Some(12))]);
+
+        server_api
+            .upload_file_to_folder_with_observer(
+                uuid!("00000000-0000-0000-0000-000000000001").into(),
+                &path,
+                Arc::new(NoopObserver),
+                UploadOptions::default(),
+            )
+            .await?;
+        mock.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upload_file_with_bounded_progress() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let server_api = AuthorizedServerApi {
+            base_url: server.url().parse().unwrap(),
+            token: "gofile_token".into(),
+            client: ApiClient::default(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.txt");
+        std::fs::write(&path, "file content").unwrap();
+        let folder_id = uuid!("00000000-0000-0000-0000-000000000001");
+
+        let mock = server
+            .mock("POST", "/contents/uploadfile")
+            .with_status(200)
+            .with_body(
+                r#"{
+                "status": "ok",
+                "data": {
+                    "downloadPage": "http://example.com/path/file.txt",
+                    "code": "bar",
+                    "parentFolder": "00000000-0000-0000-0000-000000000001",
+                    "fileId": "00000000-0000-0000-0000-000000000002",
+                    "fileName": "test.txt",
+                    "md5": "d10b4c3ff123b26dc068d43a8bef2d23"
+                }
+            }"#,
+            )
+            .expect(2)
+            .create();
+        let options = UploadOptions::default()
+            .chunk_size(2)
+            .progress_interval(0, Duration::ZERO);
+
+        // nobody reads while the upload runs, everything past the first message is dropped
+        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+        server_api
+            .upload_file_to_folder_with_bounded_progress(
+                FolderId(folder_id),
+                &path,
+                tx,
+                BackpressurePolicy::DropWhenFull,
+                options.clone(),
+            )
+            .await?;
+        let mut uploaded = Vec::new();
+        while let Some(message) = rx.recv().await {
+            uploaded.push(message.uploaded);
+        }
+        assert_eq!(uploaded, vec![2]);
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel::<UploadedMessage>(1);
+        let receiver = tokio::spawn(async move {
+            let mut uploaded = Vec::new();
+            while let Some(message) = rx.recv().await {
+                uploaded.push(message.uploaded);
+            }
+            uploaded
+        });
+        server_api
+            .upload_file_to_folder_with_bounded_progress(
+                FolderId(folder_id),
+                &path,
+                tx,
+                BackpressurePolicy::WaitForCapacity,
+                options,
+            )
+            .await?;
+        assert_eq!(receiver.await.unwrap(), vec![2, 4, 6, 8, 10, 12]);
+        mock.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upload_file_md5() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let server_api = AuthorizedServerApi {
+            base_url: server.url().parse().unwrap(),
+            token: "gofile_token".into(),
+            client: ApiClient::default(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.txt");
+        std::fs::write(&path, "file content").unwrap();
+
+        let response = |md5: &str| {
+            serde_json::json!({
+                "status": "ok",
+                "data": {
+                    "downloadPage": "http://example.com/path/file.txt",
+                    "code": "bar",
+                    "parentFolder": "00000000-0000-0000-0000-000000000001",
+                    "fileId": "00000000-0000-0000-0000-000000000002",
+                    "fileName": "test.txt",
+                    "md5": md5
+                }
+            })
+            .to_string()
+        };
+        let folder_id = uuid!("00000000-0000-0000-0000-000000000001");
+
+        let mock = server
+            .mock("POST", "/contents/uploadfile")
+            .with_status(200)
+            .with_body(response("d10b4c3ff123b26dc068d43a8bef2d23"))
+            .create();
+        let uploaded = server_api
+            .upload_file_to_folder(FolderId(folder_id), &path)
+            .await?;
+        assert_eq!(uploaded.local_md5, Some(uploaded.md5));
+        mock.remove();
+
+        let mock = server
+            .mock("POST", "/contents/uploadfile")
+            .with_status(200)
+            .with_body(response("000000000000000000000000000001ff"))
+            .expect(2)
+            .create();
+        let result = server_api
+            .upload_file_to_folder(FolderId(folder_id), &path)
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::ChecksumMismatch { expected, .. }) if expected == "d10b4c3ff123b26dc068d43a8bef2d23"
+        ));
+
+        let options = UploadOptions {
+            verify_md5: false,
+            ..Default::default()
+        };
+        let uploaded = server_api
+            .upload_file_to_folder_with_options(FolderId(folder_id), &path, None, options)
+            .await?;
+        assert_eq!(uploaded.local_md5, None);
+        mock.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upload_file_chunk_size() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let server_api = AuthorizedServerApi {
+            base_url: server.url().parse().unwrap(),
+            token: "gofile_token".into(),
+            client: ApiClient::default(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.txt");
+        std::fs::write(&path, "file content").unwrap();
+        let folder_id = uuid!("00000000-0000-0000-0000-000000000001");
+
+        let mock = server
+            .mock("POST", "/contents/uploadfile")
+            .with_status(200)
+            .with_body(
+                r#"{
+                "status": "ok",
+                "data": {
+                    "downloadPage": "http://example.com/path/file.txt",
+                    "code": "bar",
+                    "parentFolder": "00000000-0000-0000-0000-000000000001",
+                    "fileId": "00000000-0000-0000-0000-000000000002",
+                    "fileName": "test.txt",
+                    "md5": "d10b4c3ff123b26dc068d43a8bef2d23"
+                }
+            }"#,
+            )
+            .expect(2)
+            .create();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let options = UploadOptions::default()
+            .chunk_size(5)
+            .progress_interval(0, Duration::ZERO);
+        server_api
+            .upload_file_to_folder_with_options(FolderId(folder_id), &path, Some(tx), options)
+            .await?;
+        let mut uploaded = Vec::new();
+        while let Some(message) = rx.recv().await {
+            uploaded.push(message.uploaded);
+        }
+        assert_eq!(uploaded, vec![5, 10, 12]);
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let options = UploadOptions::default()
+            .chunk_size(2)
+            .progress_interval(5, Duration::ZERO);
+        server_api
+            .upload_file_to_folder_with_options(FolderId(folder_id), &path, Some(tx), options)
+            .await?;
+        let mut uploaded = Vec::new();
+        while let Some(message) = rx.recv().await {
+            uploaded.push(message.uploaded);
+        }
+        assert_eq!(uploaded, vec![6, 12]);
+        mock.assert();
+
+        let options = UploadOptions::default().chunk_size(0);
+        let result = server_api
+            .upload_file_to_folder_with_options(FolderId(folder_id), &path, None, options)
+            .await;
+        assert!(matches!(result, Err(Error::InvalidChunkSize(0))));
+        assert_eq!(
+            UploadOptions::default()
+                .chunk_size(usize::MAX)
+                .checked_chunk_size()?,
+            MAX_CHUNK_SIZE
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upload_bytes() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let server_api = AuthorizedServerApi {
+            base_url: server.url().parse().unwrap(),
+            token: "gofile_token".into(),
+            client: ApiClient::default(),
+        };
+
+        let mock = server
+            .mock("POST", "/contents/uploadfile")
+            .match_header("transfer-encoding", Matcher::Missing)
+            .match_body(Matcher::AllOf(vec![
+                Matcher::Regex(String::from("generated report")),
+                Matcher::Regex(String::from("report.txt")),
+                Matcher::Regex(String::from("00000000-0000-0000-0000-000000000001")),
+            ]))
+            .with_status(200)
+            .with_body(
+                r#"{
+                "status": "ok",
+                "data": {
+                    "downloadPage": "http://example.com/path/report.txt",
+                    "code": "bar",
+                    "parentFolder": "00000000-0000-0000-0000-000000000001",
+                    "fileId": "00000000-0000-0000-0000-000000000002",
+                    "fileName": "report.txt",
+                    "md5": "000000000000000000000000000001ff"
+                }
+            }"#,
+            )
+            .expect(1)
+            .create();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let uploaded_file = server_api
+            .upload_bytes_to_folder(
+                uuid!("00000000-0000-0000-0000-000000000001").into(),
+                "report.txt",
+                "generated report",
+                Some(tx),
+            )
+            .await?;
+        assert_eq!(
+            uploaded_file.file_id,
+            uuid!("00000000-0000-0000-0000-000000000002")
+        );
+        let message = rx.recv().await.unwrap();
+        assert_eq!((message.uploaded, message.total), (16, Some(16)));
+        assert!(rx.recv().await.is_none());
+        mock.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upload_reader() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let server_api = AuthorizedServerApi {
+            base_url: server.url().parse().unwrap(),
+            token: "gofile_token".into(),
+            client: ApiClient::default(),
+        };
+
+        let mock = server
+            .mock("POST", "/contents/uploadfile")
+            .match_body(Matcher::Regex(String::from("streamed content")))
+            .with_status(200)
+            .with_body(
+                r#"{
+                "status": "ok",
+                "data": {
+                    "downloadPage": "http://example.com/path/stream.bin",
+                    "code": "bar",
+                    "parentFolder": "00000000-0000-0000-0000-000000000001",
+                    "fileId": "00000000-0000-0000-0000-000000000002",
+                    "fileName": "stream.bin",
+                    "md5": "000000000000000000000000000001ff"
+                }
+            }"#,
+            )
+            .expect(2)
+            .create();
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        server_api
+            .upload_reader("stream.bin", &b"streamed content"[..], None, Some(tx))
+            .await?;
+        let mut last = None;
+        while let Some(message) = rx.recv().await {
+            last = Some((message.uploaded, message.total));
+        }
+        assert_eq!(last, Some((16, None)));
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        server_api
+            .upload_reader("stream.bin", &b"streamed content"[..], Some(16), Some(tx))
+            .await?;
+        let mut last = None;
+        while let Some(message) = rx.recv().await {
+            last = Some((message.uploaded, message.total));
+        }
+        assert_eq!(last, Some((16, Some(16))));
+        mock.assert();
+
+        Ok(())
+    }
+
+    #[test]
+    fn authorize_guest() {
+        let api = Api::default();
+        let mut uploaded_file = UploadedFile {
+            guest_token: Some("guest_token".into()),
+            download_page: Url::parse("https://gofile.io/d/Z19n9a").unwrap(),
+            code: Code::new("Z19n9a").unwrap(),
+            parent_folder: uuid!("3dbc2f87-4c1e-4a81-badc-af004e61a5b4").into(),
+            file_id: uuid!("4991e6d7-5217-46ae-af3d-c9174adae924").into(),
+            file_name: "example.mp4".into(),
+            md5: Md5::default(),
+            local_md5: None,
+            server: None,
+            extra: Extra::default(),
+        };
+
+        let authorized_api = api.authorize_guest(&uploaded_file).unwrap();
+        assert_eq!(authorized_api.token, "guest_token");
+        assert_eq!(authorized_api.base_url, api.base_url);
+
+        uploaded_file.guest_token = None;
+        assert!(matches!(
+            api.authorize_guest(&uploaded_file),
+            Err(Error::MissingGuestToken(id)) if id == uploaded_file.file_id
+        ));
+    }
+
+    #[tokio::test]
+    async fn shared_client() -> Result<(), Error> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let connections = Arc::new(AtomicUsize::new(0));
+        let accepted = connections.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                accepted.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let body =
+                        r#"{"status":"ok","data":{"servers":[{"name":"store1","zone":"eu"}]}}"#;
+                    let res = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let mut buf = vec![0; 4096];
+                    // each request is a small GET that arrives in one read
+                    while let Ok(1..) = socket.read(&mut buf).await {
+                        if socket.write_all(res.as_bytes()).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+
+        let api = Api {
+            base_url: base_url.parse().unwrap(),
+            ..Default::default()
+        };
+        api.get_servers().await?;
+        let server_api = api.get_server(ZonePreference::Any).await?;
+        assert_eq!(server_api.base_url.as_str(), "https://store1.gofile.io/");
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn with_client() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("x-app", "gofile-test".parse().unwrap());
+        let client = Client::builder().default_headers(headers).build().unwrap();
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        }
+        .with_client(client);
+
+        let mocks = vec![
+            server
+                .mock("GET", "/servers")
+                .match_header("x-app", "gofile-test")
+                .with_body(r#"{"status":"ok","data":{"servers":[{"name":"store1","zone":"eu"}]}}"#)
+                .create(),
+            server
+                .mock("GET", "/accounts/getid?token=gofile_token")
+                .match_header("x-app", "gofile-test")
+                .with_body(
+                    r#"{"status":"ok","data":{"id":"00000000-0000-0000-0000-000000000001"}}"#,
+                )
+                .create(),
+            server
+                .mock("POST", "/contents/uploadfile")
+                .match_header("x-app", "gofile-test")
+                .with_body(
+                    serde_json::json!({
+                        "status": "ok",
+                        "data": {
+                            "downloadPage": "https://gofile.io/d/foo",
+                            "code": "foo",
+                            "parentFolder": "00000000-0000-0000-0000-000000000001",
+                            "fileId": "00000000-0000-0000-0000-000000000002",
+                            "fileName": "test.txt",
+                            "md5": hex::encode(Md5Hasher::digest("ggg"))
+                        }
+                    })
+                    .to_string(),
+                )
+                .create(),
+        ];
+
+        let server_api = api.get_server(ZonePreference::Any).await?;
+        api.authorize("gofile_token").get_account_id().await?;
+        // the storage server inherits the client, only its url is swapped for the mock
+        ServerApi {
+            base_url: server.url().parse().unwrap(),
+            ..server_api
+        }
+        .upload_file_with_filename("test.txt", "ggg")
+        .await?;
+
+        for mock in mocks {
+            mock.assert();
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rate_limited() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        };
+
+        let mock = server
+            .mock("GET", "/servers")
+            .with_status(429)
+            .with_header("retry-after", "7")
+            .with_body("Too Many Requests")
+            .expect(1)
+            .create();
+        let result = api.get_server(ZonePreference::Any).await;
+        assert!(matches!(
+            result,
+            Err(Error::RateLimited {
+                retry_after: Some(retry_after),
+                ..
+            }) if retry_after == Duration::from_secs(7)
+        ));
+        mock.assert();
+
+        let mock = server
+            .mock("GET", "/servers")
+            .with_status(429)
+            .with_header("retry-after", "Wed, 21 Oct 2015 07:28:00 GMT")
+            .with_body(r#"{ "status": "error-rateLimit", "data": {} }"#)
+            .expect(1)
+            .create();
+        let result = api.get_server(ZonePreference::Any).await;
+        // a date in the past means retrying right away
+        assert!(matches!(
+            result,
+            Err(Error::RateLimited {
+                retry_after: Some(Duration::ZERO),
+                ..
+            })
+        ));
+        mock.assert();
+
+        let mock = server
+            .mock("GET", "/servers")
+            .with_status(200)
+            .with_body(r#"{ "status": "error-rateLimit", "data": {} }"#)
+            .expect(1)
+            .create();
+        let result = api.get_server(ZonePreference::Any).await;
+        assert!(matches!(
+            result,
+            Err(Error::RateLimited {
+                retry_after: None,
+                ..
+            })
+        ));
+        mock.assert();
+
+        Ok(())
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn tracing_spans() -> Result<(), Error> {
+        use std::fmt::Write;
+        use tracing::{field::Field, span, Event, Subscriber};
+        use tracing_subscriber::{layer::Context, prelude::*, Layer, Registry};
+
+        // every span and event of this crate as "name field=value ..."
+        #[derive(Clone, Default)]
+        struct Capture(Arc<Mutex<Vec<String>>>);
+
+        struct Fields(String);
+
+        impl tracing::field::Visit for Fields {
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                let _ = write!(self.0, " {}={:?}", field.name(), value);
+            }
+        }
+
+        impl<S: Subscriber> Layer<S> for Capture {
+            fn on_new_span(&self, attrs: &span::Attributes<'_>, _: &span::Id, _: Context<'_, S>) {
+                if !attrs.metadata().target().starts_with("gofile_api") {
+                    return;
+                }
+                let mut fields = Fields(attrs.metadata().name().into());
+                attrs.record(&mut fields);
+                self.0.lock().unwrap().push(fields.0);
+            }
+
+            fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+                if !event.metadata().target().starts_with("gofile_api") {
+                    return;
+                }
+                let mut fields = Fields("event".into());
+                event.record(&mut fields);
+                self.0.lock().unwrap().push(fields.0);
+            }
+        }
+
+        let capture = Capture::default();
+        let _guard = tracing::subscriber::set_default(Registry::default().with(capture.clone()));
+
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        };
+        let authorized = api.authorize("gofile_secret_token");
+        let content_id = uuid!("00000000-0000-0000-0000-000000000001");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.txt");
+        std::fs::write(&path, "kkk").unwrap();
+
+        let mocks = vec![
+            server
+                .mock("GET", "/servers")
+                .with_status(429)
+                .with_header("retry-after", "3")
+                .create(),
+            server
+                .mock(
+                    "PUT",
+                    "/contents/00000000-0000-0000-0000-000000000001/update",
+                )
+                .with_body(r#"{ "status": "ok", "data": {} }"#)
+                .create(),
+            server
+                .mock("POST", "/contents/uploadfile")
+                .with_body(
+                    serde_json::json!({
+                        "status": "ok",
+                        "data": {
+                            "downloadPage": "https://gofile.io/d/foo",
+                            "code": "foo",
+                            "parentFolder": "00000000-0000-0000-0000-000000000001",
+                            "fileId": "00000000-0000-0000-0000-000000000002",
+                            "fileName": "test.txt",
+                            "md5": hex::encode(Md5Hasher::digest("kkk"))
+                        }
+                    })
+                    .to_string(),
+                )
+                .create(),
+        ];
+
+        assert!(api.get_server(ZonePreference::Any).await.is_err());
+        authorized
+            .set_password_option(ContentId(content_id), "gofile_secret_password")
+            .await?;
+        let server_api = AuthorizedServerApi {
+            base_url: server.url().parse().unwrap(),
+            token: authorized.token.clone(),
+            client: ApiClient::default(),
+        };
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        server_api
+            .upload_file_to_folder_with_progress(FolderId(content_id), &path, Some(tx))
+            .await?;
+        for mock in mocks {
+            mock.assert();
+        }
+
+        let captured = capture.0.lock().unwrap().join("\n");
+        for expected in [
+            "request method=GET path=\"/servers\"",
+            "event message=response status=429",
+            "event message=rate limited by gofile retry_after=Some(3s)",
+            "set_password_option content_id=00000000-0000-0000-0000-000000000001",
+            "request method=PUT path=\"/contents/00000000-0000-0000-0000-000000000001/update\"",
+            "upload_file_to_folder_with_progress folder_id=00000000-0000-0000-0000-000000000001",
+            "event message=upload at 100% uploaded=3 total=3",
+            "event message=response status=200",
+        ] {
+            assert!(
+                captured.contains(expected),
+                "{} not in\n{}",
+                expected,
+                captured
+            );
+        }
+        assert!(!captured.contains("gofile_secret"), "{}", captured);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn error_context() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        };
+
+        let page = format!("<html><body>{}</body></html>", "bad gateway ".repeat(1000));
+        let mock = server
+            .mock("GET", "/servers")
+            .with_status(502)
+            .with_header("content-type", "text/html")
+            .with_header("cf-ray", "8a1b2c3d4e5f6789-CDG")
+            .with_body(&page)
+            .expect(1)
+            .create();
+        let err = api.get_server(ZonePreference::Any).await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::HttpStatusCodeError(_, StatusCode::BAD_GATEWAY, _)
+        ));
+        // the details stay out of the message
+        assert!(!err.to_string().contains("html"));
+        let context = err.context().unwrap();
+        assert_eq!(context.header("CF-Ray"), Some("8a1b2c3d4e5f6789-CDG"));
+        assert_eq!(context.header("content-type"), Some("text/html"));
+        assert_eq!(context.header("x-request-id"), None);
+        assert!(context.body.starts_with("<html><body>bad gateway"));
+        assert_eq!(context.body.len(), CONTEXT_BODY_LIMIT);
+        assert!(context.body_truncated);
+        mock.assert();
+
+        let mock = server
+            .mock("GET", "/servers")
+            .with_status(200)
+            .with_header("x-request-id", "req-42")
+            .with_body(r#"{ "status": "error-maintenance", "data": {} }"#)
+            .expect(1)
+            .create();
+        let err = api.get_server(ZonePreference::Any).await.unwrap_err();
+        assert!(
+            matches!(&err, Error::ApiStatusError(_, status, _) if status.as_str() == "error-maintenance")
+        );
+        let context = err.context().unwrap();
+        assert_eq!(context.header("x-request-id"), Some("req-42"));
+        assert_eq!(
+            context.body,
+            r#"{ "status": "error-maintenance", "data": {} }"#
+        );
+        assert!(!context.body_truncated);
+        mock.assert();
+
+        assert!(Error::EmptyServerList.context().is_none());
+        Ok(())
+    }
+
+    // Lists its storage hosts as the servers, each one answers after its delay or never
+    #[derive(Debug)]
+    struct LatencyTransport(Vec<(&'static str, Option<Duration>)>);
+
+    impl Transport for LatencyTransport {
+        fn execute(
+            &self,
+            req: reqwest::Request,
+        ) -> futures::future::BoxFuture<'static, Result<Response, Error>> {
+            use reqwest::ResponseBuilderExt;
+            let url = req.url().clone();
+            let (delay, body) = match url.host_str() {
+                Some("api.gofile.io") => {
+                    let servers: Vec<_> = self
+                        .0
+                        .iter()
+                        .map(|(name, _)| serde_json::json!({ "name": name, "zone": "eu" }))
+                        .collect();
+                    let body =
+                        serde_json::json!({ "status": "ok", "data": { "servers": servers } });
+                    (Some(Duration::ZERO), body.to_string())
+                }
+                host => {
+                    let name = host.unwrap().trim_end_matches(".gofile.io");
+                    let (_, delay) = self.0.iter().find(|(host, _)| *host == name).unwrap();
+                    (*delay, String::new())
+                }
+            };
+            Box::pin(async move {
+                match delay {
+                    Some(delay) if delay.is_zero() => {}
+                    Some(delay) => tokio::time::sleep(delay).await,
+                    None => futures::future::pending().await,
+                }
+                let res = http::Response::builder().status(200).url(url).body(body);
+                Ok(Response::from(res.unwrap()))
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn get_fastest_server() -> Result<(), Error> {
+        let api = |hosts| Api {
+            client: ApiClient::default().with_transport(Arc::new(LatencyTransport(hosts))),
+            ..Default::default()
+        };
+        let probe_timeout = Duration::from_millis(300);
+
+        let server_api = api(vec![
+            ("store1", None),
+            ("store2", Some(Duration::from_millis(200))),
+            ("store3", Some(Duration::from_millis(10))),
+        ])
+        .get_fastest_server(probe_timeout)
+        .await?;
+        assert_eq!(server_api.base_url.as_str(), "https://store3.gofile.io/");
+
+        // the server that hangs doesn't hold the others back
+        let started_at = Instant::now();
+        let authorized = api(vec![("store1", None), ("store2", Some(Duration::ZERO))])
+            .authorize("gofile_token")
+            .get_fastest_server(Duration::from_secs(10))
+            .await?;
+        assert_eq!(authorized.base_url.as_str(), "https://store2.gofile.io/");
+        assert_eq!(authorized.token, "gofile_token");
+        assert!(started_at.elapsed() < Duration::from_secs(5));
+
+        // answers that came in together go to the first listed server
+        for (hosts, expected) in [
+            (["store1", "store2"], "https://store1.gofile.io/"),
+            (["store2", "store1"], "https://store2.gofile.io/"),
+        ] {
+            let hosts = hosts.map(|host| (host, Some(Duration::ZERO))).to_vec();
+            let server_api = api(hosts).get_fastest_server(probe_timeout).await?;
+            assert_eq!(server_api.base_url.as_str(), expected);
+        }
+
+        // nobody answered in time, get_server's pick is taken once the probes gave up
+        let started_at = Instant::now();
+        let server_api = api(vec![
+            ("store1", Some(Duration::from_secs(30))),
+            ("store2", None),
+        ])
+        .get_fastest_server(probe_timeout)
+        .await?;
+        assert_eq!(server_api.base_url.as_str(), "https://store1.gofile.io/");
+        assert!(started_at.elapsed() >= probe_timeout);
+        assert!(started_at.elapsed() < Duration::from_secs(5));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn errors_hide_token() {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        }
+        .authorize("gofile_secret");
+
+        for (status, body) in [
+            (401, r#"{ "status": "error-auth", "data": {} }"#),
+            (502, "<html>bad gateway</html>"),
+            (200, r#"{ "status": "ok", "data": { "moved": true } }"#),
+            (429, ""),
+        ] {
+            let mock = server
+                .mock("GET", "/accounts/getid?token=gofile_secret")
+                .with_status(status)
+                .with_body(body)
+                .expect(1)
+                .create();
+            let err = api.get_account_id().await.unwrap_err();
+            assert!(!err.to_string().contains("gofile_secret"), "{}", err);
+            assert!(!format!("{:?}", err).contains("gofile_secret"), "{:?}", err);
+            if let Error::HttpStatusCodeError(url, ..) = &err {
+                assert_eq!(url.query(), Some("token=REDACTED"));
+            }
+            mock.assert();
+            mock.remove();
+        }
+
+        // reqwest keeps the url in its own errors
+        let api = Api::new()
+            .with_base_url("http://127.0.0.1:1")
+            .unwrap()
+            .authorize("gofile_secret");
+        let err = api.get_account_id().await.unwrap_err();
+        assert!(matches!(err, Error::HttpRequestError(_)));
+        assert!(!err.to_string().contains("gofile_secret"), "{}", err);
+        assert!(!format!("{:?}", err).contains("gofile_secret"), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn io_error_kind() {
+        let url: Url = "https://api.gofile.io/contents".parse().unwrap();
+        let context = Box::<ErrorContext>::default;
+        let status = |status| Error::HttpStatusCodeError(url.clone(), status, context());
+        let code = |code| Error::ApiStatusError(url.clone(), code, context());
+        let io_error = |kind| io::Error::new(kind, "io");
+        let connect = reqwest::get("http://127.0.0.1:0/").await.unwrap_err();
+
+        for (err, kind) in [
+            (Error::HttpRequestError(connect), io::ErrorKind::Other),
+            (status(StatusCode::NOT_FOUND), io::ErrorKind::NotFound),
+            (
+                status(StatusCode::UNAUTHORIZED),
+                io::ErrorKind::PermissionDenied,
+            ),
+            (
+                status(StatusCode::FORBIDDEN),
+                io::ErrorKind::PermissionDenied,
+            ),
+            (
+                status(StatusCode::TOO_MANY_REQUESTS),
+                io::ErrorKind::WouldBlock,
+            ),
+            (status(StatusCode::GATEWAY_TIMEOUT), io::ErrorKind::TimedOut),
+            (status(StatusCode::BAD_GATEWAY), io::ErrorKind::Other),
+            (code(GofileErrorCode::NotFound), io::ErrorKind::NotFound),
+            (code(GofileErrorCode::Auth), io::ErrorKind::PermissionDenied),
+            (code(GofileErrorCode::NoServer), io::ErrorKind::Other),
+            (
+                code(GofileErrorCode::Other("error-maintenance".into())),
+                io::ErrorKind::Other,
+            ),
+            (
+                Error::PasswordRequired(url.clone()),
+                io::ErrorKind::PermissionDenied,
+            ),
+            (
+                Error::WrongPassword(url.clone()),
+                io::ErrorKind::PermissionDenied,
+            ),
+            (
+                Error::NotPremium(url.clone()),
+                io::ErrorKind::PermissionDenied,
+            ),
+            (
+                Error::DownloadForbidden(url.clone()),
+                io::ErrorKind::PermissionDenied,
+            ),
+            (
+                Error::RateLimited {
+                    url: url.clone(),
+                    retry_after: None,
+                },
+                io::ErrorKind::WouldBlock,
+            ),
+            (
+                Error::Stalled(url.clone(), Duration::from_secs(1)),
+                io::ErrorKind::TimedOut,
+            ),
+            (
+                Error::ChecksumMismatch {
+                    expected: "a".into(),
+                    actual: "b".into(),
+                    path: "x".into(),
+                },
+                io::ErrorKind::InvalidData,
+            ),
+            (
+                Error::Decode {
+                    url: url.clone(),
+                    serde_error: serde_json::from_str::<Value>("{").unwrap_err(),
+                    body_snippet: "{".into(),
+                },
+                io::ErrorKind::InvalidData,
+            ),
+            (
+                Error::CouldntOpenFile("x".into(), io_error(io::ErrorKind::NotFound)),
+                io::ErrorKind::NotFound,
+            ),
+            (
+                Error::CouldntWriteFile("x".into(), io_error(io::ErrorKind::StorageFull)),
+                io::ErrorKind::StorageFull,
+            ),
+            (
+                Error::CouldntWrite(io_error(io::ErrorKind::BrokenPipe)),
+                io::ErrorKind::BrokenPipe,
+            ),
+            (Error::FileExists("x".into()), io::ErrorKind::AlreadyExists),
+            (
+                Error::InvalidName("x".into(), "bad".into()),
+                io::ErrorKind::InvalidInput,
+            ),
+            (Error::InvalidChunkSize(0), io::ErrorKind::InvalidInput),
+            (
+                Error::InvalidContentUrl(url.clone(), "bad".into()),
+                io::ErrorKind::InvalidInput,
+            ),
+            (Error::EmptyServerList, io::ErrorKind::Other),
+            (Error::UploadCancelled("x".into()), io::ErrorKind::Other),
+            (
+                Error::QuotaExceeded {
+                    needed: 2,
+                    available: 1,
+                },
+                io::ErrorKind::Other,
+            ),
+        ] {
+            assert_eq!(err.kind_hint(), kind, "{:?}", err);
+            let message = err.to_string();
+            let io_err = io::Error::from(err);
+            assert_eq!(io_err.kind(), kind);
+            assert_eq!(io_err.to_string(), message);
+            assert!(io_err.get_ref().unwrap().is::<Error>());
+        }
+    }
+
+    #[tokio::test]
+    async fn decode_error() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        };
+
+        // valid json gofile reshaped, with a token next to the field that changed
+        let body =
+            r#"{ "status": "ok", "data": { "servers": "moved", "token": "gofile_secret" } }"#;
+        let mock = server
+            .mock("GET", "/servers")
+            .with_status(200)
+            .with_body(body)
+            .expect(1)
+            .create();
+        let err = api.get_server(ZonePreference::Any).await.unwrap_err();
+        let Error::Decode {
+            url, body_snippet, ..
+        } = &err
+        else {
+            panic!("{:?}", err);
+        };
+        assert_eq!(url.path(), "/servers");
+        assert_eq!(
+            &**body_snippet,
+            r#"{ "status": "ok", "data": { "servers": "moved", "token": "REDACTED" } }"#
+        );
+        assert!(!err.to_string().contains("\"data\""));
+        assert!(std::error::Error::source(&err).is_some());
+        mock.assert();
+        mock.remove();
+
+        // not json at all, only the start is kept
+        let body = format!("{{ \"status\": \"ok\", {}", "x".repeat(5000));
+        let mock = server
+            .mock("GET", "/servers")
+            .with_status(200)
+            .with_body(&body)
+            .expect(1)
+            .create();
+        match api.get_server(ZonePreference::Any).await {
+            Err(Error::Decode { body_snippet, .. }) => {
+                assert_eq!(body_snippet.len(), DECODE_SNIPPET_LIMIT);
+                assert!(body.starts_with(&*body_snippet));
+            }
+            result => panic!("{:?}", result),
+        }
+        mock.assert();
+
+        // an authorized call has the token in its url, the body may echo it too
+        let api = api.authorize("gofile_secret");
+        for (status, body) in [
+            (
+                200,
+                r#"{ "status": "ok", "data": { "token": "gofile_secret" } }"#,
+            ),
+            (
+                401,
+                r#"{ "status": "error-auth", "data": { "token": "gofile_secret" } }"#,
+            ),
+        ] {
+            let mock = server
+                .mock("GET", "/accounts/getid?token=gofile_secret")
+                .with_status(status)
+                .with_body(body)
+                .expect(1)
+                .create();
+            let err = api.get_account_id().await.unwrap_err();
+            assert!(matches!(
+                err,
+                Error::Decode { .. } | Error::ApiStatusError(..)
+            ));
+            assert!(!err.to_string().contains("gofile_secret"), "{}", err);
+            assert!(!format!("{:?}", err).contains("gofile_secret"), "{:?}", err);
+            if let Some(context) = err.context() {
+                assert!(context.body.contains(r#""token": "REDACTED""#));
+            }
+            mock.assert();
+            mock.remove();
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn gofile_error_code() {
+        for (status, code) in [
+            ("error-notFound", GofileErrorCode::NotFound),
+            ("error-notPremium", GofileErrorCode::NotPremium),
+            ("error-rateLimit", GofileErrorCode::RateLimit),
+            ("error-auth", GofileErrorCode::Auth),
+            ("error-passwordRequired", GofileErrorCode::PasswordRequired),
+            ("error-passwordWrong", GofileErrorCode::WrongPassword),
+            ("error-noServer", GofileErrorCode::NoServer),
+            (
+                "error-maintenance",
+                GofileErrorCode::Other("error-maintenance".into()),
+            ),
+            (
+                "error-NOTFOUND",
+                GofileErrorCode::Other("error-NOTFOUND".into()),
+            ),
+        ] {
+            assert_eq!(GofileErrorCode::from(status), code);
+            assert_eq!(code.as_str(), status);
+            assert_eq!(code.to_string(), status);
+        }
+        assert!(GofileErrorCode::RateLimit.is_retryable());
+        assert!(!GofileErrorCode::Auth.is_retryable());
+        assert!(!GofileErrorCode::Other("error-maintenance".into()).is_retryable());
+
+        let url: Url = "https://api.gofile.io/contents".parse().unwrap();
+        let err = Api::status_error(url.clone(), "error-auth".into(), None, Box::default());
+        assert_eq!(err.code(), Some(GofileErrorCode::Auth));
+        let err = Api::status_error(url.clone(), "error-notPremium".into(), None, Box::default());
+        assert!(matches!(err, Error::NotPremium(_)));
+        assert_eq!(err.code(), Some(GofileErrorCode::NotPremium));
+        let err = Api::status_error(url, "error-rateLimit".into(), None, Box::default());
+        assert_eq!(err.code(), Some(GofileErrorCode::RateLimit));
+        assert_eq!(Error::EmptyServerList.code(), None);
+    }
+
+    #[tokio::test]
+    async fn error_classification() {
+        use std::error::Error as _;
+
+        let url: Url = "https://api.gofile.io/contents".parse().unwrap();
+        let context = Box::<ErrorContext>::default;
+        let io_error = || std::io::Error::new(std::io::ErrorKind::PermissionDenied, "denied");
+
+        // nothing listens on a port that was just released
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let connect_url = format!("http://127.0.0.1:{}/", port);
+        let connect = reqwest::get(&connect_url).await.unwrap_err();
+        let builder = reqwest::Client::new()
+            .get("not a url")
+            .send()
+            .await
+            .unwrap_err();
+
+        for (err, retryable, status) in [
+            (Error::HttpRequestError(connect), true, None),
+            (Error::HttpRequestError(builder), false, None),
+            (
+                Error::HttpStatusCodeError(url.clone(), StatusCode::BAD_GATEWAY, context()),
+                true,
+                Some(StatusCode::BAD_GATEWAY),
+            ),
+            (
+                Error::HttpStatusCodeError(url.clone(), StatusCode::TOO_MANY_REQUESTS, context()),
+                true,
+                Some(StatusCode::TOO_MANY_REQUESTS),
+            ),
+            (
+                Error::HttpStatusCodeError(url.clone(), StatusCode::NOT_FOUND, context()),
+                false,
+                Some(StatusCode::NOT_FOUND),
+            ),
+            (
+                Error::ApiStatusError(url.clone(), GofileErrorCode::Auth, context()),
+                false,
+                None,
+            ),
+            (
+                Error::ApiStatusError(url.clone(), GofileErrorCode::NotFound, context()),
+                false,
+                None,
+            ),
+            (
+                Error::RateLimited {
+                    url: url.clone(),
+                    retry_after: None,
+                },
+                true,
+                None,
+            ),
+            (
+                Error::Stalled(url.clone(), Duration::from_secs(1)),
+                true,
+                None,
+            ),
+            (Error::PasswordRequired(url.clone()), false, None),
+            (Error::WrongPassword(url.clone()), false, None),
+            (Error::NotPremium(url.clone()), false, None),
+            (Error::RedirectBlocked(url.clone()), false, None),
+            (Error::DownloadForbidden(url.clone()), false, None),
+            (Error::WebsiteTokenNotFound(url.clone()), false, None),
+            (
+                Error::InvalidContentUrl(url.clone(), "bad".into()),
+                false,
+                None,
+            ),
+            (
+                Error::Decode {
+                    url: url.clone(),
+                    serde_error: serde_json::from_str::<Value>("{").unwrap_err(),
+                    body_snippet: "{".into(),
+                },
+                false,
+                None,
+            ),
+            (
+                Error::ChecksumMismatch {
+                    expected: "a".into(),
+                    actual: "b".into(),
+                    path: "x".into(),
+                },
+                false,
+                None,
+            ),
+            (Error::CouldntOpenFile("x".into(), io_error()), false, None),
+            (Error::CouldntWriteFile("x".into(), io_error()), false, None),
+            (Error::CouldntWrite(io_error()), false, None),
+            (
+                Error::InvalidFilePath("x".into(), "bad".into()),
+                false,
+                None,
+            ),
+            (Error::InvalidName("x".into(), "bad".into()), false, None),
+            (Error::InvalidCode("x".into(), "bad".into()), false, None),
+            (Error::InvalidChunkSize(0), false, None),
+            (Error::InvalidConfig("bad".into()), false, None),
+            (Error::EmptyServerList, false, None),
+            (
+                Error::QuotaExceeded {
+                    needed: 2,
+                    available: 1,
+                },
+                false,
+                None,
+            ),
+        ] {
+            assert_eq!(err.is_retryable(), retryable, "{:?}", err);
+            assert_eq!(err.status_code(), status, "{:?}", err);
+        }
+
+        let err = Error::NotPremium(url.clone());
+        assert_eq!(err.url(), Some(&url));
+        let err = Error::RateLimited {
+            url: url.clone(),
+            retry_after: None,
+        };
+        assert_eq!(err.url(), Some(&url));
+        let err = Error::HttpRequestError(reqwest::get(&connect_url).await.unwrap_err());
+        assert_eq!(err.url().map(Url::as_str), Some(connect_url.as_str()));
+        assert!(err.source().is_some());
+        assert_eq!(Error::EmptyServerList.url(), None);
+
+        // the underlying error stays reachable for error reporters
+        let err = Error::CouldntOpenFile("x".into(), io_error());
+        let source = err.source().unwrap();
+        assert_eq!(
+            source.downcast_ref::<std::io::Error>().unwrap().kind(),
+            std::io::ErrorKind::PermissionDenied
+        );
+        assert!(
+            Error::TokenEnvError("X".into(), std::env::VarError::NotPresent)
+                .source()
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn url() -> Result<(), Error> {
+        for (base_url, expected) in [
+            (
+                "https://api.gofile.io",
+                "https://api.gofile.io/contents/foo",
+            ),
+            (
+                "https://api.gofile.io/",
+                "https://api.gofile.io/contents/foo",
+            ),
+            (
+                "http://127.0.0.1:8080/gofile",
+                "http://127.0.0.1:8080/gofile/contents/foo",
+            ),
+            (
+                "http://127.0.0.1:8080/gofile/",
+                "http://127.0.0.1:8080/gofile/contents/foo",
+            ),
+        ] {
+            let base_url = Url::parse(base_url).unwrap();
+            assert_eq!(Api::url(&base_url, "contents/foo")?.as_str(), expected);
+            assert_eq!(Api::url(&base_url, "/contents/foo")?.as_str(), expected);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn with_base_url() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/proxy/gofile/servers")
+            .with_body(r#"{"status":"ok","data":{"servers":[{"name":"store1","zone":"eu"}]}}"#)
+            .expect(2)
+            .create();
+
+        for base_url in [
+            format!("{}/proxy/gofile", server.url()),
+            format!("{}/proxy/gofile/", server.url()),
+        ] {
+            let api = Api::new().with_base_url(base_url.as_str())?;
+            api.get_server(ZonePreference::Any).await?;
+        }
+        mock.assert();
+
+        let base_url = Url::parse(&server.url()).unwrap();
+        assert_eq!(
+            Api::new().with_base_url(base_url.clone())?.base_url,
+            base_url
+        );
+        for result in [
+            Api::new().with_base_url("api.gofile.io"),
+            Api::new().with_base_url("ftp://api.gofile.io"),
+            Api::new().with_base_url(Url::parse("mailto:gofile@example.com").unwrap()),
+        ] {
+            assert!(matches!(result, Err(Error::InvalidBaseUrl(..))));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn raw_requests() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        }
+        .authorize("gofile_token");
+
+        let mut mocks = vec![server
+            .mock("GET", "/accounts/website")
+            .match_query(Matcher::UrlEncoded("page".into(), "2".into()))
+            .match_header("authorization", "Bearer gofile_token")
+            .with_body(r#"{ "status": "ok", "data": { "items": [1, 2] } }"#)
+            .expect(1)
+            .create()];
+        for method in ["POST", "PUT", "DELETE"] {
+            mocks.push(
+                server
+                    .mock(method, "/contents/new")
+                    .match_header("authorization", "Bearer gofile_token")
+                    .match_body(Matcher::Json(serde_json::json!({ "method": method })))
+                    .with_body(
+                        serde_json::json!({ "status": "ok", "data": { "method": method } })
+                            .to_string(),
+                    )
+                    .expect(1)
+                    .create(),
+            );
+        }
+
+        let data = api.raw_get("accounts/website", &[("page", "2")]).await?;
+        assert_eq!(data, serde_json::json!({ "items": [1, 2] }));
+        let data = api
+            .raw_post_json("/contents/new", serde_json::json!({ "method": "POST" }))
+            .await?;
+        assert_eq!(data["method"], "POST");
+        let data = api
+            .raw_put_json("contents/new", serde_json::json!({ "method": "PUT" }))
+            .await?;
+        assert_eq!(data["method"], "PUT");
+        let data = api
+            .raw_delete_json("contents/new", serde_json::json!({ "method": "DELETE" }))
+            .await?;
+        assert_eq!(data["method"], "DELETE");
+        for mock in mocks {
+            mock.assert();
+        }
+
+        // the same error mapping as the typed calls
+        let mock = server
+            .mock("GET", "/contents/missing")
+            .with_status(404)
+            .with_body(r#"{ "status": "error-notFound", "data": {} }"#)
+            .expect(1)
+            .create();
+        let result = api.raw_get("contents/missing", &[]).await;
+        assert!(matches!(
+            result,
+            Err(Error::ApiStatusError(_, GofileErrorCode::NotFound, _))
+        ));
+        mock.assert();
+
+        // the token is never sent to another host
+        let result = api.raw_get("https://example.com/steal", &[]).await;
+        assert!(matches!(result, Err(Error::InvalidBaseUrl(..))));
         Ok(())
     }
 }
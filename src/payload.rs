@@ -1,37 +1,71 @@
-use chrono::{serde::ts_seconds, DateTime, Utc};
+use crate::{Code, ContentId, FileId, FolderId, Md5, Token};
+use chrono::{
+    serde::{ts_seconds, ts_seconds_option},
+    DateTime, Utc,
+};
 use mime::Mime;
-use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
-use std::{collections::HashMap, str::FromStr};
+use serde::{de, ser, ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::{collections::HashMap, net::IpAddr, str::FromStr};
 use url::Url;
 use uuid::Uuid;
 
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateFolderApiPayload {
-    pub token: String,
-    pub parent_folder_id: Uuid,
+    pub token: Token,
+    pub parent_folder_id: FolderId,
     pub folder_name: String,
 }
 
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UpdateContentApiPayload {
-    pub token: String,
-
     #[serde(flatten)]
     pub opt: ContentOpt,
 }
 
-#[derive(Debug, Serialize)]
-#[serde(tag = "option", content = "value", rename_all = "camelCase")]
+// old `{"token", "option", "value"}` body, still accepted by some deployments
+#[derive(Debug)]
+pub struct LegacyUpdateContentApiPayload {
+    pub token: Token,
+    pub opt: ContentOpt,
+}
+
+impl Serialize for LegacyUpdateContentApiPayload {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let value = serde_json::to_value(&self.opt).map_err(ser::Error::custom)?;
+        let option = match value["attribute"].as_str() {
+            Some("expiry") => "expire",
+            Some(attribute) => attribute,
+            None => return Err(ser::Error::custom("ContentOpt has no attribute")),
+        };
+        let mut map = s.serialize_map(Some(3))?;
+        map.serialize_entry("token", &self.token)?;
+        map.serialize_entry("option", option)?;
+        map.serialize_entry("value", &value["attributeValue"])?;
+        map.end()
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(
+    tag = "attribute",
+    content = "attributeValue",
+    rename_all = "camelCase"
+)]
 pub enum ContentOpt {
     #[serde(serialize_with = "to_string")]
     Public(bool),
 
+    Name(String),
     Password(String),
     Description(String),
 
-    #[serde(with = "ts_seconds")]
+    #[serde(rename = "expiry", with = "ts_seconds")]
     Expire(DateTime<Utc>),
 
     #[serde(serialize_with = "comma_separated_string_from_vec")]
@@ -39,91 +73,459 @@ pub enum ContentOpt {
 
     #[serde(serialize_with = "to_string")]
     DirectLink(bool),
+
+    #[serde(rename = "password", serialize_with = "empty_string")]
+    ClearPassword,
+
+    #[serde(rename = "description", serialize_with = "empty_string")]
+    ClearDescription,
+
+    #[serde(rename = "expiry", serialize_with = "empty_string")]
+    ClearExpire,
 }
 
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CopyContentApiPayload {
-    pub token: String,
+    pub token: Token,
+
+    #[serde(serialize_with = "comma_separated_string_from_vec")]
+    pub contents_id: Vec<ContentId>,
+    pub folder_id_dest: FolderId,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveContentApiPayload {
+    pub token: Token,
 
     #[serde(serialize_with = "comma_separated_string_from_vec")]
-    pub contents_id: Vec<Uuid>,
-    pub folder_id_dest: Uuid,
+    pub contents_id: Vec<ContentId>,
+    pub folder_id_dest: FolderId,
 }
 
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DeleteContentApiPayload {
-    pub token: String,
+    pub token: Token,
 
     #[serde(serialize_with = "comma_separated_string_from_vec")]
-    pub contents_id: Vec<Uuid>,
+    pub contents_id: Vec<ContentId>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GetContentOptions {
+    pub sort_field: Option<SortField>,
+    pub sort_direction: Option<SortDirection>,
+    pub content_filter: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortField {
+    Name,
+    Size,
+    CreateTime,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+impl GetContentOptions {
+    pub fn sort_by(mut self, field: SortField, direction: SortDirection) -> Self {
+        self.sort_field = Some(field);
+        self.sort_direction = Some(direction);
+        self
+    }
+
+    pub fn sort_by_name(self) -> Self {
+        self.sort_by(SortField::Name, SortDirection::Asc)
+    }
+
+    pub fn sort_by_size_desc(self) -> Self {
+        self.sort_by(SortField::Size, SortDirection::Desc)
+    }
+
+    pub fn sort_by_newest(self) -> Self {
+        self.sort_by(SortField::CreateTime, SortDirection::Desc)
+    }
+
+    pub fn filter(mut self, content_filter: impl Into<String>) -> Self {
+        self.content_filter = Some(content_filter.into());
+        self
+    }
+
+    pub fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![];
+        if let Some(field) = self.sort_field {
+            let field = match field {
+                SortField::Name => "name",
+                SortField::Size => "size",
+                SortField::CreateTime => "createTime",
+            };
+            params.push(("sortField", field.to_string()));
+        }
+        if let Some(direction) = self.sort_direction {
+            let direction = match direction {
+                SortDirection::Asc => "1",
+                SortDirection::Desc => "-1",
+            };
+            params.push(("sortDirection", direction.to_string()));
+        }
+        if let Some(content_filter) = &self.content_filter {
+            params.push(("contentFilter", content_filter.clone()));
+        }
+        params
+    }
 }
 
-#[derive(Debug, PartialEq, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirectLinkOptions {
+    #[serde(with = "ts_seconds_option", skip_serializing_if = "Option::is_none")]
+    pub expire_time: Option<DateTime<Utc>>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub source_ips_allowed: Vec<IpAddr>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub domains_allowed: Vec<String>,
+
+    #[serde(
+        serialize_with = "colon_separated_pairs",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub auth: Vec<(String, String)>,
+}
+
+impl DirectLinkOptions {
+    pub fn expire_time(mut self, expire_time: DateTime<Utc>) -> Self {
+        self.expire_time = Some(expire_time);
+        self
+    }
+
+    pub fn source_ip_allowed(mut self, ip: IpAddr) -> Self {
+        self.source_ips_allowed.push(ip);
+        self
+    }
+
+    pub fn domain_allowed(mut self, domain: impl Into<String>) -> Self {
+        self.domains_allowed.push(domain.into());
+        self
+    }
+
+    pub fn auth(mut self, user: impl Into<String>, password: impl Into<String>) -> Self {
+        self.auth.push((user.into(), password.into()));
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ApiResult<T> {
     pub status: String,
     pub data: T,
 }
 
-#[derive(Debug, PartialEq, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Servers {
+    // the servers gofile suggests for this client
     pub servers: Vec<Server>,
+
+    // every server of every zone, only in newer responses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub servers_all_zone: Option<Vec<Server>>,
 }
 
-#[derive(Debug, PartialEq, Deserialize)]
+impl Servers {
+    // The suggested servers followed by the other zones' ones, without duplicates
+    pub fn all(&self) -> Vec<&Server> {
+        let mut all: Vec<&Server> = vec![];
+        for server in self
+            .servers
+            .iter()
+            .chain(self.servers_all_zone.iter().flatten())
+        {
+            if all.iter().all(|known| known.name != server.name) {
+                all.push(server);
+            }
+        }
+        all
+    }
+
+    pub fn in_zone(&self, zone: &Zone) -> Vec<&Server> {
+        self.all()
+            .into_iter()
+            .filter(|server| server.zone == *zone)
+            .collect()
+    }
+
+    // The first suggested server, or any server when none is suggested
+    pub fn best(&self) -> Option<&Server> {
+        self.all().into_iter().next()
+    }
+
+    // The first server matching the preference, in the order of all()
+    pub fn select(&self, preference: &ZonePreference) -> Option<&Server> {
+        match preference {
+            ZonePreference::Any => self.best(),
+            ZonePreference::Zone(zone) => self.in_zone(zone).into_iter().next(),
+            ZonePreference::Preferred(zones) => zones
+                .iter()
+                .find_map(|zone| self.in_zone(zone).into_iter().next())
+                .or_else(|| self.best()),
+        }
+    }
+}
+
+// Where Api::get_server looks for a server. Zone only accepts that zone, Preferred tries each
+// zone in order and takes any server when none of them has one.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum ZonePreference {
+    #[default]
+    Any,
+    Zone(Zone),
+    Preferred(Vec<Zone>),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Server {
     pub name: String,
-    pub zone: String,
+    pub zone: Zone,
+
+    #[serde(flatten, skip_serializing_if = "Extra::is_empty")]
+    pub extra: Extra,
 }
 
-#[derive(Debug, PartialEq, Deserialize)]
+// Read case-insensitively, a zone this crate doesn't know is kept as it came
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Zone {
+    Eu,
+    Na,
+    Ap,
+    Other(String),
+}
+
+impl Zone {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Zone::Eu => "eu",
+            Zone::Na => "na",
+            Zone::Ap => "ap",
+            Zone::Other(zone) => zone,
+        }
+    }
+}
+
+impl From<&str> for Zone {
+    fn from(zone: &str) -> Self {
+        match zone.to_ascii_lowercase().as_str() {
+            "eu" => Zone::Eu,
+            "na" => Zone::Na,
+            "ap" => Zone::Ap,
+            _ => Zone::Other(zone.into()),
+        }
+    }
+}
+
+impl std::fmt::Display for Zone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for Zone {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        s.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Zone {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let zone = String::deserialize(d)?;
+        Ok(Zone::from(zone.as_str()))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UploadedFile {
-    pub guest_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guest_token: Option<Token>,
     pub download_page: Url,
-    pub code: String,
-    pub parent_folder: Uuid,
-    pub file_id: Uuid,
+    pub code: Code,
+    pub parent_folder: FolderId,
+    pub file_id: FileId,
     pub file_name: String,
 
-    #[serde(with = "hex::serde")]
-    pub md5: [u8; 16],
+    pub md5: Md5,
+
+    // md5 of the bytes actually sent, only set for verified path uploads
+    #[serde(skip)]
+    pub local_md5: Option<Md5>,
+
+    // base url of the server that took the upload
+    #[serde(skip)]
+    pub server: Option<String>,
+
+    #[serde(flatten, skip_serializing_if = "Extra::is_empty")]
+    pub extra: Extra,
 }
 
-#[derive(Debug, PartialEq, Deserialize)]
-#[serde(rename_all = "camelCase")]
+// Response fields this crate doesn't model yet, keyed by their json name
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Extra(pub HashMap<String, Value>);
+
+impl Extra {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl std::ops::Deref for Extra {
+    type Target = HashMap<String, Value>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl std::ops::DerefMut for Extra {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+// See the Deserialize impl below for where unknown fields end up
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(remote = "Self", rename_all = "camelCase")]
 pub struct Content {
-    pub id: Uuid,
+    pub id: ContentId,
     pub name: String,
-    pub parent_folder: Option<Uuid>,
 
-    #[serde(with = "ts_seconds")]
-    pub create_time: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_folder: Option<FolderId>,
+
+    // missing for some freshly created folders
+    #[serde(
+        default,
+        with = "tolerant_ts_seconds",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub create_time: Option<DateTime<Utc>>,
+
+    #[serde(
+        default,
+        with = "tolerant_ts_seconds",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub mod_time: Option<DateTime<Utc>>,
 
     #[serde(flatten)]
     pub kind: ContentKind,
+
+    #[serde(flatten, skip_serializing_if = "Extra::is_empty")]
+    pub extra: Extra,
+}
+
+impl Content {
+    // The id as a folder id, None for files and unknown kinds
+    pub fn folder_id(&self) -> Option<FolderId> {
+        matches!(self.kind, ContentKind::Folder { .. }).then_some(FolderId(self.id.0))
+    }
+
+    pub fn file_id(&self) -> Option<FileId> {
+        matches!(self.kind, ContentKind::File { .. }).then_some(FileId(self.id.0))
+    }
+}
+
+// The kind sees every field Content doesn't read itself, so its extra would hold the same
+// unknown fields as Content's. They're kept on Content only, and unknown kinds keep them in raw.
+impl<'de> Deserialize<'de> for Content {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut content = Content::deserialize(d)?;
+        content.extra = match &mut content.kind {
+            ContentKind::Folder { extra, .. } | ContentKind::File { extra, .. } => {
+                std::mem::take(extra)
+            }
+            ContentKind::Unknown { .. } => Extra::default(),
+        };
+        Ok(content)
+    }
+}
+
+impl Serialize for Content {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        Content::serialize(self, s)
+    }
 }
 
-#[derive(Debug, PartialEq, Deserialize)]
-#[serde(tag = "type", rename_all = "camelCase")]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentPage {
+    #[serde(flatten)]
+    pub content: Content,
+    pub metadata: PageMetadata,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageMetadata {
+    #[serde(rename = "totalCount")]
+    pub total_children: u64,
+    pub page: u32,
+    pub page_size: u32,
+}
+
+// The derives are only used for folders and files, see the Deserialize impl below
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(remote = "Self", tag = "type", rename_all = "camelCase")]
 pub enum ContentKind {
     #[serde(rename_all = "camelCase")]
     Folder {
-        code: String,
+        code: Code,
 
-        #[serde(default)]
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
         public: bool,
 
-        children_ids: Vec<Uuid>,
+        #[serde(
+            default,
+            deserialize_with = "vec_from_comma_separated_string",
+            serialize_with = "comma_separated_string_from_option_vec",
+            skip_serializing_if = "Option::is_none"
+        )]
+        tags: Option<Vec<String>>,
+
+        #[serde(default)]
+        children_ids: Vec<ContentId>,
 
         // only top folder
+        #[serde(skip_serializing_if = "Option::is_none")]
         total_download_count: Option<u32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
         total_size: Option<u64>,
-        children: Option<HashMap<Uuid, Content>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        children: Option<HashMap<ContentId, Content>>,
+
+        #[serde(flatten, skip_serializing_if = "Extra::is_empty")]
+        extra: Extra,
     },
 
     #[serde(rename_all = "camelCase")]
@@ -131,32 +533,452 @@ pub enum ContentKind {
         size: u64,
         download_count: u32,
 
-        #[serde(with = "hex::serde")]
-        md5: [u8; 16],
+        // missing from some listings of content the account doesn't own
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        md5: Option<Md5>,
 
-        #[serde(deserialize_with = "mime_from_str")]
+        #[serde(deserialize_with = "mime_from_str", serialize_with = "to_string")]
         mimetype: Mime,
-        server_selected: String,
-        link: Url,
+
+        // older responses name a single server, newer ones list them, see ContentKind::server
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        server_selected: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        servers: Option<Vec<String>>,
+
+        // missing for non-owned or non-premium content, see ContentKind::best_link
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        link: Option<Url>,
+
+        #[serde(
+            default,
+            deserialize_with = "vec_from_comma_separated_string",
+            serialize_with = "comma_separated_string_from_option_vec",
+            skip_serializing_if = "Option::is_none"
+        )]
+        tags: Option<Vec<String>>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        direct_links: Option<HashMap<Uuid, DirectLink>>,
+
+        #[serde(flatten, skip_serializing_if = "Extra::is_empty")]
+        extra: Extra,
     },
+
+    // A type this crate doesn't know yet, kept so one odd child doesn't fail a whole listing.
+    // raw holds every field except the ones Content reads itself.
+    #[serde(skip)]
+    Unknown { kind: String, raw: Value },
+}
+
+impl ContentKind {
+    // The download link of a file, or an active direct link when the listing has none
+    pub fn best_link(&self) -> Option<&Url> {
+        let ContentKind::File {
+            link, direct_links, ..
+        } = self
+        else {
+            return None;
+        };
+        let now = Utc::now();
+        link.as_ref().or_else(|| {
+            direct_links
+                .iter()
+                .flat_map(|direct_links| direct_links.values())
+                .find(|direct_link| direct_link.expire_time.is_none_or(|expire| now < expire))
+                .map(|direct_link| &direct_link.link)
+        })
+    }
+
+    // The storage server holding a file, like "store1"
+    pub fn server(&self) -> Option<&str> {
+        let ContentKind::File {
+            server_selected,
+            servers,
+            ..
+        } = self
+        else {
+            return None;
+        };
+        server_selected
+            .as_deref()
+            .or_else(|| servers.as_ref()?.first().map(String::as_str))
+    }
 }
 
-#[derive(Debug, PartialEq, Deserialize)]
+impl<'de> Deserialize<'de> for ContentKind {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut raw = Value::deserialize(d)?;
+        match raw.get("type").and_then(Value::as_str) {
+            Some("folder") => normalize_children(&mut raw),
+            Some("file") | None => {}
+            Some(kind) => {
+                return Ok(ContentKind::Unknown {
+                    kind: kind.into(),
+                    raw,
+                })
+            }
+        }
+        ContentKind::deserialize(raw).map_err(de::Error::custom)
+    }
+}
+
+fn sorted_ids(children: &HashMap<ContentId, Content>) -> Vec<ContentId> {
+    let mut ids: Vec<_> = children.keys().copied().collect();
+    ids.sort();
+    ids
+}
+
+// Older responses list children ids in childrenIds and the children in contents, newer ones
+// only have a children map. Either way the children end up in children and their ids in
+// childrenIds.
+fn normalize_children(folder: &mut Value) {
+    let Some(folder) = folder.as_object_mut() else {
+        return;
+    };
+    if let Some(Value::Object(contents)) = folder.remove("contents") {
+        match folder.get_mut("children") {
+            Some(Value::Object(children)) => {
+                for (id, child) in contents {
+                    children.entry(id).or_insert(child);
+                }
+            }
+            _ => {
+                folder.insert("children".into(), Value::Object(contents));
+            }
+        }
+    }
+    let Some(Value::Object(children)) = folder.get("children") else {
+        return;
+    };
+    let ids: Vec<_> = children.keys().map(|id| Value::from(id.as_str())).collect();
+    let children_ids = folder
+        .entry("childrenIds")
+        .or_insert_with(|| Value::Array(vec![]));
+    if let Value::Array(children_ids) = children_ids {
+        for id in ids {
+            if !children_ids.contains(&id) {
+                children_ids.push(id);
+            }
+        }
+    }
+}
+
+impl Serialize for ContentKind {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            ContentKind::Unknown { raw, .. } => raw.serialize(s),
+            // the newer shape, childrenIds only stays when it says more than the children map
+            ContentKind::Folder {
+                children_ids,
+                children: Some(children),
+                ..
+            } if sorted_ids(children) == *children_ids => {
+                let mut folder = ContentKind::serialize(self, serde_json::value::Serializer)
+                    .map_err(ser::Error::custom)?;
+                if let Some(folder) = folder.as_object_mut() {
+                    folder.remove("childrenIds");
+                }
+                folder.serialize(s)
+            }
+            _ => ContentKind::serialize(self, s),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirectLink {
+    pub id: Uuid,
+
+    #[serde(rename = "directLink")]
+    pub link: Url,
+
+    #[serde(
+        default,
+        with = "ts_seconds_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub expire_time: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountId {
+    pub id: Uuid,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AccountDetails {
     pub id: Uuid,
-    pub token: String,
+    pub token: Token,
     pub email: String,
-    pub tier: String,
-    pub root_folder: Uuid,
+    pub tier: Tier,
+    pub root_folder: FolderId,
     pub files_count: u32,
     pub total_size: u64,
+
+    // storage limit of the subscription, absent for unlimited accounts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subscription_limit_storage: Option<u64>,
+
+    // the fields below are only in newer responses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats_current: Option<AccountStats>,
+
+    // limits of the tier, null when unlimited
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub files_count_limit: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_size_limit: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub traffic_direct_generated_limit: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credit: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency_sign: Option<String>,
+
+    // like "patreon", absent without a subscription
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subscription_provider: Option<String>,
+    #[serde(
+        default,
+        with = "tolerant_ts_seconds",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub subscription_end_date: Option<DateTime<Utc>>,
+
+    #[serde(flatten, skip_serializing_if = "Extra::is_empty")]
+    pub extra: Extra,
+}
+
+impl AccountDetails {
+    pub fn is_premium(&self) -> bool {
+        self.tier.is_premium()
+    }
+
+    // Bytes left before the storage limit, None for unlimited accounts
+    pub fn remaining_storage(&self) -> Option<u64> {
+        let limit = self.total_size_limit.or(self.subscription_limit_storage)?;
+        let used = self
+            .stats_current
+            .as_ref()
+            .and_then(|stats| stats.storage)
+            .unwrap_or(self.total_size);
+        Some(limit.saturating_sub(used))
+    }
+}
+
+// Read case-insensitively, a tier this crate doesn't know is kept as it came
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Tier {
+    Guest,
+    Standard,
+    Premium,
+    Donor,
+    Other(String),
+}
+
+impl Tier {
+    // Only the known paid tiers, premium only calls would fail for the others
+    pub fn is_premium(&self) -> bool {
+        matches!(self, Tier::Premium | Tier::Donor)
+    }
+
+    // Only the known free tiers, an unknown one may well be a new paid tier
+    pub fn is_free(&self) -> bool {
+        matches!(self, Tier::Guest | Tier::Standard)
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Tier::Guest => "guest",
+            Tier::Standard => "standard",
+            Tier::Premium => "premium",
+            Tier::Donor => "donor",
+            Tier::Other(tier) => tier,
+        }
+    }
+}
+
+impl From<&str> for Tier {
+    fn from(tier: &str) -> Self {
+        match tier.to_ascii_lowercase().as_str() {
+            "guest" => Tier::Guest,
+            "standard" => Tier::Standard,
+            "premium" => Tier::Premium,
+            "donor" => Tier::Donor,
+            _ => Tier::Other(tier.into()),
+        }
+    }
+}
+
+impl std::fmt::Display for Tier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for Tier {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        s.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Tier {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let tier = String::deserialize(d)?;
+        Ok(Tier::from(tier.as_str()))
+    }
 }
 
-#[derive(Debug, PartialEq, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountStats {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_count: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub folder_count: Option<u64>,
+
+    // bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub traffic_direct_generated: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub traffic_req_downloaded: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub traffic_web_downloaded: Option<u64>,
+
+    #[serde(flatten, skip_serializing_if = "Extra::is_empty")]
+    pub extra: Extra,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CopiedContents {
+    // source content id -> id of the created copy
+    pub contents: HashMap<ContentId, ContentId>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct MovedContents {
+    // moved content id -> id of the content in the destination folder
+    pub contents: HashMap<ContentId, ContentId>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteStatus {
+    pub status: String,
+}
+
+impl DeleteStatus {
+    pub fn is_ok(&self) -> bool {
+        self.status == "ok"
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct NoInfo {}
 
+// Unix seconds as an integer or a float, the fraction is dropped. Null is None, strings are
+// still an error.
+mod tolerant_ts_seconds {
+    use chrono::{DateTime, TimeZone, Utc};
+    use serde::{de, Deserializer, Serializer};
+    use std::fmt;
+
+    pub fn serialize<S>(time: &Option<DateTime<Utc>>, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match time {
+            Some(time) => s.serialize_i64(time.timestamp()),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(d: D) -> Result<Option<DateTime<Utc>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        d.deserialize_option(TimestampVisitor)
+    }
+
+    struct TimestampVisitor;
+
+    impl<'de> de::Visitor<'de> for TimestampVisitor {
+        type Value = Option<DateTime<Utc>>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a unix timestamp in seconds")
+        }
+
+        fn visit_none<E>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_unit<E>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_some<D>(self, d: D) -> Result<Self::Value, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            d.deserialize_any(self)
+        }
+
+        fn visit_i64<E>(self, secs: i64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            match Utc.timestamp_opt(secs, 0).single() {
+                Some(time) => Ok(Some(time)),
+                None => Err(E::custom(format!("timestamp out of range: {}", secs))),
+            }
+        }
+
+        fn visit_u64<E>(self, secs: u64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            let secs = i64::try_from(secs)
+                .map_err(|_| E::custom(format!("timestamp out of range: {}", secs)))?;
+            self.visit_i64(secs)
+        }
+
+        fn visit_f64<E>(self, secs: f64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            if !secs.is_finite() || secs.abs() > i64::MAX as f64 {
+                return Err(E::custom(format!("timestamp out of range: {}", secs)));
+            }
+            self.visit_i64(secs.trunc() as i64)
+        }
+    }
+}
+
 fn mime_from_str<'de, D>(d: D) -> Result<Mime, D::Error>
 where
     D: Deserializer<'de>,
@@ -168,6 +990,22 @@ where
     }
 }
 
+fn vec_from_comma_separated_string<'de, D>(d: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let Some(comma_separated_str) = Option::<String>::deserialize(d)? else {
+        return Ok(None);
+    };
+    Ok(Some(
+        comma_separated_str
+            .split(',')
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+            .collect(),
+    ))
+}
+
 fn comma_separated_string_from_vec<T, S>(vec: &[T], s: S) -> Result<S::Ok, S::Error>
 where
     T: ToString,
@@ -181,6 +1019,37 @@ where
     s.serialize_str(&comma_separated_str)
 }
 
+fn comma_separated_string_from_option_vec<S>(
+    vec: &Option<Vec<String>>,
+    s: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match vec {
+        Some(vec) => comma_separated_string_from_vec(vec, s),
+        None => s.serialize_none(),
+    }
+}
+
+fn empty_string<S>(s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    s.serialize_str("")
+}
+
+fn colon_separated_pairs<S>(pairs: &[(String, String)], s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    s.collect_seq(
+        pairs
+            .iter()
+            .map(|(user, password)| format!("{}:{}", user, password)),
+    )
+}
+
 fn to_string<T, S>(v: T, s: S) -> Result<S::Ok, S::Error>
 where
     T: ToString,
@@ -207,58 +1076,111 @@ mod tests {
                 "folderName": "bar",
             }),
             CreateFolderApiPayload {
-                token: String::from("foo"),
-                parent_folder_id: uuid!("00000000-0000-0000-0000-000000000001"),
+                token: Token::from("foo"),
+                parent_folder_id: uuid!("00000000-0000-0000-0000-000000000001").into(),
                 folder_name: String::from("bar"),
             },
         );
         assert_serialize(
-            json!({ "token": "foo", "option": "public", "value": "true" }),
+            json!({ "attribute": "public", "attributeValue": "true" }),
             UpdateContentApiPayload {
-                token: String::from("foo"),
                 opt: ContentOpt::Public(true),
             },
         );
         assert_serialize(
-            json!({ "token": "foo", "option": "public", "value": "false" }),
+            json!({ "attribute": "public", "attributeValue": "false" }),
             UpdateContentApiPayload {
-                token: String::from("foo"),
                 opt: ContentOpt::Public(false),
             },
         );
         assert_serialize(
-            json!({ "token": "foo", "option": "password", "value": "bar" }),
+            json!({ "attribute": "name", "attributeValue": "new name" }),
+            UpdateContentApiPayload {
+                opt: ContentOpt::Name(String::from("new name")),
+            },
+        );
+        assert_serialize(
+            json!({ "attribute": "password", "attributeValue": "bar" }),
             UpdateContentApiPayload {
-                token: String::from("foo"),
                 opt: ContentOpt::Password(String::from("bar")),
             },
         );
         assert_serialize(
-            json!({ "token": "foo", "option": "description", "value": "bar" }),
+            json!({ "attribute": "description", "attributeValue": "bar" }),
             UpdateContentApiPayload {
-                token: String::from("foo"),
                 opt: ContentOpt::Description(String::from("bar")),
             },
         );
+        assert_serialize(
+            json!({ "attribute": "expiry", "attributeValue": 1000000000 }),
+            UpdateContentApiPayload {
+                opt: ContentOpt::Expire(Utc.with_ymd_and_hms(2001, 9, 9, 1, 46, 40).unwrap()),
+            },
+        );
+        assert_serialize(
+            json!({ "attribute": "tags", "attributeValue": "bar,baz" }),
+            UpdateContentApiPayload {
+                opt: ContentOpt::Tags(vec![String::from("bar"), String::from("baz")]),
+            },
+        );
+        assert_serialize(
+            json!({ "attribute": "directLink", "attributeValue": "false" }),
+            UpdateContentApiPayload {
+                opt: ContentOpt::DirectLink(false),
+            },
+        );
+        assert_serialize(
+            json!({ "attribute": "password", "attributeValue": "" }),
+            UpdateContentApiPayload {
+                opt: ContentOpt::ClearPassword,
+            },
+        );
+        assert_serialize(
+            json!({ "attribute": "description", "attributeValue": "" }),
+            UpdateContentApiPayload {
+                opt: ContentOpt::ClearDescription,
+            },
+        );
+        assert_serialize(
+            json!({ "attribute": "expiry", "attributeValue": "" }),
+            UpdateContentApiPayload {
+                opt: ContentOpt::ClearExpire,
+            },
+        );
+        assert_serialize(
+            json!({ "token": "foo", "option": "public", "value": "true" }),
+            LegacyUpdateContentApiPayload {
+                token: Token::from("foo"),
+                opt: ContentOpt::Public(true),
+            },
+        );
         assert_serialize(
             json!({ "token": "foo", "option": "expire", "value": 1000000000 }),
-            UpdateContentApiPayload {
-                token: String::from("foo"),
+            LegacyUpdateContentApiPayload {
+                token: Token::from("foo"),
                 opt: ContentOpt::Expire(Utc.with_ymd_and_hms(2001, 9, 9, 1, 46, 40).unwrap()),
             },
         );
         assert_serialize(
-            json!({ "token": "foo", "option": "tags", "value": "bar,baz" }),
-            UpdateContentApiPayload {
-                token: String::from("foo"),
-                opt: ContentOpt::Tags(vec![String::from("bar"), String::from("baz")]),
+            json!({ "token": "foo", "option": "expire", "value": "" }),
+            LegacyUpdateContentApiPayload {
+                token: Token::from("foo"),
+                opt: ContentOpt::ClearExpire,
             },
         );
         assert_serialize(
-            json!({ "token": "foo", "option": "directLink", "value": "false" }),
-            UpdateContentApiPayload {
-                token: String::from("foo"),
-                opt: ContentOpt::DirectLink(false),
+            json!({
+                "token": "foo",
+                "contentsId": "00000000-0000-0000-0000-000000000001,00000000-0000-0000-0000-000000000002",
+                "folderIdDest": "00000000-0000-0000-0000-000000000003",
+            }),
+            CopyContentApiPayload {
+                token: Token::from("foo"),
+                contents_id: vec![
+                    uuid!("00000000-0000-0000-0000-000000000001").into(),
+                    uuid!("00000000-0000-0000-0000-000000000002").into(),
+                ],
+                folder_id_dest: uuid!("00000000-0000-0000-0000-000000000003").into(),
             },
         );
         assert_serialize(
@@ -267,13 +1189,13 @@ mod tests {
                 "contentsId": "00000000-0000-0000-0000-000000000001,00000000-0000-0000-0000-000000000002",
                 "folderIdDest": "00000000-0000-0000-0000-000000000003",
             }),
-            CopyContentApiPayload {
-                token: String::from("foo"),
+            MoveContentApiPayload {
+                token: Token::from("foo"),
                 contents_id: vec![
-                    uuid!("00000000-0000-0000-0000-000000000001"),
-                    uuid!("00000000-0000-0000-0000-000000000002"),
+                    uuid!("00000000-0000-0000-0000-000000000001").into(),
+                    uuid!("00000000-0000-0000-0000-000000000002").into(),
                 ],
-                folder_id_dest: uuid!("00000000-0000-0000-0000-000000000003"),
+                folder_id_dest: uuid!("00000000-0000-0000-0000-000000000003").into(),
             },
         );
         assert_serialize(
@@ -282,20 +1204,70 @@ mod tests {
                 "contentsId": "00000000-0000-0000-0000-000000000001,00000000-0000-0000-0000-000000000002",
             }),
             DeleteContentApiPayload {
-                token: String::from("foo"),
+                token: Token::from("foo"),
                 contents_id: vec![
-                    uuid!("00000000-0000-0000-0000-000000000001"),
-                    uuid!("00000000-0000-0000-0000-000000000002"),
+                    uuid!("00000000-0000-0000-0000-000000000001").into(),
+                    uuid!("00000000-0000-0000-0000-000000000002").into(),
                 ],
             },
         );
     }
 
+    #[test]
+    fn get_content_options() {
+        assert!(GetContentOptions::default().params().is_empty());
+        assert_eq!(
+            GetContentOptions::default()
+                .sort_by_name()
+                .filter("*.mkv")
+                .params(),
+            vec![
+                ("sortField", String::from("name")),
+                ("sortDirection", String::from("1")),
+                ("contentFilter", String::from("*.mkv")),
+            ]
+        );
+        assert_eq!(
+            GetContentOptions::default().sort_by_size_desc().params(),
+            vec![
+                ("sortField", String::from("size")),
+                ("sortDirection", String::from("-1")),
+            ]
+        );
+        assert_eq!(
+            GetContentOptions::default().sort_by_newest().params(),
+            vec![
+                ("sortField", String::from("createTime")),
+                ("sortDirection", String::from("-1")),
+            ]
+        );
+    }
+
+    #[test]
+    fn direct_link_options() {
+        assert_serialize(json!({}), DirectLinkOptions::default());
+        assert_serialize(
+            json!({
+                "expireTime": 1000000000,
+                "sourceIpsAllowed": ["192.0.2.1", "2001:db8::1"],
+                "domainsAllowed": ["example.com"],
+                "auth": ["user:pass", "user2:pass2"],
+            }),
+            DirectLinkOptions::default()
+                .expire_time(Utc.with_ymd_and_hms(2001, 9, 9, 1, 46, 40).unwrap())
+                .source_ip_allowed(IpAddr::from([192, 0, 2, 1]))
+                .source_ip_allowed("2001:db8::1".parse().unwrap())
+                .domain_allowed("example.com")
+                .auth("user", "pass")
+                .auth("user2", "pass2"),
+        );
+    }
+
     fn assert_serialize<T>(expected_value: Value, payload: T)
     where
         T: Serialize + Debug,
     {
-        assert!(0 < format!("{:?}", payload).len());
+        assert!(!format!("{:?}", payload).is_empty());
         assert_eq!(expected_value, to_value(&payload).unwrap());
     }
 
@@ -315,8 +1287,10 @@ mod tests {
                 data: Servers {
                     servers: vec![Server {
                         name: String::from("foo"),
-                        zone: String::from("ja"),
+                        zone: Zone::Other(String::from("ja")),
+                        extra: Extra::default(),
                     }],
+                    servers_all_zone: None,
                 },
             },
         );
@@ -332,11 +1306,14 @@ mod tests {
             UploadedFile {
                 guest_token: None,
                 download_page: Url::parse("http://example.com/path/file.txt").unwrap(),
-                code: String::from("bar"),
-                parent_folder: uuid!("00000000-0000-0000-0000-000000000001"),
-                file_id: uuid!("00000000-0000-0000-0000-000000000002"),
+                code: Code::new("bar").unwrap(),
+                parent_folder: uuid!("00000000-0000-0000-0000-000000000001").into(),
+                file_id: uuid!("00000000-0000-0000-0000-000000000002").into(),
                 file_name: String::from("baz"),
-                md5: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x1, 0xff],
+                md5: Md5([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x1, 0xff]),
+                local_md5: None,
+                server: None,
+                extra: Extra::default(),
             },
         );
         assert_deserialize(
@@ -350,13 +1327,127 @@ mod tests {
                 "md5": "000000000000000000000000000001ff",
             }),
             UploadedFile {
-                guest_token: Some(String::from("foo")),
+                guest_token: Some(Token::from("foo")),
                 download_page: Url::parse("http://example.com/path/file.txt").unwrap(),
-                code: String::from("bar"),
-                parent_folder: uuid!("00000000-0000-0000-0000-000000000001"),
-                file_id: uuid!("00000000-0000-0000-0000-000000000002"),
+                code: Code::new("bar").unwrap(),
+                parent_folder: uuid!("00000000-0000-0000-0000-000000000001").into(),
+                file_id: uuid!("00000000-0000-0000-0000-000000000002").into(),
                 file_name: String::from("baz"),
-                md5: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x1, 0xff],
+                md5: Md5([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x1, 0xff]),
+                local_md5: None,
+                server: None,
+                extra: Extra::default(),
+            },
+        );
+        assert_deserialize(
+            json!({ "status": "ok", "data": { "id": "00000000-0000-0000-0000-000000000001" } }),
+            ApiResult {
+                status: String::from("ok"),
+                data: AccountId {
+                    id: uuid!("00000000-0000-0000-0000-000000000001"),
+                },
+            },
+        );
+        assert!(from_value::<AccountId>(json!({ "id": "foo" })).is_err());
+        assert_deserialize(
+            json!({
+                "id": "00000000-0000-0000-0000-000000000001",
+                "name": "foo",
+                "createTime": 1000000001,
+                "type": "folder",
+                "code": "bar",
+                "childrenIds": ["00000000-0000-0000-0000-000000000002"],
+                "children": {},
+                "metadata": { "totalCount": 5, "page": 2, "pageSize": 1 },
+            }),
+            ContentPage {
+                content: Content {
+                    id: uuid!("00000000-0000-0000-0000-000000000001").into(),
+                    name: String::from("foo"),
+                    parent_folder: None,
+                    create_time: Some(Utc.with_ymd_and_hms(2001, 9, 9, 1, 46, 41).unwrap()),
+                    mod_time: None,
+                    kind: ContentKind::Folder {
+                        code: Code::new("bar").unwrap(),
+                        public: false,
+                        tags: None,
+                        children_ids: vec![uuid!("00000000-0000-0000-0000-000000000002").into()],
+                        total_download_count: None,
+                        total_size: None,
+                        children: Some(HashMap::new()),
+                        extra: Extra::default(),
+                    },
+                    extra: Extra::default(),
+                },
+                metadata: PageMetadata {
+                    total_children: 5,
+                    page: 2,
+                    page_size: 1,
+                },
+            },
+        );
+        assert_deserialize(
+            json!({
+                "00000000-0000-0000-0000-000000000001": "00000000-0000-0000-0000-000000000003",
+                "00000000-0000-0000-0000-000000000002": "00000000-0000-0000-0000-000000000004",
+            }),
+            CopiedContents {
+                contents: HashMap::from_iter([
+                    (
+                        ContentId(uuid!("00000000-0000-0000-0000-000000000001")),
+                        ContentId(uuid!("00000000-0000-0000-0000-000000000003")),
+                    ),
+                    (
+                        ContentId(uuid!("00000000-0000-0000-0000-000000000002")),
+                        ContentId(uuid!("00000000-0000-0000-0000-000000000004")),
+                    ),
+                ]),
+            },
+        );
+        assert_deserialize(
+            json!({
+                "00000000-0000-0000-0000-000000000001": "00000000-0000-0000-0000-000000000001",
+            }),
+            MovedContents {
+                contents: HashMap::from_iter([(
+                    ContentId(uuid!("00000000-0000-0000-0000-000000000001")),
+                    ContentId(uuid!("00000000-0000-0000-0000-000000000001")),
+                )]),
+            },
+        );
+        assert_deserialize_lossy(
+            json!({
+                "00000000-0000-0000-0000-000000000001": { "status": "ok", "data": {} },
+                "00000000-0000-0000-0000-000000000002": { "status": "error-notFound", "data": {} },
+            }),
+            HashMap::<Uuid, DeleteStatus>::from_iter([
+                (
+                    uuid!("00000000-0000-0000-0000-000000000001"),
+                    DeleteStatus {
+                        status: String::from("ok"),
+                    },
+                ),
+                (
+                    uuid!("00000000-0000-0000-0000-000000000002"),
+                    DeleteStatus {
+                        status: String::from("error-notFound"),
+                    },
+                ),
+            ]),
+        );
+        assert_deserialize(
+            json!({
+                "id": "00000000-0000-0000-0000-000000000001",
+                "directLink": "https://store1.gofile.io/download/direct/00000000-0000-0000-0000-000000000001/foo.txt",
+                "expireTime": 1000000000,
+            }),
+            DirectLink {
+                id: uuid!("00000000-0000-0000-0000-000000000001"),
+                link: Url::parse(
+                    "https://store1.gofile.io/download/direct/00000000-0000-0000-0000-000000000001/foo.txt",
+                )
+                .unwrap(),
+                expire_time: Some(Utc.with_ymd_and_hms(2001, 9, 9, 1, 46, 40).unwrap()),
             },
         );
         assert_deserialize(
@@ -371,12 +1462,109 @@ mod tests {
             }),
             AccountDetails {
                 id: uuid!("00000000-0000-0000-0000-000000000001"),
-                token: String::from("foo"),
+                token: Token::from("foo"),
+                email: String::from("bar"),
+                tier: Tier::Other(String::from("baz")),
+                root_folder: uuid!("00000000-0000-0000-0000-000000000002").into(),
+                files_count: 1,
+                total_size: 2,
+                subscription_limit_storage: None,
+                stats_current: None,
+                files_count_limit: None,
+                total_size_limit: None,
+                traffic_direct_generated_limit: None,
+                credit: None,
+                currency: None,
+                currency_sign: None,
+                subscription_provider: None,
+                subscription_end_date: None,
+                extra: Extra::default(),
+            },
+        );
+        assert_deserialize(
+            json!({
+                "id": "00000000-0000-0000-0000-000000000001",
+                "token": "foo",
+                "email": "bar",
+                "tier": "standard",
+                "rootFolder": "00000000-0000-0000-0000-000000000002",
+                "filesCount": 1,
+                "totalSize": 2,
+                "subscriptionLimitStorage": 100,
+            }),
+            AccountDetails {
+                id: uuid!("00000000-0000-0000-0000-000000000001"),
+                token: Token::from("foo"),
                 email: String::from("bar"),
-                tier: String::from("baz"),
-                root_folder: uuid!("00000000-0000-0000-0000-000000000002"),
+                tier: Tier::Standard,
+                root_folder: uuid!("00000000-0000-0000-0000-000000000002").into(),
                 files_count: 1,
                 total_size: 2,
+                subscription_limit_storage: Some(100),
+                stats_current: None,
+                files_count_limit: None,
+                total_size_limit: None,
+                traffic_direct_generated_limit: None,
+                credit: None,
+                currency: None,
+                currency_sign: None,
+                subscription_provider: None,
+                subscription_end_date: None,
+                extra: Extra::default(),
+            },
+        );
+        assert_deserialize(
+            json!({
+                "id": "00000000-0000-0000-0000-000000000001",
+                "token": "foo",
+                "email": "bar@example.com",
+                "tier": "premium",
+                "rootFolder": "00000000-0000-0000-0000-000000000002",
+                "filesCount": 12,
+                "totalSize": 3000,
+                "statsCurrent": {
+                    "fileCount": 12,
+                    "folderCount": 3,
+                    "storage": 4000,
+                    "trafficDirectGenerated": 500,
+                    "trafficReqDownloaded": 20,
+                    "trafficWebDownloaded": 10,
+                },
+                "totalSizeLimit": 10000,
+                "trafficDirectGeneratedLimit": 100000,
+                "credit": 2.5,
+                "currency": "EUR",
+                "currencySign": "€",
+                "subscriptionProvider": "patreon",
+                "subscriptionEndDate": 1000000000,
+            }),
+            AccountDetails {
+                id: uuid!("00000000-0000-0000-0000-000000000001"),
+                token: Token::from("foo"),
+                email: String::from("bar@example.com"),
+                tier: Tier::Premium,
+                root_folder: uuid!("00000000-0000-0000-0000-000000000002").into(),
+                files_count: 12,
+                total_size: 3000,
+                subscription_limit_storage: None,
+                stats_current: Some(AccountStats {
+                    file_count: Some(12),
+                    folder_count: Some(3),
+                    storage: Some(4000),
+                    traffic_direct_generated: Some(500),
+                    traffic_req_downloaded: Some(20),
+                    traffic_web_downloaded: Some(10),
+                    extra: Extra::default(),
+                }),
+                files_count_limit: None,
+                total_size_limit: Some(10000),
+                traffic_direct_generated_limit: Some(100000),
+                credit: Some(2.5),
+                currency: Some(String::from("EUR")),
+                currency_sign: Some(String::from("€")),
+                subscription_provider: Some(String::from("patreon")),
+                subscription_end_date: Some(Utc.with_ymd_and_hms(2001, 9, 9, 1, 46, 40).unwrap()),
+                extra: Extra::default(),
             },
         );
         assert_deserialize(
@@ -387,10 +1575,6 @@ mod tests {
                 "createTime": 1000000001,
                 "type": "folder",
                 "code": "bar",
-                "childrenIds": [
-                    "00000000-0000-0000-0000-000000000003",
-                    "00000000-0000-0000-0000-000000000004",
-                ],
                 "totalDownloadCount": 10,
                 "totalSize": 20,
                 "children": {
@@ -402,6 +1586,7 @@ mod tests {
                         "type": "folder",
                         "code": "fiz",
                         "public": true,
+                        "tags": "tag1,tag2",
                         "childrenIds": [],
                     },
                     "00000000-0000-0000-0000-000000000004": {
@@ -420,56 +1605,226 @@ mod tests {
                 },
             }),
             Content {
-                id: uuid!("00000000-0000-0000-0000-000000000001"),
+                id: uuid!("00000000-0000-0000-0000-000000000001").into(),
                 name: String::from("foo"),
-                parent_folder: Some(uuid!("00000000-0000-0000-0000-000000000002")),
-                create_time: Utc.with_ymd_and_hms(2001, 9, 9, 1, 46, 41).unwrap(),
+                parent_folder: Some(uuid!("00000000-0000-0000-0000-000000000002").into()),
+                create_time: Some(Utc.with_ymd_and_hms(2001, 9, 9, 1, 46, 41).unwrap()),
+                mod_time: None,
                 kind: ContentKind::Folder {
-                    code: String::from("bar"),
+                    code: Code::new("bar").unwrap(),
                     public: false,
+                    tags: None,
                     children_ids: vec![
-                        uuid!("00000000-0000-0000-0000-000000000003"),
-                        uuid!("00000000-0000-0000-0000-000000000004"),
+                        uuid!("00000000-0000-0000-0000-000000000003").into(),
+                        uuid!("00000000-0000-0000-0000-000000000004").into(),
                     ],
                     total_download_count: Some(10),
                     total_size: Some(20),
                     children: Some(HashMap::from_iter([
                         (
-                            uuid!("00000000-0000-0000-0000-000000000003"),
+                            ContentId(uuid!("00000000-0000-0000-0000-000000000003")),
                             Content {
-                                id: uuid!("00000000-0000-0000-0000-000000000003"),
+                                id: uuid!("00000000-0000-0000-0000-000000000003").into(),
                                 name: String::from("baz"),
-                                parent_folder: Some(uuid!("00000000-0000-0000-0000-000000000001")),
-                                create_time: Utc.with_ymd_and_hms(2001, 9, 9, 1, 46, 42).unwrap(),
+                                parent_folder: Some(
+                                    uuid!("00000000-0000-0000-0000-000000000001").into(),
+                                ),
+                                create_time: Some(
+                                    Utc.with_ymd_and_hms(2001, 9, 9, 1, 46, 42).unwrap(),
+                                ),
+                                mod_time: None,
                                 kind: ContentKind::Folder {
-                                    code: String::from("fiz"),
+                                    code: Code::new("fiz").unwrap(),
                                     public: true,
+                                    tags: Some(vec![String::from("tag1"), String::from("tag2")]),
                                     children_ids: vec![],
                                     total_download_count: None,
                                     total_size: None,
                                     children: None,
+                                    extra: Extra::default(),
                                 },
+                                extra: Extra::default(),
                             },
                         ),
                         (
-                            uuid!("00000000-0000-0000-0000-000000000004"),
+                            ContentId(uuid!("00000000-0000-0000-0000-000000000004")),
                             Content {
-                                id: uuid!("00000000-0000-0000-0000-000000000004"),
+                                id: uuid!("00000000-0000-0000-0000-000000000004").into(),
                                 name: String::from("foz"),
-                                parent_folder: Some(uuid!("00000000-0000-0000-0000-000000000001")),
-                                create_time: Utc.with_ymd_and_hms(2001, 9, 9, 1, 46, 43).unwrap(),
+                                parent_folder: Some(
+                                    uuid!("00000000-0000-0000-0000-000000000001").into(),
+                                ),
+                                create_time: Some(
+                                    Utc.with_ymd_and_hms(2001, 9, 9, 1, 46, 43).unwrap(),
+                                ),
+                                mod_time: None,
                                 kind: ContentKind::File {
                                     size: 20,
                                     download_count: 10,
-                                    md5: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x1, 0xff],
+                                    md5: Some(Md5([
+                                        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x1, 0xff,
+                                    ])),
                                     mimetype: Mime::from_str("text/plain").unwrap(),
-                                    server_selected: String::from("fez"),
-                                    link: Url::parse("http://example.com/path/file.txt").unwrap(),
+                                    server_selected: Some(String::from("fez")),
+                                    servers: None,
+                                    link: Some(
+                                        Url::parse("http://example.com/path/file.txt").unwrap(),
+                                    ),
+                                    tags: None,
+                                    direct_links: None,
+                                    extra: Extra::default(),
+                                },
+                                extra: Extra::default(),
+                            },
+                        ),
+                    ])),
+                    extra: Extra::default(),
+                },
+                extra: Extra::default(),
+            },
+        );
+
+        assert_deserialize(
+            json!({
+                "id": "00000000-0000-0000-0000-000000000004",
+                "name": "foz",
+                "createTime": 1000000003,
+                "type": "file",
+                "size": 20,
+                "downloadCount": 10,
+                "md5": "000000000000000000000000000001ff",
+                "mimetype": "video/x-matroska",
+                "serverSelected": "fez",
+                "link": "http://example.com/path/file.mkv",
+                "tags": "tag1",
+                "directLinks": {
+                    "00000000-0000-0000-0000-000000000005": {
+                        "id": "00000000-0000-0000-0000-000000000005",
+                        "directLink": "https://store1.gofile.io/download/direct/00000000-0000-0000-0000-000000000005/foz",
+                    },
+                },
+            }),
+            Content {
+                id: uuid!("00000000-0000-0000-0000-000000000004").into(),
+                name: String::from("foz"),
+                parent_folder: None,
+                create_time: Some(Utc.with_ymd_and_hms(2001, 9, 9, 1, 46, 43).unwrap()),
+                mod_time: None,
+                kind: ContentKind::File {
+                    size: 20,
+                    download_count: 10,
+                    md5: Some(Md5([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x1, 0xff])),
+                    mimetype: Mime::from_str("video/x-matroska").unwrap(),
+                    server_selected: Some(String::from("fez")),
+                    servers: None,
+                    link: Some(Url::parse("http://example.com/path/file.mkv").unwrap()),
+                    tags: Some(vec![String::from("tag1")]),
+                    direct_links: Some(HashMap::from_iter([(
+                        uuid!("00000000-0000-0000-0000-000000000005"),
+                        DirectLink {
+                            id: uuid!("00000000-0000-0000-0000-000000000005"),
+                            link: Url::parse(
+                                "https://store1.gofile.io/download/direct/00000000-0000-0000-0000-000000000005/foz",
+                            )
+                            .unwrap(),
+                            expire_time: None,
+                        },
+                    )])),
+                    extra: Extra::default(),
+                },
+                extra: Extra::default(),
+            },
+        );
+
+        assert_deserialize(
+            json!({
+                "id": "00000000-0000-0000-0000-000000000001",
+                "name": "foo",
+                "createTime": 1000000001,
+                "type": "folder",
+                "code": "bar",
+                "children": {
+                    "00000000-0000-0000-0000-000000000003": {
+                        "id": "00000000-0000-0000-0000-000000000003",
+                        "name": "baz",
+                        "createTime": 1000000002,
+                        "type": "folder",
+                        "code": "fiz",
+                        "childrenIds": [],
+                    },
+                    "00000000-0000-0000-0000-000000000004": {
+                        "id": "00000000-0000-0000-0000-000000000004",
+                        "name": "foz",
+                        "createTime": 1000000003,
+                        "type": "shortcut",
+                        "target": "00000000-0000-0000-0000-000000000003",
+                    },
+                },
+            }),
+            Content {
+                id: uuid!("00000000-0000-0000-0000-000000000001").into(),
+                name: String::from("foo"),
+                parent_folder: None,
+                create_time: Some(Utc.with_ymd_and_hms(2001, 9, 9, 1, 46, 41).unwrap()),
+                mod_time: None,
+                kind: ContentKind::Folder {
+                    code: Code::new("bar").unwrap(),
+                    public: false,
+                    tags: None,
+                    children_ids: vec![
+                        uuid!("00000000-0000-0000-0000-000000000003").into(),
+                        uuid!("00000000-0000-0000-0000-000000000004").into(),
+                    ],
+                    total_download_count: None,
+                    total_size: None,
+                    children: Some(HashMap::from_iter([
+                        (
+                            ContentId(uuid!("00000000-0000-0000-0000-000000000003")),
+                            Content {
+                                id: uuid!("00000000-0000-0000-0000-000000000003").into(),
+                                name: String::from("baz"),
+                                parent_folder: None,
+                                create_time: Some(
+                                    Utc.with_ymd_and_hms(2001, 9, 9, 1, 46, 42).unwrap(),
+                                ),
+                                mod_time: None,
+                                kind: ContentKind::Folder {
+                                    code: Code::new("fiz").unwrap(),
+                                    public: false,
+                                    tags: None,
+                                    children_ids: vec![],
+                                    total_download_count: None,
+                                    total_size: None,
+                                    children: None,
+                                    extra: Extra::default(),
+                                },
+                                extra: Extra::default(),
+                            },
+                        ),
+                        (
+                            ContentId(uuid!("00000000-0000-0000-0000-000000000004")),
+                            Content {
+                                id: uuid!("00000000-0000-0000-0000-000000000004").into(),
+                                name: String::from("foz"),
+                                parent_folder: None,
+                                create_time: Some(
+                                    Utc.with_ymd_and_hms(2001, 9, 9, 1, 46, 43).unwrap(),
+                                ),
+                                mod_time: None,
+                                kind: ContentKind::Unknown {
+                                    kind: String::from("shortcut"),
+                                    raw: json!({
+                                        "type": "shortcut",
+                                        "target": "00000000-0000-0000-0000-000000000003",
+                                    }),
                                 },
+                                extra: Extra::default(),
                             },
                         ),
                     ])),
+                    extra: Extra::default(),
                 },
+                extra: Extra::default(),
             },
         );
 
@@ -489,11 +1844,439 @@ mod tests {
         .is_err());
     }
 
+    #[test]
+    fn timestamps() {
+        let folder = |times: Value| {
+            let mut folder = json!({
+                "id": "00000000-0000-0000-0000-000000000001",
+                "name": "foo",
+                "type": "folder",
+                "code": "bar",
+            });
+            folder
+                .as_object_mut()
+                .unwrap()
+                .extend(times.as_object().unwrap().clone());
+            from_value::<Content>(folder)
+        };
+        let time = Utc.with_ymd_and_hms(2023, 11, 14, 22, 13, 20).unwrap();
+
+        let content = folder(json!({ "createTime": 1700000000, "modTime": 1700000001 })).unwrap();
+        assert_eq!(content.create_time, Some(time));
+        assert_eq!(content.mod_time, Some(time + chrono::Duration::seconds(1)));
+        let content = folder(json!({ "createTime": 1700000000.923 })).unwrap();
+        assert_eq!(content.create_time, Some(time));
+        assert_eq!(content.mod_time, None);
+        let content = folder(json!({ "createTime": null })).unwrap();
+        assert_eq!(content.create_time, None);
+        let content = folder(json!({})).unwrap();
+        assert_eq!((content.create_time, content.mod_time), (None, None));
+
+        assert!(folder(json!({ "createTime": "1700000000" })).is_err());
+        assert!(folder(json!({ "modTime": "yesterday" })).is_err());
+        assert!(folder(json!({ "createTime": 1e300 })).is_err());
+    }
+
+    #[test]
+    fn children_shapes() {
+        let folder = |fields: Value| {
+            let mut folder = json!({
+                "id": "00000000-0000-0000-0000-000000000001",
+                "name": "foo",
+                "type": "folder",
+                "code": "bar",
+            });
+            folder
+                .as_object_mut()
+                .unwrap()
+                .extend(fields.as_object().unwrap().clone());
+            folder
+        };
+        let child = |id: &str, name: &str| {
+            json!({
+                "id": id,
+                "name": name,
+                "parentFolder": "00000000-0000-0000-0000-000000000001",
+                "type": "folder",
+                "code": name,
+                "childrenIds": [],
+            })
+        };
+        let baz = child("00000000-0000-0000-0000-000000000003", "baz");
+        let foz = child("00000000-0000-0000-0000-000000000004", "foz");
+        let new_style = folder(json!({
+            "children": {
+                "00000000-0000-0000-0000-000000000003": baz,
+                "00000000-0000-0000-0000-000000000004": foz,
+            },
+        }));
+        let old_style = folder(json!({
+            "childrenIds": [
+                "00000000-0000-0000-0000-000000000003",
+                "00000000-0000-0000-0000-000000000004",
+            ],
+            "contents": {
+                "00000000-0000-0000-0000-000000000003": baz,
+                "00000000-0000-0000-0000-000000000004": foz,
+            },
+        }));
+        let mixed = folder(json!({
+            "childrenIds": ["00000000-0000-0000-0000-000000000003"],
+            "children": { "00000000-0000-0000-0000-000000000003": baz },
+            "contents": {
+                "00000000-0000-0000-0000-000000000003": child("00000000-0000-0000-0000-000000000003", "stale"),
+                "00000000-0000-0000-0000-000000000004": foz,
+            },
+        }));
+
+        let content: Content = from_value(new_style.clone()).unwrap();
+        let ContentKind::Folder {
+            children_ids,
+            children: Some(children),
+            ..
+        } = &content.kind
+        else {
+            panic!("not a folder with children");
+        };
+        assert_eq!(
+            children_ids,
+            &[
+                uuid!("00000000-0000-0000-0000-000000000003"),
+                uuid!("00000000-0000-0000-0000-000000000004"),
+            ]
+        );
+        assert_eq!(children.len(), 2);
+        assert_eq!(from_value::<Content>(old_style).unwrap(), content);
+        // children wins over contents when both have the same child
+        assert_eq!(from_value::<Content>(mixed).unwrap(), content);
+        // always written back in the newer shape
+        assert_eq!(to_value(&content).unwrap(), new_style);
+
+        // ids of children that weren't fetched are kept
+        let partial = folder(json!({
+            "childrenIds": [
+                "00000000-0000-0000-0000-000000000004",
+                "00000000-0000-0000-0000-000000000005",
+            ],
+            "children": { "00000000-0000-0000-0000-000000000004": foz },
+        }));
+        let content: Content = from_value(partial.clone()).unwrap();
+        assert_eq!(to_value(&content).unwrap(), partial);
+    }
+
+    #[test]
+    fn account_details() {
+        let account = |fields: Value| {
+            let mut account = json!({
+                "id": "00000000-0000-0000-0000-000000000001",
+                "token": "foo",
+                "email": "bar",
+                "tier": "standard",
+                "rootFolder": "00000000-0000-0000-0000-000000000002",
+                "filesCount": 1,
+                "totalSize": 30,
+            });
+            account
+                .as_object_mut()
+                .unwrap()
+                .extend(fields.as_object().unwrap().clone());
+            from_value::<AccountDetails>(account).unwrap()
+        };
+
+        let standard = account(json!({}));
+        assert!(!standard.is_premium());
+        assert_eq!(standard.remaining_storage(), None);
+        assert!(!account(json!({ "tier": "guest" })).is_premium());
+        assert!(account(json!({ "tier": "premium" })).is_premium());
+
+        assert_eq!(
+            account(json!({ "subscriptionLimitStorage": 100 })).remaining_storage(),
+            Some(70)
+        );
+        // the newer limit and stats win over the older fields
+        let premium = account(json!({
+            "tier": "premium",
+            "subscriptionLimitStorage": 100,
+            "totalSizeLimit": 200,
+            "statsCurrent": { "storage": 150 },
+        }));
+        assert_eq!(premium.remaining_storage(), Some(50));
+        let full = account(json!({ "totalSizeLimit": 20 }));
+        assert_eq!(full.remaining_storage(), Some(0));
+    }
+
+    #[test]
+    fn tier() {
+        for (json, tier) in [
+            ("guest", Tier::Guest),
+            ("standard", Tier::Standard),
+            ("premium", Tier::Premium),
+            ("donor", Tier::Donor),
+            ("enterprise", Tier::Other(String::from("enterprise"))),
+        ] {
+            assert_deserialize(json!(json), tier);
+        }
+        assert_eq!(from_value::<Tier>(json!("Premium")).unwrap(), Tier::Premium);
+        assert_eq!(from_value::<Tier>(json!("DONOR")).unwrap(), Tier::Donor);
+        // unknown tiers keep their case
+        let other = from_value::<Tier>(json!("Enterprise")).unwrap();
+        assert_eq!(other, Tier::Other(String::from("Enterprise")));
+        assert_eq!(to_value(&other).unwrap(), json!("Enterprise"));
+        assert!(from_value::<Tier>(json!(1)).is_err());
+
+        assert!(Tier::Premium.is_premium());
+        assert!(Tier::Donor.is_premium());
+        assert!(!Tier::Guest.is_premium());
+        assert!(!Tier::Standard.is_premium());
+        assert!(!other.is_premium());
+        assert!(Tier::Guest.is_free());
+        assert!(Tier::Standard.is_free());
+        assert!(!Tier::Premium.is_free());
+        // neither, left to the server
+        assert!(!other.is_free());
+    }
+
+    #[test]
+    fn servers() {
+        let servers: Servers = from_value(json!({
+            "servers": [
+                { "name": "store3", "zone": "na", "load": 0.25 },
+                { "name": "store1", "zone": "eu" },
+            ],
+            "serversAllZone": [
+                { "name": "store1", "zone": "eu" },
+                { "name": "store2", "zone": "eu", "load": 0.5 },
+                { "name": "store3", "zone": "na", "load": 0.25 },
+                { "name": "store4", "zone": "ap" },
+            ],
+        }))
+        .unwrap();
+        fn names(servers: Vec<&Server>) -> Vec<&str> {
+            servers
+                .into_iter()
+                .map(|server| server.name.as_str())
+                .collect()
+        }
+        assert_eq!(
+            names(servers.all()),
+            ["store3", "store1", "store2", "store4"]
+        );
+        assert_eq!(names(servers.in_zone(&Zone::Eu)), ["store1", "store2"]);
+        assert!(servers.in_zone(&Zone::from("sa")).is_empty());
+        assert_eq!(servers.best().unwrap().name, "store3");
+        assert_eq!(servers.servers[0].extra.get("load"), Some(&json!(0.25)));
+
+        let select = |preference| servers.select(&preference).map(|server| &server.name);
+        assert_eq!(ZonePreference::default(), ZonePreference::Any);
+        assert_eq!(select(ZonePreference::Any).unwrap(), "store3");
+        assert_eq!(select(ZonePreference::Zone("eu".into())).unwrap(), "store1");
+        assert_eq!(select(ZonePreference::Zone("ap".into())).unwrap(), "store4");
+        assert_eq!(select(ZonePreference::Zone("sa".into())), None);
+        let preferred = |zones: &[&str]| {
+            ZonePreference::Preferred(zones.iter().map(|&zone| zone.into()).collect())
+        };
+        assert_eq!(select(preferred(&["sa", "ap", "eu"])).unwrap(), "store4");
+        assert_eq!(select(preferred(&["eu", "na"])).unwrap(), "store1");
+        // none of the zones has a server, any one will do
+        assert_eq!(select(preferred(&["sa"])).unwrap(), "store3");
+        assert_eq!(select(preferred(&[])).unwrap(), "store3");
+
+        let servers: Servers = from_value(json!({
+            "servers": [],
+            "serversAllZone": [{ "name": "store4", "zone": "ap" }],
+        }))
+        .unwrap();
+        assert_eq!(servers.best().unwrap().name, "store4");
+
+        let servers: Servers = from_value(json!({ "servers": [] })).unwrap();
+        assert_eq!(servers.servers_all_zone, None);
+        assert!(servers.all().is_empty());
+        assert_eq!(servers.best(), None);
+        assert_eq!(servers.select(&ZonePreference::Any), None);
+    }
+
+    #[test]
+    fn zone() {
+        for (zone, expected) in [
+            ("eu", Zone::Eu),
+            ("na", Zone::Na),
+            ("ap", Zone::Ap),
+            ("EU", Zone::Eu),
+            ("sa", Zone::Other(String::from("sa"))),
+            ("Mars-1", Zone::Other(String::from("Mars-1"))),
+        ] {
+            let parsed: Zone = from_value(json!(zone)).unwrap();
+            assert_eq!(parsed, expected);
+            assert_eq!(Zone::from(zone), expected);
+        }
+        assert_eq!(to_value(Zone::Na).unwrap(), json!("na"));
+        assert_eq!(
+            to_value(Zone::Other(String::from("Mars-1"))).unwrap(),
+            json!("Mars-1")
+        );
+        assert_eq!(Zone::Ap.to_string(), "ap");
+
+        let server: Server = from_value(json!({ "name": "store9", "zone": "sa" })).unwrap();
+        assert_eq!(server.zone, Zone::Other(String::from("sa")));
+        assert_eq!(
+            to_value(&server).unwrap(),
+            json!({ "name": "store9", "zone": "sa" })
+        );
+    }
+
+    #[test]
+    fn extra_fields() {
+        let json = json!({
+            "id": "00000000-0000-0000-0000-000000000001",
+            "name": "foo",
+            "type": "folder",
+            "code": "bar",
+            "owner": { "id": "00000000-0000-0000-0000-000000000002" },
+            "children": {
+                "00000000-0000-0000-0000-000000000003": {
+                    "id": "00000000-0000-0000-0000-000000000003",
+                    "name": "baz",
+                    "type": "file",
+                    "size": 20,
+                    "downloadCount": 10,
+                    "mimetype": "text/plain",
+                    "thumbnail": "https://example.com/thumb.png",
+                    "virusScan": "clean",
+                },
+            },
+        });
+        let content: Content = from_value(json.clone()).unwrap();
+        assert_eq!(content.name, "foo");
+        assert_eq!(
+            content.extra.get("owner"),
+            Some(&json!({ "id": "00000000-0000-0000-0000-000000000002" }))
+        );
+        assert_eq!(content.extra.len(), 1);
+        let ContentKind::Folder {
+            code,
+            extra,
+            children: Some(children),
+            ..
+        } = &content.kind
+        else {
+            panic!("not a folder with children");
+        };
+        assert_eq!(code, "bar");
+        assert!(extra.is_empty());
+        let child = &children[&ContentId(uuid!("00000000-0000-0000-0000-000000000003"))];
+        assert!(matches!(child.kind, ContentKind::File { size: 20, .. }));
+        assert_eq!(child.extra.get("virusScan"), Some(&json!("clean")));
+        assert_eq!(child.extra.len(), 2);
+        assert_eq!(to_value(&content).unwrap(), json);
+
+        let account: AccountDetails = from_value(json!({
+            "id": "00000000-0000-0000-0000-000000000001",
+            "token": "foo",
+            "email": "foo@example.com",
+            "tier": "standard",
+            "rootFolder": "00000000-0000-0000-0000-000000000002",
+            "filesCount": 1,
+            "totalSize": 20,
+            "ipTraffic30": 4096,
+        }))
+        .unwrap();
+        assert_eq!(account.total_size, 20);
+        assert_eq!(account.extra.get("ipTraffic30"), Some(&json!(4096)));
+
+        let uploaded: UploadedFile = from_value(json!({
+            "downloadPage": "http://example.com/path/file.txt",
+            "code": "bar",
+            "parentFolder": "00000000-0000-0000-0000-000000000001",
+            "fileId": "00000000-0000-0000-0000-000000000002",
+            "fileName": "baz",
+            "md5": "000000000000000000000000000001ff",
+            "servers": ["store1"],
+        }))
+        .unwrap();
+        assert_eq!(uploaded.file_name, "baz");
+        assert_eq!(uploaded.extra.get("servers"), Some(&json!(["store1"])));
+    }
+
+    #[test]
+    fn file_fields() {
+        let old_style = json!({
+            "id": "00000000-0000-0000-0000-000000000001",
+            "name": "foo",
+            "createTime": 1000000001,
+            "type": "file",
+            "size": 20,
+            "downloadCount": 10,
+            "md5": "000000000000000000000000000001ff",
+            "mimetype": "text/plain",
+            "serverSelected": "store1",
+            "link": "https://store1.gofile.io/download/web/00000000-0000-0000-0000-000000000001/foo",
+        });
+        let content = from_value::<Content>(old_style.clone()).unwrap();
+        assert_eq!(content.kind.server(), Some("store1"));
+        assert_eq!(
+            content.kind.best_link().map(Url::as_str),
+            Some("https://store1.gofile.io/download/web/00000000-0000-0000-0000-000000000001/foo")
+        );
+        assert_eq!(to_value(&content).unwrap(), old_style);
+
+        let new_style = json!({
+            "id": "00000000-0000-0000-0000-000000000001",
+            "name": "foo",
+            "createTime": 1000000001,
+            "type": "file",
+            "size": 20,
+            "downloadCount": 10,
+            "mimetype": "text/plain",
+            "servers": ["store3", "store4"],
+            "directLinks": {
+                "00000000-0000-0000-0000-000000000002": {
+                    "id": "00000000-0000-0000-0000-000000000002",
+                    "directLink": "https://store3.gofile.io/download/direct/00000000-0000-0000-0000-000000000002/foo",
+                    "expireTime": 1000000000,
+                },
+                "00000000-0000-0000-0000-000000000003": {
+                    "id": "00000000-0000-0000-0000-000000000003",
+                    "directLink": "https://store3.gofile.io/download/direct/00000000-0000-0000-0000-000000000003/foo",
+                },
+            },
+        });
+        let content = from_value::<Content>(new_style.clone()).unwrap();
+        let ContentKind::File { md5, link, .. } = &content.kind else {
+            panic!("not a file");
+        };
+        assert_eq!((md5, link), (&None, &None));
+        assert_eq!(content.kind.server(), Some("store3"));
+        // the expired direct link is passed over
+        assert_eq!(
+            content.kind.best_link().map(Url::as_str),
+            Some(
+                "https://store3.gofile.io/download/direct/00000000-0000-0000-0000-000000000003/foo"
+            )
+        );
+        assert_eq!(to_value(&content).unwrap(), new_style);
+
+        let mut bad_md5 = new_style;
+        bad_md5["md5"] = json!("not hex");
+        assert!(from_value::<Content>(bad_md5).is_err());
+    }
+
+    // Also checks the payload serializes back to the same json
     fn assert_deserialize<T>(expected_value: Value, payload: T)
     where
-        T: DeserializeOwned + Debug + PartialEq,
+        T: DeserializeOwned + Serialize + Clone + Debug + PartialEq,
+    {
+        assert_eq!(to_value(&payload).unwrap(), expected_value);
+        assert_deserialize_lossy(expected_value, payload);
+    }
+
+    // For json with fields the payload doesn't keep, only the typed round trip is checked
+    fn assert_deserialize_lossy<T>(expected_value: Value, payload: T)
+    where
+        T: DeserializeOwned + Serialize + Clone + Debug + PartialEq,
     {
-        assert!(0 < format!("{:?}", payload).len());
+        assert!(!format!("{:?}", payload).is_empty());
         assert_eq!(from_value::<T>(expected_value).unwrap(), payload);
+        let round_trip = from_value::<T>(to_value(payload.clone()).unwrap()).unwrap();
+        assert_eq!(round_trip, payload);
     }
 }
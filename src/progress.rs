@@ -0,0 +1,125 @@
+use crate::UploadedMessage;
+use std::{sync::Arc, time::Duration};
+use tokio::sync::mpsc::{error::TrySendError, Sender, UnboundedSender};
+use uuid::Uuid;
+
+// Callback alternative to the progress channels. Observers are shared through an Arc and
+// called from whatever task drives the transfer, so they should return quickly.
+pub trait ProgressObserver: Send + Sync {
+    fn on_progress(&self, uuid: Uuid, uploaded: u64, total: Option<u64>);
+
+    // uploads report the whole message, observers only interested in the counts can ignore it
+    fn on_message(&self, message: UploadedMessage) {
+        self.on_progress(message.uuid, message.uploaded, message.total);
+    }
+
+    // a closed observer gets no more calls for the transfer
+    fn is_closed(&self) -> bool {
+        false
+    }
+}
+
+impl ProgressObserver for UnboundedSender<UploadedMessage> {
+    fn on_progress(&self, uuid: Uuid, uploaded: u64, total: Option<u64>) {
+        self.on_message(UploadedMessage {
+            uuid,
+            uploaded,
+            total,
+            retry: false,
+            file_name: Arc::from(""),
+            elapsed: Duration::ZERO,
+            bytes_per_sec: 0,
+        });
+    }
+
+    fn on_message(&self, message: UploadedMessage) {
+        let _ = self.send(message);
+    }
+
+    fn is_closed(&self) -> bool {
+        UnboundedSender::is_closed(self)
+    }
+}
+
+// Lets synchronous code receive progress without a tokio channel
+impl ProgressObserver for std::sync::mpsc::Sender<UploadedMessage> {
+    fn on_progress(&self, uuid: Uuid, uploaded: u64, total: Option<u64>) {
+        self.on_message(UploadedMessage {
+            uuid,
+            uploaded,
+            total,
+            retry: false,
+            file_name: Arc::from(""),
+            elapsed: Duration::ZERO,
+            bytes_per_sec: 0,
+        });
+    }
+
+    fn on_message(&self, message: UploadedMessage) {
+        let _ = self.send(message);
+    }
+}
+
+impl<F> ProgressObserver for F
+where
+    F: Fn(Uuid, u64, Option<u64>) + Send + Sync,
+{
+    fn on_progress(&self, uuid: Uuid, uploaded: u64, total: Option<u64>) {
+        self(uuid, uploaded, total)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopObserver;
+
+impl ProgressObserver for NoopObserver {
+    fn on_progress(&self, _uuid: Uuid, _uploaded: u64, _total: Option<u64>) {}
+}
+
+// What a bounded progress channel does with a message while it's full
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BackpressurePolicy {
+    // progress is lossy anyway, the upload never waits on the receiver. The final message can
+    // be dropped too.
+    #[default]
+    DropWhenFull,
+    // every message is delivered, a slow receiver slows the upload down with it. A receiver
+    // that stops reading without dropping the channel stalls the upload until its future is
+    // dropped.
+    WaitForCapacity,
+}
+
+#[derive(Clone)]
+pub(crate) enum ProgressSink {
+    Observer(Arc<dyn ProgressObserver>),
+    Bounded(Sender<UploadedMessage>, BackpressurePolicy),
+}
+
+impl ProgressSink {
+    // Returns false once the receiving side is gone
+    pub(crate) async fn send(&self, message: UploadedMessage) -> bool {
+        match self {
+            ProgressSink::Observer(observer) => {
+                observer.on_message(message);
+                !observer.is_closed()
+            }
+            ProgressSink::Bounded(tx, BackpressurePolicy::DropWhenFull) => {
+                !matches!(tx.try_send(message), Err(TrySendError::Closed(_)))
+            }
+            ProgressSink::Bounded(tx, BackpressurePolicy::WaitForCapacity) => {
+                tx.send(message).await.is_ok()
+            }
+        }
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        match self {
+            ProgressSink::Observer(observer) => observer.is_closed(),
+            ProgressSink::Bounded(tx, _) => tx.is_closed(),
+        }
+    }
+}
+
+pub(crate) fn channel_sink(tx: Option<UnboundedSender<UploadedMessage>>) -> Option<ProgressSink> {
+    tx.map(|tx| ProgressSink::Observer(Arc::new(tx)))
+}
@@ -0,0 +1,767 @@
+use crate::{
+    download::{checked_name, download_file_impl, file_size, same_file},
+    upload::{read_dir_entries, DirEntryKind, VisitedDirs},
+    Api, AuthorizedApi, AuthorizedServerApi, Content, ContentId, ContentKind, DownloadOptions,
+    Error, FolderId, SymlinkPolicy, UploadedMessage, ZonePreference,
+};
+use futures::{stream, StreamExt};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+use tokio::{
+    fs,
+    sync::mpsc::{unbounded_channel, UnboundedSender},
+};
+
+#[derive(Clone, Debug)]
+pub struct SyncOptions {
+    pub concurrency: usize,
+    // remove local entries that don't exist in the remote folder
+    pub delete_extraneous: bool,
+    // delete the remote copy of a file that was re-uploaded because it changed
+    pub delete_replaced: bool,
+    // how local symlinks are treated by sync_up
+    pub symlinks: SymlinkPolicy,
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            delete_extraneous: false,
+            delete_replaced: false,
+            symlinks: SymlinkPolicy::Skip,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub downloaded: Vec<PathBuf>,
+    pub skipped: Vec<PathBuf>,
+    pub deleted: Vec<PathBuf>,
+    // a remote folder and a local file (or the other way around) share a name
+    pub conflicts: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, Error)>,
+}
+
+#[derive(Debug, Default)]
+pub struct SyncUpReport {
+    pub uploaded: Vec<PathBuf>,
+    pub skipped: Vec<PathBuf>,
+    // remote copies replaced by a re-upload
+    pub deleted: Vec<ContentId>,
+    pub conflicts: Vec<PathBuf>,
+    // symlinks left out by the policy, sockets, fifos and devices
+    pub ignored: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, Error)>,
+    // size of the skipped files that didn't need to be uploaded again
+    pub bytes_saved: u64,
+}
+
+struct PlannedUpload {
+    path: PathBuf,
+    folder_id: FolderId,
+    replaces: Option<ContentId>,
+}
+
+impl AuthorizedApi {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            fields(folder_id = %folder_id, local_dir = ?local_dir.as_ref())
+        )
+    )]
+    pub async fn sync_down(
+        &self,
+        folder_id: FolderId,
+        local_dir: impl AsRef<Path>,
+        options: SyncOptions,
+    ) -> Result<SyncReport, Error> {
+        let mut report = SyncReport::default();
+        let files = self
+            .compare_folder(folder_id, local_dir.as_ref(), &options, &mut report)
+            .await?;
+
+        let results = stream::iter(files)
+            .map(|(content, path)| async move {
+                let (tx, _rx) = unbounded_channel();
+                let result = download_file_impl(
+                    &self.client,
+                    Some(self.token.as_str()),
+                    &content,
+                    &path,
+                    tx,
+                    DownloadOptions::default(),
+                )
+                .await;
+                (path, result)
+            })
+            .buffer_unordered(options.concurrency.max(1))
+            .collect::<Vec<_>>()
+            .await;
+
+        for (path, result) in results {
+            match result {
+                Ok((path, _)) => report.downloaded.push(path),
+                Err(err) => report.failed.push((path, err)),
+            }
+        }
+        report.downloaded.sort();
+        Ok(report)
+    }
+
+    // Walks the remote tree next to the local one and returns the files that are missing or
+    // changed locally
+    async fn compare_folder(
+        &self,
+        folder_id: FolderId,
+        local_dir: &Path,
+        options: &SyncOptions,
+        report: &mut SyncReport,
+    ) -> Result<Vec<(Content, PathBuf)>, Error> {
+        let root = self.get_content_by_id(folder_id.into()).await?;
+        if !matches!(root.kind, ContentKind::Folder { .. }) {
+            return Err(Error::NotAFolder(folder_id.into()));
+        }
+
+        let mut files = Vec::new();
+        let mut pending = vec![(root, local_dir.to_path_buf())];
+        while let Some((folder, dir)) = pending.pop() {
+            let ContentKind::Folder { children, .. } = folder.kind else {
+                continue;
+            };
+            if let Err(err) = fs::create_dir_all(&dir).await {
+                let err = Error::CouldntWriteFile(dir.clone(), err);
+                report.failed.push((dir, err));
+                continue;
+            }
+
+            let mut children = children
+                .unwrap_or_default()
+                .into_values()
+                .collect::<Vec<_>>();
+            children.sort_by(|a, b| a.name.cmp(&b.name));
+            let names = children
+                .iter()
+                .map(|child| child.name.clone())
+                .collect::<HashSet<_>>();
+
+            for child in children {
+                let path = match checked_name(&child.name) {
+                    Ok(name) => dir.join(name),
+                    // a name like ".." or "/abs" would sync outside local_dir
+                    Err(err) => {
+                        report.failed.push((dir.clone(), err));
+                        continue;
+                    }
+                };
+                let local = fs::symlink_metadata(&path).await.ok();
+                // followed, a symlink could lead outside local_dir too
+                if local.as_ref().is_some_and(|m| m.is_symlink()) {
+                    report.conflicts.push(path);
+                    continue;
+                }
+                match child.kind {
+                    ContentKind::Folder { .. } => {
+                        if local.is_some_and(|m| !m.is_dir()) {
+                            report.conflicts.push(path);
+                            continue;
+                        }
+                        match self.get_content_by_id(child.id).await {
+                            Ok(folder) => pending.push((folder, path)),
+                            Err(err) => report.failed.push((path, err)),
+                        }
+                    }
+                    ContentKind::File { .. } => {
+                        if local.as_ref().is_some_and(|m| m.is_dir()) {
+                            report.conflicts.push(path);
+                            continue;
+                        }
+                        if local.is_none() {
+                            files.push((child, path));
+                            continue;
+                        }
+                        match same_file(&child, &path).await {
+                            Ok(true) => report.skipped.push(path),
+                            Ok(false) => files.push((child, path)),
+                            Err(err) => report.failed.push((path, err)),
+                        }
+                    }
+                    // left alone, its name still keeps a local file from being extraneous
+                    ContentKind::Unknown { .. } => {}
+                }
+            }
+
+            if options.delete_extraneous {
+                delete_extraneous(&dir, &names, report).await;
+            }
+        }
+        Ok(files)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            fields(local_dir = ?local_dir.as_ref(), folder_id = %folder_id)
+        )
+    )]
+    pub async fn sync_up(
+        &self,
+        local_dir: impl AsRef<Path>,
+        folder_id: FolderId,
+        options: SyncOptions,
+        tx: Option<UnboundedSender<UploadedMessage>>,
+    ) -> Result<SyncUpReport, Error> {
+        let server = self.get_server(ZonePreference::Any).await?;
+        self.sync_up_with_server(&server, local_dir, folder_id, options, tx)
+            .await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            fields(local_dir = ?local_dir.as_ref(), folder_id = %folder_id)
+        )
+    )]
+    pub async fn sync_up_with_server(
+        &self,
+        server: &AuthorizedServerApi,
+        local_dir: impl AsRef<Path>,
+        folder_id: FolderId,
+        options: SyncOptions,
+        tx: Option<UnboundedSender<UploadedMessage>>,
+    ) -> Result<SyncUpReport, Error> {
+        let mut report = SyncUpReport::default();
+        let uploads = self
+            .plan_uploads(local_dir.as_ref(), folder_id, options.symlinks, &mut report)
+            .await?;
+
+        // failed deletions are reported against the endpoint that refused them
+        let delete_url = &Api::url(&self.base_url, "contents")?;
+        let tx = &tx;
+        let results = stream::iter(uploads)
+            .map(|upload| async move {
+                let result = server
+                    .upload_file_to_folder_with_progress(upload.folder_id, &upload.path, tx.clone())
+                    .await;
+                let deleted = match (&result, upload.replaces) {
+                    (Ok(_), Some(old_id)) if options.delete_replaced => Some(
+                        self.delete_contents(vec![old_id]).await.and_then(
+                            |statuses| match statuses.get(&old_id) {
+                                Some(status) if !status.is_ok() => Err(Error::ApiStatusError(
+                                    delete_url.clone(),
+                                    status.status.as_str().into(),
+                                    Box::default(),
+                                )),
+                                _ => Ok(old_id),
+                            },
+                        ),
+                    ),
+                    _ => None,
+                };
+                (upload.path, result, deleted)
+            })
+            .buffer_unordered(options.concurrency.max(1))
+            .collect::<Vec<_>>()
+            .await;
+
+        for (path, result, deleted) in results {
+            match result {
+                Ok(_) => report.uploaded.push(path.clone()),
+                Err(err) => report.failed.push((path.clone(), err)),
+            }
+            match deleted {
+                Some(Ok(old_id)) => report.deleted.push(old_id),
+                Some(Err(err)) => report.failed.push((path, err)),
+                None => {}
+            }
+        }
+        report.uploaded.sort();
+        Ok(report)
+    }
+
+    // Walks the local tree next to the remote one, creating missing remote folders, and
+    // returns the files that are missing or changed remotely
+    async fn plan_uploads(
+        &self,
+        local_dir: &Path,
+        folder_id: FolderId,
+        symlinks: SymlinkPolicy,
+        report: &mut SyncUpReport,
+    ) -> Result<Vec<PlannedUpload>, Error> {
+        let root = self.get_content_by_id(folder_id.into()).await?;
+        if !matches!(root.kind, ContentKind::Folder { .. }) {
+            return Err(Error::NotAFolder(folder_id.into()));
+        }
+
+        let mut visited = VisitedDirs::new(local_dir, symlinks).await?;
+        let mut uploads = Vec::new();
+        let mut pending = vec![(local_dir.to_path_buf(), root)];
+        while let Some((dir, folder)) = pending.pop() {
+            let Content { id, kind, .. } = folder;
+            let ContentKind::Folder { children, .. } = kind else {
+                continue;
+            };
+            let folder_id = FolderId(id.0);
+            let children = children
+                .unwrap_or_default()
+                .into_values()
+                .map(|child| (child.name.clone(), child))
+                .collect::<HashMap<_, _>>();
+            let entries = match read_dir_entries(&dir, symlinks).await {
+                Ok(entries) => entries,
+                Err(err) => {
+                    report.failed.push((dir, err));
+                    continue;
+                }
+            };
+
+            for (path, kind) in entries {
+                let is_dir = match kind {
+                    DirEntryKind::File => false,
+                    DirEntryKind::Dir => true,
+                    DirEntryKind::Ignored => {
+                        report.ignored.push(path);
+                        continue;
+                    }
+                    DirEntryKind::Broken(err) => {
+                        report.failed.push((path, err));
+                        continue;
+                    }
+                };
+                let name = path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned();
+                let remote = children.get(&name);
+                let remote_is_folder =
+                    remote.map(|child| matches!(child.kind, ContentKind::Folder { .. }));
+
+                if is_dir {
+                    match visited.enter(&path).await {
+                        Ok(true) => {}
+                        Ok(false) => {
+                            report.ignored.push(path);
+                            continue;
+                        }
+                        Err(err) => {
+                            report.failed.push((path, err));
+                            continue;
+                        }
+                    }
+                    let folder = match remote {
+                        Some(child) if remote_is_folder == Some(true) => {
+                            self.get_content_by_id(child.id).await
+                        }
+                        Some(_) => {
+                            report.conflicts.push(path);
+                            continue;
+                        }
+                        None => self.create_folder(folder_id, name).await,
+                    };
+                    match folder {
+                        Ok(folder) => pending.push((path, folder)),
+                        Err(err) => report.failed.push((path, err)),
+                    }
+                    continue;
+                }
+
+                match remote {
+                    Some(_) if remote_is_folder == Some(true) => report.conflicts.push(path),
+                    Some(child) => match same_file(child, &path).await {
+                        Ok(true) => {
+                            report.bytes_saved += file_size(child);
+                            report.skipped.push(path);
+                        }
+                        Ok(false) => uploads.push(PlannedUpload {
+                            path,
+                            folder_id,
+                            replaces: Some(child.id),
+                        }),
+                        Err(err) => report.failed.push((path, err)),
+                    },
+                    None => uploads.push(PlannedUpload {
+                        path,
+                        folder_id,
+                        replaces: None,
+                    }),
+                }
+            }
+        }
+        Ok(uploads)
+    }
+}
+
+async fn delete_extraneous(dir: &Path, names: &HashSet<String>, report: &mut SyncReport) {
+    let mut entries = match fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(err) => {
+            let err = Error::CouldntOpenFile(dir.into(), err);
+            report.failed.push((dir.into(), err));
+            return;
+        }
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        if names.contains(&*entry.file_name().to_string_lossy()) {
+            continue;
+        }
+        let path = entry.path();
+        let result = match entry.file_type().await {
+            Ok(file_type) if file_type.is_dir() => fs::remove_dir_all(&path).await,
+            _ => fs::remove_file(&path).await,
+        };
+        match result {
+            Ok(()) => report.deleted.push(path),
+            Err(err) => {
+                let err = Error::CouldntWriteFile(path.clone(), err);
+                report.failed.push((path, err));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{download::tests::file_entry, ApiClient};
+    use md5::{Digest, Md5};
+    use mockito::{Matcher, Server};
+    use serde_json::json;
+    use uuid::uuid;
+
+    #[tokio::test]
+    async fn sync_down() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        }
+        .authorize("gofile_token");
+        let dir = tempfile::tempdir().unwrap();
+        let url = server.url();
+
+        let root = json!({
+            "status": "ok",
+            "data": {
+                "id": "00000000-0000-0000-0000-000000000001",
+                "type": "folder",
+                "name": "root",
+                "createTime": 1710264457,
+                "code": "root",
+                "children": {
+                    "00000000-0000-0000-0000-000000000010": {
+                        "id": "00000000-0000-0000-0000-000000000010",
+                        "type": "folder",
+                        "name": "sub",
+                        "createTime": 1710264457,
+                        "code": "sub"
+                    },
+                    "00000000-0000-0000-0000-000000000011": file_entry(&url, "00000000-0000-0000-0000-000000000011", "a.txt", "aaa"),
+                    "00000000-0000-0000-0000-000000000012": file_entry(&url, "00000000-0000-0000-0000-000000000012", "b.txt", "bbb"),
+                    "00000000-0000-0000-0000-000000000013": file_entry(&url, "00000000-0000-0000-0000-000000000013", "c.txt", "ccc"),
+                    "00000000-0000-0000-0000-000000000014": file_entry(&url, "00000000-0000-0000-0000-000000000014", "/tmp/abs.txt", "ddd"),
+                    "00000000-0000-0000-0000-000000000015": {
+                        "id": "00000000-0000-0000-0000-000000000015",
+                        "type": "folder",
+                        "name": "..",
+                        "createTime": 1710264457,
+                        "code": "parent"
+                    },
+                }
+            }
+        });
+        let mocks = vec![
+            server
+                .mock(
+                    "GET",
+                    "/contents/00000000-0000-0000-0000-000000000001?token=gofile_token",
+                )
+                .with_status(200)
+                .with_body(root.to_string())
+                .create(),
+            server
+                .mock(
+                    "GET",
+                    "/download/web/00000000-0000-0000-0000-000000000012/b.txt",
+                )
+                .with_status(200)
+                .with_body("bbb")
+                .create(),
+            server
+                .mock(
+                    "GET",
+                    "/download/web/00000000-0000-0000-0000-000000000013/c.txt",
+                )
+                .with_status(200)
+                .with_body("ccc")
+                .create(),
+        ];
+        std::fs::write(dir.path().join("a.txt"), "aaa").unwrap();
+        std::fs::write(dir.path().join("b.txt"), "old").unwrap();
+        std::fs::write(dir.path().join("sub"), "not a folder").unwrap();
+        std::fs::write(dir.path().join("extra.txt"), "extra").unwrap();
+
+        let options = SyncOptions {
+            delete_extraneous: true,
+            ..Default::default()
+        };
+        let report = api
+            .sync_down(
+                uuid!("00000000-0000-0000-0000-000000000001").into(),
+                dir.path(),
+                options,
+            )
+            .await?;
+        assert_eq!(
+            report.downloaded,
+            vec![dir.path().join("b.txt"), dir.path().join("c.txt")]
+        );
+        assert_eq!(report.skipped, vec![dir.path().join("a.txt")]);
+        assert_eq!(report.deleted, vec![dir.path().join("extra.txt")]);
+        assert_eq!(report.conflicts, vec![dir.path().join("sub")]);
+        // names that are paths would sync outside the root
+        assert_eq!(report.failed.len(), 2);
+        for (path, err) in &report.failed {
+            assert_eq!(path, dir.path());
+            assert!(
+                matches!(err, Error::InvalidName(name, _) if name == ".." || name == "/tmp/abs.txt")
+            );
+        }
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("b.txt")).unwrap(),
+            "bbb"
+        );
+        for mock in mocks {
+            mock.assert();
+        }
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn sync_down_symlink() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        }
+        .authorize("gofile_token");
+        let dir = tempfile::tempdir().unwrap();
+
+        let root = json!({
+            "status": "ok",
+            "data": {
+                "id": "00000000-0000-0000-0000-000000000001",
+                "type": "folder",
+                "name": "root",
+                "createTime": 1710264457,
+                "code": "root",
+                "children": {
+                    "00000000-0000-0000-0000-000000000010": {
+                        "id": "00000000-0000-0000-0000-000000000010",
+                        "type": "folder",
+                        "name": "link",
+                        "createTime": 1710264457,
+                        "code": "link"
+                    },
+                }
+            }
+        });
+        let mock = server
+            .mock(
+                "GET",
+                "/contents/00000000-0000-0000-0000-000000000001?token=gofile_token",
+            )
+            .with_status(200)
+            .with_body(root.to_string())
+            .expect(1)
+            .create();
+        // a local symlink is never followed, nothing outside the root is touched
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("keep.txt"), "keep").unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("link")).unwrap();
+
+        let options = SyncOptions {
+            delete_extraneous: true,
+            ..Default::default()
+        };
+        let report = api
+            .sync_down(
+                uuid!("00000000-0000-0000-0000-000000000001").into(),
+                dir.path(),
+                options,
+            )
+            .await?;
+        assert_eq!(report.conflicts, vec![dir.path().join("link")]);
+        assert!(report.deleted.is_empty());
+        assert!(report.failed.is_empty());
+        assert!(outside.path().join("keep.txt").exists());
+        mock.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sync_up() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        }
+        .authorize("gofile_token");
+        let server_api = AuthorizedServerApi {
+            base_url: server.url().parse().unwrap(),
+            token: "gofile_token".into(),
+            client: ApiClient::default(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let url = server.url();
+
+        let root = json!({
+            "status": "ok",
+            "data": {
+                "id": "00000000-0000-0000-0000-000000000001",
+                "type": "folder",
+                "name": "root",
+                "createTime": 1710264457,
+                "code": "root",
+                "children": {
+                    "00000000-0000-0000-0000-000000000011": file_entry(&url, "00000000-0000-0000-0000-000000000011", "a.txt", "aaa"),
+                    "00000000-0000-0000-0000-000000000012": file_entry(&url, "00000000-0000-0000-0000-000000000012", "b.txt", "old"),
+                }
+            }
+        });
+        let uploaded = |body: &str| {
+            json!({
+                "status": "ok",
+                "data": {
+                    "downloadPage": "https://gofile.io/d/foo",
+                    "code": "foo",
+                    "parentFolder": "00000000-0000-0000-0000-000000000001",
+                    "fileId": "00000000-0000-0000-0000-000000000030",
+                    "fileName": "foo",
+                    "md5": hex::encode(Md5::digest(body))
+                }
+            })
+            .to_string()
+        };
+        let mocks = vec![
+            server
+                .mock(
+                    "GET",
+                    "/contents/00000000-0000-0000-0000-000000000001?token=gofile_token",
+                )
+                .with_status(200)
+                .with_body(root.to_string())
+                .create(),
+            server
+                .mock("POST", "/contents/createFolder")
+                .match_body(Matcher::Json(json!({
+                    "token": "gofile_token",
+                    "parentFolderId": "00000000-0000-0000-0000-000000000001",
+                    "folderName": "sub",
+                })))
+                .with_status(200)
+                .with_body(
+                    json!({
+                        "status": "ok",
+                        "data": {
+                            "id": "00000000-0000-0000-0000-000000000020",
+                            "type": "folder",
+                            "name": "sub",
+                            "createTime": 1710264457,
+                            "code": "sub"
+                        }
+                    })
+                    .to_string(),
+                )
+                .create(),
+            server
+                .mock("POST", "/contents/uploadfile")
+                .match_body(Matcher::AllOf(vec![
+                    Matcher::Regex("hhh".into()),
+                    Matcher::Regex("00000000-0000-0000-0000-000000000001".into()),
+                ]))
+                .with_status(200)
+                .with_body(uploaded("hhh"))
+                .create(),
+            server
+                .mock("POST", "/contents/uploadfile")
+                .match_body(Matcher::AllOf(vec![
+                    Matcher::Regex("iii".into()),
+                    Matcher::Regex("00000000-0000-0000-0000-000000000020".into()),
+                ]))
+                .with_status(200)
+                .with_body(uploaded("iii"))
+                .create(),
+            server
+                .mock("DELETE", "/contents")
+                .match_body(Matcher::Json(json!({
+                    "token": "gofile_token",
+                    "contentsId": "00000000-0000-0000-0000-000000000012",
+                })))
+                .with_status(200)
+                .with_body(
+                    r#"{
+                    "status": "ok",
+                    "data": {
+                        "00000000-0000-0000-0000-000000000012": { "status": "ok", "data": {} }
+                    }
+                }"#,
+                )
+                .create(),
+        ];
+        std::fs::write(dir.path().join("a.txt"), "aaa").unwrap();
+        std::fs::write(dir.path().join("b.txt"), "hhh").unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("sub/c.txt"), "iii").unwrap();
+
+        let options = SyncOptions {
+            delete_replaced: true,
+            ..Default::default()
+        };
+        let (tx, mut rx) = unbounded_channel();
+        let report = api
+            .sync_up_with_server(
+                &server_api,
+                dir.path(),
+                uuid!("00000000-0000-0000-0000-000000000001").into(),
+                options,
+                Some(tx),
+            )
+            .await?;
+        assert_eq!(
+            report.uploaded,
+            vec![dir.path().join("b.txt"), dir.path().join("sub/c.txt")]
+        );
+        assert_eq!(report.skipped, vec![dir.path().join("a.txt")]);
+        assert_eq!(
+            report.deleted,
+            vec![uuid!("00000000-0000-0000-0000-000000000012")]
+        );
+        assert!(report.conflicts.is_empty());
+        assert!(report.failed.is_empty());
+        assert_eq!(report.bytes_saved, 3);
+
+        let mut uploads = HashMap::new();
+        while let Some(message) = rx.recv().await {
+            uploads.insert(message.uuid, message);
+        }
+        assert_eq!(uploads.len(), 2);
+        assert!(uploads
+            .values()
+            .all(|message| message.uploaded == 3 && message.total == Some(3)));
+        for mock in mocks {
+            mock.assert();
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,927 @@
+// An in-process stand-in for gofile, for testing code built on this crate without the network.
+// It serves the endpoints the crate wraps from an in-memory tree: the server list, accounts,
+// contents, folder creation, uploads, updates, copies, moves, deletes and file downloads.
+// Sorting and filtering params are ignored, direct links aren't implemented.
+use crate::{Api, AuthorizedApi, FileId, FolderId, ServerApi};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use md5::{Digest, Md5};
+use reqwest::{
+    header::{ACCEPT_RANGES, AUTHORIZATION, CONTENT_RANGE, CONTENT_TYPE, RANGE, RETRY_AFTER},
+    StatusCode,
+};
+use serde_json::{json, Map, Value};
+use sha2::Sha256;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::oneshot;
+use url::Url;
+use uuid::Uuid;
+use warp::{
+    http::{HeaderMap, Response},
+    hyper::Body,
+    multipart::FormData,
+    path::FullPath,
+    Buf, Filter,
+};
+
+// Answer to the next request instead of serving it, see FakeGofile::fail_next
+#[derive(Clone, Debug, PartialEq)]
+pub enum Failure {
+    RateLimit(Option<Duration>),
+    NotPremium,
+    WrongPassword,
+    // a non json error page with this status, like a proxy in front of gofile
+    Status(StatusCode),
+}
+
+#[derive(Debug)]
+pub struct FakeGofile {
+    url: Url,
+    token: String,
+    account_id: Uuid,
+    root_folder: Uuid,
+    state: Arc<Mutex<State>>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl FakeGofile {
+    // Serves on an ephemeral localhost port until dropped, must be called inside a tokio runtime.
+    // The server starts with one account, see token() and root_folder().
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(State::default()));
+        let (shutdown, stopped) = oneshot::channel();
+        let (addr, server) = warp::serve(Self::routes(state.clone())).bind_with_graceful_shutdown(
+            ([127, 0, 0, 1], 0),
+            async {
+                let _ = stopped.await;
+            },
+        );
+        tokio::spawn(server);
+
+        let url =
+            Url::parse(&format!("http://{}/", addr)).expect("a socket address is a valid host");
+        let mut guard = state.lock().unwrap();
+        guard.url = Some(url.clone());
+        let account = guard.create_account(false);
+        drop(guard);
+        Self {
+            url,
+            token: account.token,
+            account_id: account.id,
+            root_folder: account.root_folder,
+            state,
+            shutdown: Some(shutdown),
+        }
+    }
+
+    // Base url for Api::with_base_url, also where uploads and downloads go
+    pub fn url(&self) -> Url {
+        self.url.clone()
+    }
+
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    pub fn account_id(&self) -> Uuid {
+        self.account_id
+    }
+
+    pub fn root_folder(&self) -> FolderId {
+        FolderId(self.root_folder)
+    }
+
+    pub fn api(&self) -> Api {
+        Api::new()
+            .with_base_url(self.url())
+            .expect("the fake server url is a valid base url")
+    }
+
+    pub fn authorized_api(&self) -> AuthorizedApi {
+        self.api().authorize(self.token.clone())
+    }
+
+    // Api::get_server always points at *.gofile.io, uploads to the fake server need this one
+    pub fn server_api(&self) -> ServerApi {
+        ServerApi {
+            base_url: self.url(),
+            client: Default::default(),
+        }
+    }
+
+    // Failures are used up in order, one per request
+    pub fn fail_next(&self, failure: Failure) {
+        self.state.lock().unwrap().failures.push_back(failure);
+    }
+
+    // Bytes stored for an uploaded file, None for folders and unknown ids
+    pub fn file_data(&self, file_id: FileId) -> Option<Bytes> {
+        let state = self.state.lock().unwrap();
+        let file = state.nodes.get(&file_id.0)?.file.as_ref()?;
+        Some(file.data.clone())
+    }
+
+    fn routes(
+        state: Arc<Mutex<State>>,
+    ) -> impl Filter<Extract = (Response<Body>,), Error = warp::Rejection> + Clone {
+        let upload_state = state.clone();
+        let upload = warp::post()
+            .and(warp::path!("contents" / "uploadfile"))
+            .and(warp::multipart::form().max_length(None))
+            .and_then(move |form: FormData| {
+                let state = upload_state.clone();
+                async move {
+                    let fields = form
+                        .and_then(|part| async move {
+                            let name = part.name().to_string();
+                            let filename = part.filename().map(String::from);
+                            let mimetype = part.content_type().map(String::from);
+                            let data = part
+                                .stream()
+                                .try_fold(Vec::new(), |mut data, chunk| async move {
+                                    data.extend_from_slice(chunk.chunk());
+                                    Ok(data)
+                                })
+                                .await?;
+                            Ok((name, (filename, mimetype, Bytes::from(data))))
+                        })
+                        .try_collect::<HashMap<_, _>>()
+                        .await
+                        .map_err(|_| warp::reject())?;
+                    Ok::<_, warp::Rejection>(state.lock().unwrap().upload(fields))
+                }
+            });
+        let api = warp::method()
+            .and(warp::path::full())
+            .and(warp::query::<HashMap<String, String>>())
+            .and(warp::header::headers_cloned())
+            .and(warp::body::bytes())
+            .map(
+                move |method: warp::http::Method,
+                      path: FullPath,
+                      query: HashMap<String, String>,
+                      headers: HeaderMap,
+                      body: Bytes| {
+                    let req = FakeRequest {
+                        method: method.to_string(),
+                        path: path
+                            .as_str()
+                            .split('/')
+                            .filter(|segment| !segment.is_empty())
+                            .map(String::from)
+                            .collect(),
+                        body: serde_json::from_slice(&body).unwrap_or(Value::Null),
+                        query,
+                        headers,
+                    };
+                    state.lock().unwrap().handle(req)
+                },
+            );
+        upload.or(api).unify()
+    }
+}
+
+impl Drop for FakeGofile {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Account {
+    id: Uuid,
+    token: String,
+    root_folder: Uuid,
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    id: Uuid,
+    name: String,
+    parent: Option<Uuid>,
+    owner: Uuid,
+    code: String,
+    create_time: DateTime<Utc>,
+    public: bool,
+    // sha256 hex, what clients send
+    password: Option<String>,
+    tags: Vec<String>,
+    // None for folders
+    file: Option<StoredFile>,
+}
+
+#[derive(Clone, Debug)]
+struct StoredFile {
+    data: Bytes,
+    mimetype: String,
+}
+
+struct FakeRequest {
+    method: String,
+    path: Vec<String>,
+    query: HashMap<String, String>,
+    headers: HeaderMap,
+    body: Value,
+}
+
+impl FakeRequest {
+    // The crate sends it as a bearer header, a query param or a body field depending on the
+    // endpoint
+    fn token(&self) -> Option<String> {
+        let bearer = self
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        bearer
+            .or(self.query.get("token").map(String::as_str))
+            .or(self.body["token"].as_str())
+            .map(String::from)
+    }
+
+    fn ids(&self, field: &str) -> Vec<Uuid> {
+        self.body[field]
+            .as_str()
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|id| id.parse().ok())
+            .collect()
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    url: Option<Url>,
+    accounts: Vec<Account>,
+    nodes: HashMap<Uuid, Node>,
+    failures: VecDeque<Failure>,
+}
+
+type Reply = Response<Body>;
+
+fn json_reply(status: StatusCode, api_status: &str, data: Value) -> Reply {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({ "status": api_status, "data": data }).to_string(),
+        ))
+        .unwrap()
+}
+
+fn ok(data: Value) -> Reply {
+    json_reply(StatusCode::OK, "ok", data)
+}
+
+fn error(status: StatusCode, api_status: &str) -> Reply {
+    json_reply(status, api_status, json!({}))
+}
+
+fn not_found() -> Reply {
+    error(StatusCode::NOT_FOUND, "error-notFound")
+}
+
+fn unauthorized() -> Reply {
+    error(StatusCode::UNAUTHORIZED, "error-auth")
+}
+
+impl State {
+    fn url(&self) -> &Url {
+        self.url.as_ref().expect("the url is set on start")
+    }
+
+    fn create_account(&mut self, guest: bool) -> Account {
+        let id = Uuid::new_v4();
+        let root = self.insert_node(None, id, String::from("root"), None);
+        // guest uploads are shared by their link
+        self.nodes.get_mut(&root).unwrap().public = guest;
+        let account = Account {
+            id,
+            token: Uuid::new_v4().simple().to_string(),
+            root_folder: root,
+        };
+        self.accounts.push(account.clone());
+        account
+    }
+
+    fn insert_node(
+        &mut self,
+        parent: Option<Uuid>,
+        owner: Uuid,
+        name: String,
+        file: Option<StoredFile>,
+    ) -> Uuid {
+        let id = Uuid::new_v4();
+        let node = Node {
+            id,
+            name,
+            parent,
+            owner,
+            code: id.simple().to_string()[..6].into(),
+            create_time: Utc::now(),
+            public: false,
+            password: None,
+            tags: vec![],
+            file,
+        };
+        self.nodes.insert(id, node);
+        id
+    }
+
+    fn account(&self, token: Option<String>) -> Option<Account> {
+        let token = token?;
+        self.accounts
+            .iter()
+            .find(|account| account.token == token)
+            .cloned()
+    }
+
+    // Children sorted by name, the order is stable across pages
+    fn children(&self, folder_id: Uuid) -> Vec<&Node> {
+        let mut children: Vec<_> = self
+            .nodes
+            .values()
+            .filter(|node| node.parent == Some(folder_id))
+            .collect();
+        children.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+        children
+    }
+
+    fn descendants(&self, id: Uuid) -> Vec<Uuid> {
+        let mut ids = vec![id];
+        let mut i = 0;
+        while i < ids.len() {
+            ids.extend(self.children(ids[i]).iter().map(|node| node.id));
+            i += 1;
+        }
+        ids
+    }
+
+    fn owned(&self, account: &Account, id: Uuid) -> Option<&Node> {
+        self.nodes.get(&id).filter(|node| node.owner == account.id)
+    }
+
+    fn owned_folder(&self, account: &Account, id: Uuid) -> Option<&Node> {
+        self.owned(account, id).filter(|node| node.file.is_none())
+    }
+
+    fn content_json(&self, node: &Node, children: Option<&[&Node]>) -> Value {
+        let mut content = json!({
+            "id": node.id,
+            "name": node.name,
+            "createTime": node.create_time.timestamp(),
+        });
+        if let Some(parent) = node.parent {
+            content["parentFolder"] = json!(parent);
+        }
+        if !node.tags.is_empty() {
+            content["tags"] = json!(node.tags.join(","));
+        }
+        let Some(file) = &node.file else {
+            content["type"] = json!("folder");
+            content["code"] = json!(node.code);
+            content["public"] = json!(node.public);
+            content["childrenIds"] = json!(self
+                .children(node.id)
+                .iter()
+                .map(|child| child.id)
+                .collect::<Vec<_>>());
+            if let Some(children) = children {
+                let children: Map<_, _> = children
+                    .iter()
+                    .map(|child| (child.id.to_string(), self.content_json(child, None)))
+                    .collect();
+                content["children"] = Value::Object(children);
+                content["totalDownloadCount"] = json!(0);
+                content["totalSize"] = json!(self
+                    .descendants(node.id)
+                    .iter()
+                    .filter_map(|id| self.nodes[id].file.as_ref())
+                    .map(|file| file.data.len())
+                    .sum::<usize>());
+            }
+            return content;
+        };
+        content["type"] = json!("file");
+        content["size"] = json!(file.data.len());
+        content["downloadCount"] = json!(0);
+        content["md5"] = json!(hex::encode(Md5::digest(&file.data)));
+        content["mimetype"] = json!(file.mimetype);
+        content["serverSelected"] = json!("store1");
+        content["link"] = json!(self.download_link(node));
+        content
+    }
+
+    fn download_link(&self, node: &Node) -> Url {
+        let mut link = self.url().clone();
+        link.path_segments_mut().unwrap().pop_if_empty().extend([
+            "download",
+            "web",
+            &node.id.to_string(),
+            &node.name,
+        ]);
+        link
+    }
+
+    fn handle(&mut self, req: FakeRequest) -> Reply {
+        if let Some(failure) = self.failures.pop_front() {
+            return Self::failure(failure);
+        }
+        let path: Vec<_> = req.path.iter().map(String::as_str).collect();
+        match (req.method.as_str(), path.as_slice()) {
+            ("GET", ["servers"]) => ok(json!({ "servers": [{ "name": "store1", "zone": "eu" }] })),
+            ("GET", ["accounts", "getid"]) => match self.account(req.token()) {
+                Some(account) => ok(json!({ "id": account.id })),
+                None => unauthorized(),
+            },
+            ("GET", ["accounts", id]) => self.account_details(&req, id),
+            ("GET", ["contents", id_or_code]) => self.get_content(&req, id_or_code),
+            ("POST", ["contents", "createFolder"]) => self.create_folder(&req),
+            ("PUT", ["contents", id, "update"]) => self.update(&req, id),
+            ("POST", ["contents", id, "directlinks"]) => self.create_direct_link(&req, id),
+            ("DELETE", ["contents", id, "directlinks", _]) => self.delete_direct_link(&req, id),
+            ("POST", ["contents", "copy"]) => self.copy(&req),
+            ("PUT", ["contents", "move"]) => self.move_contents(&req),
+            ("DELETE", ["contents"]) => self.delete(&req),
+            ("GET" | "HEAD", ["download", "web", id, _]) => self.download(&req, id),
+            _ => not_found(),
+        }
+    }
+
+    fn failure(failure: Failure) -> Reply {
+        match failure {
+            Failure::RateLimit(retry_after) => {
+                let mut res = error(StatusCode::TOO_MANY_REQUESTS, "error-rateLimit");
+                if let Some(retry_after) = retry_after {
+                    res.headers_mut()
+                        .insert(RETRY_AFTER, retry_after.as_secs().into());
+                }
+                res
+            }
+            Failure::NotPremium => error(StatusCode::UNAUTHORIZED, "error-notPremium"),
+            Failure::WrongPassword => error(StatusCode::UNAUTHORIZED, "error-passwordWrong"),
+            Failure::Status(status) => Response::builder()
+                .status(status)
+                .header(CONTENT_TYPE, "text/html")
+                .body(Body::from(format!("<html>{}</html>", status)))
+                .unwrap(),
+        }
+    }
+
+    fn account_details(&self, req: &FakeRequest, id: &str) -> Reply {
+        let Some(account) = self.account(req.token()) else {
+            return unauthorized();
+        };
+        if id != account.id.to_string() {
+            return unauthorized();
+        }
+        let files: Vec<_> = self
+            .nodes
+            .values()
+            .filter(|node| node.owner == account.id)
+            .filter_map(|node| node.file.as_ref())
+            .collect();
+        ok(json!({
+            "id": account.id,
+            "token": account.token,
+            "email": "fake@gofile.io",
+            // premium so the premium only calls like direct links reach the fake
+            "tier": "premium",
+            "rootFolder": account.root_folder,
+            "filesCount": files.len(),
+            "totalSize": files.iter().map(|file| file.data.len()).sum::<usize>(),
+        }))
+    }
+
+    fn get_content(&self, req: &FakeRequest, id_or_code: &str) -> Reply {
+        let node = match id_or_code.parse::<Uuid>() {
+            Ok(id) => self.nodes.get(&id),
+            Err(_) => self.nodes.values().find(|node| node.code == id_or_code),
+        };
+        let Some(node) = node else {
+            return not_found();
+        };
+        let owner = self
+            .account(req.token())
+            .is_some_and(|account| account.id == node.owner);
+        if !owner {
+            if let Some(password) = &node.password {
+                match req.query.get("password") {
+                    None => return error(StatusCode::UNAUTHORIZED, "error-passwordRequired"),
+                    Some(hash) if hash != password => {
+                        return error(StatusCode::UNAUTHORIZED, "error-passwordWrong")
+                    }
+                    Some(_) => {}
+                }
+            } else if !node.public {
+                return error(StatusCode::UNAUTHORIZED, "error-notPublic");
+            }
+        }
+
+        let children = self.children(node.id);
+        let page = req
+            .query
+            .get("page")
+            .and_then(|page| page.parse::<usize>().ok());
+        let page_size = req
+            .query
+            .get("pageSize")
+            .and_then(|page_size| page_size.parse::<usize>().ok());
+        let (Some(page), Some(page_size)) = (page, page_size) else {
+            return ok(self.content_json(node, Some(&children)));
+        };
+        let start = page.saturating_sub(1) * page_size;
+        let end = children.len().min(start + page_size);
+        let mut content =
+            self.content_json(node, Some(children.get(start..end).unwrap_or_default()));
+        content["metadata"] = json!({
+            "totalCount": children.len(),
+            "page": page,
+            "pageSize": page_size,
+        });
+        ok(content)
+    }
+
+    fn create_folder(&mut self, req: &FakeRequest) -> Reply {
+        let Some(account) = self.account(req.token()) else {
+            return unauthorized();
+        };
+        let parent = req.body["parentFolderId"]
+            .as_str()
+            .and_then(|id| id.parse().ok());
+        let Some(parent) = parent.filter(|&id| self.owned_folder(&account, id).is_some()) else {
+            return not_found();
+        };
+        let name = req.body["folderName"].as_str().unwrap_or_default().into();
+        let id = self.insert_node(Some(parent), account.id, name, None);
+        ok(self.content_json(&self.nodes[&id], None))
+    }
+
+    fn update(&mut self, req: &FakeRequest, id: &str) -> Reply {
+        let Some(account) = self.account(req.token()) else {
+            return unauthorized();
+        };
+        let id = id.parse().ok();
+        let Some(id) = id.filter(|&id| self.owned(&account, id).is_some()) else {
+            return not_found();
+        };
+        let value = req.body["attributeValue"].as_str().unwrap_or_default();
+        let node = self.nodes.get_mut(&id).unwrap();
+        match req.body["attribute"].as_str() {
+            Some("name") => node.name = value.into(),
+            Some("public") => node.public = value == "true",
+            Some("password") if value.is_empty() => node.password = None,
+            Some("password") => node.password = Some(hex::encode(Sha256::digest(value))),
+            Some("tags") => {
+                node.tags = value
+                    .split(',')
+                    .filter(|tag| !tag.is_empty())
+                    .map(String::from)
+                    .collect()
+            }
+            // accepted without effect
+            Some("description" | "expiry" | "directLink") => {}
+            _ => return error(StatusCode::BAD_REQUEST, "error-badRequest"),
+        }
+        ok(json!({}))
+    }
+
+    // The link is the normal download link, the fake doesn't keep track of direct links
+    fn create_direct_link(&self, req: &FakeRequest, id: &str) -> Reply {
+        let Some(account) = self.account(req.token()) else {
+            return unauthorized();
+        };
+        let Some(node) = id.parse().ok().and_then(|id| self.owned(&account, id)) else {
+            return not_found();
+        };
+        ok(json!({ "id": Uuid::new_v4(), "directLink": self.download_link(node) }))
+    }
+
+    fn delete_direct_link(&self, req: &FakeRequest, id: &str) -> Reply {
+        let Some(account) = self.account(req.token()) else {
+            return unauthorized();
+        };
+        match id.parse().ok().and_then(|id| self.owned(&account, id)) {
+            Some(_) => ok(json!({})),
+            None => not_found(),
+        }
+    }
+
+    // Checks the destination and every content before touching anything
+    fn checked_transfer(&self, req: &FakeRequest) -> Option<(Vec<Uuid>, Uuid)> {
+        let account = self.account(req.token())?;
+        let dest = req.body["folderIdDest"].as_str()?.parse().ok()?;
+        self.owned_folder(&account, dest)?;
+        let ids = req.ids("contentsId");
+        if ids.iter().any(|&id| self.owned(&account, id).is_none()) {
+            return None;
+        }
+        Some((ids, dest))
+    }
+
+    fn copy(&mut self, req: &FakeRequest) -> Reply {
+        let Some((ids, dest)) = self.checked_transfer(req) else {
+            return not_found();
+        };
+        let copies: HashMap<_, _> = ids
+            .into_iter()
+            .map(|id| (id, self.copy_node(id, dest)))
+            .collect();
+        ok(json!(copies))
+    }
+
+    fn copy_node(&mut self, id: Uuid, parent: Uuid) -> Uuid {
+        let mut node = self.nodes[&id].clone();
+        let copy = Uuid::new_v4();
+        let children: Vec<_> = self.children(id).iter().map(|child| child.id).collect();
+        node.id = copy;
+        node.parent = Some(parent);
+        node.code = copy.simple().to_string()[..6].into();
+        self.nodes.insert(copy, node);
+        for child in children {
+            self.copy_node(child, copy);
+        }
+        copy
+    }
+
+    fn move_contents(&mut self, req: &FakeRequest) -> Reply {
+        let Some((ids, dest)) = self.checked_transfer(req) else {
+            return not_found();
+        };
+        if ids.iter().any(|&id| self.descendants(id).contains(&dest)) {
+            return error(StatusCode::BAD_REQUEST, "error-badRequest");
+        }
+        for id in &ids {
+            self.nodes.get_mut(id).unwrap().parent = Some(dest);
+        }
+        ok(json!(ids
+            .into_iter()
+            .map(|id| (id, id))
+            .collect::<HashMap<_, _>>()))
+    }
+
+    fn delete(&mut self, req: &FakeRequest) -> Reply {
+        let Some(account) = self.account(req.token()) else {
+            return unauthorized();
+        };
+        let mut statuses = HashMap::new();
+        for id in req.ids("contentsId") {
+            let status = if self.owned(&account, id).is_some() && id != account.root_folder {
+                for id in self.descendants(id) {
+                    self.nodes.remove(&id);
+                }
+                "ok"
+            } else {
+                "error-notFound"
+            };
+            statuses.insert(id, json!({ "status": status }));
+        }
+        ok(json!(statuses))
+    }
+
+    // Anyone with the link may download, a single `bytes=start-[end]` range is honoured
+    fn download(&self, req: &FakeRequest, id: &str) -> Reply {
+        let node = id.parse().ok().and_then(|id: Uuid| self.nodes.get(&id));
+        let Some(file) = node.and_then(|node| node.file.as_ref()) else {
+            return not_found();
+        };
+        let len = file.data.len();
+        let range = req
+            .headers
+            .get(RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("bytes="))
+            .and_then(|range| range.split_once('-'))
+            .and_then(|(start, end)| {
+                let start: usize = start.parse().ok()?;
+                let end = match end {
+                    "" => len.checked_sub(1)?,
+                    end => end.parse::<usize>().ok()?.min(len.checked_sub(1)?),
+                };
+                (start <= end).then_some((start, end))
+            });
+        let res = Response::builder()
+            .header(CONTENT_TYPE, file.mimetype.as_str())
+            .header(ACCEPT_RANGES, "bytes");
+        match range {
+            Some((start, end)) => res
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, len))
+                .body(Body::from(file.data.slice(start..=end))),
+            None => res
+                .status(StatusCode::OK)
+                .body(Body::from(file.data.clone())),
+        }
+        .unwrap()
+    }
+
+    fn upload(
+        &mut self,
+        mut fields: HashMap<String, (Option<String>, Option<String>, Bytes)>,
+    ) -> Reply {
+        if let Some(failure) = self.failures.pop_front() {
+            return Self::failure(failure);
+        }
+        let mut text = |name: &str| {
+            fields
+                .remove(name)
+                .map(|(_, _, value)| String::from_utf8_lossy(&value).into_owned())
+        };
+        let token = text("token");
+        let folder_id = text("folderId");
+        let (account, guest_token) = match token {
+            Some(token) => match self.account(Some(token)) {
+                Some(account) => (account, None),
+                None => return unauthorized(),
+            },
+            None if folder_id.is_some() => return unauthorized(),
+            None => {
+                let account = self.create_account(true);
+                let token = account.token.clone();
+                (account, Some(token))
+            }
+        };
+        let folder = match folder_id {
+            Some(id) => id.parse().ok(),
+            None => Some(account.root_folder),
+        };
+        let Some(folder) = folder.filter(|&id| self.owned_folder(&account, id).is_some()) else {
+            return not_found();
+        };
+        let Some((name, mimetype, data)) = fields.remove("file") else {
+            return error(StatusCode::BAD_REQUEST, "error-badRequest");
+        };
+        let name = name.unwrap_or_else(|| String::from("file"));
+        let mimetype = mimetype.unwrap_or_else(|| String::from("application/octet-stream"));
+        let md5 = hex::encode(Md5::digest(&data));
+        let file = StoredFile { data, mimetype };
+        let id = self.insert_node(Some(folder), account.id, name.clone(), Some(file));
+        let code = &self.nodes[&folder].code;
+        let mut uploaded = json!({
+            "downloadPage": self.url().join(&format!("d/{}", code)).unwrap(),
+            "code": code,
+            "parentFolder": folder,
+            "fileId": id,
+            "fileName": name,
+            "md5": md5,
+        });
+        if let Some(guest_token) = guest_token {
+            uploaded["guestToken"] = json!(guest_token);
+        }
+        ok(uploaded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ContentKind, ContentOpt, Error, NoInfo};
+    use std::time::Duration;
+
+    fn children(content: &crate::Content) -> Vec<String> {
+        let ContentKind::Folder {
+            children: Some(children),
+            ..
+        } = &content.kind
+        else {
+            panic!("not a folder with children");
+        };
+        let mut names: Vec<_> = children.values().map(|child| child.name.clone()).collect();
+        names.sort();
+        names
+    }
+
+    #[tokio::test]
+    async fn fake_gofile_workflow() -> Result<(), Error> {
+        let fake = FakeGofile::start().await;
+        let api = fake.authorized_api();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.txt");
+        std::fs::write(&path, "ggg").unwrap();
+
+        let account_id = api.get_account_id().await?;
+        assert_eq!(account_id, fake.account_id());
+        let details = api.get_account_details(account_id).await?;
+        assert_eq!(details.root_folder, fake.root_folder());
+
+        let folder = api.create_folder(fake.root_folder(), "docs").await?;
+        let server_api = fake.server_api().authorize(fake.token());
+        let uploaded = server_api
+            .upload_file_to_folder(folder.folder_id().unwrap(), &path)
+            .await?;
+        assert_eq!(uploaded.md5, <[u8; 16]>::from(Md5::digest("ggg")));
+        assert_eq!(fake.file_data(uploaded.file_id).unwrap(), "ggg");
+
+        api.set_option::<NoInfo>(
+            uploaded.file_id.into(),
+            ContentOpt::Name("renamed.txt".into()),
+        )
+        .await?;
+        let content = api.get_content_by_id(folder.id).await?;
+        assert_eq!(children(&content), ["renamed.txt"]);
+
+        let archive = api.create_folder(fake.root_folder(), "archive").await?;
+        let copied = api
+            .copy_content(vec![folder.id], archive.folder_id().unwrap())
+            .await?;
+        let copy = copied.contents[&folder.id];
+        api.move_contents(vec![uploaded.file_id.into()], archive.folder_id().unwrap())
+            .await?;
+        assert_eq!(
+            children(&api.get_content_by_id(archive.id).await?),
+            ["docs", "renamed.txt"]
+        );
+        assert_eq!(
+            children(&api.get_content_by_id(copy).await?),
+            ["renamed.txt"]
+        );
+
+        let file = api.get_content_by_id(uploaded.file_id.into()).await?;
+        let dest = dir.path().join("downloaded.txt");
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        api.download_file(&file, &dest, tx).await?;
+        assert_eq!(std::fs::read(&dest).unwrap(), b"ggg");
+
+        let deleted = api.delete_contents(vec![archive.id]).await?;
+        assert!(deleted[&archive.id].is_ok());
+        assert!(fake.file_data(uploaded.file_id).is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fake_gofile_guest_upload() -> Result<(), Error> {
+        let fake = FakeGofile::start().await;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.txt");
+        std::fs::write(&path, "hhh").unwrap();
+
+        let uploaded = fake.server_api().upload_file(&path).await?;
+        let content = fake.api().get_content_by_code(&uploaded.code).await?;
+        assert_eq!(children(&content), ["test.txt"]);
+
+        let guest = fake.api().authorize_guest(&uploaded)?;
+        let folder = guest.create_folder(uploaded.parent_folder, "sub").await?;
+        assert_eq!(folder.parent_folder, Some(uploaded.parent_folder));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fake_gofile_errors() -> Result<(), Error> {
+        let fake = FakeGofile::start().await;
+        let api = fake.authorized_api();
+        let folder = api.create_folder(fake.root_folder(), "secret").await?;
+        api.set_option::<NoInfo>(folder.id, ContentOpt::Password("pass".into()))
+            .await?;
+        let anonymous = fake.api().authorize("someone_else");
+        assert!(matches!(
+            anonymous.get_content_by_code(folder_code(&folder)).await,
+            Err(Error::PasswordRequired(_))
+        ));
+        assert!(matches!(
+            anonymous
+                .get_content_with_password(folder_code(&folder), "wrong")
+                .await,
+            Err(Error::WrongPassword(_))
+        ));
+        anonymous
+            .get_content_with_password(folder_code(&folder), "pass")
+            .await?;
+
+        fake.fail_next(Failure::RateLimit(Some(Duration::from_secs(3))));
+        assert!(matches!(
+            api.get_account_id().await,
+            Err(Error::RateLimited {
+                retry_after: Some(retry_after),
+                ..
+            }) if retry_after == Duration::from_secs(3)
+        ));
+        // the first direct link looks the tier up, the failure goes to the next one's request
+        api.create_direct_link(folder.id).await?;
+        fake.fail_next(Failure::NotPremium);
+        fake.fail_next(Failure::Status(StatusCode::BAD_GATEWAY));
+        assert!(matches!(
+            api.create_direct_link(folder.id).await,
+            Err(Error::NotPremium(_))
+        ));
+        assert!(matches!(
+            api.get_account_id().await,
+            Err(Error::HttpStatusCodeError(_, StatusCode::BAD_GATEWAY, _))
+        ));
+        api.get_account_id().await?;
+        Ok(())
+    }
+
+    fn folder_code(folder: &crate::Content) -> String {
+        match &folder.kind {
+            ContentKind::Folder { code, .. } => code.to_string(),
+            _ => panic!("not a folder"),
+        }
+    }
+}
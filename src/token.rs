@@ -0,0 +1,208 @@
+use crate::Error;
+use serde::{Deserialize, Serialize};
+use std::{env, fmt};
+use url::Url;
+
+pub(crate) const REDACTED: &str = "REDACTED";
+// json fields holding account tokens, in request and response bodies
+pub(crate) const TOKEN_FIELDS: [&str; 3] = ["token", "guestToken", "accountToken"];
+
+// An account token. It's redacted when printed so it can't leak through a log line, use
+// `as_str` where the actual value is needed.
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Token(String);
+
+impl Token {
+    pub fn new(token: impl Into<String>) -> Self {
+        Token(token.into())
+    }
+
+    // Reads the token from the env var `name`, an empty var counts as unset
+    pub fn from_env(name: &str) -> Result<Self, Error> {
+        Self::from_var(name, env::var(name))
+    }
+
+    // The lookup is passed in so tests don't have to touch the process environment
+    fn from_var(name: &str, var: Result<String, env::VarError>) -> Result<Self, Error> {
+        match var {
+            Ok(token) if token.is_empty() => Err(Error::TokenEnvError(
+                name.to_string(),
+                env::VarError::NotPresent,
+            )),
+            Ok(token) => Ok(Token(token)),
+            Err(err) => Err(Error::TokenEnvError(name.to_string(), err)),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for Token {
+    fn from(token: String) -> Self {
+        Token(token)
+    }
+}
+
+impl From<&str> for Token {
+    fn from(token: &str) -> Self {
+        Token(token.to_string())
+    }
+}
+
+impl PartialEq<str> for Token {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Token {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Token(****)")
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Token(****)")
+    }
+}
+
+// Redacts the string values of TOKEN_FIELDS in text that looks like json but may be cut short or
+// not parse at all, like the start of a response body
+pub(crate) fn scrub_tokens(text: &str) -> String {
+    let mut scrubbed = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some((start, end)) = token_value(rest) {
+        scrubbed.push_str(&rest[..start]);
+        scrubbed.push_str(REDACTED);
+        rest = &rest[end..];
+    }
+    scrubbed.push_str(rest);
+    scrubbed
+}
+
+// The url with the values of TOKEN_FIELDS query pairs redacted, for urls kept in errors
+pub(crate) fn redacted_url(url: &Url) -> Url {
+    let mut url = url.clone();
+    if !url
+        .query_pairs()
+        .any(|(key, _)| TOKEN_FIELDS.contains(&key.as_ref()))
+    {
+        return url;
+    }
+    let pairs = url
+        .query_pairs()
+        .map(|(key, value)| {
+            let value = if TOKEN_FIELDS.contains(&key.as_ref()) {
+                REDACTED.to_string()
+            } else {
+                value.into_owned()
+            };
+            (key.into_owned(), value)
+        })
+        .collect::<Vec<_>>();
+    url.query_pairs_mut().clear().extend_pairs(pairs);
+    url
+}
+
+// Byte range of the first token value in text, between its quotes or up to the end when the
+// closing quote was cut off
+fn token_value(text: &str) -> Option<(usize, usize)> {
+    let mut from = 0;
+    loop {
+        let (_, key_end) = TOKEN_FIELDS
+            .iter()
+            .filter_map(|field| {
+                let key = format!("\"{}\"", field);
+                let start = from + text[from..].find(&key)?;
+                Some((start, start + key.len()))
+            })
+            .min()?;
+        let value = text[key_end..]
+            .trim_start()
+            .strip_prefix(':')
+            .map(str::trim_start)
+            .and_then(|value| value.strip_prefix('"'));
+        if let Some(value) = value {
+            let start = text.len() - value.len();
+            let mut escaped = false;
+            let len = value
+                .char_indices()
+                .find(|&(_, c)| {
+                    let closing = !escaped && c == '"';
+                    escaped = !escaped && c == '\\';
+                    closing
+                })
+                .map_or(value.len(), |(i, _)| i);
+            return Some((start, start + len));
+        }
+        from = key_end;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{from_value, json, to_value};
+
+    #[test]
+    fn token() {
+        let token = Token::from("gofile_token");
+        assert_eq!(token, "gofile_token");
+        assert_eq!(token.as_str(), "gofile_token");
+        assert_eq!(format!("{:?}", token), "Token(****)");
+        assert_eq!(token.to_string(), "Token(****)");
+        assert_eq!(
+            format!("{:?}", Some(Token::new("gofile_token"))),
+            "Some(Token(****))"
+        );
+        assert_eq!(to_value(&token).unwrap(), json!("gofile_token"));
+        assert_eq!(from_value::<Token>(json!("gofile_token")).unwrap(), token);
+
+        let name = "GOFILE_TOKEN";
+        assert_eq!(
+            Token::from_var(name, Ok("gofile_token".to_string())).unwrap(),
+            token
+        );
+        for var in [Ok(String::new()), Err(env::VarError::NotPresent)] {
+            assert!(matches!(
+                Token::from_var(name, var),
+                Err(Error::TokenEnvError(name, env::VarError::NotPresent)) if name == "GOFILE_TOKEN"
+            ));
+        }
+    }
+
+    #[test]
+    fn redact_url() {
+        let url = Url::parse("https://api.gofile.io/contents/abc?token=secret&page=2").unwrap();
+        assert_eq!(
+            redacted_url(&url).as_str(),
+            "https://api.gofile.io/contents/abc?token=REDACTED&page=2"
+        );
+        let url = Url::parse("https://api.gofile.io/contents/abc?wt=4fd6sg89d7s6").unwrap();
+        assert_eq!(redacted_url(&url), url);
+    }
+
+    #[test]
+    fn scrub() {
+        assert_eq!(
+            scrub_tokens(r#"{"token": "abc", "name": "token", "guestToken":"d\"ef", "x": 1}"#),
+            r#"{"token": "REDACTED", "name": "token", "guestToken":"REDACTED", "x": 1}"#
+        );
+        assert_eq!(
+            scrub_tokens(r#"{"data": {"accountToken": "abc"#),
+            r#"{"data": {"accountToken": "REDACTED"#
+        );
+        assert_eq!(scrub_tokens(r#"{"token": null}"#), r#"{"token": null}"#);
+        assert_eq!(scrub_tokens("not json"), "not json");
+    }
+}
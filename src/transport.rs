@@ -0,0 +1,209 @@
+use crate::Error;
+use futures::future::BoxFuture;
+use reqwest::{Client, Request, Response};
+use std::fmt::Debug;
+
+// Sends the requests built by ApiClient. Every request of an Api and of everything derived
+// from it goes through the same transport, so swapping it swaps the network for all of them.
+pub trait Transport: Debug + Send + Sync {
+    fn execute(&self, req: Request) -> BoxFuture<'static, Result<Response, Error>>;
+}
+
+#[derive(Clone, Debug)]
+pub struct ReqwestTransport(pub Client);
+
+impl Transport for ReqwestTransport {
+    fn execute(&self, req: Request) -> BoxFuture<'static, Result<Response, Error>> {
+        let send = self.0.execute(req);
+        Box::pin(async move { Ok(send.await?) })
+    }
+}
+
+#[cfg(test)]
+pub(crate) use mock::*;
+
+#[cfg(test)]
+mod mock {
+    use super::*;
+    use reqwest::{Method, ResponseBuilderExt, StatusCode};
+    use std::{collections::VecDeque, sync::Mutex};
+    use url::Url;
+
+    #[derive(Clone, Debug)]
+    pub(crate) struct RecordedRequest {
+        pub method: Method,
+        pub url: Url,
+        pub authorization: Option<String>,
+        // None for streamed bodies like uploads
+        pub body: Option<Vec<u8>>,
+    }
+
+    // Answers with canned responses in order and keeps every request it was given
+    #[derive(Debug, Default)]
+    pub(crate) struct MockTransport {
+        responses: Mutex<VecDeque<(StatusCode, String)>>,
+        pub requests: Mutex<Vec<RecordedRequest>>,
+    }
+
+    impl MockTransport {
+        pub fn respond(&self, status: StatusCode, body: impl Into<String>) -> &Self {
+            self.responses
+                .lock()
+                .unwrap()
+                .push_back((status, body.into()));
+            self
+        }
+
+        pub fn requests(&self) -> Vec<RecordedRequest> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    impl Transport for MockTransport {
+        fn execute(&self, req: Request) -> BoxFuture<'static, Result<Response, Error>> {
+            self.requests.lock().unwrap().push(RecordedRequest {
+                method: req.method().clone(),
+                url: req.url().clone(),
+                authorization: req
+                    .headers()
+                    .get(reqwest::header::AUTHORIZATION)
+                    .and_then(|value| value.to_str().ok())
+                    .map(String::from),
+                body: req.body().and_then(|body| body.as_bytes()).map(Vec::from),
+            });
+            let (status, body) = self
+                .responses
+                .lock()
+                .unwrap()
+                .pop_front()
+                .expect("a canned response for every request");
+            let res = http::Response::builder()
+                .status(status)
+                .url(req.url().clone())
+                .body(body)
+                .unwrap();
+            Box::pin(async move { Ok(Response::from(res)) })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Api, ApiClient, ContentId, ContentOpt, ZonePreference};
+    use reqwest::{Method, StatusCode};
+    use std::sync::Arc;
+    use uuid::uuid;
+
+    fn mock_api() -> (Api, Arc<MockTransport>) {
+        let transport = Arc::new(MockTransport::default());
+        let api = Api {
+            client: ApiClient::default().with_transport(transport.clone()),
+            ..Default::default()
+        };
+        (api, transport)
+    }
+
+    #[tokio::test]
+    async fn mock_transport() -> Result<(), Error> {
+        let (api, transport) = mock_api();
+        transport
+            .respond(
+                StatusCode::OK,
+                r#"{"status":"ok","data":{"servers":[{"name":"store1","zone":"eu"}]}}"#,
+            )
+            .respond(StatusCode::OK, r#"{ "status": "ok", "data": {} }"#)
+            .respond(
+                StatusCode::OK,
+                r#"{"status":"ok","data":{"id":"00000000-0000-0000-0000-000000000009"}}"#,
+            )
+            .respond(
+                StatusCode::OK,
+                serde_json::json!({
+                    "status": "ok",
+                    "data": {
+                        "id": "00000000-0000-0000-0000-000000000009",
+                        "token": "gofile_token",
+                        "email": "foo@example.com",
+                        "tier": "premium",
+                        "rootFolder": "00000000-0000-0000-0000-000000000004",
+                        "filesCount": 0,
+                        "totalSize": 0,
+                    }
+                })
+                .to_string(),
+            )
+            .respond(
+                StatusCode::UNAUTHORIZED,
+                r#"{ "status": "error-notPremium", "data": {} }"#,
+            );
+
+        let server_api = api.get_server(ZonePreference::Any).await?;
+        assert_eq!(server_api.base_url.as_str(), "https://store1.gofile.io/");
+        let authorized = api.authorize("gofile_token");
+        let content_id = ContentId(uuid!("00000000-0000-0000-0000-000000000001"));
+        authorized
+            .set_option::<serde_json::Value>(content_id, ContentOpt::Public(true))
+            .await?;
+        let result = authorized.create_direct_link(content_id).await;
+        assert!(matches!(result, Err(Error::NotPremium(_))));
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 5);
+        assert_eq!(requests[0].method, Method::GET);
+        assert_eq!(requests[0].url.as_str(), "https://api.gofile.io/servers");
+        assert_eq!(requests[1].method, Method::PUT);
+        assert_eq!(
+            requests[1].url.path(),
+            "/contents/00000000-0000-0000-0000-000000000001/update"
+        );
+        assert_eq!(
+            requests[1].authorization.as_deref(),
+            Some("Bearer gofile_token")
+        );
+        let body: serde_json::Value =
+            serde_json::from_slice(requests[1].body.as_deref().unwrap()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({ "attribute": "public", "attributeValue": "true" })
+        );
+        // the tier lookup in front of the direct link
+        assert_eq!(requests[2].method, Method::GET);
+        assert_eq!(requests[2].url.path(), "/accounts/getid");
+        assert_eq!(requests[2].url.query(), Some("token=gofile_token"));
+        assert_eq!(requests[3].method, Method::GET);
+        assert_eq!(
+            requests[3].url.path(),
+            "/accounts/00000000-0000-0000-0000-000000000009"
+        );
+        assert_eq!(requests[4].method, Method::POST);
+        assert_eq!(
+            requests[4].url.path(),
+            "/contents/00000000-0000-0000-0000-000000000001/directlinks"
+        );
+        assert_eq!(
+            requests[4].authorization.as_deref(),
+            Some("Bearer gofile_token")
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn mock_transport_errors() {
+        let (api, transport) = mock_api();
+        transport
+            .respond(StatusCode::BAD_GATEWAY, "<html>bad gateway</html>")
+            .respond(StatusCode::OK, "not json");
+
+        let err = api.get_server(ZonePreference::Any).await.unwrap_err();
+        assert!(matches!(
+            &err,
+            Error::HttpStatusCodeError(_, StatusCode::BAD_GATEWAY, _)
+        ));
+        assert_eq!(err.context().unwrap().body, "<html>bad gateway</html>");
+        assert!(matches!(
+            api.get_server(ZonePreference::Any).await,
+            Err(Error::Decode { .. })
+        ));
+    }
+}
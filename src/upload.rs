@@ -0,0 +1,2486 @@
+use crate::{
+    download::same_file, progress::channel_sink, Api, AuthorizedApi, AuthorizedServerApi, Code,
+    Content, ContentKind, ContentOpt, Error, FileId, FolderId, NoInfo, ServerApi, Token,
+    UploadedFile, UploadedMessage, ZonePreference,
+};
+use chrono::{DateTime, Utc};
+use futures::{stream, StreamExt};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use md5::{Digest, Md5 as Md5Hasher};
+use mime::Mime;
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, OnceLock,
+    },
+    time::Duration,
+};
+use tokio::{
+    fs,
+    sync::mpsc::{unbounded_channel, UnboundedSender},
+};
+use url::Url;
+use uuid::Uuid;
+
+pub const DEFAULT_CHUNK_SIZE: usize = 256 * 1024;
+// every running upload holds a buffer this big at most
+pub const MAX_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+#[derive(Clone, Debug)]
+pub struct UploadOptions {
+    // hash the file while it's sent and compare with the md5 reported by gofile
+    pub verify_md5: bool,
+    // size of the reads from the file, also how often progress is reported
+    pub chunk_size: usize,
+    // content type of the file part, gofile guesses it from the extension otherwise
+    pub mime: Option<Mime>,
+    // sent as is next to the file, for form fields this crate doesn't know about
+    pub extra_fields: Vec<(String, String)>,
+    // a progress message needs both this many new bytes and this much time since the last one
+    pub progress_bytes: u64,
+    pub progress_interval: Duration,
+    // treat a dropped progress receiver as a cancellation instead of just going quiet
+    pub abort_on_closed_progress: bool,
+    pub filename_policy: FilenamePolicy,
+}
+
+// What to do with file names that aren't valid utf-8
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FilenamePolicy {
+    #[default]
+    Strict,
+    // invalid sequences are replaced with U+FFFD
+    Lossy,
+    // like Lossy, with a short hash of the original bytes so distinct names stay distinct
+    LossyWithHash,
+}
+
+impl Default for UploadOptions {
+    fn default() -> Self {
+        Self {
+            verify_md5: true,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            mime: None,
+            extra_fields: Vec::new(),
+            progress_bytes: 64 * 1024,
+            progress_interval: Duration::from_millis(100),
+            abort_on_closed_progress: false,
+            filename_policy: FilenamePolicy::Strict,
+        }
+    }
+}
+
+impl UploadOptions {
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    pub fn progress_interval(mut self, bytes: u64, interval: Duration) -> Self {
+        self.progress_bytes = bytes;
+        self.progress_interval = interval;
+        self
+    }
+
+    pub fn mime_str(mut self, mime: &str) -> Result<Self, Error> {
+        let parsed = mime
+            .parse()
+            .map_err(|err| Error::InvalidMime(mime.into(), format!("{}", err)))?;
+        self.mime = Some(parsed);
+        Ok(self)
+    }
+
+    pub fn extra_field(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extra_fields.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn filename_policy(mut self, policy: FilenamePolicy) -> Self {
+        self.filename_policy = policy;
+        self
+    }
+
+    pub(crate) fn checked_chunk_size(&self) -> Result<usize, Error> {
+        match self.chunk_size {
+            0 => Err(Error::InvalidChunkSize(0)),
+            chunk_size => Ok(chunk_size.min(MAX_CHUNK_SIZE)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct UploadDirOptions {
+    // glob patterns matched against the path relative to the uploaded directory,
+    // an empty list uploads every file
+    pub include: Vec<String>,
+    // excluded directories are skipped along with everything inside them
+    pub exclude: Vec<String>,
+    pub symlinks: SymlinkPolicy,
+    pub concurrency: usize,
+    // fail before uploading anything when the files don't fit in the account storage
+    pub check_quota: bool,
+}
+
+impl Default for UploadDirOptions {
+    fn default() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            symlinks: SymlinkPolicy::Skip,
+            concurrency: 4,
+            check_quota: false,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct UploadDirManifest {
+    // keyed by the path relative to the uploaded directory
+    pub uploaded: BTreeMap<PathBuf, UploadedFile>,
+    // symlinks left out by the policy, sockets, fifos and devices
+    pub ignored: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, Error)>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    #[default]
+    Skip,
+    // uploads links as their targets, a link loop is only stopped by the os limit on nested links
+    Follow,
+    // like Follow, but a directory already walked through another path is ignored
+    FollowWithCycleDetection,
+}
+
+pub(crate) enum DirEntryKind {
+    File,
+    Dir,
+    Ignored,
+    // a link pointing nowhere
+    Broken(Error),
+}
+
+// Remembers the directories walked so far when cycles have to be detected
+pub(crate) struct VisitedDirs {
+    policy: SymlinkPolicy,
+    dirs: HashSet<PathBuf>,
+}
+
+// Stops `upload_many` from starting new uploads, the ones already running are let finish
+#[derive(Clone, Debug, Default)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// Uploads are retried after the errors Error::is_retryable accepts and after corrupted
+// transfers, any other error is returned right away. A Retry-After sent by the
+// server replaces the backoff for that attempt
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    // including the first attempt
+    pub max_attempts: u32,
+    // doubled after every failed attempt, up to max_backoff
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+            .min(self.max_backoff)
+    }
+
+    // A delay asked for by the server wins over the computed backoff
+    fn delay(&self, attempt: u32, err: &Error) -> Duration {
+        match err {
+            Error::RateLimited {
+                retry_after: Some(retry_after),
+                ..
+            } => *retry_after,
+            _ => self.backoff(attempt),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct UploadManyOptions {
+    pub concurrency: usize,
+    pub retry: RetryPolicy,
+    pub cancel: CancelHandle,
+    // account checked for enough storage before the first upload starts
+    pub check_quota: Option<AuthorizedApi>,
+}
+
+impl Default for UploadManyOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            retry: RetryPolicy::default(),
+            cancel: CancelHandle::default(),
+            check_quota: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuotaStatus {
+    // the account doesn't report a storage limit
+    Unlimited,
+    // bytes left after the required ones are stored
+    Available(u64),
+    Exceeded { needed: u64, available: u64 },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum UploadQueueMessage {
+    File(UploadedMessage),
+    Total {
+        files_done: usize,
+        files_total: usize,
+        bytes_done: u64,
+        bytes_total: u64,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct UploadTarget {
+    // tried in order until one of them accepts the file
+    pub servers: Vec<AuthorizedServerApi>,
+    pub max_servers: usize,
+    pub retry: RetryPolicy,
+}
+
+impl Default for UploadTarget {
+    fn default() -> Self {
+        Self {
+            servers: Vec::new(),
+            max_servers: 3,
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+// Anonymous uploads that all land in the folder created by the first one, reusing the guest
+// token gofile handed out with it
+#[derive(Debug)]
+pub struct UploadSession {
+    server: ServerApi,
+    state: OnceLock<SessionState>,
+    // concurrent uploads wait for the first one instead of each creating a folder
+    first_upload: tokio::sync::Mutex<()>,
+}
+
+#[derive(Debug)]
+struct SessionState {
+    guest_token: Option<Token>,
+    folder_id: FolderId,
+    download_page: Url,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ShareOptions {
+    pub expire: Option<DateTime<Utc>>,
+    pub password: Option<String>,
+    // None leaves the folder as gofile created it, anonymous uploads are public
+    pub public: Option<bool>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShareResult {
+    pub download_page: Url,
+    pub code: Code,
+    pub folder_id: FolderId,
+    pub file_id: FileId,
+    // owns the folder of an anonymous share, needed to change or delete it later
+    pub guest_token: Option<Token>,
+}
+
+impl From<UploadedFile> for ShareResult {
+    fn from(uploaded: UploadedFile) -> Self {
+        Self {
+            download_page: uploaded.download_page,
+            code: uploaded.code,
+            folder_id: uploaded.parent_folder,
+            file_id: uploaded.file_id,
+            guest_token: uploaded.guest_token,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DedupUpload {
+    Uploaded(UploadedFile),
+    // a file with the same name and md5, nothing was uploaded
+    AlreadyPresent(Content),
+    // a file with the same name but other content, the folder has both now
+    UploadedNameConflict {
+        uploaded: UploadedFile,
+        existing: Box<Content>,
+    },
+}
+
+// Where share_file stopped, the steps after Upload fail with a download page already existing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShareStep {
+    Server,
+    Upload,
+    Expire,
+    Password,
+    Public,
+}
+
+impl ShareStep {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShareStep::Server => "server",
+            ShareStep::Upload => "upload",
+            ShareStep::Expire => "expire",
+            ShareStep::Password => "password",
+            ShareStep::Public => "public",
+        }
+    }
+
+    fn failed(self, uploaded: Option<UploadedFile>, source: Error) -> Error {
+        Error::ShareFailed {
+            step: self,
+            uploaded: uploaded.map(Box::new),
+            source: Box::new(source),
+        }
+    }
+}
+
+impl std::fmt::Display for ShareStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// Lifecycle of a single transfer, every Started is followed by either Completed or Failed
+#[derive(Clone, Debug, PartialEq)]
+pub enum TransferEvent {
+    Started {
+        uuid: Uuid,
+        name: String,
+        total: Option<u64>,
+    },
+    Progress {
+        uuid: Uuid,
+        bytes: u64,
+    },
+    Completed {
+        uuid: Uuid,
+        result: Box<UploadedFile>,
+    },
+    Failed {
+        uuid: Uuid,
+        error: String,
+    },
+}
+
+struct PlannedFile {
+    path: PathBuf,
+    relative: PathBuf,
+    folder_id: FolderId,
+}
+
+impl AuthorizedServerApi {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            fields(local_dir = ?local_dir.as_ref(), dest_folder = %dest_folder)
+        )
+    )]
+    pub async fn upload_dir(
+        &self,
+        api: &AuthorizedApi,
+        local_dir: impl AsRef<Path>,
+        dest_folder: FolderId,
+        options: UploadDirOptions,
+        tx: Option<UnboundedSender<UploadedMessage>>,
+    ) -> Result<UploadDirManifest, Error> {
+        let mut manifest = UploadDirManifest::default();
+        if options.check_quota {
+            // sized on a dry run of the walk, so no folder is created when the files don't fit
+            let files = plan_dir(
+                None,
+                local_dir.as_ref(),
+                dest_folder,
+                &options,
+                &mut UploadDirManifest::default(),
+            )
+            .await?;
+            let mut needed = 0;
+            for file in &files {
+                needed += fs::metadata(&file.path)
+                    .await
+                    .map_or(0, |metadata| metadata.len());
+            }
+            api.ensure_quota(needed).await?;
+        }
+
+        let files = plan_dir(
+            Some(api),
+            local_dir.as_ref(),
+            dest_folder,
+            &options,
+            &mut manifest,
+        )
+        .await?;
+
+        let tx = &tx;
+        let results = stream::iter(files)
+            .map(|file| async move {
+                let result = self
+                    .upload_file_to_folder_with_progress(file.folder_id, &file.path, tx.clone())
+                    .await;
+                (file.relative, result)
+            })
+            .buffer_unordered(options.concurrency.max(1))
+            .collect::<Vec<_>>()
+            .await;
+
+        for (relative, result) in results {
+            match result {
+                Ok(uploaded) => {
+                    manifest.uploaded.insert(relative, uploaded);
+                }
+                Err(err) => manifest.failed.push((relative, err)),
+            }
+        }
+        manifest.failed.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(manifest)
+    }
+}
+
+impl AuthorizedServerApi {
+    // The results are in the same order as `files`
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(folder_id = %folder_id)))]
+    pub async fn upload_many(
+        &self,
+        files: Vec<PathBuf>,
+        folder_id: FolderId,
+        options: UploadManyOptions,
+        tx: Option<UnboundedSender<UploadQueueMessage>>,
+    ) -> Vec<Result<UploadedFile, Error>> {
+        let mut sizes = Vec::with_capacity(files.len());
+        for path in &files {
+            sizes.push(
+                fs::metadata(path)
+                    .await
+                    .map_or(0, |metadata| metadata.len()),
+            );
+        }
+
+        let files_total = files.len();
+        let bytes_total = sizes.iter().sum();
+        let files_done = &AtomicUsize::new(0);
+        let bytes_done = &AtomicU64::new(0);
+        let tx = &tx;
+        let send_total = move || {
+            if let Some(tx) = tx {
+                let _ = tx.send(UploadQueueMessage::Total {
+                    files_done: files_done.load(Ordering::Relaxed),
+                    files_total,
+                    bytes_done: bytes_done.load(Ordering::Relaxed),
+                    bytes_total,
+                });
+            }
+        };
+        if let Some(api) = &options.check_quota {
+            if let Err(err) = api.ensure_quota(bytes_total).await {
+                // the first file carries the reason, none of them was attempted
+                let mut err = Some(err);
+                return files
+                    .into_iter()
+                    .map(|path| Err(err.take().unwrap_or(Error::UploadCancelled(path))))
+                    .collect();
+            }
+        }
+        send_total();
+
+        let options = &options;
+        let mut results = stream::iter(files.into_iter().enumerate())
+            .map(|(index, path)| async move {
+                if options.cancel.is_cancelled() {
+                    return (index, Err(Error::UploadCancelled(path)));
+                }
+
+                let (file_tx, mut file_rx) = unbounded_channel();
+                let upload = self.upload_path_with_retry(
+                    folder_id,
+                    &path,
+                    options.retry.clone(),
+                    Some(file_tx),
+                );
+                let forward = async {
+                    let mut last = 0;
+                    while let Some(message) = file_rx.recv().await {
+                        // a retry sends the bytes of the failed attempt again
+                        if message.retry && message.uploaded == 0 {
+                            bytes_done.fetch_sub(last, Ordering::Relaxed);
+                            last = 0;
+                        }
+                        bytes_done.fetch_add(message.uploaded - last, Ordering::Relaxed);
+                        last = message.uploaded;
+                        if let Some(tx) = tx {
+                            let _ = tx.send(UploadQueueMessage::File(message));
+                        }
+                        send_total();
+                    }
+                };
+                let (result, ()) = futures::join!(upload, forward);
+                files_done.fetch_add(1, Ordering::Relaxed);
+                send_total();
+                (index, result)
+            })
+            .buffer_unordered(options.concurrency.max(1))
+            .collect::<Vec<_>>()
+            .await;
+
+        results.sort_by_key(|(index, _)| *index);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+
+    // The file is opened again for every attempt, all progress messages share one uuid
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(folder_id = %folder_id, path = ?path.as_ref()))
+    )]
+    pub async fn upload_path_with_retry(
+        &self,
+        folder_id: FolderId,
+        path: impl AsRef<Path>,
+        policy: RetryPolicy,
+        tx: Option<UnboundedSender<UploadedMessage>>,
+    ) -> Result<UploadedFile, Error> {
+        self.upload_path_with_retry_impl(
+            folder_id,
+            path.as_ref(),
+            &policy,
+            Uuid::new_v4(),
+            false,
+            &tx,
+        )
+        .await
+    }
+
+    // `restarted` flags every message as a retry, for uploads moved over from another server
+    async fn upload_path_with_retry_impl(
+        &self,
+        folder_id: FolderId,
+        path: &Path,
+        policy: &RetryPolicy,
+        uuid: Uuid,
+        restarted: bool,
+        tx: &Option<UnboundedSender<UploadedMessage>>,
+    ) -> Result<UploadedFile, Error> {
+        let mut attempt = 1;
+        loop {
+            let retry = restarted || attempt > 1;
+            let (attempt_tx, mut attempt_rx) = unbounded_channel();
+            let upload =
+                self.upload_file_to_folder_with_progress(folder_id, path, Some(attempt_tx));
+            let forward = async {
+                let mut total = None;
+                while let Some(message) = attempt_rx.recv().await {
+                    total = message.total;
+                    if let Some(tx) = tx {
+                        let _ = tx.send(UploadedMessage {
+                            uuid,
+                            retry,
+                            ..message
+                        });
+                    }
+                }
+                total
+            };
+            let (result, total) = futures::join!(upload, forward);
+
+            match result {
+                Err(err) if attempt < policy.max_attempts && is_retryable(&err) => {
+                    let delay = policy.delay(attempt, &err);
+                    event!(warn, attempt, ?delay, error = %err, "retrying the upload");
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                    if let Some(tx) = tx {
+                        let _ = tx.send(restart_message(uuid, path, total));
+                    }
+                }
+                result => return result,
+            }
+        }
+    }
+
+    // Event based counterpart of upload_file_to_folder_with_options, which keeps sending plain
+    // UploadedMessages. Failed is sent before the error is returned.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(folder_id = %folder_id, path = ?path.as_ref()))
+    )]
+    pub async fn upload_file_to_folder_with_events(
+        &self,
+        folder_id: FolderId,
+        path: impl AsRef<Path>,
+        options: UploadOptions,
+        tx: UnboundedSender<TransferEvent>,
+    ) -> Result<UploadedFile, Error> {
+        let path = path.as_ref();
+        let uuid = Uuid::new_v4();
+        let _ = tx.send(TransferEvent::Started {
+            uuid,
+            name: path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into(),
+            total: fs::metadata(path).await.ok().map(|metadata| metadata.len()),
+        });
+
+        let (progress_tx, mut progress_rx) = unbounded_channel();
+        let upload =
+            self.upload_file_to_folder_with_options(folder_id, path, Some(progress_tx), options);
+        let forward = async {
+            while let Some(message) = progress_rx.recv().await {
+                let _ = tx.send(TransferEvent::Progress {
+                    uuid,
+                    bytes: message.uploaded,
+                });
+            }
+        };
+        let (result, ()) = futures::join!(upload, forward);
+
+        let _ = tx.send(match &result {
+            Ok(uploaded) => TransferEvent::Completed {
+                uuid,
+                result: Box::new(uploaded.clone()),
+            },
+            Err(err) => TransferEvent::Failed {
+                uuid,
+                error: err.to_string(),
+            },
+        });
+        result
+    }
+}
+
+impl ServerApi {
+    pub fn session(&self) -> UploadSession {
+        UploadSession {
+            server: self.clone(),
+            state: OnceLock::new(),
+            first_upload: tokio::sync::Mutex::new(()),
+        }
+    }
+}
+
+impl UploadSession {
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(path = ?path.as_ref())))]
+    pub async fn upload_file(&self, path: impl AsRef<Path>) -> Result<UploadedFile, Error> {
+        self.upload_file_with_options(path, None, UploadOptions::default())
+            .await
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(path = ?path.as_ref())))]
+    pub async fn upload_file_with_options(
+        &self,
+        path: impl AsRef<Path>,
+        tx: Option<UnboundedSender<UploadedMessage>>,
+        options: UploadOptions,
+    ) -> Result<UploadedFile, Error> {
+        let path = path.as_ref();
+        let sink = channel_sink(tx);
+        let state = match self.state.get() {
+            Some(state) => state,
+            None => {
+                let _first_upload = self.first_upload.lock().await;
+                match self.state.get() {
+                    Some(state) => state,
+                    None => {
+                        let uploaded = self
+                            .server
+                            .upload_path_impl(path, None, None, sink, &options)
+                            .await?;
+                        let _ = self.state.set(SessionState {
+                            guest_token: uploaded.guest_token.clone(),
+                            folder_id: uploaded.parent_folder,
+                            download_page: uploaded.download_page.clone(),
+                        });
+                        return Ok(uploaded);
+                    }
+                }
+            }
+        };
+        self.server
+            .upload_path_impl(
+                path,
+                Some(state.folder_id),
+                state.guest_token.clone(),
+                sink,
+                &options,
+            )
+            .await
+    }
+
+    // None until the first upload went through
+    pub fn download_page(&self) -> Option<&Url> {
+        self.state.get().map(|state| &state.download_page)
+    }
+
+    pub fn guest_token(&self) -> Option<&Token> {
+        self.state.get()?.guest_token.as_ref()
+    }
+}
+
+impl Api {
+    // Uploads the file anonymously to a new folder and sets the options on that folder with
+    // the guest token, one at a time
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(path = ?path.as_ref())))]
+    pub async fn share_file(
+        &self,
+        path: impl AsRef<Path>,
+        options: ShareOptions,
+    ) -> Result<ShareResult, Error> {
+        let server = self
+            .get_server(ZonePreference::Any)
+            .await
+            .map_err(|err| ShareStep::Server.failed(None, err))?;
+        self.share_file_on(&server, path, options).await
+    }
+
+    // Like share_file, on a server the caller picked
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(path = ?path.as_ref())))]
+    pub async fn share_file_on(
+        &self,
+        server: &ServerApi,
+        path: impl AsRef<Path>,
+        options: ShareOptions,
+    ) -> Result<ShareResult, Error> {
+        let uploaded = server
+            .upload_file(path)
+            .await
+            .map_err(|err| ShareStep::Upload.failed(None, err))?;
+        match self.authorize_guest(&uploaded) {
+            Ok(api) => api.set_share_options(uploaded, &options).await,
+            Err(err) => Err(ShareStep::Upload.failed(Some(uploaded), err)),
+        }
+    }
+}
+
+impl AuthorizedApi {
+    // Like Api::share_file, the new folder belongs to the account
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(path = ?path.as_ref())))]
+    pub async fn share_file(
+        &self,
+        path: impl AsRef<Path>,
+        options: ShareOptions,
+    ) -> Result<ShareResult, Error> {
+        let server = self
+            .get_server(ZonePreference::Any)
+            .await
+            .map_err(|err| ShareStep::Server.failed(None, err))?;
+        self.share_file_on(&server, path, options).await
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(path = ?path.as_ref())))]
+    pub async fn share_file_on(
+        &self,
+        server: &AuthorizedServerApi,
+        path: impl AsRef<Path>,
+        options: ShareOptions,
+    ) -> Result<ShareResult, Error> {
+        let uploaded = server
+            .upload_file(path)
+            .await
+            .map_err(|err| ShareStep::Upload.failed(None, err))?;
+        self.set_share_options(uploaded, &options).await
+    }
+
+    async fn set_share_options(
+        &self,
+        uploaded: UploadedFile,
+        options: &ShareOptions,
+    ) -> Result<ShareResult, Error> {
+        let folder_id = uploaded.parent_folder.into();
+        let steps = [
+            options
+                .expire
+                .map(|expire| (ShareStep::Expire, ContentOpt::Expire(expire))),
+            options
+                .password
+                .clone()
+                .map(|password| (ShareStep::Password, ContentOpt::Password(password))),
+            options
+                .public
+                .map(|public| (ShareStep::Public, ContentOpt::Public(public))),
+        ];
+        for (step, opt) in steps.into_iter().flatten() {
+            if let Err(err) = self.set_option::<NoInfo>(folder_id, opt).await {
+                return Err(step.failed(Some(uploaded), err));
+            }
+        }
+        Ok(uploaded.into())
+    }
+
+    // Skips the upload when the folder already has a file with the same name and md5, the
+    // local file is only hashed when a name matches
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(folder_id = %folder_id, path = ?path.as_ref()))
+    )]
+    pub async fn upload_unless_exists(
+        &self,
+        server: &ServerApi,
+        path: impl AsRef<Path>,
+        folder_id: FolderId,
+    ) -> Result<DedupUpload, Error> {
+        let path = path.as_ref();
+        let options = UploadOptions::default();
+        let name = upload_filename(path, options.filename_policy)?;
+        let folder = self.get_content_by_id(folder_id.into()).await?;
+        let ContentKind::Folder { children, .. } = folder.kind else {
+            return Err(Error::NotAFolder(folder_id.into()));
+        };
+
+        let mut conflict = None;
+        for child in children.unwrap_or_default().into_values() {
+            if child.name != name || !matches!(child.kind, ContentKind::File { .. }) {
+                continue;
+            }
+            if same_file(&child, path).await? {
+                return Ok(DedupUpload::AlreadyPresent(child));
+            }
+            conflict = Some(child);
+        }
+
+        let uploaded = server
+            .upload_path_impl(
+                path,
+                Some(folder_id),
+                Some(self.token.clone()),
+                None,
+                &options,
+            )
+            .await?;
+        Ok(match conflict {
+            Some(existing) => {
+                event!(
+                    warn,
+                    name,
+                    "uploaded next to a different file of the same name"
+                );
+                DedupUpload::UploadedNameConflict {
+                    uploaded,
+                    existing: Box::new(existing),
+                }
+            }
+            None => DedupUpload::Uploaded(uploaded),
+        })
+    }
+}
+
+impl AuthorizedApi {
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn get_upload_target(&self) -> Result<UploadTarget, Error> {
+        let api = self.server_list_api();
+        let servers = api.get_servers().await?;
+        Ok(UploadTarget {
+            servers: servers
+                .all()
+                .into_iter()
+                .map(|server| Ok(api.server(server)?.authorize(self.token.clone())))
+                .collect::<Result<_, Error>>()?,
+            ..Default::default()
+        })
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(required_bytes))
+    )]
+    pub async fn check_quota(&self, required_bytes: u64) -> Result<QuotaStatus, Error> {
+        let account_id = self.get_account_id().await?;
+        let details = self.get_account_details(account_id).await?;
+        let Some(available) = details.remaining_storage() else {
+            return Ok(QuotaStatus::Unlimited);
+        };
+        if required_bytes > available {
+            Ok(QuotaStatus::Exceeded {
+                needed: required_bytes,
+                available,
+            })
+        } else {
+            Ok(QuotaStatus::Available(available - required_bytes))
+        }
+    }
+
+    async fn ensure_quota(&self, required_bytes: u64) -> Result<(), Error> {
+        match self.check_quota(required_bytes).await? {
+            QuotaStatus::Exceeded { needed, available } => {
+                Err(Error::QuotaExceeded { needed, available })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl UploadTarget {
+    // Each server gets the full retry policy before the upload moves on to the next one
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(folder_id = %folder_id, path = ?path.as_ref()))
+    )]
+    pub async fn upload_file_to_folder(
+        &self,
+        folder_id: FolderId,
+        path: impl AsRef<Path>,
+        tx: Option<UnboundedSender<UploadedMessage>>,
+    ) -> Result<UploadedFile, Error> {
+        let path = path.as_ref();
+        let uuid = Uuid::new_v4();
+        let servers = self.servers.iter().take(self.max_servers.max(1));
+        let mut last_err = Error::EmptyServerList;
+        for (index, server) in servers.enumerate() {
+            let restarted = index > 0;
+            if let (true, Some(tx)) = (restarted, &tx) {
+                let _ = tx.send(restart_message(uuid, path, None));
+            }
+            match server
+                .upload_path_with_retry_impl(folder_id, path, &self.retry, uuid, restarted, &tx)
+                .await
+            {
+                Err(err) if is_server_failure(&err) => last_err = err,
+                result => return result,
+            }
+        }
+        Err(last_err)
+    }
+}
+
+fn restart_message(uuid: Uuid, path: &Path, total: Option<u64>) -> UploadedMessage {
+    UploadedMessage {
+        uuid,
+        uploaded: 0,
+        total,
+        retry: true,
+        file_name: path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into(),
+        elapsed: Duration::ZERO,
+        bytes_per_sec: 0,
+    }
+}
+
+// The storage server itself is unreachable or broken, another one may still work
+fn is_server_failure(err: &Error) -> bool {
+    match err {
+        Error::HttpRequestError(err) => err.is_connect() || err.is_timeout() || err.is_request(),
+        Error::HttpStatusCodeError(_, status, _) => status.is_server_error(),
+        _ => false,
+    }
+}
+
+// A corrupted transfer is worth sending again on top of what fails for transient reasons
+fn is_retryable(err: &Error) -> bool {
+    matches!(err, Error::ChecksumMismatch { .. }) || err.is_retryable()
+}
+
+pub(crate) fn upload_filename(path: &Path, policy: FilenamePolicy) -> Result<String, Error> {
+    let Some(filename) = path.file_name() else {
+        return Err(Error::InvalidFilePath(
+            path.into(),
+            "Couldn't get the filename.".into(),
+        ));
+    };
+    if let Some(filename) = filename.to_str() {
+        return Ok(filename.into());
+    }
+
+    let lossy = filename.to_string_lossy();
+    match policy {
+        FilenamePolicy::Strict => Err(Error::InvalidFilePath(
+            path.into(),
+            "The filename isn't valid utf-8, use FilenamePolicy::Lossy to upload it anyway.".into(),
+        )),
+        FilenamePolicy::Lossy => Ok(lossy.into_owned()),
+        FilenamePolicy::LossyWithHash => {
+            let hash = hex::encode(&Md5Hasher::digest(filename.as_encoded_bytes())[..4]);
+            // keep the extension last so gofile still guesses the type
+            let lossy = Path::new(lossy.as_ref());
+            let stem = lossy.file_stem().unwrap_or_default().to_string_lossy();
+            Ok(match lossy.extension() {
+                Some(extension) => format!("{}-{}.{}", stem, hash, extension.to_string_lossy()),
+                None => format!("{}-{}", stem, hash),
+            })
+        }
+    }
+}
+
+// Mirrors the local tree under `dest_folder` and returns the files left to upload, without an
+// api nothing is created and every file is planned into `dest_folder`
+async fn plan_dir(
+    api: Option<&AuthorizedApi>,
+    local_dir: &Path,
+    dest_folder: FolderId,
+    options: &UploadDirOptions,
+    manifest: &mut UploadDirManifest,
+) -> Result<Vec<PlannedFile>, Error> {
+    let include = glob_set(&options.include)?;
+    let exclude = glob_set(&options.exclude)?;
+    let mut visited = VisitedDirs::new(local_dir, options.symlinks).await?;
+
+    let mut files = Vec::new();
+    let mut pending = vec![(local_dir.to_path_buf(), dest_folder)];
+    while let Some((dir, folder_id)) = pending.pop() {
+        let entries = match read_dir_entries(&dir, options.symlinks).await {
+            Ok(entries) => entries,
+            Err(err) if dir == local_dir => return Err(err),
+            Err(err) => {
+                let relative = dir.strip_prefix(local_dir).unwrap_or(&dir).to_path_buf();
+                manifest.failed.push((relative, err));
+                continue;
+            }
+        };
+
+        for (path, kind) in entries {
+            let relative = path.strip_prefix(local_dir).unwrap_or(&path).to_path_buf();
+            if exclude.is_match(&relative) {
+                continue;
+            }
+            match kind {
+                DirEntryKind::File => {
+                    if options.include.is_empty() || include.is_match(&relative) {
+                        files.push(PlannedFile {
+                            path,
+                            relative,
+                            folder_id,
+                        });
+                    }
+                    continue;
+                }
+                DirEntryKind::Dir => {}
+                DirEntryKind::Ignored => {
+                    manifest.ignored.push(relative);
+                    continue;
+                }
+                DirEntryKind::Broken(err) => {
+                    manifest.failed.push((relative, err));
+                    continue;
+                }
+            }
+
+            match visited.enter(&path).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    manifest.ignored.push(relative);
+                    continue;
+                }
+                Err(err) => {
+                    manifest.failed.push((relative, err));
+                    continue;
+                }
+            }
+            let name = path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+            let Some(api) = api else {
+                pending.push((path, folder_id));
+                continue;
+            };
+            match api.create_folder(folder_id, name).await {
+                Ok(folder) => pending.push((path, FolderId(folder.id.0))),
+                Err(err) => manifest.failed.push((relative, err)),
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn glob_set(patterns: &[String]) -> Result<GlobSet, Error> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern)
+            .map_err(|err| Error::InvalidPattern(pattern.clone(), format!("{}", err)))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|err| Error::InvalidPattern(patterns.join(", "), format!("{}", err)))
+}
+
+impl VisitedDirs {
+    pub(crate) async fn new(root: &Path, policy: SymlinkPolicy) -> Result<Self, Error> {
+        let mut visited = Self {
+            policy,
+            dirs: HashSet::new(),
+        };
+        visited.enter(root).await?;
+        Ok(visited)
+    }
+
+    // false when the directory was walked already
+    pub(crate) async fn enter(&mut self, dir: &Path) -> Result<bool, Error> {
+        if self.policy != SymlinkPolicy::FollowWithCycleDetection {
+            return Ok(true);
+        }
+        let canonical = fs::canonicalize(dir)
+            .await
+            .map_err(|err| Error::CouldntOpenFile(dir.into(), err))?;
+        Ok(self.dirs.insert(canonical))
+    }
+}
+
+// Lists a directory sorted by path, resolving symlinks as the policy says
+pub(crate) async fn read_dir_entries(
+    dir: &Path,
+    symlinks: SymlinkPolicy,
+) -> Result<Vec<(PathBuf, DirEntryKind)>, Error> {
+    let read_error = |path: &Path, err: std::io::Error| Error::CouldntOpenFile(path.into(), err);
+
+    let mut entries = Vec::new();
+    let mut read_dir = fs::read_dir(dir)
+        .await
+        .map_err(|err| read_error(dir, err))?;
+    while let Some(entry) = read_dir
+        .next_entry()
+        .await
+        .map_err(|err| read_error(dir, err))?
+    {
+        let path = entry.path();
+        let file_type = entry
+            .file_type()
+            .await
+            .map_err(|err| read_error(&path, err))?;
+        let file_type = if !file_type.is_symlink() {
+            file_type
+        } else if symlinks == SymlinkPolicy::Skip {
+            entries.push((path, DirEntryKind::Ignored));
+            continue;
+        } else {
+            match fs::metadata(&path).await {
+                Ok(metadata) => metadata.file_type(),
+                Err(err) => {
+                    let err = read_error(&path, err);
+                    entries.push((path, DirEntryKind::Broken(err)));
+                    continue;
+                }
+            }
+        };
+        let kind = if file_type.is_dir() {
+            DirEntryKind::Dir
+        } else if file_type.is_file() {
+            DirEntryKind::File
+        } else {
+            DirEntryKind::Ignored
+        };
+        entries.push((path, kind));
+    }
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{download::tests::file_entry, Api, ApiClient, GofileErrorCode, Md5};
+    use mockito::{Matcher, Server};
+    use serde_json::json;
+    use tokio::sync::mpsc::unbounded_channel;
+    use uuid::uuid;
+
+    #[tokio::test]
+    async fn upload_dir() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        }
+        .authorize("gofile_token");
+        let server_api = AuthorizedServerApi {
+            base_url: server.url().parse().unwrap(),
+            token: "gofile_token".into(),
+            client: ApiClient::default(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+
+        let uploaded = |file_id: &str, name: &str, body: &str| {
+            json!({
+                "status": "ok",
+                "data": {
+                    "downloadPage": "https://gofile.io/d/foo",
+                    "code": "foo",
+                    "parentFolder": "00000000-0000-0000-0000-000000000001",
+                    "fileId": file_id,
+                    "fileName": name,
+                    "md5": hex::encode(Md5Hasher::digest(body))
+                }
+            })
+            .to_string()
+        };
+        let mocks = vec![
+            server
+                .mock("POST", "/contents/createFolder")
+                .match_body(Matcher::Json(json!({
+                    "token": "gofile_token",
+                    "parentFolderId": "00000000-0000-0000-0000-000000000001",
+                    "folderName": "sub",
+                })))
+                .with_status(200)
+                .with_body(
+                    json!({
+                        "status": "ok",
+                        "data": {
+                            "id": "00000000-0000-0000-0000-000000000020",
+                            "type": "folder",
+                            "name": "sub",
+                            "createTime": 1710264457,
+                            "code": "sub"
+                        }
+                    })
+                    .to_string(),
+                )
+                .create(),
+            server
+                .mock("POST", "/contents/uploadfile")
+                .match_body(Matcher::AllOf(vec![
+                    Matcher::Regex("ggg".into()),
+                    Matcher::Regex("00000000-0000-0000-0000-000000000001".into()),
+                ]))
+                .with_status(200)
+                .with_body(uploaded(
+                    "00000000-0000-0000-0000-000000000031",
+                    "a.txt",
+                    "ggg",
+                ))
+                .create(),
+            server
+                .mock("POST", "/contents/uploadfile")
+                .match_body(Matcher::AllOf(vec![
+                    Matcher::Regex("iii".into()),
+                    Matcher::Regex("00000000-0000-0000-0000-000000000020".into()),
+                ]))
+                .with_status(200)
+                .with_body(uploaded(
+                    "00000000-0000-0000-0000-000000000032",
+                    "c.txt",
+                    "iii",
+                ))
+                .create(),
+            server
+                .mock("POST", "/contents/uploadfile")
+                .match_body(Matcher::Regex("kkk".into()))
+                .with_status(500)
+                .create(),
+        ];
+        std::fs::write(dir.path().join("a.txt"), "ggg").unwrap();
+        std::fs::write(dir.path().join("b.log"), "hhh").unwrap();
+        std::fs::write(dir.path().join("e.txt"), "kkk").unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("sub/c.txt"), "iii").unwrap();
+        std::fs::create_dir(dir.path().join("skip")).unwrap();
+        std::fs::write(dir.path().join("skip/d.txt"), "ddd").unwrap();
+
+        let options = UploadDirOptions {
+            include: vec!["*.txt".into()],
+            exclude: vec!["skip".into()],
+            ..Default::default()
+        };
+        let (tx, mut rx) = unbounded_channel();
+        let manifest = server_api
+            .upload_dir(
+                &api,
+                dir.path(),
+                uuid!("00000000-0000-0000-0000-000000000001").into(),
+                options,
+                Some(tx),
+            )
+            .await?;
+        assert_eq!(
+            manifest.uploaded.keys().collect::<Vec<_>>(),
+            vec![Path::new("a.txt"), Path::new("sub/c.txt")]
+        );
+        assert_eq!(
+            manifest.uploaded[Path::new("sub/c.txt")].file_id,
+            uuid!("00000000-0000-0000-0000-000000000032")
+        );
+        assert_eq!(manifest.failed.len(), 1);
+        assert_eq!(manifest.failed[0].0, Path::new("e.txt"));
+
+        let mut uploads = HashSet::new();
+        while let Some(message) = rx.recv().await {
+            uploads.insert(message.uuid);
+        }
+        assert_eq!(uploads.len(), 3);
+        for mock in mocks {
+            mock.assert();
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upload_many() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let server_api = AuthorizedServerApi {
+            base_url: server.url().parse().unwrap(),
+            token: "gofile_token".into(),
+            client: ApiClient::default(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+
+        let uploaded = |file_id: &str, body: &str| {
+            json!({
+                "status": "ok",
+                "data": {
+                    "downloadPage": "https://gofile.io/d/foo",
+                    "code": "foo",
+                    "parentFolder": "00000000-0000-0000-0000-000000000001",
+                    "fileId": file_id,
+                    "fileName": "foo",
+                    "md5": hex::encode(Md5Hasher::digest(body))
+                }
+            })
+            .to_string()
+        };
+        let mocks = vec![
+            server
+                .mock("POST", "/contents/uploadfile")
+                .match_body(Matcher::Regex("ggg".into()))
+                .with_status(200)
+                .with_body(uploaded("00000000-0000-0000-0000-000000000031", "ggg"))
+                .create(),
+            // the first attempt for b.txt fails and is retried
+            server
+                .mock("POST", "/contents/uploadfile")
+                .match_body(Matcher::Regex("hhh".into()))
+                .with_status(503)
+                .expect(1)
+                .create(),
+            server
+                .mock("POST", "/contents/uploadfile")
+                .match_body(Matcher::Regex("hhh".into()))
+                .with_status(200)
+                .with_body(uploaded("00000000-0000-0000-0000-000000000032", "hhh"))
+                .create(),
+            server
+                .mock("POST", "/contents/uploadfile")
+                .match_body(Matcher::Regex("iii".into()))
+                .with_status(200)
+                .with_body(uploaded("00000000-0000-0000-0000-000000000033", "iii"))
+                .create(),
+        ];
+        let files = ["a.txt", "b.txt", "c.txt"].map(|name| dir.path().join(name));
+        for (path, body) in files.iter().zip(["ggg", "hhh", "iii"]) {
+            std::fs::write(path, body).unwrap();
+        }
+
+        let (tx, mut rx) = unbounded_channel();
+        let results = server_api
+            .upload_many(
+                files.to_vec(),
+                uuid!("00000000-0000-0000-0000-000000000001").into(),
+                UploadManyOptions {
+                    concurrency: 2,
+                    ..Default::default()
+                },
+                Some(tx),
+            )
+            .await;
+        let ids = results
+            .into_iter()
+            .map(|result| result.map(|uploaded| uploaded.file_id))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(
+            ids,
+            vec![
+                uuid!("00000000-0000-0000-0000-000000000031"),
+                uuid!("00000000-0000-0000-0000-000000000032"),
+                uuid!("00000000-0000-0000-0000-000000000033"),
+            ]
+        );
+
+        let mut last = None;
+        while let Some(message) = rx.recv().await {
+            if let UploadQueueMessage::Total { .. } = message {
+                last = Some(message);
+            }
+        }
+        assert_eq!(
+            last,
+            Some(UploadQueueMessage::Total {
+                files_done: 3,
+                files_total: 3,
+                bytes_done: 9,
+                bytes_total: 9,
+            })
+        );
+        for mock in mocks {
+            mock.assert();
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upload_many_cancelled() {
+        let server_api = AuthorizedServerApi {
+            base_url: "http://127.0.0.1:1".parse().unwrap(),
+            token: "gofile_token".into(),
+            client: ApiClient::default(),
+        };
+        let options = UploadManyOptions::default();
+        options.cancel.cancel();
+
+        let results = server_api
+            .upload_many(
+                vec!["a.txt".into()],
+                uuid!("00000000-0000-0000-0000-000000000001").into(),
+                options,
+                None,
+            )
+            .await;
+        assert!(matches!(&results[..], [Err(Error::UploadCancelled(_))]));
+    }
+
+    #[tokio::test]
+    async fn upload_path_with_retry() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let server_api = AuthorizedServerApi {
+            base_url: server.url().parse().unwrap(),
+            token: "gofile_token".into(),
+            client: ApiClient::default(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        std::fs::write(&path, "aaa").unwrap();
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_millis(1),
+            ..Default::default()
+        };
+        let folder_id = uuid!("00000000-0000-0000-0000-000000000001");
+
+        let mocks = vec![
+            server
+                .mock("POST", "/contents/uploadfile")
+                .with_status(502)
+                .expect(1)
+                .create(),
+            server
+                .mock("POST", "/contents/uploadfile")
+                .with_status(200)
+                .with_body(r#"{ "status": "error-rateLimit", "data": {} }"#)
+                .expect(1)
+                .create(),
+            server
+                .mock("POST", "/contents/uploadfile")
+                .with_status(200)
+                .with_body(
+                    json!({
+                        "status": "ok",
+                        "data": {
+                            "downloadPage": "https://gofile.io/d/foo",
+                            "code": "foo",
+                            "parentFolder": "00000000-0000-0000-0000-000000000001",
+                            "fileId": "00000000-0000-0000-0000-000000000031",
+                            "fileName": "a.txt",
+                            "md5": hex::encode(Md5Hasher::digest("aaa"))
+                        }
+                    })
+                    .to_string(),
+                )
+                .create(),
+        ];
+
+        let (tx, mut rx) = unbounded_channel();
+        let uploaded = server_api
+            .upload_path_with_retry(FolderId(folder_id), &path, policy.clone(), Some(tx))
+            .await?;
+        assert_eq!(
+            uploaded.file_id,
+            uuid!("00000000-0000-0000-0000-000000000031")
+        );
+        let mut messages = Vec::new();
+        while let Some(message) = rx.recv().await {
+            messages.push((message.uploaded, message.retry));
+        }
+        assert_eq!(
+            messages,
+            vec![(3, false), (0, true), (3, true), (0, true), (3, true)]
+        );
+        for mock in mocks {
+            mock.assert();
+        }
+
+        // an invalid folder isn't worth retrying
+        let mock = server
+            .mock("POST", "/contents/uploadfile")
+            .with_status(400)
+            .with_body(r#"{ "status": "error-notFound", "data": {} }"#)
+            .expect(1)
+            .create();
+        let result = server_api
+            .upload_path_with_retry(FolderId(folder_id), &path, policy.clone(), None)
+            .await;
+        assert!(matches!(result, Err(Error::ApiStatusError(..))));
+        mock.assert();
+        mock.remove();
+
+        // neither is a refused token
+        let mock = server
+            .mock("POST", "/contents/uploadfile")
+            .with_status(401)
+            .with_body(r#"{ "status": "error-auth", "data": {} }"#)
+            .expect(1)
+            .create();
+        let result = server_api
+            .upload_path_with_retry(FolderId(folder_id), &path, policy, None)
+            .await;
+        assert_eq!(result.unwrap_err().code(), Some(GofileErrorCode::Auth));
+        mock.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upload_target_failover() -> Result<(), Error> {
+        let mut broken = Server::new_async().await;
+        let mut working = Server::new_async().await;
+        let target = UploadTarget {
+            servers: [&broken, &working]
+                .map(|server| AuthorizedServerApi {
+                    base_url: server.url().parse().unwrap(),
+                    token: "gofile_token".into(),
+                    client: ApiClient::default(),
+                })
+                .to_vec(),
+            retry: RetryPolicy {
+                max_attempts: 2,
+                initial_backoff: Duration::from_millis(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        std::fs::write(&path, "aaa").unwrap();
+
+        let mocks = vec![
+            broken
+                .mock("POST", "/contents/uploadfile")
+                .with_status(502)
+                .expect(2)
+                .create(),
+            working
+                .mock("POST", "/contents/uploadfile")
+                .with_status(200)
+                .with_body(
+                    json!({
+                        "status": "ok",
+                        "data": {
+                            "downloadPage": "https://gofile.io/d/foo",
+                            "code": "foo",
+                            "parentFolder": "00000000-0000-0000-0000-000000000001",
+                            "fileId": "00000000-0000-0000-0000-000000000031",
+                            "fileName": "a.txt",
+                            "md5": hex::encode(Md5Hasher::digest("aaa"))
+                        }
+                    })
+                    .to_string(),
+                )
+                .create(),
+        ];
+
+        let (tx, mut rx) = unbounded_channel();
+        let uploaded = target
+            .upload_file_to_folder(
+                uuid!("00000000-0000-0000-0000-000000000001").into(),
+                &path,
+                Some(tx),
+            )
+            .await?;
+        assert_eq!(uploaded.server, Some(working.url()));
+        let mut uuids = HashSet::new();
+        let mut last = None;
+        while let Some(message) = rx.recv().await {
+            uuids.insert(message.uuid);
+            last = Some((message.uploaded, message.retry));
+        }
+        assert_eq!(uuids.len(), 1);
+        assert_eq!(last, Some((3, true)));
+        for mock in mocks {
+            mock.assert();
+        }
+
+        // a client error isn't the server's fault
+        let mock = broken
+            .mock("POST", "/contents/uploadfile")
+            .with_status(400)
+            .expect(1)
+            .create();
+        let target = UploadTarget {
+            servers: vec![
+                AuthorizedServerApi {
+                    base_url: broken.url().parse().unwrap(),
+                    token: "gofile_token".into(),
+                    client: ApiClient::default(),
+                };
+                2
+            ],
+            ..Default::default()
+        };
+        let result = target
+            .upload_file_to_folder(
+                uuid!("00000000-0000-0000-0000-000000000001").into(),
+                &path,
+                None,
+            )
+            .await;
+        assert!(matches!(result, Err(Error::HttpStatusCodeError(..))));
+        mock.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upload_file_with_events() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let server_api = AuthorizedServerApi {
+            base_url: server.url().parse().unwrap(),
+            token: "gofile_token".into(),
+            client: ApiClient::default(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        std::fs::write(&path, "ggg").unwrap();
+        let folder_id = uuid!("00000000-0000-0000-0000-000000000001");
+
+        let mock = server
+            .mock("POST", "/contents/uploadfile")
+            .with_status(200)
+            .with_body(
+                json!({
+                    "status": "ok",
+                    "data": {
+                        "downloadPage": "https://gofile.io/d/foo",
+                        "code": "foo",
+                        "parentFolder": "00000000-0000-0000-0000-000000000001",
+                        "fileId": "00000000-0000-0000-0000-000000000031",
+                        "fileName": "a.txt",
+                        "md5": hex::encode(Md5Hasher::digest("ggg"))
+                    }
+                })
+                .to_string(),
+            )
+            .create();
+        let (tx, mut rx) = unbounded_channel();
+        let uploaded = server_api
+            .upload_file_to_folder_with_events(
+                FolderId(folder_id),
+                &path,
+                UploadOptions::default(),
+                tx,
+            )
+            .await?;
+        let mut events = Vec::new();
+        while let Some(event) = rx.recv().await {
+            events.push(event);
+        }
+        let uuid = match events.first() {
+            Some(TransferEvent::Started { uuid, .. }) => *uuid,
+            event => panic!("unexpected first event {:?}", event),
+        };
+        assert_eq!(
+            events,
+            vec![
+                TransferEvent::Started {
+                    uuid,
+                    name: "a.txt".into(),
+                    total: Some(3),
+                },
+                TransferEvent::Progress { uuid, bytes: 3 },
+                TransferEvent::Completed {
+                    uuid,
+                    result: Box::new(uploaded),
+                },
+            ]
+        );
+        mock.remove();
+
+        let mock = server
+            .mock("POST", "/contents/uploadfile")
+            .with_status(500)
+            .create();
+        let (tx, mut rx) = unbounded_channel();
+        let result = server_api
+            .upload_file_to_folder_with_events(
+                FolderId(folder_id),
+                &path,
+                UploadOptions::default(),
+                tx,
+            )
+            .await;
+        assert!(result.is_err());
+        let mut last = None;
+        while let Some(event) = rx.recv().await {
+            last = Some(event);
+        }
+        assert!(matches!(last, Some(TransferEvent::Failed { .. })));
+        mock.assert();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upload_unless_exists() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        }
+        .authorize("gofile_token");
+        let server_api = ServerApi {
+            base_url: server.url().parse().unwrap(),
+            client: ApiClient::default(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let url = server.url();
+        let folder_id = FolderId(uuid!("00000000-0000-0000-0000-000000000001"));
+
+        let folder = json!({
+            "status": "ok",
+            "data": {
+                "id": "00000000-0000-0000-0000-000000000001",
+                "type": "folder",
+                "name": "backup",
+                "createTime": 1710264457,
+                "code": "backup",
+                "children": {
+                    "00000000-0000-0000-0000-000000000011": file_entry(&url, "00000000-0000-0000-0000-000000000011", "a.txt", "ggg"),
+                    "00000000-0000-0000-0000-000000000012": file_entry(&url, "00000000-0000-0000-0000-000000000012", "b.txt", "old"),
+                }
+            }
+        });
+        let uploaded = |name: &str, body: &str| {
+            json!({
+                "status": "ok",
+                "data": {
+                    "downloadPage": "https://gofile.io/d/backup",
+                    "code": "backup",
+                    "parentFolder": "00000000-0000-0000-0000-000000000001",
+                    "fileId": "00000000-0000-0000-0000-000000000030",
+                    "fileName": name,
+                    "md5": hex::encode(Md5Hasher::digest(body))
+                }
+            })
+            .to_string()
+        };
+        let mut mocks = vec![server
+            .mock(
+                "GET",
+                "/contents/00000000-0000-0000-0000-000000000001?token=gofile_token",
+            )
+            .with_status(200)
+            .with_body(folder.to_string())
+            .expect(3)
+            .create()];
+        for (name, body) in [("b.txt", "new"), ("c.txt", "iii")] {
+            mocks.push(
+                server
+                    .mock("POST", "/contents/uploadfile")
+                    .match_body(Matcher::AllOf(vec![
+                        Matcher::Regex(body.into()),
+                        Matcher::Regex("00000000-0000-0000-0000-000000000001".into()),
+                    ]))
+                    .with_status(200)
+                    .with_body(uploaded(name, body))
+                    .expect(1)
+                    .create(),
+            );
+        }
+        for (name, body) in [("a.txt", "ggg"), ("b.txt", "new"), ("c.txt", "iii")] {
+            std::fs::write(dir.path().join(name), body).unwrap();
+        }
+
+        let result = api
+            .upload_unless_exists(&server_api, dir.path().join("a.txt"), folder_id)
+            .await?;
+        assert!(matches!(result, DedupUpload::AlreadyPresent(content) if content.name == "a.txt"));
+
+        let result = api
+            .upload_unless_exists(&server_api, dir.path().join("b.txt"), folder_id)
+            .await?;
+        match result {
+            DedupUpload::UploadedNameConflict { uploaded, existing } => {
+                assert_eq!(uploaded.md5, Md5::of("new"));
+                assert_eq!(existing.name, "b.txt");
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        let result = api
+            .upload_unless_exists(&server_api, dir.path().join("c.txt"), folder_id)
+            .await?;
+        assert!(matches!(result, DedupUpload::Uploaded(uploaded) if uploaded.file_name == "c.txt"));
+        for mock in mocks {
+            mock.assert();
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn share_file() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        };
+        let upload_server = ServerApi {
+            base_url: server.url().parse().unwrap(),
+            client: ApiClient::default(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("build.zip");
+        std::fs::write(&path, "zip").unwrap();
+        let uploaded = |guest_token: Option<&str>| {
+            json!({
+                "status": "ok",
+                "data": {
+                    "guestToken": guest_token,
+                    "downloadPage": "https://gofile.io/d/foo",
+                    "code": "foo",
+                    "parentFolder": "00000000-0000-0000-0000-000000000001",
+                    "fileId": "00000000-0000-0000-0000-000000000002",
+                    "fileName": "build.zip",
+                    "md5": hex::encode(Md5Hasher::digest("zip"))
+                }
+            })
+            .to_string()
+        };
+        let options = ShareOptions {
+            expire: Some(Utc::now() + chrono::Duration::days(1)),
+            password: Some("secret".into()),
+            public: Some(true),
+        };
+
+        // the options are set on the new folder with the guest token, in order
+        let upload_mock = server
+            .mock("POST", "/contents/uploadfile")
+            .with_status(200)
+            .with_body(uploaded(Some("guest_token")))
+            .create();
+        let update_mocks = ["expiry", "password", "public"].map(|attribute| {
+            server
+                .mock(
+                    "PUT",
+                    "/contents/00000000-0000-0000-0000-000000000001/update",
+                )
+                .match_header("authorization", "Bearer guest_token")
+                .match_body(Matcher::PartialJson(json!({ "attribute": attribute })))
+                .with_status(200)
+                .with_body(r#"{ "status": "ok", "data": {} }"#)
+                .create()
+        });
+        let shared = api
+            .share_file_on(&upload_server, &path, options.clone())
+            .await?;
+        assert_eq!(shared.download_page.as_str(), "https://gofile.io/d/foo");
+        assert_eq!(shared.code, "foo");
+        assert_eq!(shared.guest_token, Some("guest_token".into()));
+        upload_mock.assert();
+        for mock in &update_mocks {
+            mock.assert();
+        }
+
+        // a failed option still hands back the upload, its link exists
+        server.reset();
+        server
+            .mock("POST", "/contents/uploadfile")
+            .with_status(200)
+            .with_body(uploaded(None))
+            .create();
+        server
+            .mock(
+                "PUT",
+                "/contents/00000000-0000-0000-0000-000000000001/update",
+            )
+            .match_header("authorization", "Bearer gofile_token")
+            .match_body(Matcher::PartialJson(json!({ "attribute": "expiry" })))
+            .with_status(200)
+            .with_body(r#"{ "status": "ok", "data": {} }"#)
+            .create();
+        let password_mock = server
+            .mock(
+                "PUT",
+                "/contents/00000000-0000-0000-0000-000000000001/update",
+            )
+            .match_body(Matcher::PartialJson(json!({ "attribute": "password" })))
+            .with_status(401)
+            .with_body(r#"{ "status": "error-notPremium", "data": {} }"#)
+            .create();
+        let public_mock = server
+            .mock(
+                "PUT",
+                "/contents/00000000-0000-0000-0000-000000000001/update",
+            )
+            .match_body(Matcher::PartialJson(json!({ "attribute": "public" })))
+            .expect(0)
+            .create();
+        let result = api
+            .authorize("gofile_token")
+            .share_file_on(
+                &AuthorizedServerApi {
+                    base_url: server.url().parse().unwrap(),
+                    client: ApiClient::default(),
+                    token: "gofile_token".into(),
+                },
+                &path,
+                options.clone(),
+            )
+            .await;
+        match result {
+            Err(Error::ShareFailed {
+                step: ShareStep::Password,
+                uploaded: Some(uploaded),
+                source,
+            }) => {
+                assert_eq!(uploaded.download_page.as_str(), "https://gofile.io/d/foo");
+                assert!(matches!(*source, Error::NotPremium(_)));
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+        password_mock.assert();
+        public_mock.assert();
+
+        server
+            .mock("GET", "/servers")
+            .with_status(200)
+            .with_body(r#"{ "status": "ok", "data": { "servers": [] } }"#)
+            .create();
+        let result = api.share_file(&path, options).await;
+        assert!(matches!(
+            result,
+            Err(Error::ShareFailed {
+                step: ShareStep::Server,
+                uploaded: None,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upload_session() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let session = ServerApi {
+            base_url: server.url().parse().unwrap(),
+            client: ApiClient::default(),
+        }
+        .session();
+        let dir = tempfile::tempdir().unwrap();
+        let paths = ["a.txt", "b.txt", "c.txt"].map(|name| dir.path().join(name));
+        for path in &paths {
+            std::fs::write(path, "ggg").unwrap();
+        }
+        assert_eq!(session.download_page(), None);
+
+        let uploaded = |guest_token: Option<&str>| {
+            json!({
+                "status": "ok",
+                "data": {
+                    "guestToken": guest_token,
+                    "downloadPage": "https://gofile.io/d/foo",
+                    "code": "foo",
+                    "parentFolder": "00000000-0000-0000-0000-000000000001",
+                    "fileId": "00000000-0000-0000-0000-000000000031",
+                    "fileName": "a.txt",
+                    "md5": hex::encode(Md5Hasher::digest("ggg"))
+                }
+            })
+            .to_string()
+        };
+        let mocks = vec![
+            server
+                .mock("POST", "/contents/uploadfile")
+                .match_body(Matcher::AllOf(vec![
+                    Matcher::Regex("name=\"token\"\r\n\r\nguest_token".into()),
+                    Matcher::Regex(
+                        "name=\"folderId\"\r\n\r\n00000000-0000-0000-0000-000000000001".into(),
+                    ),
+                ]))
+                .with_status(200)
+                .with_body(uploaded(None))
+                .expect(2)
+                .create(),
+            server
+                .mock("POST", "/contents/uploadfile")
+                .with_status(200)
+                .with_body(uploaded(Some("guest_token")))
+                .expect(1)
+                .create(),
+        ];
+
+        let results =
+            futures::future::join_all(paths.iter().map(|path| session.upload_file(path))).await;
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(
+            session.download_page().map(Url::as_str),
+            Some("https://gofile.io/d/foo")
+        );
+        assert_eq!(session.guest_token().unwrap(), "guest_token");
+        for mock in mocks {
+            mock.assert();
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn check_quota() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        }
+        .authorize("gofile_token");
+
+        let account = |limit: Option<u64>| {
+            let mut data = json!({
+                "id": "00000000-0000-0000-0000-000000000001",
+                "token": "gofile_token",
+                "email": "foo@example.com",
+                "tier": "standard",
+                "rootFolder": "00000000-0000-0000-0000-000000000002",
+                "filesCount": 3,
+                "totalSize": 600,
+            });
+            if let Some(limit) = limit {
+                data["subscriptionLimitStorage"] = json!(limit);
+            }
+            json!({ "status": "ok", "data": data }).to_string()
+        };
+        let mocks = vec![
+            server
+                .mock("GET", "/accounts/getid?token=gofile_token")
+                .with_body(
+                    r#"{"status":"ok","data":{"id":"00000000-0000-0000-0000-000000000001"}}"#,
+                )
+                .expect(4)
+                .create(),
+            server
+                .mock(
+                    "GET",
+                    "/accounts/00000000-0000-0000-0000-000000000001?token=gofile_token",
+                )
+                .with_body(account(Some(1000)))
+                .expect(3)
+                .create(),
+        ];
+
+        assert_eq!(api.check_quota(100).await?, QuotaStatus::Available(300));
+        assert_eq!(
+            api.check_quota(500).await?,
+            QuotaStatus::Exceeded {
+                needed: 500,
+                available: 400
+            }
+        );
+
+        let results = AuthorizedServerApi {
+            base_url: server.url().parse().unwrap(),
+            token: "gofile_token".into(),
+            client: ApiClient::default(),
+        }
+        .upload_many(
+            vec!["a.txt".into(), "b.txt".into()],
+            uuid!("00000000-0000-0000-0000-000000000002").into(),
+            UploadManyOptions {
+                check_quota: Some(api.clone()),
+                ..Default::default()
+            },
+            None,
+        )
+        .await;
+        // the files don't exist, so nothing is needed and the uploads themselves fail
+        assert!(results
+            .iter()
+            .all(|result| matches!(result, Err(Error::CouldntOpenFile(..)))));
+
+        let unlimited = server
+            .mock(
+                "GET",
+                "/accounts/00000000-0000-0000-0000-000000000001?token=gofile_token",
+            )
+            .with_body(account(None))
+            .expect(1)
+            .create();
+        assert_eq!(api.check_quota(u64::MAX).await?, QuotaStatus::Unlimited);
+
+        for mock in mocks {
+            mock.assert();
+        }
+        unlimited.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upload_many_quota_exceeded() {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        }
+        .authorize("gofile_token");
+        let dir = tempfile::tempdir().unwrap();
+        let files = vec![dir.path().join("a.txt"), dir.path().join("b.txt")];
+        for file in &files {
+            fs::write(file, "ggg").await.unwrap();
+        }
+
+        server
+            .mock("GET", "/accounts/getid?token=gofile_token")
+            .with_body(r#"{"status":"ok","data":{"id":"00000000-0000-0000-0000-000000000001"}}"#)
+            .create();
+        server
+            .mock(
+                "GET",
+                "/accounts/00000000-0000-0000-0000-000000000001?token=gofile_token",
+            )
+            .with_body(r#"{"status":"ok","data":{"id":"00000000-0000-0000-0000-000000000001","token":"gofile_token","email":"foo@example.com","tier":"standard","rootFolder":"00000000-0000-0000-0000-000000000002","filesCount":1,"totalSize":5,"subscriptionLimitStorage":10}}"#)
+            .create();
+        let upload = server
+            .mock("POST", "/contents/uploadfile")
+            .expect(0)
+            .create();
+
+        let results = AuthorizedServerApi {
+            base_url: server.url().parse().unwrap(),
+            token: "gofile_token".into(),
+            client: ApiClient::default(),
+        }
+        .upload_many(
+            files,
+            uuid!("00000000-0000-0000-0000-000000000002").into(),
+            UploadManyOptions {
+                check_quota: Some(api),
+                ..Default::default()
+            },
+            None,
+        )
+        .await;
+        assert!(matches!(
+            &results[..],
+            [
+                Err(Error::QuotaExceeded {
+                    needed: 6,
+                    available: 5
+                }),
+                Err(Error::UploadCancelled(_))
+            ]
+        ));
+        upload.assert();
+    }
+
+    #[tokio::test]
+    async fn upload_dir_quota_exceeded() {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        }
+        .authorize("gofile_token");
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("a.txt"), "ggg").unwrap();
+        std::fs::write(dir.path().join("sub/b.txt"), "hhh").unwrap();
+
+        server
+            .mock("GET", "/accounts/getid?token=gofile_token")
+            .with_body(r#"{"status":"ok","data":{"id":"00000000-0000-0000-0000-000000000001"}}"#)
+            .create();
+        server
+            .mock(
+                "GET",
+                "/accounts/00000000-0000-0000-0000-000000000001?token=gofile_token",
+            )
+            .with_body(r#"{"status":"ok","data":{"id":"00000000-0000-0000-0000-000000000001","token":"gofile_token","email":"foo@example.com","tier":"standard","rootFolder":"00000000-0000-0000-0000-000000000002","filesCount":1,"totalSize":5,"subscriptionLimitStorage":10}}"#)
+            .create();
+        // the sub folder isn't created before the quota is known to fit
+        let create_folder = server
+            .mock("POST", "/contents/createFolder")
+            .expect(0)
+            .create();
+        let upload = server
+            .mock("POST", "/contents/uploadfile")
+            .expect(0)
+            .create();
+
+        let result = AuthorizedServerApi {
+            base_url: server.url().parse().unwrap(),
+            client: ApiClient::default(),
+            token: "gofile_token".into(),
+        }
+        .upload_dir(
+            &api,
+            dir.path(),
+            uuid!("00000000-0000-0000-0000-000000000002").into(),
+            UploadDirOptions {
+                check_quota: true,
+                ..Default::default()
+            },
+            None,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(Error::QuotaExceeded {
+                needed: 6,
+                available: 5
+            })
+        ));
+        create_folder.assert();
+        upload.assert();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn upload_filename_policy() {
+        use std::{ffi::OsString, os::unix::ffi::OsStringExt};
+
+        let dir = Path::new("/tmp");
+        let valid = dir.join("report.txt");
+        for policy in [
+            FilenamePolicy::Strict,
+            FilenamePolicy::Lossy,
+            FilenamePolicy::LossyWithHash,
+        ] {
+            assert_eq!(upload_filename(&valid, policy).unwrap(), "report.txt");
+        }
+
+        let first = dir.join(OsString::from_vec(b"caf\xe9.txt".to_vec()));
+        let second = dir.join(OsString::from_vec(b"caf\xe8.txt".to_vec()));
+        assert!(matches!(
+            upload_filename(&first, FilenamePolicy::Strict),
+            Err(Error::InvalidFilePath(path, message))
+                if path == first && message.contains("FilenamePolicy::Lossy")
+        ));
+        assert_eq!(
+            upload_filename(&first, FilenamePolicy::Lossy).unwrap(),
+            "caf\u{FFFD}.txt"
+        );
+        assert_eq!(
+            upload_filename(&first, FilenamePolicy::Lossy).unwrap(),
+            upload_filename(&second, FilenamePolicy::Lossy).unwrap()
+        );
+
+        let hashed = upload_filename(&first, FilenamePolicy::LossyWithHash).unwrap();
+        assert!(hashed.starts_with("caf\u{FFFD}-"));
+        assert!(hashed.ends_with(".txt"));
+        assert_eq!(hashed.len(), "caf\u{FFFD}-.txt".len() + 8);
+        assert_ne!(
+            hashed,
+            upload_filename(&second, FilenamePolicy::LossyWithHash).unwrap()
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn upload_dir_symlink_loop() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let api = Api {
+            base_url: server.url().parse().unwrap(),
+            ..Default::default()
+        }
+        .authorize("gofile_token");
+        let server_api = AuthorizedServerApi {
+            base_url: server.url().parse().unwrap(),
+            token: "gofile_token".into(),
+            client: ApiClient::default(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("sub/a.txt"), "ggg").unwrap();
+        std::os::unix::fs::symlink(dir.path(), dir.path().join("sub/loop")).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("missing"), dir.path().join("broken")).unwrap();
+
+        let mocks = vec![
+            server
+                .mock("POST", "/contents/createFolder")
+                .match_body(Matcher::PartialJson(json!({ "folderName": "sub" })))
+                .with_body(
+                    json!({
+                        "status": "ok",
+                        "data": {
+                            "id": "00000000-0000-0000-0000-000000000020",
+                            "type": "folder",
+                            "name": "sub",
+                            "createTime": 1710264457,
+                            "code": "sub"
+                        }
+                    })
+                    .to_string(),
+                )
+                .expect(1)
+                .create(),
+            server
+                .mock("POST", "/contents/uploadfile")
+                .with_body(
+                    json!({
+                        "status": "ok",
+                        "data": {
+                            "downloadPage": "https://gofile.io/d/foo",
+                            "code": "foo",
+                            "parentFolder": "00000000-0000-0000-0000-000000000020",
+                            "fileId": "00000000-0000-0000-0000-000000000031",
+                            "fileName": "a.txt",
+                            "md5": hex::encode(Md5Hasher::digest("ggg"))
+                        }
+                    })
+                    .to_string(),
+                )
+                .expect(1)
+                .create(),
+        ];
+
+        let options = UploadDirOptions {
+            symlinks: SymlinkPolicy::FollowWithCycleDetection,
+            ..Default::default()
+        };
+        let manifest = tokio::time::timeout(
+            Duration::from_secs(10),
+            server_api.upload_dir(
+                &api,
+                dir.path(),
+                uuid!("00000000-0000-0000-0000-000000000001").into(),
+                options,
+                None,
+            ),
+        )
+        .await
+        .expect("the walk should stop at the loop")?;
+
+        assert_eq!(
+            manifest.uploaded.keys().collect::<Vec<_>>(),
+            vec![Path::new("sub/a.txt")]
+        );
+        assert_eq!(manifest.ignored, vec![Path::new("sub/loop")]);
+        assert!(matches!(
+            &manifest.failed[..],
+            [(path, Error::CouldntOpenFile(..))] if path == Path::new("broken")
+        ));
+        for mock in mocks {
+            mock.assert();
+        }
+
+        // skipped links are only listed
+        let manifest = server_api
+            .upload_dir(
+                &api,
+                dir.path().join("sub"),
+                uuid!("00000000-0000-0000-0000-000000000001").into(),
+                UploadDirOptions {
+                    include: vec!["*.log".into()],
+                    ..Default::default()
+                },
+                None,
+            )
+            .await?;
+        assert!(manifest.uploaded.is_empty());
+        assert_eq!(manifest.ignored, vec![Path::new("loop")]);
+        assert!(manifest.failed.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upload_path_with_retry_after() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let server_api = AuthorizedServerApi {
+            base_url: server.url().parse().unwrap(),
+            token: "gofile_token".into(),
+            client: ApiClient::default(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        std::fs::write(&path, "hhh").unwrap();
+        // the computed backoff alone would take far longer than the test allows
+        let policy = RetryPolicy {
+            max_attempts: 2,
+            initial_backoff: Duration::from_secs(60),
+            max_backoff: Duration::from_secs(60),
+        };
+
+        let mocks = vec![
+            server
+                .mock("POST", "/contents/uploadfile")
+                .with_status(429)
+                .with_header("retry-after", "0")
+                .expect(1)
+                .create(),
+            server
+                .mock("POST", "/contents/uploadfile")
+                .with_status(200)
+                .with_body(
+                    json!({
+                        "status": "ok",
+                        "data": {
+                            "downloadPage": "https://gofile.io/d/foo",
+                            "code": "foo",
+                            "parentFolder": "00000000-0000-0000-0000-000000000001",
+                            "fileId": "00000000-0000-0000-0000-000000000031",
+                            "fileName": "a.txt",
+                            "md5": hex::encode(Md5Hasher::digest("hhh"))
+                        }
+                    })
+                    .to_string(),
+                )
+                .create(),
+        ];
+
+        let uploaded = tokio::time::timeout(
+            Duration::from_secs(10),
+            server_api.upload_path_with_retry(
+                uuid!("00000000-0000-0000-0000-000000000001").into(),
+                &path,
+                policy,
+                None,
+            ),
+        )
+        .await
+        .expect("Retry-After should replace the backoff")?;
+        assert_eq!(
+            uploaded.file_id,
+            uuid!("00000000-0000-0000-0000-000000000031")
+        );
+        for mock in mocks {
+            mock.assert();
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,534 @@
+// Record/replay transport for integration tests. Recording sends through another transport
+// and appends every exchange to a JSON cassette, replaying answers from the cassette without
+// any network. Tokens are redacted from what gets written, so cassettes can be committed.
+use crate::{
+    token::{REDACTED, TOKEN_FIELDS},
+    Error, Transport,
+};
+use futures::future::BoxFuture;
+use reqwest::{Request, Response, ResponseBuilderExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use url::Url;
+
+// recomputed or meaningless on replay, the recorded body is already decompressed
+const SKIPPED_HEADERS: [&str; 5] = [
+    "content-encoding",
+    "content-length",
+    "transfer-encoding",
+    "set-cookie",
+    "date",
+];
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: CassetteRequest,
+    pub response: CassetteResponse,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CassetteRequest {
+    pub method: String,
+    // path and query, tokens redacted
+    pub path: String,
+    // requests match on method, path and this, None for streamed bodies like uploads
+    pub body_sha256: Option<String>,
+    // only kept to make mismatches readable
+    pub body: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CassetteResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    // the body is hex encoded, it wasn't valid utf-8
+    #[serde(default)]
+    pub binary: bool,
+}
+
+#[derive(Debug)]
+enum Mode {
+    Record(Arc<dyn Transport>),
+    Replay,
+}
+
+#[derive(Debug)]
+pub struct VcrTransport {
+    mode: Mode,
+    path: PathBuf,
+    cassette: Arc<Mutex<Cassette>>,
+    // interactions already replayed, each one answers a single request
+    used: Arc<Mutex<Vec<bool>>>,
+}
+
+impl VcrTransport {
+    // Starts a new cassette at path, overwriting any previous one once the first request ends
+    pub fn record(path: impl Into<PathBuf>, inner: Arc<dyn Transport>) -> Self {
+        Self {
+            mode: Mode::Record(inner),
+            path: path.into(),
+            cassette: Default::default(),
+            used: Default::default(),
+        }
+    }
+
+    // A request is answered by the first unused interaction with the same method, path and body,
+    // so concurrent requests may come in any order. One matching none of them fails with
+    // Error::CassetteMismatch next to the closest recorded one.
+    pub fn replay(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = path.into();
+        let json = std::fs::read(&path).map_err(|err| Error::CouldntOpenFile(path.clone(), err))?;
+        let cassette: Cassette = serde_json::from_slice(&json)
+            .map_err(|err| Error::CouldntOpenFile(path.clone(), err.into()))?;
+        let used = vec![false; cassette.interactions.len()];
+        Ok(Self {
+            mode: Mode::Replay,
+            path,
+            cassette: Arc::new(Mutex::new(cassette)),
+            used: Arc::new(Mutex::new(used)),
+        })
+    }
+
+    // Replays the cassette when it exists, records it through inner otherwise
+    pub fn record_once(path: impl Into<PathBuf>, inner: Arc<dyn Transport>) -> Result<Self, Error> {
+        let path = path.into();
+        match path.exists() {
+            true => Self::replay(path),
+            false => Ok(Self::record(path, inner)),
+        }
+    }
+
+    pub fn cassette(&self) -> Cassette {
+        self.cassette.lock().unwrap().clone()
+    }
+
+    fn replayed(&self, req: &Request, recorded: CassetteRequest) -> Result<Response, Error> {
+        let cassette = self.cassette.lock().unwrap();
+        let mut used = self.used.lock().unwrap();
+        let found =
+            cassette
+                .interactions
+                .iter()
+                .zip(used.iter())
+                .position(|(interaction, used)| {
+                    let expected = &interaction.request;
+                    !used
+                        && expected.method == recorded.method
+                        && expected.path == recorded.path
+                        && expected.body_sha256 == recorded.body_sha256
+                });
+        let Some(i) = found else {
+            return Err(Error::CassetteMismatch {
+                path: self.path.clone(),
+                expected: closest(&cassette, &used, &recorded).map(describe),
+                actual: describe(&recorded),
+            });
+        };
+        used[i] = true;
+        let recorded = &cassette.interactions[i].response;
+        let body = match recorded.binary {
+            true => hex::decode(&recorded.body).map_err(|err| {
+                Error::InvalidConfig(format!("The cassette body is invalid: {}.", err))
+            })?,
+            false => recorded.body.clone().into_bytes(),
+        };
+        to_response(req.url().clone(), recorded.status, &recorded.headers, body)
+    }
+}
+
+// Rewritten after every exchange, a test failing halfway still leaves what it recorded
+fn save(path: &Path, cassette: &Cassette) -> Result<(), Error> {
+    let write_error = |err: std::io::Error| Error::CouldntWriteFile(path.into(), err);
+    let json = serde_json::to_vec_pretty(cassette).map_err(|err| write_error(err.into()))?;
+    std::fs::write(path, json).map_err(write_error)
+}
+
+impl Transport for VcrTransport {
+    fn execute(&self, req: Request) -> BoxFuture<'static, Result<Response, Error>> {
+        let secrets = secrets(&req);
+        let recorded = record_request(&req, &secrets);
+        let inner = match &self.mode {
+            Mode::Replay => {
+                let res = self.replayed(&req, recorded);
+                return Box::pin(async move { res });
+            }
+            Mode::Record(inner) => inner.clone(),
+        };
+        let url = req.url().clone();
+        let path = self.path.clone();
+        let cassette = self.cassette.clone();
+        Box::pin(async move {
+            let res = inner.execute(req).await?;
+            let status = res.status().as_u16();
+            let headers: Vec<_> = res
+                .headers()
+                .iter()
+                .filter(|(name, _)| !SKIPPED_HEADERS.contains(&name.as_str()))
+                .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.into())))
+                .collect();
+            let bytes = res.bytes().await?;
+            let (body, binary) = match std::str::from_utf8(&bytes) {
+                Ok(text) => (redact_text(text, &secrets), false),
+                Err(_) => (hex::encode(&bytes), true),
+            };
+            // the caller gets what was received, only the cassette is redacted
+            let res = to_response(url, status, &headers, bytes.to_vec());
+            let response = CassetteResponse {
+                status,
+                headers,
+                body,
+                binary,
+            };
+            let mut cassette = cassette.lock().unwrap();
+            cassette.interactions.push(Interaction {
+                request: recorded,
+                response,
+            });
+            save(&path, &cassette)?;
+            res
+        })
+    }
+}
+
+// The unused interaction the request most likely meant: one with the same method and path,
+// else the same method, else the next one in order
+fn closest<'a>(
+    cassette: &'a Cassette,
+    used: &[bool],
+    req: &CassetteRequest,
+) -> Option<&'a CassetteRequest> {
+    let unused = || {
+        cassette
+            .interactions
+            .iter()
+            .zip(used)
+            .filter(|(_, used)| !**used)
+            .map(|(interaction, _)| &interaction.request)
+    };
+    unused()
+        .find(|expected| expected.method == req.method && expected.path == req.path)
+        .or_else(|| unused().find(|expected| expected.method == req.method))
+        .or_else(|| unused().next())
+}
+
+fn describe(req: &CassetteRequest) -> String {
+    let mut description = format!("  {} {}\n", req.method, req.path);
+    if let Some(hash) = &req.body_sha256 {
+        let _ = writeln!(description, "  body sha256 {}", hash);
+    }
+    if let Some(body) = &req.body {
+        let _ = writeln!(description, "  {}", body);
+    }
+    description
+}
+
+// Tokens sent with the request, from the bearer header, the query or a json body
+fn secrets(req: &Request) -> Vec<String> {
+    let bearer = req
+        .headers()
+        .get(reqwest::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(String::from);
+    let query = req
+        .url()
+        .query_pairs()
+        .filter(|(key, _)| TOKEN_FIELDS.contains(&key.as_ref()))
+        .map(|(_, value)| value.into_owned());
+    let body = req
+        .body()
+        .and_then(|body| body.as_bytes())
+        .and_then(|body| serde_json::from_slice::<Value>(body).ok())
+        .into_iter()
+        .flat_map(|body| {
+            TOKEN_FIELDS
+                .iter()
+                .filter_map(|field| body[field].as_str().map(String::from))
+                .collect::<Vec<_>>()
+        });
+    bearer
+        .into_iter()
+        .chain(query)
+        .chain(body)
+        .filter(|secret| !secret.is_empty())
+        .collect()
+}
+
+fn redact_text(text: &str, secrets: &[String]) -> String {
+    let mut text = match serde_json::from_str::<Value>(text) {
+        Ok(mut json) => {
+            redact_json(&mut json);
+            json.to_string()
+        }
+        Err(_) => text.to_string(),
+    };
+    for secret in secrets {
+        text = text.replace(secret.as_str(), REDACTED);
+    }
+    text
+}
+
+fn redact_json(json: &mut Value) {
+    match json {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if TOKEN_FIELDS.contains(&key.as_str()) && value.is_string() {
+                    *value = Value::from(REDACTED);
+                } else {
+                    redact_json(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact_json),
+        _ => {}
+    }
+}
+
+fn record_request(req: &Request, secrets: &[String]) -> CassetteRequest {
+    let url = req.url();
+    let mut path = url.path().to_string();
+    if let Some(query) = url.query() {
+        path = format!("{}?{}", path, redact_text(query, secrets));
+    }
+    let body = req
+        .body()
+        .and_then(|body| body.as_bytes())
+        .map(|body| redact_text(&String::from_utf8_lossy(body), secrets));
+    CassetteRequest {
+        method: req.method().to_string(),
+        path,
+        body_sha256: body.as_ref().map(|body| hex::encode(Sha256::digest(body))),
+        body,
+    }
+}
+
+fn to_response(
+    url: Url,
+    status: u16,
+    headers: &[(String, String)],
+    body: Vec<u8>,
+) -> Result<Response, Error> {
+    let mut res = http::Response::builder().status(status).url(url);
+    for (name, value) in headers {
+        res = res.header(name, value);
+    }
+    let res = res.body(body).map_err(|err| {
+        Error::InvalidConfig(format!("The cassette response is invalid: {}.", err))
+    })?;
+    Ok(Response::from(res))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Api, ContentOpt, FolderId, NoInfo, ReqwestTransport};
+    use mockito::Server;
+    use uuid::uuid;
+
+    fn vcr_api(base_url: &str, transport: VcrTransport) -> Result<crate::AuthorizedApi, Error> {
+        Ok(Api::builder()
+            .base_url(base_url)
+            .transport(Arc::new(transport))
+            .build()?
+            .authorize("gofile_token"))
+    }
+
+    #[tokio::test]
+    async fn vcr_record_and_replay() -> Result<(), Error> {
+        let mut server = Server::new_async().await;
+        let base_url = server.url();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cassette.json");
+        let account_id = uuid!("00000000-0000-0000-0000-000000000001");
+        let mocks = vec![
+            server
+                .mock("GET", "/accounts/getid?token=gofile_token")
+                .with_body(
+                    r#"{"status":"ok","data":{"id":"00000000-0000-0000-0000-000000000001"}}"#,
+                )
+                .create_async()
+                .await,
+            server
+                .mock(
+                    "GET",
+                    "/accounts/00000000-0000-0000-0000-000000000001?token=gofile_token",
+                )
+                .with_body(
+                    serde_json::json!({
+                        "status": "ok",
+                        "data": {
+                            "id": account_id,
+                            "token": "gofile_token",
+                            "email": "foo@bar.com",
+                            "tier": "standard",
+                            "rootFolder": "00000000-0000-0000-0000-000000000002",
+                            "filesCount": 1,
+                            "totalSize": 3
+                        }
+                    })
+                    .to_string(),
+                )
+                .create_async()
+                .await,
+            server
+                .mock(
+                    "PUT",
+                    "/contents/00000000-0000-0000-0000-000000000002/update",
+                )
+                .with_status(401)
+                .with_body(r#"{"status":"error-notPremium","data":{}}"#)
+                .create_async()
+                .await,
+        ];
+        let folder_id = FolderId(uuid!("00000000-0000-0000-0000-000000000002"));
+
+        let inner = Arc::new(ReqwestTransport(reqwest::Client::new()));
+        let api = vcr_api(&base_url, VcrTransport::record(&path, inner))?;
+        assert_eq!(api.get_account_id().await?, account_id);
+        let details = api.get_account_details(account_id).await?;
+        assert_eq!(details.token, "gofile_token");
+        let result = api
+            .set_option::<NoInfo>(folder_id.into(), ContentOpt::Public(true))
+            .await;
+        assert!(matches!(result, Err(Error::NotPremium(_))));
+        for mock in mocks {
+            mock.assert_async().await;
+        }
+        drop(server);
+
+        let cassette = std::fs::read_to_string(&path).unwrap();
+        assert!(!cassette.contains("gofile_token"));
+        assert_eq!(
+            serde_json::from_str::<Cassette>(&cassette)
+                .unwrap()
+                .interactions[0]
+                .request
+                .path,
+            "/accounts/getid?token=REDACTED"
+        );
+
+        let api = vcr_api(&base_url, VcrTransport::replay(&path)?)?;
+        assert_eq!(api.get_account_id().await?, account_id);
+        let details = api.get_account_details(account_id).await?;
+        assert_eq!(details.root_folder, folder_id);
+        assert_eq!(details.token, REDACTED);
+        let result = api
+            .set_option::<NoInfo>(folder_id.into(), ContentOpt::Public(true))
+            .await;
+        assert!(matches!(result, Err(Error::NotPremium(_))));
+        Ok(())
+    }
+
+    fn get_interaction(path: &str, body: &str) -> Interaction {
+        Interaction {
+            request: CassetteRequest {
+                method: String::from("GET"),
+                path: String::from(path),
+                body_sha256: None,
+                body: None,
+            },
+            response: CassetteResponse {
+                status: 200,
+                headers: vec![],
+                body: String::from(body),
+                binary: false,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn vcr_replay_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cassette.json");
+        let cassette = Cassette {
+            interactions: vec![get_interaction(
+                "/servers",
+                r#"{"status":"ok","data":{"servers":[]}}"#,
+            )],
+        };
+        save(&path, &cassette).unwrap();
+
+        let api = vcr_api("http://localhost", VcrTransport::replay(&path).unwrap()).unwrap();
+        let result = api.get_account_id().await;
+        assert!(matches!(
+            &result,
+            Err(Error::CassetteMismatch { expected: Some(expected), actual, .. })
+                if expected == "  GET /servers\n"
+                    && actual == "  GET /accounts/getid?token=REDACTED\n"
+        ));
+        let message = result.unwrap_err().to_string();
+        assert!(message.contains("expected:\n  GET /servers\nactual:\n  GET /accounts/getid"));
+
+        // a recorded request with the same path is closer than the next one in order
+        let mut other = get_interaction("/accounts/getid?token=REDACTED", "");
+        other.request.body_sha256 = Some(String::from("abc"));
+        other.request.body = Some(String::from("{}"));
+        let cassette = Cassette {
+            interactions: vec![cassette.interactions[0].clone(), other],
+        };
+        save(&path, &cassette).unwrap();
+        let api = vcr_api("http://localhost", VcrTransport::replay(&path).unwrap()).unwrap();
+        assert!(matches!(
+            api.get_account_id().await,
+            Err(Error::CassetteMismatch { expected: Some(expected), .. })
+                if expected == "  GET /accounts/getid?token=REDACTED\n  body sha256 abc\n  {}\n"
+        ));
+    }
+
+    #[tokio::test]
+    async fn vcr_replay_out_of_order() -> Result<(), Error> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cassette.json");
+        let account_id = uuid!("00000000-0000-0000-0000-000000000001");
+        let details = serde_json::json!({
+            "status": "ok",
+            "data": {
+                "id": account_id,
+                "token": REDACTED,
+                "email": "foo@bar.com",
+                "tier": "standard",
+                "rootFolder": "00000000-0000-0000-0000-000000000002",
+                "filesCount": 1,
+                "totalSize": 3
+            }
+        });
+        let cassette = Cassette {
+            interactions: vec![
+                get_interaction(
+                    "/accounts/getid?token=REDACTED",
+                    r#"{"status":"ok","data":{"id":"00000000-0000-0000-0000-000000000001"}}"#,
+                ),
+                get_interaction(
+                    "/accounts/00000000-0000-0000-0000-000000000001?token=REDACTED",
+                    &details.to_string(),
+                ),
+            ],
+        };
+        save(&path, &cassette).unwrap();
+
+        // recorded as getid then details, replayed the other way around
+        let api = vcr_api("http://localhost", VcrTransport::replay(&path)?)?;
+        let details = api.get_account_details(account_id).await?;
+        assert_eq!(details.files_count, 1);
+        assert_eq!(api.get_account_id().await?, account_id);
+
+        // each interaction answers once
+        assert!(matches!(
+            api.get_account_id().await,
+            Err(Error::CassetteMismatch { expected: None, .. })
+        ));
+        Ok(())
+    }
+}